@@ -3,6 +3,8 @@
 //! Procedural attributes for Omnia guests.
 
 mod otel;
+#[cfg(feature = "schema-check")]
+mod schema;
 
 use proc_macro::TokenStream;
 use quote::quote;
@@ -38,3 +40,15 @@ pub fn instrument(args: TokenStream, item: TokenStream) -> TokenStream {
 
     TokenStream::from(new_fn)
 }
+
+/// Validates an `entity!` declaration against the crate's checked-in schema file.
+///
+/// Expanded by `entity!` when the `schema-check` feature of `omnia-guest` is
+/// enabled; not intended to be called directly.
+#[cfg(feature = "schema-check")]
+#[doc(hidden)]
+#[proc_macro]
+pub fn check_entity(input: TokenStream) -> TokenStream {
+    let decl = parse_macro_input!(input as schema::EntityDecl);
+    TokenStream::from(schema::check(&decl))
+}
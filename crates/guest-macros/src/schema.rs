@@ -0,0 +1,264 @@
+//! Implementation details for the `check_entity!` schema validation macro.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{Error, Ident, LitStr, Token, bracketed, parenthesized};
+
+/// Environment variable naming the schema file, relative to the crate root.
+const SCHEMA_ENV: &str = "OMNIA_SCHEMA";
+const DEFAULT_FILES: [&str; 2] = ["schema.json", "schema.sql"];
+
+const IF_NOT_EXISTS: &str = "IF NOT EXISTS";
+
+/// Table name to column names.
+type Schema = BTreeMap<String, BTreeSet<String>>;
+
+/// The table, explicit column mappings, and fields of one `entity!`.
+pub struct EntityDecl {
+    table: LitStr,
+    columns: Vec<(LitStr, LitStr, LitStr)>,
+    fields: Vec<Ident>,
+}
+
+impl Parse for EntityDecl {
+    fn parse(input: ParseStream) -> Result<Self> {
+        keyword(input, "table")?;
+        let table = input.parse()?;
+        input.parse::<Token![,]>()?;
+
+        keyword(input, "columns")?;
+        let content;
+        bracketed!(content in input);
+        let columns = Punctuated::<_, Token![,]>::parse_terminated_with(&content, column_spec)?;
+        input.parse::<Token![,]>()?;
+
+        keyword(input, "fields")?;
+        let content;
+        bracketed!(content in input);
+        let fields = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(Self {
+            table,
+            columns: columns.into_iter().collect(),
+            fields: fields.into_iter().collect(),
+        })
+    }
+}
+
+fn column_spec(input: ParseStream) -> Result<(LitStr, LitStr, LitStr)> {
+    let inner;
+    parenthesized!(inner in input);
+    let col_table = inner.parse()?;
+    inner.parse::<Token![,]>()?;
+    let col_name = inner.parse()?;
+    inner.parse::<Token![,]>()?;
+    let col_field = inner.parse()?;
+    Ok((col_table, col_name, col_field))
+}
+
+fn keyword(input: ParseStream, name: &str) -> Result<()> {
+    let ident: Ident = input.parse()?;
+    if ident != name {
+        return Err(Error::new(ident.span(), format!("expected `{name}`")));
+    }
+    input.parse::<Token![=]>()?;
+    Ok(())
+}
+
+pub fn check(decl: &EntityDecl) -> TokenStream {
+    let path = match schema_path() {
+        Ok(path) => path,
+        Err(message) => return Error::new(Span::call_site(), message).to_compile_error(),
+    };
+    let schema = match load(&path) {
+        Ok(schema) => schema,
+        Err(message) => return Error::new(decl.table.span(), message).to_compile_error(),
+    };
+
+    let mut errors = Vec::new();
+    let table = decl.table.value();
+    if lookup(&schema, &table).is_none() {
+        errors.push(Error::new(decl.table.span(), format!("table `{table}` is not in the schema")));
+    }
+
+    for field in &decl.fields {
+        let name = field.to_string();
+        let name = name.trim_start_matches("r#");
+        let (col_table, column) = decl
+            .columns
+            .iter()
+            .find(|(_, _, alias)| alias.value() == name)
+            .map_or_else(|| (table.clone(), name.to_string()), |(t, c, _)| (t.value(), c.value()));
+
+        // A missing table is reported once above.
+        let Some(columns) = lookup(&schema, &col_table) else {
+            if col_table != table {
+                errors.push(Error::new(
+                    field.span(),
+                    format!("table `{col_table}` is not in the schema"),
+                ));
+            }
+            continue;
+        };
+        if !columns.contains(&column) {
+            errors.push(Error::new(
+                field.span(),
+                format!("column `{col_table}.{column}` is not in the schema"),
+            ));
+        }
+    }
+
+    let errors = errors.iter().map(Error::to_compile_error);
+    // Referencing the file makes cargo rebuild the crate when the schema changes.
+    let path = path.to_string_lossy();
+    quote! {
+        const _: &[u8] = include_bytes!(#path);
+        #(#errors)*
+    }
+}
+
+fn schema_path() -> std::result::Result<PathBuf, String> {
+    let root =
+        std::env::var("CARGO_MANIFEST_DIR").map_err(|e| format!("CARGO_MANIFEST_DIR: {e}"))?;
+    let root = Path::new(&root);
+    if let Ok(path) = std::env::var(SCHEMA_ENV) {
+        return Ok(root.join(path));
+    }
+    DEFAULT_FILES.iter().map(|file| root.join(file)).find(|path| path.exists()).ok_or_else(|| {
+        format!("no schema file found: set {SCHEMA_ENV} or add {}", DEFAULT_FILES.join(" or "))
+    })
+}
+
+fn load(path: &Path) -> std::result::Result<Schema, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("reading schema {}: {e}", path.display()))?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(|e| format!("parsing schema {}: {e}", path.display()))
+    } else {
+        Ok(parse_sql(&text))
+    }
+}
+
+/// Find a table by exact name, then by its unqualified name.
+fn lookup<'a>(schema: &'a Schema, table: &str) -> Option<&'a BTreeSet<String>> {
+    schema.get(table).or_else(|| schema.get(table.rsplit('.').next()?))
+}
+
+/// Extract tables and columns from the `CREATE TABLE` statements of a SQL dump.
+fn parse_sql(sql: &str) -> Schema {
+    let mut schema = Schema::new();
+    let upper = sql.to_ascii_uppercase();
+    let mut offset = 0;
+
+    while let Some(found) = upper[offset..].find("CREATE TABLE") {
+        let start = offset + found + "CREATE TABLE".len();
+        let Some(open) = sql[start..].find('(').map(|i| start + i) else {
+            break;
+        };
+        let Some(close) = matching_paren(sql, open) else {
+            break;
+        };
+
+        let name = sql[start..open].trim();
+        let name = match name.get(..IF_NOT_EXISTS.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(IF_NOT_EXISTS) => &name[prefix.len()..],
+            _ => name,
+        };
+        let table = unquote(name.trim());
+
+        let columns = split_top_level(&sql[open + 1..close])
+            .filter_map(|definition| definition.split_whitespace().next())
+            .filter(|first| !is_constraint(first))
+            .map(unquote)
+            .collect();
+        schema.insert(table, columns);
+        offset = close;
+    }
+    schema
+}
+
+fn matching_paren(sql: &str, open: usize) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (i, ch) in sql[open..].char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn split_top_level(body: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0_usize;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (i, ch) in body.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&body[start..]);
+    parts.into_iter().map(str::trim).filter(|part| !part.is_empty())
+}
+
+fn is_constraint(word: &str) -> bool {
+    ["CONSTRAINT", "PRIMARY", "FOREIGN", "UNIQUE", "CHECK", "KEY", "INDEX", "EXCLUDE"]
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+/// Strip identifier quoting from each segment of a possibly qualified name.
+fn unquote(name: &str) -> String {
+    name.split('.')
+        .map(|segment| segment.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']')))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_create_table_columns() {
+        let schema = parse_sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS "trips" (
+                trip_id TEXT PRIMARY KEY,
+                "route" VARCHAR(32) NOT NULL,
+                fare NUMERIC(10, 2),
+                CONSTRAINT trips_route FOREIGN KEY (route) REFERENCES routes (route_id)
+            );
+            create table analytics.events (id BIGINT, payload JSONB);
+            "#,
+        );
+
+        let trips: Vec<_> = schema["trips"].iter().map(String::as_str).collect();
+        assert_eq!(trips, ["fare", "route", "trip_id"]);
+        assert!(lookup(&schema, "analytics.events").is_some_and(|c| c.contains("payload")));
+    }
+
+    #[test]
+    fn qualified_lookup_falls_back_to_table_name() {
+        let schema = parse_sql("CREATE TABLE trips (trip_id TEXT);");
+        assert!(lookup(&schema, "public.trips").is_some());
+    }
+}
@@ -16,6 +16,7 @@ pub fn expand(config: &Config) -> TokenStream {
     let Codegen {
         mode,
         host_types,
+        backend_types,
         server_types,
         backends_ty,
         backends_def,
@@ -41,7 +42,7 @@ pub fn expand(config: &Config) -> TokenStream {
 
             impl omnia::Wiring<#backends_ty> for Hooks {
                 fn link(deployment: &mut omnia::Deployment<omnia::StoreCtx<#backends_ty>>) -> Result<()> {
-                    #(deployment.host::<#host_types, #backends_ty>()?;)*
+                    #(deployment.host::<#host_types, #backends_ty>()?.backend::<#backend_types>();)*
                     Ok(())
                 }
 
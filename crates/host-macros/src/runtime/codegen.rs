@@ -12,6 +12,7 @@ use crate::runtime::parse::{Config, HostEntry, ManifestSpec, Mode};
 pub struct Codegen {
     pub mode: Mode,
     pub host_types: Vec<Path>,
+    pub backend_types: Vec<Path>,
     pub server_types: Vec<Path>,
     pub backends_ty: TokenStream,
     pub backends_def: TokenStream,
@@ -22,6 +23,8 @@ impl From<&Config> for Codegen {
     fn from(config: &Config) -> Self {
         let host_entries = &config.host_entries;
         let host_types: Vec<Path> = host_entries.iter().map(|entry| entry.host.clone()).collect();
+        let backend_types: Vec<Path> =
+            host_entries.iter().map(|entry| entry.backend.clone()).collect();
         let server_types: Vec<Path> =
             host_types.iter().filter(|host| is_server(host)).cloned().collect();
 
@@ -32,6 +35,7 @@ impl From<&Config> for Codegen {
         Self {
             mode: config.mode,
             host_types,
+            backend_types,
             server_types,
             backends_ty,
             backends_def,
@@ -213,8 +217,9 @@ fn host_impl(host: &Path, field: &Ident) -> TokenStream {
                 fn http_view<'a>(
                     &'a mut self,
                     table: &'a mut omnia::wasmtime_wasi::ResourceTable,
+                    guest: Option<&omnia::GuestId>,
                 ) -> omnia_wasi_http::WasiHttpCtxView<'a> {
-                    self.#field.as_view(table)
+                    self.#field.as_view(table, guest)
                 }
             }
         },
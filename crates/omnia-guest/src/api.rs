@@ -1,21 +1,33 @@
 //! Transport-neutral operation invocation and transport adapters.
 
+pub mod audit;
+pub mod auth;
 /// Typed command routing over application operations.
 pub mod command;
+pub mod cors;
 pub mod http;
 /// Typed operation inputs and transport-neutral metadata.
 pub mod invocation;
 /// Provider-owning invocation primitives.
 pub mod invoke;
+pub mod limit;
+pub mod lineage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod local;
 /// Typed exact-topic messaging routing.
 pub mod messaging;
 /// Stateless application operations.
 pub mod operation;
+pub mod tenant;
 
+pub use auth::{Claims, JwtAuth};
+pub use cors::Cors;
 pub use http::{HttpError, HttpResult};
 pub use invocation::{Invocation, Metadata};
 pub use invoke::{CallContext, Invoker};
+pub use limit::BodyLimit;
 pub use operation::Operation;
+pub use tenant::{Tenancy, Tenant};
 
 /// The typed outcome supplied to a route projector.
 #[derive(Debug)]
@@ -0,0 +1,163 @@
+//! Redaction rules for opt-in request and delivery audit logging.
+//!
+//! Enable auditing with [`http::Router::audit`](crate::api::http::Router::audit)
+//! or [`messaging::Router::audit`](crate::api::messaging::Router::audit). Each
+//! handled request or delivery emits one structured `tracing` event on the
+//! `omnia::audit` target after sensitive headers, metadata, and JSON payload
+//! fields have been masked.
+
+use std::collections::BTreeMap;
+
+use http::HeaderMap;
+use serde_json::Value;
+
+/// Tracing target for audit events.
+pub const TARGET: &str = "omnia::audit";
+
+const MASK: &str = "[redacted]";
+
+/// Which headers, metadata entries, and payload fields to mask before logging.
+#[derive(Clone, Debug)]
+pub struct Redaction {
+    headers: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            headers: vec![
+                "authorization".to_string(),
+                "proxy-authorization".to_string(),
+                "cookie".to_string(),
+                "set-cookie".to_string(),
+            ],
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl Redaction {
+    /// Create rules that mask credential-bearing headers only.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also mask the named header or metadata key (case-insensitive).
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>) -> Self {
+        self.headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Mask every JSON object field with this name, at any depth of the payload.
+    #[must_use]
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.fields.push(name.into());
+        self
+    }
+
+    /// Render headers with masked values for redacted names.
+    #[must_use]
+    pub fn headers(&self, headers: &HeaderMap) -> BTreeMap<String, String> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.headers.iter().any(|h| h == name.as_str()) {
+                    MASK.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.as_str().to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Render transport metadata with masked values for redacted keys.
+    #[must_use]
+    pub fn metadata(&self, metadata: &[(String, String)]) -> Vec<(String, String)> {
+        metadata
+            .iter()
+            .map(|(key, value)| {
+                let value = if self.headers.iter().any(|h| key.eq_ignore_ascii_case(h)) {
+                    MASK.to_string()
+                } else {
+                    value.clone()
+                };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Render a payload for logging: JSON with redacted fields masked, otherwise its size.
+    #[must_use]
+    pub fn payload(&self, payload: &[u8]) -> String {
+        if payload.is_empty() {
+            return String::new();
+        }
+        serde_json::from_slice::<Value>(payload).map_or_else(
+            |_| format!("<{} bytes>", payload.len()),
+            |mut value| {
+                self.mask(&mut value);
+                value.to_string()
+            },
+        )
+    }
+
+    fn mask(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, field) in object.iter_mut() {
+                    if self.fields.iter().any(|f| f == key) {
+                        *field = Value::String(MASK.to_string());
+                    } else {
+                        self.mask(field);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.mask(item)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn strips_authorization_by_default() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-request-id", HeaderValue::from_static("abc"));
+
+        let rendered = Redaction::new().headers(&headers);
+        assert_eq!(rendered["authorization"], MASK);
+        assert_eq!(rendered["x-request-id"], "abc");
+    }
+
+    #[test]
+    fn masks_nested_payload_fields() {
+        let redaction = Redaction::new().field("operator_id");
+        let payload = br#"{"trip":"t1","vehicles":[{"operator_id":"op-7","speed":12}]}"#;
+
+        let rendered = redaction.payload(payload);
+        assert!(!rendered.contains("op-7"));
+        assert!(rendered.contains(r#""speed":12"#));
+    }
+
+    #[test]
+    fn non_json_payload_renders_size() {
+        assert_eq!(Redaction::new().payload(&[0xff, 0x00, 0x01]), "<3 bytes>");
+    }
+
+    #[test]
+    fn masks_metadata_case_insensitively() {
+        let redaction = Redaction::new().header("X-Api-Key");
+        let rendered = redaction.metadata(&[("x-api-key".to_string(), "k".to_string())]);
+        assert_eq!(rendered[0].1, MASK);
+    }
+}
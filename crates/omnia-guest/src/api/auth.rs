@@ -0,0 +1,513 @@
+//! Bearer-token authentication for HTTP routers.
+//!
+//! Enable authentication with [`http::Router::jwt`](crate::api::http::Router::jwt).
+//! Every request must then carry `Authorization: Bearer <token>`, where the
+//! token is a JWT signed with `RS256` or `ES256` by a key in the issuer's JSON
+//! Web Key Set, names the configured issuer and one of the accepted audiences,
+//! and has not expired. Requests without such a token are answered with
+//! `401 Unauthorized`; the claims of an accepted token are inserted into the
+//! request extensions as [`Claims`]:
+//!
+//! ```rust,ignore
+//! async fn whoami(claims: Claims) -> HttpResult<String> {
+//!     Ok(claims.subject().unwrap_or_default().to_string())
+//! }
+//! ```
+//!
+//! The key set is fetched with [`HttpRequest`] and kept in the [`StateStore`]
+//! under a key derived from its URL. A token naming a key the cached set lacks
+//! triggers a refetch, at most once every 30 seconds. When the JWKS endpoint
+//! itself requires credentials, [`JwtAuth::identity`] names a wasi-identity
+//! identity whose access token is sent with the fetch.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, anyhow, bail};
+use axum::body::Body;
+use axum::extract::FromRequestParts;
+use axum::response::{IntoResponse, Response};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::request::Parts;
+use http::{Request, StatusCode};
+use p256::ecdsa::signature::Verifier as _;
+use p256::ecdsa::{Signature as EcSignature, VerifyingKey as EcKey};
+use rsa::pkcs1v15::{Signature as RsaSignature, VerifyingKey as RsaKey};
+use rsa::sha2::Sha256;
+use rsa::{BigUint, RsaPublicKey};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{HttpRequest, Identity, StateStore};
+
+/// The least time between key set refetches for tokens naming an unknown key.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How bearer tokens are verified.
+#[derive(Clone, Debug)]
+pub struct JwtAuth {
+    issuer: String,
+    jwks_url: String,
+    audiences: Vec<String>,
+    identity: Option<String>,
+    leeway: Duration,
+    cache_keys: Duration,
+}
+
+impl JwtAuth {
+    /// Accept tokens issued by `issuer` and signed by a key published at
+    /// `jwks_url`.
+    #[must_use]
+    pub fn new(issuer: impl Into<String>, jwks_url: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            jwks_url: jwks_url.into(),
+            audiences: Vec::new(),
+            identity: None,
+            leeway: Duration::from_secs(60),
+            cache_keys: Duration::from_secs(300),
+        }
+    }
+
+    /// Accept tokens for `audience`. Once any audience is added, a token must
+    /// name one of them in its `aud` claim.
+    #[must_use]
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    /// Authenticate the key set fetch with an access token for the
+    /// wasi-identity identity `name`.
+    #[must_use]
+    pub fn identity(mut self, name: impl Into<String>) -> Self {
+        self.identity = Some(name.into());
+        self
+    }
+
+    /// Tolerate clock skew of `leeway` when checking `exp` and `nbf`. Defaults
+    /// to 60 seconds.
+    #[must_use]
+    pub const fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Cache the key set for `max_age`, or fetch it for every request when
+    /// zero. Defaults to 5 minutes.
+    #[must_use]
+    pub const fn cache_keys(mut self, max_age: Duration) -> Self {
+        self.cache_keys = max_age;
+        self
+    }
+
+    /// The issuer's key set, from the state store while cached.
+    ///
+    /// A cached set that lacks the key `kid` names is refetched, so a rotated
+    /// signing key is picked up before the cache expires. Refetches for
+    /// unknown keys happen at most once per [`REFRESH_INTERVAL`], so tokens
+    /// naming made-up keys cannot drive a fetch per request.
+    pub(crate) async fn keys<P>(&self, provider: &P, kid: Option<&str>) -> Result<KeySet>
+    where
+        P: HttpRequest + Identity + StateStore,
+    {
+        let key = format!("omnia:jwks:{}", self.jwks_url);
+        if !self.cache_keys.is_zero()
+            && let Some(body) =
+                StateStore::get(provider, &key).await.context("reading cached key set")?
+        {
+            let keys: KeySet = serde_json::from_slice(&body).context("parsing key set")?;
+            if keys.knows(kid) || !self.may_refresh(provider).await? {
+                return Ok(keys);
+            }
+        }
+
+        let body = self.fetch_keys(provider).await?;
+        if !self.cache_keys.is_zero() {
+            let ttl = Some(self.cache_keys.as_secs().max(1));
+            if let Err(error) = StateStore::set(provider, &key, &body, ttl).await {
+                tracing::warn!(error = %error, "failed to cache key set");
+            }
+        }
+        serde_json::from_slice(&body).context("parsing key set")
+    }
+
+    /// Claim the refetch slot for an unknown key, if no refetch has run in
+    /// the last [`REFRESH_INTERVAL`].
+    async fn may_refresh<P: StateStore>(&self, provider: &P) -> Result<bool> {
+        let key = format!("omnia:jwks-refresh:{}", self.jwks_url);
+        if StateStore::get(provider, &key).await.context("reading key set refresh")?.is_some() {
+            return Ok(false);
+        }
+        let ttl = Some(REFRESH_INTERVAL.as_secs());
+        StateStore::set(provider, &key, &[1], ttl).await.context("recording key set refresh")?;
+        Ok(true)
+    }
+
+    async fn fetch_keys<P: HttpRequest + Identity>(&self, provider: &P) -> Result<Vec<u8>> {
+        let mut builder = Request::get(&self.jwks_url);
+        if let Some(identity) = &self.identity {
+            let token = Identity::access_token(provider, identity.clone())
+                .await
+                .with_context(|| format!("getting access token of identity `{identity}`"))?;
+            builder = builder.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        let response = HttpRequest::fetch(provider, builder.body(Body::empty())?)
+            .await
+            .with_context(|| format!("fetching key set from {}", self.jwks_url))?;
+        if !response.status().is_success() {
+            bail!("key set endpoint {} answered {}", self.jwks_url, response.status());
+        }
+        let body = response.into_body().to_vec();
+        serde_json::from_slice::<KeySet>(&body).context("parsing key set")?;
+        Ok(body)
+    }
+
+    /// Verify `token` against `keys` at `now`, in seconds since the Unix
+    /// epoch, and return its claims.
+    pub(crate) fn verify(&self, token: &str, keys: &KeySet, now: u64) -> Result<Claims> {
+        let (input, signature) =
+            token.rsplit_once('.').ok_or_else(|| anyhow!("malformed token"))?;
+        let (header, payload) = input.split_once('.').ok_or_else(|| anyhow!("malformed token"))?;
+        let header: Header =
+            serde_json::from_slice(&decode(header)?).context("invalid token header")?;
+        let signature = decode(signature)?;
+
+        let signed = keys
+            .keys
+            .iter()
+            .filter(|key| header.kid.is_none() || key.kid == header.kid)
+            .any(|key| key.verify(&header.alg, input.as_bytes(), &signature));
+        if !signed {
+            bail!("token is not signed by a known key");
+        }
+
+        let claims = Claims(serde_json::from_slice(&decode(payload)?).context("invalid claims")?);
+        self.check(&claims, now)?;
+        Ok(claims)
+    }
+
+    fn check(&self, claims: &Claims, now: u64) -> Result<()> {
+        if claims.get("iss").and_then(Value::as_str) != Some(self.issuer.as_str()) {
+            bail!("token is not issued by `{}`", self.issuer);
+        }
+        if !self.audiences.is_empty() {
+            let accepted = |aud: &str| self.audiences.iter().any(|audience| audience == aud);
+            let matches = match claims.get("aud") {
+                Some(Value::String(aud)) => accepted(aud),
+                Some(Value::Array(auds)) => auds.iter().filter_map(Value::as_str).any(accepted),
+                _ => false,
+            };
+            if !matches {
+                bail!("token is not issued for an accepted audience");
+            }
+        }
+
+        let leeway = self.leeway.as_secs();
+        let expiry =
+            claims.get("exp").and_then(Value::as_u64).ok_or_else(|| anyhow!("no expiry"))?;
+        if expiry.saturating_add(leeway) <= now {
+            bail!("token expired");
+        }
+        if let Some(not_before) = claims.get("nbf").and_then(Value::as_u64)
+            && not_before > now.saturating_add(leeway)
+        {
+            bail!("token is not valid yet");
+        }
+        Ok(())
+    }
+}
+
+/// The claims of a request's verified bearer token.
+///
+/// Inserted into the request extensions by a router with
+/// [`jwt`](crate::api::http::Router::jwt) authentication, and extracted by
+/// handlers that take it as an argument.
+#[derive(Clone, Debug, Default)]
+pub struct Claims(Map<String, Value>);
+
+impl Claims {
+    /// The `sub` claim.
+    #[must_use]
+    pub fn subject(&self) -> Option<&str> {
+        self.get("sub").and_then(Value::as_str)
+    }
+
+    /// The claim `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// Deserialize the claims into `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the claims do not match `T`.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_value(Value::Object(self.0.clone())).context("deserializing claims")
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Claims {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or_else(|| unauthorized("no verified token"))
+    }
+}
+
+/// A `401 Unauthorized` response asking for a bearer token.
+pub(crate) fn unauthorized(description: &str) -> Response {
+    (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, "Bearer")], description.to_string())
+        .into_response()
+}
+
+/// The key id `token`'s header names, if it names one.
+pub(crate) fn key_id(token: &str) -> Option<String> {
+    let (header, _) = token.split_once('.')?;
+    serde_json::from_slice::<Header>(&decode(header).ok()?).ok()?.kid
+}
+
+/// Seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn decode(part: &str) -> Result<Vec<u8>> {
+    Base64UrlUnpadded::decode_vec(part).map_err(|error| anyhow!("invalid base64url: {error}"))
+}
+
+/// A JSON Web Key Set.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct KeySet {
+    keys: Vec<Jwk>,
+}
+
+impl KeySet {
+    /// Whether the set holds the key `kid`; any set will do for no `kid`.
+    fn knows(&self, kid: Option<&str>) -> bool {
+        kid.is_none_or(|kid| self.keys.iter().any(|key| key.kid.as_deref() == Some(kid)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+impl Jwk {
+    fn verify(&self, alg: &str, message: &[u8], signature: &[u8]) -> bool {
+        if self.alg.as_deref().is_some_and(|key_alg| key_alg != alg) {
+            return false;
+        }
+        match (alg, self.kty.as_str()) {
+            ("RS256", "RSA") => self.verify_rsa(message, signature).is_some(),
+            ("ES256", "EC") if self.crv.as_deref() == Some("P-256") => {
+                self.verify_ec(message, signature).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn verify_rsa(&self, message: &[u8], signature: &[u8]) -> Option<()> {
+        let n = BigUint::from_bytes_be(&decode(self.n.as_deref()?).ok()?);
+        let e = BigUint::from_bytes_be(&decode(self.e.as_deref()?).ok()?);
+        let key = RsaKey::<Sha256>::new(RsaPublicKey::new(n, e).ok()?);
+        key.verify(message, &RsaSignature::try_from(signature).ok()?).ok()
+    }
+
+    fn verify_ec(&self, message: &[u8], signature: &[u8]) -> Option<()> {
+        let x = decode(self.x.as_deref()?).ok()?;
+        let y = decode(self.y.as_deref()?).ok()?;
+        if x.len() != 32 || y.len() != 32 {
+            return None;
+        }
+        let point = p256::EncodedPoint::from_affine_coordinates(
+            p256::FieldBytes::from_slice(&x),
+            p256::FieldBytes::from_slice(&y),
+            false,
+        );
+        let key = EcKey::from_encoded_point(&point).ok()?;
+        key.verify(message, &EcSignature::from_slice(signature).ok()?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::error::Error;
+    use std::sync::Mutex;
+
+    use bytes::Bytes;
+    use http_body::Body as HttpBody;
+    use p256::ecdsa::SigningKey;
+    use p256::ecdsa::signature::Signer;
+    use serde_json::json;
+
+    use super::*;
+    use crate::capabilities::MemoryStore;
+
+    const NOW: u64 = 1_800_000_000;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&p256::FieldBytes::from([7; 32])).unwrap()
+    }
+
+    fn jwks(kids: &[&str]) -> Value {
+        let point = signing_key().verifying_key().to_encoded_point(false);
+        let keys: Vec<Value> = kids
+            .iter()
+            .map(|kid| {
+                json!({
+                    "kty": "EC", "kid": kid, "crv": "P-256",
+                    "x": Base64UrlUnpadded::encode_string(point.x().unwrap()),
+                    "y": Base64UrlUnpadded::encode_string(point.y().unwrap()),
+                })
+            })
+            .collect();
+        json!({ "keys": keys })
+    }
+
+    fn key_set() -> KeySet {
+        serde_json::from_value(jwks(&["k1"])).unwrap()
+    }
+
+    /// An issuer publishing `jwks`, counting how often it is fetched.
+    #[derive(Default)]
+    struct Issuer {
+        jwks: Mutex<Value>,
+        fetches: Mutex<usize>,
+        store: MemoryStore,
+    }
+
+    impl Issuer {
+        fn publish(&self, kids: &[&str]) {
+            *self.jwks.lock().unwrap() = jwks(kids);
+        }
+
+        fn fetches(&self) -> usize {
+            *self.fetches.lock().unwrap()
+        }
+    }
+
+    impl HttpRequest for Issuer {
+        async fn fetch<T>(&self, _request: Request<T>) -> Result<http::Response<Bytes>>
+        where
+            T: HttpBody + Any + Send,
+            T::Data: Into<Vec<u8>>,
+            T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+        {
+            *self.fetches.lock().unwrap() += 1;
+            let body = self.jwks.lock().unwrap().to_string();
+            Ok(http::Response::new(Bytes::from(body)))
+        }
+    }
+
+    impl Identity for Issuer {
+        async fn access_token(&self, _identity: String) -> Result<String> {
+            bail!("no identities")
+        }
+    }
+
+    impl StateStore for Issuer {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.store.get(key).await
+        }
+
+        async fn set(
+            &self, key: &str, value: &[u8], ttl_secs: Option<u64>,
+        ) -> Result<Option<Vec<u8>>> {
+            self.store.set(key, value, ttl_secs).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.store.delete(key).await
+        }
+    }
+
+    fn token(alg: &str, claims: &Value) -> String {
+        let header = json!({ "alg": alg, "kid": "k1" });
+        let input = format!(
+            "{}.{}",
+            Base64UrlUnpadded::encode_string(header.to_string().as_bytes()),
+            Base64UrlUnpadded::encode_string(claims.to_string().as_bytes()),
+        );
+        let signature: EcSignature = signing_key().sign(input.as_bytes());
+        format!("{input}.{}", Base64UrlUnpadded::encode_string(&signature.to_bytes()))
+    }
+
+    fn auth() -> JwtAuth {
+        JwtAuth::new("https://login.example.com", "https://login.example.com/jwks")
+            .audience("fleet")
+    }
+
+    #[test]
+    fn accepts_valid_token() {
+        let claims = json!({
+            "iss": "https://login.example.com", "aud": ["fleet", "billing"],
+            "sub": "operator-7", "exp": NOW + 60,
+        });
+
+        let verified = auth().verify(&token("ES256", &claims), &key_set(), NOW).unwrap();
+        assert_eq!(verified.subject(), Some("operator-7"));
+    }
+
+    #[test]
+    fn rejects_wrong_issuer_audience_and_expiry() {
+        let keys = key_set();
+        let cases = [
+            json!({ "iss": "https://other.example.com", "aud": "fleet", "exp": NOW + 60 }),
+            json!({ "iss": "https://login.example.com", "aud": "billing", "exp": NOW + 60 }),
+            json!({ "iss": "https://login.example.com", "aud": "fleet", "exp": NOW - 120 }),
+            json!({ "iss": "https://login.example.com", "aud": "fleet" }),
+        ];
+        for claims in cases {
+            auth().verify(&token("ES256", &claims), &keys, NOW).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn rejects_forged_tokens() {
+        let keys = key_set();
+        let claims = json!({ "iss": "https://login.example.com", "aud": "fleet", "exp": NOW + 60 });
+        let valid = token("ES256", &claims);
+
+        let (input, _) = valid.rsplit_once('.').unwrap();
+        auth().verify(&format!("{input}."), &keys, NOW).unwrap_err();
+        auth().verify(&token("none", &claims), &keys, NOW).unwrap_err();
+        auth().verify(&valid, &KeySet::default(), NOW).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn refetches_once_for_an_unknown_key() {
+        let issuer = Issuer::default();
+        issuer.publish(&["k1"]);
+        auth().keys(&issuer, Some("k1")).await.unwrap();
+        auth().keys(&issuer, Some("k1")).await.unwrap();
+        assert_eq!(issuer.fetches(), 1);
+
+        issuer.publish(&["k1", "k2"]);
+        let keys = auth().keys(&issuer, Some("k2")).await.unwrap();
+        assert!(keys.knows(Some("k2")));
+        assert_eq!(issuer.fetches(), 2);
+
+        auth().keys(&issuer, Some("forged")).await.unwrap();
+        assert_eq!(issuer.fetches(), 2);
+    }
+}
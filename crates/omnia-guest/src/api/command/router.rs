@@ -13,7 +13,7 @@ use clap_complete::Shell;
 use super::builder::{Binding, Decoder, Outcome, Projector};
 use super::response::CommandResponse;
 use crate::api::Provider;
-use crate::api::invocation::{Invocation, Metadata};
+use crate::api::invocation::{Invocation, Metadata, mint_id};
 use crate::api::invoke::Invoker;
 use crate::api::operation::Operation;
 
@@ -564,7 +564,7 @@ where
             Some(route) => {
                 // Uniform with HTTP and messaging: the transport mints the
                 // invocation's request id, which doubles as correlation id.
-                let metadata = Metadata::minted(format!("{:032x}", rand::random::<u128>()));
+                let metadata = Metadata::minted(mint_id());
                 route.dispatch(leaf_matches, &globals, &self.invoker, &metadata).await
             }
             None => CommandResponse::failure("command route was not registered\n", 1),
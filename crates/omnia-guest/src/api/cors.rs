@@ -0,0 +1,266 @@
+//! Cross-origin resource sharing for browser-facing routers.
+//!
+//! Enable CORS with [`http::Router::cors`](crate::api::http::Router::cors), or
+//! with [`Cors::apply`] on an Axum router handed to `omnia_wasi_http::serve`.
+//! Preflight `OPTIONS` requests from an allowed origin are answered directly,
+//! before any route or other layer runs; other requests from an allowed origin
+//! are served as usual and their responses gain the CORS headers. Requests
+//! from other origins are served without CORS headers, so the browser blocks
+//! them.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+
+/// Which origins, methods, and headers browsers may use across origins.
+#[derive(Clone, Debug)]
+pub struct Cors {
+    origins: Option<Vec<String>>,
+    methods: Vec<Method>,
+    headers: Option<Vec<HeaderName>>,
+    expose: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self {
+            origins: Some(Vec::new()),
+            methods: vec![Method::GET, Method::HEAD, Method::POST],
+            headers: Some(Vec::new()),
+            expose: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl Cors {
+    /// Create a policy that allows no origins, and `GET`, `HEAD`, and `POST`
+    /// without extra headers once origins are added.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow requests from `origin`, such as `https://app.example.com`.
+    #[must_use]
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        let origin = origin.into().trim_end_matches('/').to_ascii_lowercase();
+        if let Some(origins) = &mut self.origins {
+            origins.push(origin);
+        }
+        self
+    }
+
+    /// Allow requests from any origin.
+    #[must_use]
+    pub fn allow_any_origin(mut self) -> Self {
+        self.origins = None;
+        self
+    }
+
+    /// Allow exactly the listed methods.
+    #[must_use]
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Allow the listed request headers, beyond those browsers always allow.
+    #[must_use]
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.headers = Some(headers.into_iter().collect());
+        self
+    }
+
+    /// Allow whatever request headers a preflight asks for.
+    #[must_use]
+    pub fn allow_any_header(mut self) -> Self {
+        self.headers = None;
+        self
+    }
+
+    /// Let browser scripts read the listed response headers.
+    #[must_use]
+    pub fn expose_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.expose = headers.into_iter().collect();
+        self
+    }
+
+    /// Let browsers send cookies and credentials. The allowed origin is then
+    /// echoed rather than answered with `*`.
+    #[must_use]
+    pub const fn allow_credentials(mut self, allow: bool) -> Self {
+        self.credentials = allow;
+        self
+    }
+
+    /// Let browsers cache a preflight result for `max_age`.
+    #[must_use]
+    pub const fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Apply the policy to every route of `router`.
+    pub fn apply<S>(self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let cors = Arc::new(self);
+        router.layer(middleware::from_fn(move |request: Request, next: Next| {
+            handle(Arc::clone(&cors), request, next)
+        }))
+    }
+
+    /// The `Access-Control-Allow-Origin` value for `origin`, if it is allowed.
+    fn allowed_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        match &self.origins {
+            None if !self.credentials => Some(HeaderValue::from_static("*")),
+            None => Some(origin.clone()),
+            Some(origins) => {
+                let requested = origin.to_str().ok()?.to_ascii_lowercase();
+                origins.contains(&requested).then(|| origin.clone())
+            }
+        }
+    }
+
+    fn preflight(&self, allow_origin: HeaderValue, request: &HeaderMap) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        headers.insert(ACCESS_CONTROL_ALLOW_METHODS, join(self.methods.iter().map(Method::as_str)));
+        let allow_headers = self.headers.as_ref().map_or_else(
+            || request.get(ACCESS_CONTROL_REQUEST_HEADERS).cloned(),
+            |names| Some(join(names.iter().map(HeaderName::as_str))),
+        );
+        if let Some(allow_headers) = allow_headers.filter(|value| !value.is_empty()) {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+        }
+        if let Some(max_age) = self.max_age {
+            headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+        }
+        if self.credentials {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        headers.insert(
+            VARY,
+            HeaderValue::from_static(
+                "origin, access-control-request-method, access-control-request-headers",
+            ),
+        );
+        (StatusCode::NO_CONTENT, headers).into_response()
+    }
+
+    fn decorate(&self, allow_origin: HeaderValue, response: &mut Response) {
+        let headers = response.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.credentials {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        if !self.expose.is_empty() {
+            headers.insert(
+                ACCESS_CONTROL_EXPOSE_HEADERS,
+                join(self.expose.iter().map(HeaderName::as_str)),
+            );
+        }
+        headers.append(VARY, HeaderValue::from_static("origin"));
+    }
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> HeaderValue {
+    let joined = values.collect::<Vec<_>>().join(", ");
+    HeaderValue::from_str(&joined).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+async fn handle(cors: Arc<Cors>, request: Request, next: Next) -> Response {
+    let Some(allow_origin) = request.headers().get(ORIGIN).and_then(|o| cors.allowed_origin(o))
+    else {
+        return next.run(request).await;
+    };
+
+    let preflight = request.method() == Method::OPTIONS
+        && request.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+    if preflight {
+        return cors.preflight(allow_origin, request.headers());
+    }
+
+    let mut response = next.run(request).await;
+    cors.decorate(allow_origin, &mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn router(cors: Cors) -> Router {
+        cors.apply(Router::new().route("/trips", post(|| async { "created" })))
+    }
+
+    fn request(method: Method, origin: &str) -> http::request::Builder {
+        Request::builder().method(method).uri("/trips").header(ORIGIN, origin)
+    }
+
+    #[tokio::test]
+    async fn answers_preflight_for_allowed_origin() {
+        let cors = Cors::new()
+            .allow_origin("https://app.example.com")
+            .allow_methods([Method::POST])
+            .allow_headers([http::header::CONTENT_TYPE])
+            .max_age(Duration::from_secs(600));
+        let preflight = request(Method::OPTIONS, "https://app.example.com")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router(cors).oneshot(preflight).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "POST");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+    }
+
+    #[tokio::test]
+    async fn decorates_responses_for_allowed_origins() {
+        let cors = Cors::new().allow_any_origin().expose_headers([http::header::ETAG]);
+        let response = router(cors)
+            .oneshot(request(Method::POST, "https://any.example").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(response.headers()[ACCESS_CONTROL_EXPOSE_HEADERS], "etag");
+    }
+
+    #[tokio::test]
+    async fn ignores_other_origins() {
+        let cors = Cors::new().allow_origin("https://app.example.com");
+        let preflight = request(Method::OPTIONS, "https://evil.example")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router(cors).oneshot(preflight).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
@@ -2,17 +2,33 @@
 
 use std::any::TypeId;
 use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
 
-use axum::Router as AxumRouter;
-use axum::extract::{RawPathParams, RawQuery, State};
+use axum::body::Body;
+use axum::extract::rejection::RawPathParamsRejection;
+use axum::extract::{RawPathParams, RawQuery, Request, State};
+use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{self, MethodRouter};
-use http::header::CONTENT_TYPE;
+use axum::{Extension, Router as AxumRouter};
+use http::header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
 use http::{HeaderMap, HeaderValue, Method, StatusCode};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+use crate::api::audit::{self, Redaction};
+use crate::api::auth::{self, JwtAuth};
+use crate::api::cors::Cors;
+use crate::api::limit::{self, BodyLimit};
+use crate::api::tenant::Tenancy;
 use crate::api::{Invocation, Invoker, Metadata, Operation, Provider};
+use crate::shed::{LoadShedder, Priority, ShedPolicy};
+use crate::{HttpRequest, Identity, StateStore};
+
+/// The most request body the audit layer buffers when the router sets no body
+/// limit.
+const AUDIT_BODY_LIMIT: usize = 1024 * 1024;
 
 /// Result type for HTTP handlers.
 pub type HttpResult<T, E = HttpError> = Result<T, E>;
@@ -205,8 +221,16 @@ pub struct Router<P: Provider> {
     inner: AxumRouter<Invoker<P>>,
     invoker: Invoker<P>,
     inventory: Vec<RouteInfo>,
+    audit: Option<Redaction>,
+    tenancy: Option<Tenancy>,
+    auth: Option<Layer>,
+    shed: Option<Layer>,
+    body_limit: Option<BodyLimit>,
+    cors: Option<Cors>,
 }
 
+type Layer = Box<dyn FnOnce(AxumRouter) -> AxumRouter + Send>;
+
 impl<P: Provider> Router<P> {
     /// Create an empty per-request router backed by one invoker.
     #[must_use]
@@ -215,9 +239,52 @@ impl<P: Provider> Router<P> {
             inner: AxumRouter::new(),
             invoker,
             inventory: Vec::new(),
+            audit: None,
+            tenancy: None,
+            auth: None,
+            shed: None,
+            body_limit: None,
+            cors: None,
         }
     }
 
+    /// Log every handled request, masking fields selected by `redaction`.
+    ///
+    /// Auditing reads each request body into memory to log it, up to the
+    /// router's [`body_limit`](Self::body_limit), or 1 MiB without one. A
+    /// larger body is answered with `413 Payload Too Large`.
+    #[must_use]
+    pub fn audit(mut self, redaction: Redaction) -> Self {
+        self.audit = Some(redaction);
+        self
+    }
+
+    /// Resolve a tenant for every request through `tenancy`, answering
+    /// `400 Bad Request` when the id is missing and `404 Not Found` when it is
+    /// not allowed. Operations see the id as [`Metadata::tenant`].
+    #[must_use]
+    pub fn tenants(mut self, tenancy: Tenancy) -> Self {
+        self.tenancy = Some(tenancy);
+        self
+    }
+
+    /// Answer CORS preflight requests and add CORS headers to responses, as
+    /// allowed by `cors`. Preflight requests are answered before tenancy,
+    /// auditing, and load shedding apply.
+    #[must_use]
+    pub fn cors(mut self, cors: Cors) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Reject request bodies over `max` bytes with `413 Payload Too Large`,
+    /// before handlers or auditing buffer them.
+    #[must_use]
+    pub const fn body_limit(mut self, max: usize) -> Self {
+        self.body_limit = Some(BodyLimit::new(max));
+        self
+    }
+
     /// Register one typed method route.
     #[must_use]
     pub fn route(mut self, path: &str, route: MethodRoute<P>) -> Self {
@@ -238,10 +305,198 @@ impl<P: Provider> Router<P> {
 
     /// Finish the router for Axum or a WASI HTTP adapter.
     pub fn into_axum(self) -> AxumRouter {
-        self.inner.with_state(self.invoker)
+        let mut router = self.inner.with_state(self.invoker);
+        // Layers run after routing, so the tenant can come from a path parameter.
+        if let Some(tenancy) = self.tenancy {
+            let tenancy = Arc::new(tenancy);
+            router = router.layer(middleware::from_fn(
+                move |params: Result<RawPathParams, RawPathParamsRejection>,
+                      request: Request,
+                      next: Next| {
+                    resolve_tenant(Arc::clone(&tenancy), params.ok(), request, next)
+                },
+            ));
+        }
+        if let Some(layer) = self.auth {
+            router = layer(router);
+        }
+        if let Some(redaction) = self.audit {
+            let redaction = Arc::new(redaction);
+            let max = self.body_limit.map_or(AUDIT_BODY_LIMIT, BodyLimit::max);
+            router = router.layer(middleware::from_fn(move |request: Request, next: Next| {
+                audit_request(Arc::clone(&redaction), max, request, next)
+            }));
+        }
+        if let Some(layer) = self.shed {
+            router = layer(router);
+        }
+        if let Some(body_limit) = self.body_limit {
+            router = body_limit.apply(router);
+        }
+        match self.cors {
+            Some(cors) => cors.apply(router),
+            None => router,
+        }
     }
 }
 
+impl<P: Provider + StateStore> Router<P> {
+    /// Answer `429 Too Many Requests` while the component is overloaded.
+    ///
+    /// Requests are admitted at [`Priority::Normal`] against the load record
+    /// described by `policy`. The check fails open: if the state store cannot
+    /// be read the request is served.
+    #[must_use]
+    pub fn shed(mut self, policy: ShedPolicy) -> Self {
+        let invoker = self.invoker.clone();
+        let policy = Arc::new(policy);
+        self.shed = Some(Box::new(move |router: AxumRouter| {
+            router.layer(middleware::from_fn(move |request: Request, next: Next| {
+                shed_request(invoker.clone(), Arc::clone(&policy), request, next)
+            }))
+        }));
+        self
+    }
+}
+
+impl<P: Provider + HttpRequest + Identity + StateStore> Router<P> {
+    /// Require a bearer token verified by `auth`, answering
+    /// `401 Unauthorized` when it is missing or invalid.
+    ///
+    /// Handlers read the verified claims from the [`Claims`](auth::Claims)
+    /// request extension. The check runs before tenant resolution, so a
+    /// [`Tenancy::claim`] source reads a verified token.
+    #[must_use]
+    pub fn jwt(mut self, auth: JwtAuth) -> Self {
+        let invoker = self.invoker.clone();
+        let auth = Arc::new(auth);
+        self.auth = Some(Box::new(move |router: AxumRouter| {
+            router.layer(middleware::from_fn(move |request: Request, next: Next| {
+                authenticate(invoker.clone(), Arc::clone(&auth), request, next)
+            }))
+        }));
+        self
+    }
+}
+
+async fn authenticate<P: Provider + HttpRequest + Identity + StateStore>(
+    invoker: Invoker<P>, auth: Arc<JwtAuth>, mut request: Request, next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_owned);
+    let Some(token) = token else {
+        return auth::unauthorized("missing bearer token");
+    };
+
+    let keys = match auth.keys(invoker.provider(), auth::key_id(&token).as_deref()).await {
+        Ok(keys) => keys,
+        Err(error) => {
+            tracing::warn!(error = %error, "failed to fetch token signing keys");
+            return HttpError::from(crate::Error::BadGateway {
+                code: "keys_unavailable".to_string(),
+                description: "token signing keys are unavailable".to_string(),
+            })
+            .into_response();
+        }
+    };
+    match auth.verify(&token, &keys, auth::now()) {
+        Ok(claims) => {
+            request.extensions_mut().insert(claims);
+            next.run(request).await
+        }
+        Err(error) => {
+            tracing::debug!(error = %error, "rejected bearer token");
+            auth::unauthorized("invalid bearer token")
+        }
+    }
+}
+
+async fn shed_request<P: Provider + StateStore>(
+    invoker: Invoker<P>, policy: Arc<ShedPolicy>, request: Request, next: Next,
+) -> Response {
+    let shedder = LoadShedder::new(invoker.provider(), ShedPolicy::clone(&policy));
+    let permit = match shedder.admit(Priority::Normal).await {
+        Ok(Some(permit)) => permit,
+        Ok(None) => {
+            return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "1")], "service overloaded")
+                .into_response();
+        }
+        Err(error) => {
+            tracing::warn!(error = %error, "load check failed; serving request");
+            return next.run(request).await;
+        }
+    };
+
+    let response = next.run(request).await;
+    if let Err(error) = shedder.release(permit).await {
+        tracing::warn!(error = %error, "failed to release load permit");
+    }
+    response
+}
+
+/// The tenant resolved for a request, carried to the route in its extensions.
+#[derive(Clone)]
+struct ResolvedTenant(String);
+
+async fn resolve_tenant(
+    tenancy: Arc<Tenancy>, params: Option<RawPathParams>, mut request: Request, next: Next,
+) -> Response {
+    let headers = request.headers();
+    let tenant = tenancy.resolve(
+        |name| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_owned),
+        |name| {
+            params.as_ref()?.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_owned())
+        },
+    );
+    match tenant {
+        Ok(tenant) => {
+            request.extensions_mut().insert(ResolvedTenant(tenant));
+            next.run(request).await
+        }
+        Err(error) => HttpError::from(error).into_response(),
+    }
+}
+
+async fn audit_request(
+    redaction: Arc<Redaction>, max: usize, request: Request, next: Next,
+) -> Response {
+    let started = Instant::now();
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, max).await {
+        Ok(bytes) => bytes,
+        Err(error) if limit::exceeded(&error) => {
+            return limit::too_large();
+        }
+        Err(error) => {
+            return HttpError::from(invalid(format!("cannot read request body: {error}")))
+                .into_response();
+        }
+    };
+
+    let method = parts.method.clone();
+    let path = parts.uri.path().to_owned();
+    let headers = redaction.headers(&parts.headers);
+    let body = redaction.payload(&bytes);
+
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+    tracing::info!(
+        target: audit::TARGET,
+        method = %method,
+        path = %path,
+        headers = ?headers,
+        body = %body,
+        status = response.status().as_u16(),
+        elapsed_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+        "http request handled",
+    );
+    response
+}
+
 /// Consume a per-request router through the WASI HTTP export.
 ///
 /// Omnia creates one component instance per HTTP request, so callers should
@@ -286,9 +541,11 @@ where
             |State(invoker): State<Invoker<P>>,
              params: RawPathParams,
              RawQuery(query): RawQuery,
-             headers: HeaderMap| async move {
+             headers: HeaderMap,
+             tenant: Option<Extension<ResolvedTenant>>| async move {
                 let input = query_input::<O::Input>(&params, query.as_deref());
-                invoke::<O, P, J>(&invoker, headers, input, projector).await
+                let tenant = tenant.map(|Extension(ResolvedTenant(tenant))| tenant);
+                invoke::<O, P, J>(&invoker, headers, tenant, input, projector).await
             },
         ),
     }
@@ -322,16 +579,19 @@ where
             |State(invoker): State<Invoker<P>>,
              params: RawPathParams,
              headers: HeaderMap,
+             tenant: Option<Extension<ResolvedTenant>>,
              body: axum::body::Bytes| async move {
                 let input = body_input::<O::Input>(&params, &body);
-                invoke::<O, P, J>(&invoker, headers, input, projector).await
+                let tenant = tenant.map(|Extension(ResolvedTenant(tenant))| tenant);
+                invoke::<O, P, J>(&invoker, headers, tenant, input, projector).await
             },
         ),
     }
 }
 
 async fn invoke<O, P, J>(
-    invoker: &Invoker<P>, headers: HeaderMap, input: Result<O::Input, DecodeError>, projector: J,
+    invoker: &Invoker<P>, headers: HeaderMap, tenant: Option<String>,
+    input: Result<O::Input, DecodeError>, projector: J,
 ) -> Response
 where
     O: Operation<P>,
@@ -342,9 +602,10 @@ where
         Ok(input) => input,
         Err(error) => return projector.decode(error),
     };
-    let metadata = Metadata::from_lookup(|name| {
+    let mut metadata = Metadata::from_lookup(|name| {
         headers.get(format!("x-{name}")).and_then(|value| value.to_str().ok()).map(str::to_owned)
     });
+    metadata.tenant = tenant;
     match invoker.invoke::<O>(Invocation::new(input).metadata(metadata)).await {
         Ok(output) => projector.output(output),
         Err(error) => projector.error(error),
@@ -386,3 +647,35 @@ fn body_input<T: DeserializeOwned>(params: &RawPathParams, body: &[u8]) -> Resul
     }
     serde_json::from_value(value).map_err(|error| invalid(format!("invalid request body: {error}")))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use bytes::Bytes;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn audited(max: usize) -> AxumRouter {
+        let echo = post(|body: Bytes| async move { body.len().to_string() });
+        let redaction = Arc::new(Redaction::new());
+        AxumRouter::new().route("/upload", echo).layer(middleware::from_fn(
+            move |request: Request, next: Next| {
+                audit_request(Arc::clone(&redaction), max, request, next)
+            },
+        ))
+    }
+
+    fn upload(size: usize) -> Request {
+        Request::builder().method("POST").uri("/upload").body(Body::from(vec![0; size])).unwrap()
+    }
+
+    #[tokio::test]
+    async fn audit_buffers_bodies_up_to_its_limit() {
+        let response = audited(16).oneshot(upload(16)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = audited(16).oneshot(upload(17)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
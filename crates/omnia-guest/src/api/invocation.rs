@@ -14,6 +14,14 @@ pub struct Metadata {
 
     /// The latest instant at which the caller considers the work useful.
     pub deadline: Option<SystemTime>,
+
+    /// The tenant the invocation runs for, resolved by a router with tenancy
+    /// enabled.
+    pub tenant: Option<String>,
+
+    /// Transport headers of a messaging delivery, in delivery order. Empty
+    /// for other transports and never carried to child work.
+    pub headers: Vec<(String, String)>,
 }
 
 impl Metadata {
@@ -28,6 +36,8 @@ impl Metadata {
             request_id,
             causation_id: lookup("causation-id"),
             deadline: None,
+            tenant: None,
+            headers: Vec::new(),
         }
     }
 
@@ -41,10 +51,34 @@ impl Metadata {
             request_id: Some(request_id),
             causation_id: None,
             deadline: None,
+            tenant: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Metadata for work caused by this invocation, such as a published message.
+    ///
+    /// Mints a fresh request id, keeps the correlation id (falling back to this
+    /// request id), records this request id as the causation id, and carries
+    /// the deadline forward.
+    #[must_use]
+    pub fn child(&self) -> Self {
+        Self {
+            request_id: Some(mint_id()),
+            correlation_id: self.correlation_id.clone().or_else(|| self.request_id.clone()),
+            causation_id: self.request_id.clone(),
+            deadline: self.deadline,
+            tenant: self.tenant.clone(),
+            headers: Vec::new(),
         }
     }
 }
 
+/// A fresh random request id.
+pub(crate) fn mint_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
 /// One typed operation invocation.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Invocation<I> {
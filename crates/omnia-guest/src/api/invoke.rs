@@ -4,6 +4,7 @@ use std::sync::Arc;
 use crate::api::Provider;
 use crate::api::invocation::{Invocation, Metadata};
 use crate::api::operation::Operation;
+use crate::api::tenant::Tenant;
 
 /// Context shared with an operation call.
 #[derive(Clone, Copy, Debug)]
@@ -18,6 +19,16 @@ pub struct CallContext<'a, P: Provider> {
     pub metadata: &'a Metadata,
 }
 
+impl<'a, P: Provider> CallContext<'a, P> {
+    /// The provider scoped to the tenant resolved for this call, when the
+    /// router has tenancy enabled.
+    #[must_use]
+    pub fn tenant(&self) -> Option<Tenant<'a, P>> {
+        let id = self.metadata.tenant.as_deref()?;
+        Some(Tenant::new(id, self.provider))
+    }
+}
+
 /// Provider-owning operation invoker.
 ///
 /// Clones share one provider allocation. Transports define its lifetime; HTTP
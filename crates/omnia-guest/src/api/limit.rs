@@ -0,0 +1,109 @@
+//! Request body size limits.
+//!
+//! Enable a limit with [`http::Router::body_limit`](crate::api::http::Router::body_limit),
+//! or with [`BodyLimit::apply`] on an Axum router handed to
+//! `omnia_wasi_http::serve`. A request whose `Content-Length` exceeds the
+//! limit is answered with `413 Payload Too Large` before its body is read. A
+//! body sent without a length is cut off once it passes the limit, and the
+//! extractor reading it answers `413`, so an oversized upload is never
+//! buffered whole in linear memory.
+
+use axum::Router;
+use axum::body::Body;
+use axum::extract::{DefaultBodyLimit, Request};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::header::CONTENT_LENGTH;
+use http_body_util::{LengthLimitError, Limited};
+
+/// The largest request body a router accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BodyLimit {
+    max: usize,
+}
+
+impl BodyLimit {
+    /// Accept request bodies of at most `max` bytes.
+    #[must_use]
+    pub const fn new(max: usize) -> Self {
+        Self { max }
+    }
+
+    /// The largest body accepted, in bytes.
+    pub(crate) const fn max(self) -> usize {
+        self.max
+    }
+
+    /// Apply the limit to every route of `router`.
+    pub fn apply<S>(self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let max = self.max;
+        router
+            .layer(middleware::from_fn(move |request: Request, next: Next| {
+                limit(max, request, next)
+            }))
+            .layer(DefaultBodyLimit::max(max))
+    }
+}
+
+async fn limit(max: usize, request: Request, next: Next) -> Response {
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > max as u64) {
+        return too_large();
+    }
+    next.run(request.map(|body| Body::new(Limited::new(body, max)))).await
+}
+
+/// A `413 Payload Too Large` response.
+pub(crate) fn too_large() -> Response {
+    (StatusCode::PAYLOAD_TOO_LARGE, "request body is too large").into_response()
+}
+
+/// Whether reading a body failed because it passed its limit.
+pub(crate) fn exceeded(error: &(dyn std::error::Error + 'static)) -> bool {
+    std::iter::successors(Some(error), |error| error.source())
+        .any(<dyn std::error::Error>::is::<LengthLimitError>)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use bytes::Bytes;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn router() -> Router {
+        let echo = post(|body: Bytes| async move { body.len().to_string() });
+        BodyLimit::new(16).apply(Router::new().route("/upload", echo))
+    }
+
+    fn upload() -> http::request::Builder {
+        Request::builder().method("POST").uri("/upload")
+    }
+
+    #[tokio::test]
+    async fn rejects_declared_length_over_limit() {
+        let request = upload().header(CONTENT_LENGTH, "17").body(Body::empty()).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn cuts_off_undeclared_bodies() {
+        let request = upload().body(Body::from(vec![0; 17])).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let request = upload().body(Body::from(vec![0; 16])).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,157 @@
+//! Message lineage across consume, handle, and publish.
+//!
+//! A message published while handling a delivery carries that delivery's
+//! lineage: a fresh `request-id`, the same `correlation-id`, and the
+//! delivery's request id as its `causation-id`. The messaging router reads the
+//! same names back into [`Metadata`], so the chain continues downstream.
+//!
+//! [`publish`] also records each message against its cause for
+//! [`LINEAGE_TTL_SECS`], and [`produced`] lists them, to find where an update
+//! went when it never arrives at the end of a pipeline.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::api::Metadata;
+use crate::api::tenant::TENANT_HEADER;
+use crate::{Message, Publish, StateStore};
+
+/// How long produced-message records are kept, in seconds.
+pub const LINEAGE_TTL_SECS: u64 = 3600;
+
+const KEY_PREFIX: &str = "lineage:";
+
+/// A message published while handling an input.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Produced {
+    /// The topic the message was published to.
+    pub topic: String,
+
+    /// The request id stamped on the message.
+    pub request_id: String,
+}
+
+/// Stamp `message` with lineage caused by `cause`, returning the metadata it now carries.
+pub fn stamp(message: &mut Message, cause: &Metadata) -> Metadata {
+    let lineage = cause.child();
+    let headers = [
+        ("request-id", &lineage.request_id),
+        ("correlation-id", &lineage.correlation_id),
+        ("causation-id", &lineage.causation_id),
+        (TENANT_HEADER, &lineage.tenant),
+    ];
+    for (name, value) in headers {
+        if let Some(value) = value {
+            message.headers.insert(name.to_string(), value.clone());
+        }
+    }
+    lineage
+}
+
+/// Publish `message` to `topic` as an output of `cause` and record it for [`produced`].
+///
+/// # Errors
+///
+/// Returns an error if publishing the message or recording its lineage fails.
+pub async fn publish<P>(
+    provider: &P, cause: &Metadata, topic: &str, mut message: Message,
+) -> Result<Metadata>
+where
+    P: Publish + StateStore,
+{
+    let lineage = stamp(&mut message, cause);
+    provider.send(topic, &message).await?;
+    tracing::debug!(
+        topic,
+        request_id = lineage.request_id.as_deref(),
+        correlation_id = lineage.correlation_id.as_deref(),
+        causation_id = lineage.causation_id.as_deref(),
+        "message published",
+    );
+
+    if let (Some(cause_id), Some(request_id)) = (&cause.request_id, &lineage.request_id) {
+        let mut records = produced(provider, cause_id).await?;
+        records.push(Produced {
+            topic: topic.to_string(),
+            request_id: request_id.clone(),
+        });
+        let key = format!("{KEY_PREFIX}{cause_id}");
+        provider.set(&key, &serde_json::to_vec(&records)?, Some(LINEAGE_TTL_SECS)).await?;
+    }
+    Ok(lineage)
+}
+
+/// The messages published while handling the input with `request_id`, in publish order.
+///
+/// Records expire after [`LINEAGE_TTL_SECS`]; an unknown or expired input has none.
+///
+/// # Errors
+///
+/// Returns an error if the state store fails or holds an unreadable record.
+pub async fn produced<P: StateStore>(provider: &P, request_id: &str) -> Result<Vec<Produced>> {
+    let Some(bytes) = provider.get(&format!("{KEY_PREFIX}{request_id}")).await? else {
+        return Ok(Vec::new());
+    };
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Broker {
+        state: Mutex<HashMap<String, Vec<u8>>>,
+        sent: Mutex<Vec<(String, Message)>>,
+    }
+
+    impl StateStore for Broker {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.state.lock().unwrap().get(key).cloned())
+        }
+
+        async fn set(
+            &self, key: &str, value: &[u8], _ttl_secs: Option<u64>,
+        ) -> Result<Option<Vec<u8>>> {
+            Ok(self.state.lock().unwrap().insert(key.to_string(), value.to_vec()))
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.state.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    impl Publish for Broker {
+        async fn send(&self, topic: &str, message: &Message) -> Result<()> {
+            self.sent.lock().unwrap().push((topic.to_string(), message.clone()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn published_messages_carry_and_record_lineage() {
+        let provider = Broker::default();
+        let cause = Metadata::from_lookup(|name| match name {
+            "request-id" => Some("input-1".to_string()),
+            "correlation-id" => Some("trace-1".to_string()),
+            _ => None,
+        });
+
+        let first = publish(&provider, &cause, "trips.updated", Message::new(b"{}")).await.unwrap();
+        publish(&provider, &cause, "trips.audit", Message::new(b"{}")).await.unwrap();
+
+        let headers = provider.sent.lock().unwrap()[0].1.headers.clone();
+        assert_eq!(headers["correlation-id"], "trace-1");
+        assert_eq!(headers["causation-id"], "input-1");
+        assert_eq!(Some(&headers["request-id"]), first.request_id.as_ref());
+
+        let records = produced(&provider, "input-1").await.unwrap();
+        let topics: Vec<_> = records.iter().map(|record| record.topic.as_str()).collect();
+        assert_eq!(topics, ["trips.updated", "trips.audit"]);
+        assert_eq!(produced(&provider, "input-2").await.unwrap(), Vec::<Produced>::new());
+    }
+}
@@ -0,0 +1,209 @@
+//! Invoke HTTP routes and messaging handlers from a native command line.
+//!
+//! A [`Harness`] wraps the same routers a guest serves through its WASI
+//! exports, built over a native provider (typically the in-memory mocks used
+//! by tests), and drives one request or delivery per process run. A small
+//! binary target gated on `not(target_arch = "wasm32")` is enough to exercise
+//! handlers without building and deploying a component:
+//!
+//! ```text
+//! cargo run --bin local -- http POST /api/items '{"name":"widget"}'
+//! cargo run --bin local -- message orders.created @fixtures/order.json
+//! ```
+
+use std::ffi::OsString;
+use std::future::poll_fn;
+
+use axum::body::{Body, to_bytes};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use http::{HeaderName, HeaderValue, Method, Request};
+use tower::Service;
+
+use crate::api::Provider;
+use crate::api::command::CommandResponse;
+use crate::api::http::Router as HttpRouter;
+use crate::api::messaging::{Delivery, Router as MessagingRouter};
+
+/// A command-line driver over a guest's HTTP and messaging routers.
+pub struct Harness<P: Provider> {
+    http: Option<HttpRouter<P>>,
+    messaging: Option<MessagingRouter<P>>,
+}
+
+impl<P: Provider> Default for Harness<P> {
+    fn default() -> Self {
+        Self {
+            http: None,
+            messaging: None,
+        }
+    }
+}
+
+impl<P: Provider> Harness<P> {
+    /// Create a harness with no routers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the `http` subcommand from `router`.
+    #[must_use]
+    pub fn http(mut self, router: HttpRouter<P>) -> Self {
+        self.http = Some(router);
+        self
+    }
+
+    /// Serve the `message` subcommand from `router`.
+    #[must_use]
+    pub fn messaging(mut self, router: MessagingRouter<P>) -> Self {
+        self.messaging = Some(router);
+        self
+    }
+
+    /// Parse and execute one argument vector.
+    ///
+    /// `http METHOD PATH [BODY] [-H name:value]...` sends one request and
+    /// prints the response status and body; any non-2xx status exits 1.
+    /// `message TOPIC [PAYLOAD] [-m name=value]...` delivers one message and
+    /// prints `ack`, or the delivery failure with exit status 1. A body or
+    /// payload of the form `@path` is read from that file.
+    pub async fn execute<I, T>(self, argv: I) -> CommandResponse
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = match command().try_get_matches_from(argv) {
+            Ok(matches) => matches,
+            Err(error) => {
+                let exit = u8::try_from(error.exit_code()).unwrap_or(1);
+                let rendered = error.render().to_string();
+                return if error.use_stderr() {
+                    CommandResponse::failure(rendered, exit)
+                } else {
+                    CommandResponse::success(rendered)
+                };
+            }
+        };
+        let result = match matches.subcommand() {
+            Some(("http", sub_matches)) => match self.http {
+                Some(router) => http(router, sub_matches).await,
+                None => Err("no HTTP router is registered".to_string()),
+            },
+            Some(("message", sub_matches)) => match &self.messaging {
+                Some(router) => message(router, sub_matches).await,
+                None => Err("no messaging router is registered".to_string()),
+            },
+            _ => Err("a subcommand is required".to_string()),
+        };
+        result.unwrap_or_else(|error| CommandResponse::failure(format!("{error}\n"), 1))
+    }
+}
+
+fn command() -> Command {
+    Command::new("local")
+        .about("Invoke guest handlers against native providers")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("http")
+                .about("Send one HTTP request")
+                .arg(Arg::new("method").required(true))
+                .arg(Arg::new("path").required(true))
+                .arg(Arg::new("body"))
+                .arg(
+                    Arg::new("header")
+                        .short('H')
+                        .long("header")
+                        .value_name("NAME:VALUE")
+                        .action(ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("message")
+                .about("Deliver one message")
+                .arg(Arg::new("topic").required(true))
+                .arg(Arg::new("payload"))
+                .arg(
+                    Arg::new("metadata")
+                        .short('m')
+                        .long("metadata")
+                        .value_name("NAME=VALUE")
+                        .action(ArgAction::Append),
+                ),
+        )
+}
+
+async fn http<P: Provider>(
+    router: HttpRouter<P>, args: &ArgMatches,
+) -> Result<CommandResponse, String> {
+    let method = arg(args, "method").unwrap_or_default();
+    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|error| format!("invalid method: {error}"))?;
+    let body = payload(args, "body")?;
+
+    let mut request = Request::builder().method(method).uri(arg(args, "path").unwrap_or_default());
+    if !body.is_empty() {
+        request = request.header(http::header::CONTENT_TYPE, "application/json");
+    }
+    for header in args.get_many::<String>("header").into_iter().flatten() {
+        let (name, value) =
+            header.split_once(':').ok_or_else(|| format!("invalid header `{header}`"))?;
+        let name = HeaderName::try_from(name.trim()).map_err(|error| error.to_string())?;
+        let value = HeaderValue::try_from(value.trim()).map_err(|error| error.to_string())?;
+        request = request.header(name, value);
+    }
+    let request = request.body(Body::from(body)).map_err(|error| error.to_string())?;
+
+    let mut service = router.into_axum();
+    let Ok(()) = poll_fn(|cx| Service::<Request<Body>>::poll_ready(&mut service, cx)).await;
+    let Ok(response) = service.call(request).await;
+
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|error| format!("cannot read response body: {error}"))?;
+    let mut stdout = format!("{status}\n").into_bytes();
+    stdout.extend_from_slice(&bytes);
+    if !bytes.ends_with(b"\n") {
+        stdout.push(b'\n');
+    }
+    Ok(CommandResponse {
+        stdout,
+        stderr: Vec::new(),
+        exit: u8::from(!status.is_success()),
+    })
+}
+
+async fn message<P: Provider>(
+    router: &MessagingRouter<P>, args: &ArgMatches,
+) -> Result<CommandResponse, String> {
+    let mut metadata = Vec::new();
+    for entry in args.get_many::<String>("metadata").into_iter().flatten() {
+        let (name, value) =
+            entry.split_once('=').ok_or_else(|| format!("invalid metadata `{entry}`"))?;
+        metadata.push((name.to_owned(), value.to_owned()));
+    }
+    let delivery = Delivery {
+        topic: arg(args, "topic").map(str::to_owned),
+        payload: payload(args, "payload")?,
+        content_type: Some("application/json".to_owned()),
+        metadata,
+    };
+    router.handle(delivery).await.map_err(|error| error.to_string())?;
+    Ok(CommandResponse::success("ack\n"))
+}
+
+fn arg<'a>(args: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    args.get_one::<String>(name).map(String::as_str)
+}
+
+fn payload(args: &ArgMatches, name: &str) -> Result<Vec<u8>, String> {
+    arg(args, name).map_or_else(
+        || Ok(Vec::new()),
+        |value| {
+            value.strip_prefix('@').map_or_else(
+                || Ok(value.as_bytes().to_vec()),
+                |path| std::fs::read(path).map_err(|error| format!("cannot read {path}: {error}")),
+            )
+        },
+    )
+}
@@ -10,11 +10,14 @@ use std::pin::Pin;
 use std::sync::Arc;
 
 use serde::de::DeserializeOwned;
+use tracing::Instrument;
 
 use crate::api::Provider;
-use crate::api::invocation::{Invocation, Metadata};
+use crate::api::audit::{self, Redaction};
+use crate::api::invocation::{Invocation, Metadata, mint_id};
 use crate::api::invoke::Invoker;
 use crate::api::operation::Operation;
+use crate::api::tenant::Tenancy;
 
 /// An owned inbound delivery independent of a messaging binding.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -80,6 +83,9 @@ pub enum DeliveryError {
     UnhandledTopic(String),
     /// Application-local projection rejected the delivery.
     Rejected(String),
+    /// Application-local projection asked for the delivery to be redelivered
+    /// later, without the host retrying or dead-lettering it now.
+    Requeue(String),
 }
 
 impl fmt::Display for DeliveryError {
@@ -87,7 +93,7 @@ impl fmt::Display for DeliveryError {
         match self {
             Self::MissingTopic => f.write_str("message is missing topic"),
             Self::UnhandledTopic(topic) => write!(f, "unhandled topic: {topic}"),
-            Self::Rejected(error) => f.write_str(error),
+            Self::Rejected(error) | Self::Requeue(error) => f.write_str(error),
         }
     }
 }
@@ -169,7 +175,7 @@ type DispatchFuture<'a> = Pin<Box<dyn Future<Output = Result<(), DeliveryError>>
 trait ErasedRoute<P: Provider>: Send + Sync {
     fn operation(&self) -> TypeId;
     fn dispatch<'a>(
-        &'a self, delivery: &'a Delivery, invoker: &'a Invoker<P>,
+        &'a self, delivery: &'a Delivery, invoker: &'a Invoker<P>, tenant: Option<String>,
     ) -> DispatchFuture<'a>;
 }
 
@@ -191,22 +197,32 @@ where
     }
 
     fn dispatch<'a>(
-        &'a self, delivery: &'a Delivery, invoker: &'a Invoker<P>,
+        &'a self, delivery: &'a Delivery, invoker: &'a Invoker<P>, tenant: Option<String>,
     ) -> DispatchFuture<'a> {
         Box::pin(async move {
             let input = match self.decoder.decode(delivery) {
                 Ok(input) => input,
                 Err(error) => return self.projector.project(Outcome::Decode(error)),
             };
-            let metadata = Metadata::from_lookup(|name| {
-                delivery
-                    .metadata
-                    .iter()
-                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
-                    .map(|(_, value)| value.clone())
-            });
-            let outcome = match invoker.invoke::<O>(Invocation::new(input).metadata(metadata)).await
-            {
+            let mut metadata = Metadata::from_lookup(|name| metadata_value(delivery, name));
+            // A delivery without a request id still gets one, so messages it
+            // causes can name it as their cause.
+            if metadata.request_id.is_none() {
+                let minted = Metadata::minted(mint_id());
+                metadata.correlation_id = metadata.correlation_id.or(minted.correlation_id);
+                metadata.request_id = minted.request_id;
+            }
+            metadata.tenant = tenant;
+            metadata.headers.clone_from(&delivery.metadata);
+            let span = tracing::info_span!(
+                "message.handle",
+                topic = delivery.topic.as_deref().unwrap_or_default(),
+                request_id = metadata.request_id.as_deref(),
+                correlation_id = metadata.correlation_id.as_deref(),
+                causation_id = metadata.causation_id.as_deref(),
+            );
+            let invocation = Invocation::new(input).metadata(metadata);
+            let outcome = match invoker.invoke::<O>(invocation).instrument(span).await {
                 Ok(output) => Outcome::Output(output),
                 Err(error) => Outcome::Operation(error),
             };
@@ -241,6 +257,8 @@ pub struct Router<P: Provider> {
     invoker: Invoker<P>,
     routes: BTreeMap<String, Arc<dyn ErasedRoute<P>>>,
     inventory: Vec<RouteInfo>,
+    audit: Option<Redaction>,
+    tenancy: Option<Tenancy>,
 }
 
 impl<P: Provider> Router<P> {
@@ -251,9 +269,27 @@ impl<P: Provider> Router<P> {
             invoker,
             routes: BTreeMap::new(),
             inventory: Vec::new(),
+            audit: None,
+            tenancy: None,
         }
     }
 
+    /// Log every handled delivery, masking fields selected by `redaction`.
+    #[must_use]
+    pub fn audit(mut self, redaction: Redaction) -> Self {
+        self.audit = Some(redaction);
+        self
+    }
+
+    /// Resolve a tenant for every delivery through `tenancy`, rejecting
+    /// deliveries whose id is missing or not allowed. Operations see the id as
+    /// [`Metadata::tenant`].
+    #[must_use]
+    pub fn tenants(mut self, tenancy: Tenancy) -> Self {
+        self.tenancy = Some(tenancy);
+        self
+    }
+
     /// Register one operation for one exact topic.
     ///
     /// # Panics
@@ -295,20 +331,51 @@ impl<P: Provider> Router<P> {
     /// Returns missing-topic, unhandled-topic, decoding, operation, or
     /// application-local projection failures.
     pub async fn handle(&self, delivery: Delivery) -> Result<(), DeliveryError> {
+        let result = self.dispatch(&delivery).await;
+        if let Some(redaction) = &self.audit {
+            tracing::info!(
+                target: audit::TARGET,
+                topic = delivery.topic.as_deref().unwrap_or_default(),
+                metadata = ?redaction.metadata(&delivery.metadata),
+                payload = %redaction.payload(&delivery.payload),
+                outcome = %result.as_ref().err().map_or_else(|| "ack".to_string(), ToString::to_string),
+                "message handled",
+            );
+        }
+        result
+    }
+
+    async fn dispatch(&self, delivery: &Delivery) -> Result<(), DeliveryError> {
         let topic = delivery.topic.as_deref().ok_or(DeliveryError::MissingTopic)?;
         let route = self
             .routes
             .get(topic)
             .ok_or_else(|| DeliveryError::UnhandledTopic(topic.to_owned()))?;
-        route.dispatch(&delivery, &self.invoker).await
+        let tenant = match &self.tenancy {
+            Some(tenancy) => Some(
+                tenancy
+                    .resolve(|name| metadata_value(delivery, name), |_| None)
+                    .map_err(|error| DeliveryError::Rejected(error.to_string()))?,
+            ),
+            None => None,
+        };
+        route.dispatch(delivery, &self.invoker, tenant).await
     }
 }
 
+fn metadata_value(delivery: &Delivery, name: &str) -> Option<String> {
+    delivery
+        .metadata
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
 /// Adapt a WIT message to an owned delivery and dispatch it.
 ///
-/// The current WIT contract carries only `result<_, error>`: success
-/// acknowledges the delivery, while every router failure is returned as
-/// `error.other` for the host to interpret.
+/// Success acknowledges the delivery. A [`DeliveryError::Requeue`] rejects it
+/// for redelivery with `nack(true)`; every router failure is returned as
+/// `error.other` for the host to retry or dead-letter.
 ///
 /// # Errors
 ///
@@ -323,8 +390,9 @@ pub async fn handle<P: Provider>(
         content_type: message.content_type(),
         metadata: message.metadata().unwrap_or_default(),
     };
-    router
-        .handle(delivery)
-        .await
-        .map_err(|error| omnia_wasi_messaging::types::Error::Other(error.to_string()))
+    let result = router.handle(delivery).await;
+    if let Err(DeliveryError::Requeue(_)) = &result {
+        omnia_wasi_messaging::settlement::nack(&message, true)?;
+    }
+    result.map_err(|error| omnia_wasi_messaging::types::Error::Other(error.to_string()))
 }
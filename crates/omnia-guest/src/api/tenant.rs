@@ -0,0 +1,356 @@
+//! Multi-tenant routing.
+//!
+//! Enable tenancy with [`http::Router::tenants`](crate::api::http::Router::tenants)
+//! or [`messaging::Router::tenants`](crate::api::messaging::Router::tenants).
+//! The router resolves a tenant id for every request or delivery from the
+//! configured [`TenantSource`], rejects it when the id is missing or not
+//! allowed, and records the id in [`Metadata::tenant`](crate::api::Metadata).
+//! Operations then reach tenant-scoped capabilities through
+//! [`CallContext::tenant`](crate::api::CallContext::tenant):
+//!
+//! ```rust,ignore
+//! let tenant = context.tenant().ok_or_else(|| bad_request!("no tenant"))?;
+//! tenant.set("vehicle:42", &bytes, None).await?; // stored as `acme:vehicle:42`
+//! tenant.send("positions", &message).await?; // published to `acme.positions`
+//! let feed = Config::get(&tenant, "FEED_URL").await?; // `ACME__FEED_URL`, then `FEED_URL`
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use base64ct::{Base64UrlUnpadded, Encoding};
+
+use crate::migrate::Migrations;
+use crate::{Config, Message, Publish, StateStore};
+
+/// Metadata name carrying the tenant id on published messages.
+pub const TENANT_HEADER: &str = "tenant-id";
+
+/// Where a router reads the tenant id from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TenantSource {
+    /// A request header, or a delivery metadata entry (case-insensitive).
+    Header(String),
+    /// A path parameter of the matched HTTP route. Deliveries have none.
+    PathParam(String),
+    /// A string claim of the bearer token in the `authorization` header or
+    /// metadata entry. The token is decoded, not verified: verify it before
+    /// it reaches the guest, at the gateway or host.
+    Claim(String),
+}
+
+/// How a router resolves and admits tenants.
+#[derive(Clone, Debug)]
+pub struct Tenancy {
+    source: TenantSource,
+    allowed: Option<BTreeSet<String>>,
+}
+
+impl Tenancy {
+    /// Resolve the tenant id from `source`, admitting any tenant.
+    #[must_use]
+    pub const fn new(source: TenantSource) -> Self {
+        Self {
+            source,
+            allowed: None,
+        }
+    }
+
+    /// Resolve the tenant id from the header or metadata entry `name`.
+    #[must_use]
+    pub fn header(name: impl Into<String>) -> Self {
+        Self::new(TenantSource::Header(name.into()))
+    }
+
+    /// Resolve the tenant id from the path parameter `name`.
+    #[must_use]
+    pub fn path_param(name: impl Into<String>) -> Self {
+        Self::new(TenantSource::PathParam(name.into()))
+    }
+
+    /// Resolve the tenant id from the bearer-token claim `name`.
+    #[must_use]
+    pub fn claim(name: impl Into<String>) -> Self {
+        Self::new(TenantSource::Claim(name.into()))
+    }
+
+    /// Admit only the listed tenants; any other id is answered as not found.
+    #[must_use]
+    pub fn allow<I, T>(mut self, tenants: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.allowed = Some(tenants.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Resolve the tenant id through a header or metadata `lookup` and a path
+    /// parameter lookup.
+    pub(crate) fn resolve(
+        &self, lookup: impl Fn(&str) -> Option<String>, param: impl Fn(&str) -> Option<String>,
+    ) -> Result<String, crate::Error> {
+        let tenant = match &self.source {
+            TenantSource::Header(name) => lookup(name),
+            TenantSource::PathParam(name) => param(name),
+            TenantSource::Claim(name) => {
+                lookup("authorization").and_then(|auth| claim(&auth, name))
+            }
+        };
+        let Some(tenant) = tenant.filter(|tenant| !tenant.is_empty()) else {
+            return Err(crate::Error::BadRequest {
+                code: "missing_tenant".to_string(),
+                description: format!("no tenant id in {}", self.describe()),
+            });
+        };
+        if !valid_id(&tenant) {
+            return Err(crate::Error::BadRequest {
+                code: "invalid_tenant".to_string(),
+                description: format!(
+                    "tenant id in {} must be lowercase letters, digits, and single inner hyphens",
+                    self.describe()
+                ),
+            });
+        }
+        if self.allowed.as_ref().is_some_and(|allowed| !allowed.contains(&tenant)) {
+            return Err(crate::Error::NotFound {
+                code: "unknown_tenant".to_string(),
+                description: format!("unknown tenant `{tenant}`"),
+            });
+        }
+        Ok(tenant)
+    }
+
+    fn describe(&self) -> String {
+        match &self.source {
+            TenantSource::Header(name) => format!("header `{name}`"),
+            TenantSource::PathParam(name) => format!("path parameter `{name}`"),
+            TenantSource::Claim(name) => format!("token claim `{name}`"),
+        }
+    }
+}
+
+/// Whether `id` is a tenant id: up to 63 lowercase letters and digits, with
+/// single hyphens between them. Such an id contains none of the separators
+/// keys, topics, and config keys are built with, so scoping is injective.
+fn valid_id(id: &str) -> bool {
+    id.len() <= 63
+        && id.split('-').all(|part| {
+            !part.is_empty() && part.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        })
+}
+
+fn claim(authorization: &str, name: &str) -> Option<String> {
+    let token = authorization.strip_prefix("Bearer ")?;
+    let payload = token.split('.').nth(1)?;
+    let bytes = Base64UrlUnpadded::decode_vec(payload).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    claims.get(name)?.as_str().map(str::to_owned)
+}
+
+/// A provider scoped to one tenant.
+///
+/// State keys are prefixed with `{tenant}:` and topics with `{tenant}.`, and
+/// published messages carry the tenant id in their [`TENANT_HEADER`] entry.
+/// Configuration resolves `{TENANT}__{key}` (the id upper-cased, with `-` as
+/// `_`) and falls back to the shared `key`. Tenant ids never contain `:`, `.`,
+/// or a double `_` once upper-cased, so no two tenants share a key, topic, or
+/// config key.
+#[derive(Debug)]
+pub struct Tenant<'a, P> {
+    id: &'a str,
+    provider: &'a P,
+}
+
+impl<'a, P> Tenant<'a, P> {
+    /// Scope `provider` to the tenant `id`, which routers have checked is
+    /// lowercase letters, digits, and single inner hyphens.
+    pub const fn new(id: &'a str, provider: &'a P) -> Self {
+        Self { id, provider }
+    }
+
+    /// The tenant id.
+    #[must_use]
+    pub const fn id(&self) -> &str {
+        self.id
+    }
+
+    /// The unscoped provider, for data shared by every tenant.
+    #[must_use]
+    pub const fn provider(&self) -> &P {
+        self.provider
+    }
+
+    /// The state-store key `key` maps to for this tenant.
+    #[must_use]
+    pub fn key(&self, key: &str) -> String {
+        format!("{}:{key}", self.id)
+    }
+
+    /// The topic `topic` maps to for this tenant.
+    #[must_use]
+    pub fn topic(&self, topic: &str) -> String {
+        format!("{}.{topic}", self.id)
+    }
+
+    /// The tenant-specific configuration key for `key`.
+    #[must_use]
+    pub fn config_key(&self, key: &str) -> String {
+        format!("{}__{key}", self.id.to_ascii_uppercase().replace('-', "_"))
+    }
+}
+
+impl<P: StateStore> StateStore for Tenant<'_, P> {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.provider.get(&self.key(key)).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl_secs: Option<u64>) -> Result<Option<Vec<u8>>> {
+        self.provider.set(&self.key(key), value, ttl_secs).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.provider.delete(&self.key(key)).await
+    }
+
+    async fn get_del(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.provider.get_del(&self.key(key)).await
+    }
+
+    async fn get_ex(&self, key: &str, ttl_secs: u64) -> Result<Option<Vec<u8>>> {
+        self.provider.get_ex(&self.key(key), ttl_secs).await
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64> {
+        self.provider.increment(&self.key(key), delta).await
+    }
+
+    async fn compare_and_swap(
+        &self, key: &str, expected: Option<&[u8]>, new: &[u8],
+    ) -> Result<bool> {
+        self.provider.compare_and_swap(&self.key(key), expected, new).await
+    }
+
+    async fn bf_add(&self, filter: &str, item: &[u8]) -> Result<bool> {
+        self.provider.bf_add(&self.key(filter), item).await
+    }
+
+    async fn bf_exists(&self, filter: &str, item: &[u8]) -> Result<bool> {
+        self.provider.bf_exists(&self.key(filter), item).await
+    }
+
+    async fn bf_reserve(&self, filter: &str, capacity: u64, error_rate: f64) -> Result<()> {
+        self.provider.bf_reserve(&self.key(filter), capacity, error_rate).await
+    }
+
+    async fn get_versioned(&self, key: &str, migrations: &Migrations) -> Result<Option<Vec<u8>>> {
+        self.provider.get_versioned(&self.key(key), migrations).await
+    }
+
+    async fn set_versioned(
+        &self, key: &str, value: &[u8], ttl_secs: Option<u64>, migrations: &Migrations,
+    ) -> Result<()> {
+        self.provider.set_versioned(&self.key(key), value, ttl_secs, migrations).await
+    }
+
+    async fn hash_set(&self, key: &str, fields: &[(&str, &[u8])]) -> Result<u32> {
+        self.provider.hash_set(&self.key(key), fields).await
+    }
+
+    async fn hash_get(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        self.provider.hash_get(&self.key(key), field).await
+    }
+
+    async fn hash_get_all(&self, key: &str) -> Result<BTreeMap<String, Vec<u8>>> {
+        self.provider.hash_get_all(&self.key(key)).await
+    }
+
+    async fn hash_delete(&self, key: &str, fields: &[&str]) -> Result<u32> {
+        self.provider.hash_delete(&self.key(key), fields).await
+    }
+}
+
+impl<P: Publish> Publish for Tenant<'_, P> {
+    async fn send(&self, topic: &str, message: &Message) -> Result<()> {
+        let mut message = message.clone();
+        message.headers.insert(TENANT_HEADER.to_string(), self.id.to_string());
+        self.provider.send(&self.topic(topic), &message).await
+    }
+
+    async fn send_batch(&self, topic: &str, messages: &[Message]) -> Result<()> {
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                let mut message = message.clone();
+                message.headers.insert(TENANT_HEADER.to_string(), self.id.to_string());
+                message
+            })
+            .collect();
+        self.provider.send_batch(&self.topic(topic), &messages).await
+    }
+}
+
+impl<P: Config> Config for Tenant<'_, P> {
+    async fn get(&self, key: &str) -> Result<String> {
+        match self.provider.get(&self.config_key(key)).await {
+            Ok(value) => Ok(value),
+            Err(_) => self.provider.get(key).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_from_claims() {
+        let payload = Base64UrlUnpadded::encode_string(br#"{"tid":"acme","sub":"u1"}"#);
+        let auth = format!("Bearer e30.{payload}.sig");
+        let tenancy = Tenancy::claim("tid").allow(["acme"]);
+
+        let tenant =
+            tenancy.resolve(|name| (name == "authorization").then(|| auth.clone()), |_| None);
+        assert_eq!(tenant.unwrap(), "acme");
+
+        let missing = tenancy.resolve(|_| None, |_| None).unwrap_err();
+        assert_eq!(missing.code(), "missing_tenant");
+    }
+
+    #[test]
+    fn rejects_tenants_not_allowed() {
+        let tenancy = Tenancy::header("x-tenant").allow(["acme"]);
+
+        let error = tenancy.resolve(|_| Some("globex".to_string()), |_| None).unwrap_err();
+        assert_eq!(error.code(), "unknown_tenant");
+    }
+
+    #[test]
+    fn namespaces_keys_topics_and_config() {
+        let tenant = Tenant::new("metro-north", &());
+
+        assert_eq!(tenant.key("vehicle:7"), "metro-north:vehicle:7");
+        assert_eq!(tenant.topic("positions"), "metro-north.positions");
+        assert_eq!(tenant.config_key("FEED_URL"), "METRO_NORTH__FEED_URL");
+    }
+
+    #[test]
+    fn rejects_separator_injection() {
+        let tenancy = Tenancy::header("x-tenant");
+        for id in ["acme:vehicle", "acme.positions", "ACME", "acme_a", "acme--a", "-acme", "acme-"]
+        {
+            let error = tenancy.resolve(|_| Some(id.to_string()), |_| None).unwrap_err();
+            assert_eq!(error.code(), "invalid_tenant", "{id}");
+        }
+        assert_eq!(tenancy.resolve(|_| Some("acme-a".to_string()), |_| None).unwrap(), "acme-a");
+    }
+
+    #[test]
+    fn scoped_names_do_not_collide() {
+        let (hyphenated, plain) = (Tenant::new("acme-a", &()), Tenant::new("acme", &()));
+
+        assert_ne!(hyphenated.config_key("FEED"), plain.config_key("A_FEED"));
+        assert_ne!(hyphenated.key("x"), plain.key("a:x"));
+        assert_ne!(Tenant::new("a-b", &()).topic("c"), Tenant::new("a", &()).topic("b.c"));
+    }
+}
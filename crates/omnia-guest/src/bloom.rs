@@ -0,0 +1,164 @@
+//! Approximate set membership stored as a single key-value entry.
+//!
+//! A [`BloomFilter`] answers "have I seen this id?" with no false negatives
+//! and a bounded false-positive rate, in a fixed number of bits regardless of
+//! how many items are added. [`StateStore::bf_add`](crate::StateStore::bf_add)
+//! and [`StateStore::bf_exists`](crate::StateStore::bf_exists) persist filters
+//! in the state store, so deduplicating millions of message ids costs one
+//! entry rather than one key per id.
+
+use std::f64::consts::LN_2;
+
+use anyhow::{Result, ensure};
+
+/// Expected items in a filter created implicitly by the first `bf_add`.
+pub const DEFAULT_CAPACITY: u64 = 100_000;
+
+/// False-positive rate of a filter created implicitly by the first `bf_add`.
+pub const DEFAULT_ERROR_RATE: f64 = 0.01;
+
+const HEADER_LEN: usize = 12;
+const MAX_HASHES: u32 = 30;
+
+/// A fixed-size Bloom filter with a stable, serializable layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    hashes: u32,
+    num_bits: u64,
+    bits: Vec<u8>,
+}
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_ERROR_RATE)
+    }
+}
+
+impl BloomFilter {
+    /// Size a filter for `capacity` items at the given false-positive rate.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+    pub fn new(capacity: u64, error_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let error_rate = error_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let num_bits = (-capacity * error_rate.ln() / (LN_2 * LN_2)).ceil().max(8.0) as u64;
+        let hashes =
+            ((num_bits as f64 / capacity) * LN_2).round().clamp(1.0, f64::from(MAX_HASHES));
+        Self {
+            hashes: hashes as u32,
+            num_bits,
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+        }
+    }
+
+    /// Add `item`, returning `true` if it was not already (probably) present.
+    pub fn insert(&mut self, item: &[u8]) -> bool {
+        let mut added = false;
+        for bit in self.positions(item) {
+            let (byte, mask) = locate(bit);
+            added |= self.bits[byte] & mask == 0;
+            self.bits[byte] |= mask;
+        }
+        added
+    }
+
+    /// Whether `item` may have been added; `false` means it definitely was not.
+    #[must_use]
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.positions(item).all(|bit| {
+            let (byte, mask) = locate(bit);
+            self.bits[byte] & mask != 0
+        })
+    }
+
+    /// Serialize the filter for storage.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.bits.len());
+        bytes.extend_from_slice(&self.hashes.to_be_bytes());
+        bytes.extend_from_slice(&self.num_bits.to_be_bytes());
+        bytes.extend_from_slice(&self.bits);
+        bytes
+    }
+
+    /// Restore a filter written by [`Self::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` is not a serialized filter.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() >= HEADER_LEN, "bloom filter header truncated");
+        let (header, bits) = bytes.split_at(HEADER_LEN);
+        let (hashes, num_bits) = header.split_at(4);
+        let hashes = u32::from_be_bytes(hashes.try_into()?);
+        let num_bits = u64::from_be_bytes(num_bits.try_into()?);
+        ensure!(
+            (1..=MAX_HASHES).contains(&hashes) && num_bits.div_ceil(8) == bits.len() as u64,
+            "bloom filter layout does not match its header"
+        );
+        Ok(Self {
+            hashes,
+            num_bits,
+            bits: bits.to_vec(),
+        })
+    }
+
+    /// Bit positions for `item` by double hashing (Kirsch–Mitzenmacher).
+    fn positions(&self, item: &[u8]) -> impl Iterator<Item = u64> + use<> {
+        let h1 = fnv1a(item, 0xcbf2_9ce4_8422_2325);
+        let h2 = fnv1a(item, 0x8422_2325_cbf2_9ce4) | 1;
+        let num_bits = self.num_bits;
+        (0..u64::from(self.hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn locate(bit: u64) -> (usize, u8) {
+    ((bit / 8) as usize, 1 << (bit % 8))
+}
+
+// FNV-1a keeps the layout stable across toolchains, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    bytes.iter().fold(seed, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserted_items_are_found() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        for id in 0..1_000_u32 {
+            filter.insert(&id.to_be_bytes());
+        }
+        assert!((0..1_000_u32).all(|id| filter.contains(&id.to_be_bytes())));
+    }
+
+    #[test]
+    fn false_positive_rate_is_bounded() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        for id in 0..1_000_u32 {
+            filter.insert(&id.to_be_bytes());
+        }
+        let false_positives =
+            (1_000..11_000_u32).filter(|id| filter.contains(&id.to_be_bytes())).count();
+        assert!(false_positives < 300, "{false_positives} false positives in 10000");
+    }
+
+    #[test]
+    fn insert_reports_new_items() {
+        let mut filter = BloomFilter::default();
+        assert!(filter.insert(b"msg-1"));
+        assert!(!filter.insert(b"msg-1"));
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let mut filter = BloomFilter::new(100, 0.05);
+        filter.insert(b"msg-1");
+        let restored = BloomFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert_eq!(restored, filter);
+        BloomFilter::from_bytes(&[0; 4]).unwrap_err();
+    }
+}
@@ -26,5 +26,7 @@ pub use messaging::{Message, Publish};
 pub use model::Model;
 #[cfg(target_arch = "wasm32")]
 pub use model::WasiModel;
+#[cfg(test)]
+pub use state::MemoryStore;
 pub use state::StateStore;
 pub use table::TableStore;
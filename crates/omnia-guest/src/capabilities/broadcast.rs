@@ -28,4 +28,159 @@ pub trait Broadcast: Send + Sync {
                 .map_err(|e| anyhow!("sending websocket event: {e}"))
         }
     }
+
+    /// Send an event to one connected peer, such as the peer whose event is
+    /// being handled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected or the event cannot be
+    /// sent.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send_to_peer(
+        &self, name: &str, peer: &str, data: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        self.send(name, data, Some(vec![peer.to_string()]))
+    }
+
+    /// Send an event to one connected peer, such as the peer whose event is
+    /// being handled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected or the event cannot be
+    /// sent.
+    #[cfg(target_arch = "wasm32")]
+    fn send_to_peer(
+        &self, name: &str, peer: &str, data: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        use anyhow::anyhow;
+        async move {
+            let client = omnia_wasi_websocket::types::Client::connect(name.to_string())
+                .await
+                .map_err(|e| anyhow!("connecting to websocket: {e}"))?;
+            let event = omnia_wasi_websocket::types::Event::new(data);
+            omnia_wasi_websocket::client::send_to_peer(&client, peer.to_string(), event)
+                .await
+                .map_err(|e| anyhow!("sending websocket event to {peer}: {e}"))
+        }
+    }
+
+    /// Add the connected peer `peer` to `group`, so events sent to the group
+    /// reach it. Unlike a peer's own subscribe request, this is not
+    /// authorized; the guest vouches for the peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected or the backend has no
+    /// groups.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn join_group(
+        &self, _name: &str, _peer: &str, group: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let group = group.to_string();
+        async move { Err(anyhow::anyhow!("cannot join group {group}: no websocket host")) }
+    }
+
+    /// Add the connected peer `peer` to `group`, so events sent to the group
+    /// reach it. Unlike a peer's own subscribe request, this is not
+    /// authorized; the guest vouches for the peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected or the backend has no
+    /// groups.
+    #[cfg(target_arch = "wasm32")]
+    fn join_group(
+        &self, name: &str, peer: &str, group: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        use anyhow::anyhow;
+        async move {
+            let client = omnia_wasi_websocket::types::Client::connect(name.to_string())
+                .await
+                .map_err(|e| anyhow!("connecting to websocket: {e}"))?;
+            omnia_wasi_websocket::client::join_group(&client, peer.to_string(), group.to_string())
+                .await
+                .map_err(|e| anyhow!("adding {peer} to websocket group {group}: {e}"))
+        }
+    }
+
+    /// Remove the connected peer `peer` from `group`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected or the backend has no
+    /// groups.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn leave_group(
+        &self, _name: &str, _peer: &str, group: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let group = group.to_string();
+        async move { Err(anyhow::anyhow!("cannot leave group {group}: no websocket host")) }
+    }
+
+    /// Remove the connected peer `peer` from `group`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected or the backend has no
+    /// groups.
+    #[cfg(target_arch = "wasm32")]
+    fn leave_group(
+        &self, name: &str, peer: &str, group: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        use anyhow::anyhow;
+        async move {
+            let client = omnia_wasi_websocket::types::Client::connect(name.to_string())
+                .await
+                .map_err(|e| anyhow!("connecting to websocket: {e}"))?;
+            omnia_wasi_websocket::client::leave_group(&client, peer.to_string(), group.to_string())
+                .await
+                .map_err(|e| anyhow!("removing {peer} from websocket group {group}: {e}"))
+        }
+    }
+
+    /// Close the connection to the peer `peer` with a close code, such as
+    /// 1008 or an application code in 4000-4999, and a reason, so the peer
+    /// learns why.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected, or the code cannot be
+    /// sent or the reason is longer than 123 bytes.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn close_peer(
+        &self, _name: &str, peer: &str, _code: u16, _reason: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let peer = peer.to_string();
+        async move { Err(anyhow::anyhow!("cannot close peer {peer}: no websocket host")) }
+    }
+
+    /// Close the connection to the peer `peer` with a close code, such as
+    /// 1008 or an application code in 4000-4999, and a reason, so the peer
+    /// learns why.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer is not connected, or the code cannot be
+    /// sent or the reason is longer than 123 bytes.
+    #[cfg(target_arch = "wasm32")]
+    fn close_peer(
+        &self, name: &str, peer: &str, code: u16, reason: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        use anyhow::anyhow;
+        async move {
+            let client = omnia_wasi_websocket::types::Client::connect(name.to_string())
+                .await
+                .map_err(|e| anyhow!("connecting to websocket: {e}"))?;
+            omnia_wasi_websocket::client::close_peer(
+                &client,
+                peer.to_string(),
+                code,
+                reason.to_string(),
+            )
+            .await
+            .map_err(|e| anyhow!("closing websocket peer {peer}: {e}"))
+        }
+    }
 }
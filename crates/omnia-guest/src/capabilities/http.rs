@@ -5,9 +5,12 @@ use std::error::Error;
 use std::future::Future;
 
 use anyhow::Result;
+use axum::body::Body as AxumBody;
 use bytes::Bytes;
-use http::{Request, Response};
+use futures::stream::BoxStream;
+use http::{Method, Request, Response};
 use http_body::Body;
+use omnia_wasi_http::UPSTREAM_HEADER;
 
 /// Fetches data from an outbound HTTP source.
 pub trait HttpRequest: Send + Sync {
@@ -29,4 +32,76 @@ pub trait HttpRequest: Send + Sync {
     {
         async move { omnia_wasi_http::handle(request).await }
     }
+
+    /// Make outbound HTTP request and return the response with its body
+    /// still streaming, so long-lived (SSE or chunked) responses can be
+    /// relayed as they arrive rather than buffered whole.
+    ///
+    /// Off `wasm32` the default awaits [`fetch`](Self::fetch) and yields the
+    /// whole body as one chunk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fetch_stream<T>(
+        &self, request: Request<T>,
+    ) -> impl Future<Output = Result<Response<BoxStream<'static, Bytes>>>> + Send
+    where
+        T: Body + Any + Send,
+        T::Data: Into<Vec<u8>>,
+        T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+    {
+        use futures::stream::{self, StreamExt};
+        async move {
+            let response = self.fetch(request).await?;
+            Ok(response.map(|body| stream::once(async move { body }).boxed()))
+        }
+    }
+
+    /// Make outbound HTTP request and return the response with its body
+    /// still streaming, so long-lived (SSE or chunked) responses can be
+    /// relayed as they arrive rather than buffered whole.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    #[cfg(target_arch = "wasm32")]
+    fn fetch_stream<T>(
+        &self, request: Request<T>,
+    ) -> impl Future<Output = Result<Response<BoxStream<'static, Bytes>>>> + Send
+    where
+        T: Body + Any + Send,
+        T::Data: Into<Vec<u8>>,
+        T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+    {
+        async move { omnia_wasi_http::stream(request).await }
+    }
+
+    /// Make an outbound request through the host-configured upstream profile `upstream`.
+    ///
+    /// The host supplies the base URL, credentials, default headers, timeout,
+    /// and retry policy; `path` (with any query) is appended to the base URL.
+    /// The request is a `POST` when `body` is given and a `GET` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be built, the profile is unknown
+    /// to the host, or the request fails.
+    fn fetch_upstream(
+        &self, upstream: &str, path: &str, body: Option<Bytes>,
+    ) -> impl Future<Output = Result<Response<Bytes>>> + Send {
+        let (method, body) = body.map_or_else(
+            || (Method::GET, AxumBody::empty()),
+            |body| (Method::POST, AxumBody::from(body)),
+        );
+        // The host replaces the authority with the profile's base URL.
+        let request = Request::builder()
+            .method(method)
+            .uri(format!("http://{upstream}/{}", path.trim_start_matches('/')))
+            .header(UPSTREAM_HEADER, upstream)
+            .body(body);
+
+        async move { self.fetch(request?).await }
+    }
 }
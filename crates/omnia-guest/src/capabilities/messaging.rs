@@ -36,18 +36,69 @@ pub trait Publish: Send + Sync {
     fn send(&self, topic: &str, message: &Message) -> impl Future<Output = Result<()>> + Send {
         use anyhow::Context;
         use omnia_wasi_messaging::producer;
-        use omnia_wasi_messaging::types::{self as wasi, Client};
+        use omnia_wasi_messaging::types::Client;
 
         async move {
             let client =
                 Client::connect("host".to_string()).await.context("connecting to broker")?;
-            let msg = wasi::Message::new(&message.payload);
-            message.headers.iter().for_each(|(k, v)| {
-                msg.add_metadata(k, v);
-            });
-            producer::send(&client, topic.to_string(), msg)
+            producer::send(&client, topic.to_string(), wasi_message(message))
                 .await
                 .with_context(|| format!("sending message to {topic}"))
         }
     }
+
+    /// Publish a batch of messages to a topic, in order.
+    ///
+    /// Off wasm32 this sends the messages one at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a message cannot be sent; messages before it may
+    /// already have been published.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send_batch(
+        &self, topic: &str, messages: &[Message],
+    ) -> impl Future<Output = Result<()>> + Send {
+        async move {
+            for message in messages {
+                self.send(topic, message).await?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Publish a batch of messages to a topic, in order, in one host call, so
+    /// high-frequency producers do not pay a round trip per message. The host
+    /// hands the whole batch to the broker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the batch cannot be sent; backends without native
+    /// batching may already have published a prefix of it.
+    #[cfg(target_arch = "wasm32")]
+    fn send_batch(
+        &self, topic: &str, messages: &[Message],
+    ) -> impl Future<Output = Result<()>> + Send {
+        use anyhow::Context;
+        use omnia_wasi_messaging::batch;
+        use omnia_wasi_messaging::types::Client;
+
+        async move {
+            let client =
+                Client::connect("host".to_string()).await.context("connecting to broker")?;
+            let batch = messages.iter().map(wasi_message).collect();
+            batch::send_batch(&client, topic.to_string(), batch)
+                .await
+                .with_context(|| format!("sending {} messages to {topic}", messages.len()))
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn wasi_message(message: &Message) -> omnia_wasi_messaging::types::Message {
+    let msg = omnia_wasi_messaging::types::Message::new(&message.payload);
+    message.headers.iter().for_each(|(k, v)| {
+        msg.add_metadata(k, v);
+    });
+    msg
 }
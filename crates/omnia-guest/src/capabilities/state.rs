@@ -1,9 +1,13 @@
 //! Key-value state capability.
 
+use std::collections::BTreeMap;
 use std::future::Future;
 
 use anyhow::Result;
 
+use crate::bloom::BloomFilter;
+use crate::migrate::Migrations;
+
 /// Store and retrieve key-value state, optionally with a TTL.
 pub trait StateStore: Send + Sync {
     /// Retrieve a previously stored value from the state store.
@@ -54,4 +58,507 @@ pub trait StateStore: Send + Sync {
             bucket.delete(key).await.context("deleting entry from cache")
         }
     }
+
+    /// Retrieve a value and delete it, so at most one caller observes it (Redis `GETDEL`).
+    ///
+    /// Off wasm32 this is a get followed by a delete.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_del(&self, key: &str) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        async move {
+            let value = self.get(key).await?;
+            if value.is_some() {
+                self.delete(key).await?;
+            }
+            Ok(value)
+        }
+    }
+
+    /// Retrieve a value and restart its TTL at `ttl_secs` (Redis `GETEX`).
+    ///
+    /// Off wasm32 this is a get followed by a set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_ex(
+        &self, key: &str, ttl_secs: u64,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        async move {
+            let value = self.get(key).await?;
+            if let Some(value) = &value {
+                self.set(key, value, Some(ttl_secs)).await?;
+            }
+            Ok(value)
+        }
+    }
+
+    /// Retrieve a value and delete it in one atomic operation, so at most one caller
+    /// observes it (Redis `GETDEL`). Suits one-shot tokens and nonces.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    #[cfg(target_arch = "wasm32")]
+    fn get_del(&self, key: &str) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        use anyhow::Context;
+        async move {
+            let bucket =
+                omnia_wasi_keyvalue::cache::open("cache").await.context("opening cache")?;
+            bucket.get_del(key).await.context("taking state from cache")
+        }
+    }
+
+    /// Retrieve a value and restart its TTL at `ttl_secs` (Redis `GETEX`), for sliding
+    /// sessions. The refresh is a compare-and-swap, so it never resurrects a value that
+    /// another caller deleted or replaced meanwhile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    #[cfg(target_arch = "wasm32")]
+    fn get_ex(
+        &self, key: &str, ttl_secs: u64,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        use anyhow::Context;
+        async move {
+            let bucket =
+                omnia_wasi_keyvalue::cache::open("cache").await.context("opening cache")?;
+            bucket.get_ex(key, ttl_secs).await.context("refreshing state in cache")
+        }
+    }
+
+    /// Add `delta` to the counter stored under `key` and return the new count (Redis
+    /// `INCRBY`). A missing key starts from zero. Counters are stored as big-endian `i64`s.
+    ///
+    /// Off wasm32 this is a get followed by a set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails, the key holds a value that is not a counter, or
+    /// the count overflows.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn increment(&self, key: &str, delta: i64) -> impl Future<Output = Result<i64>> + Send {
+        async move {
+            let base = match self.get(key).await? {
+                Some(value) => counter_value(key, &value)?,
+                None => 0,
+            };
+            let count = base
+                .checked_add(delta)
+                .ok_or_else(|| anyhow::anyhow!("incrementing `{key}` by {delta} overflows"))?;
+            self.set(key, &count.to_be_bytes(), None).await?;
+            Ok(count)
+        }
+    }
+
+    /// Store `new` under `key` only if it holds `expected` (`None`: the key is absent), and
+    /// return whether it was stored. Suits optimistic concurrency on a version or a whole
+    /// record.
+    ///
+    /// Off wasm32 this is a get followed by a set, and the new value is stored without a TTL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compare_and_swap(
+        &self, key: &str, expected: Option<&[u8]>, new: &[u8],
+    ) -> impl Future<Output = Result<bool>> + Send {
+        async move {
+            if self.get(key).await?.as_deref() != expected {
+                return Ok(false);
+            }
+            self.set(key, new, None).await?;
+            Ok(true)
+        }
+    }
+
+    /// Add `delta` to the counter stored under `key` and return the new count, in one atomic
+    /// operation (Redis `INCRBY`). A missing key starts from zero. Counters are stored as
+    /// big-endian `i64`s.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails, the key holds a value that is not a counter, or
+    /// the count overflows.
+    #[cfg(target_arch = "wasm32")]
+    fn increment(&self, key: &str, delta: i64) -> impl Future<Output = Result<i64>> + Send {
+        use anyhow::Context;
+        async move {
+            let bucket = omnia_wasi_keyvalue::store::open("cache".to_string())
+                .await
+                .context("opening cache")?;
+            omnia_wasi_keyvalue::atomics::increment(&bucket, key.to_string(), delta)
+                .await
+                .context("incrementing counter")
+        }
+    }
+
+    /// Store `new` under `key` only if it holds `expected` (`None`: the key is absent), in one
+    /// atomic operation, and return whether it was stored. Suits optimistic concurrency on a
+    /// version or a whole record.
+    ///
+    /// The comparison is against the value as [`Self::get`] returns it, and a value written
+    /// with a TTL keeps its expiry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    #[cfg(target_arch = "wasm32")]
+    fn compare_and_swap(
+        &self, key: &str, expected: Option<&[u8]>, new: &[u8],
+    ) -> impl Future<Output = Result<bool>> + Send {
+        use anyhow::Context;
+        async move {
+            let bucket =
+                omnia_wasi_keyvalue::cache::open("cache").await.context("opening cache")?;
+            bucket.compare_and_swap(key, expected, new).await.context("swapping value")
+        }
+    }
+
+    /// Add `item` to the Bloom filter stored under `filter`, creating it with default sizing
+    /// if absent. Returns `true` if the item was not (probably) already present.
+    ///
+    /// On wasm32 the filter is updated with compare-and-swap, so concurrent adds are not lost.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `filter` holds a value that is not a filter.
+    fn bf_add(&self, filter: &str, item: &[u8]) -> impl Future<Output = Result<bool>> + Send {
+        update_filter(self, filter, BloomFilter::default(), move |bloom, _| bloom.insert(item))
+    }
+
+    /// Whether `item` may have been added to the Bloom filter stored under `filter`.
+    ///
+    /// A missing filter contains nothing; `false` means the item was definitely never added.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `filter` holds a value that is not a filter.
+    fn bf_exists(&self, filter: &str, item: &[u8]) -> impl Future<Output = Result<bool>> + Send {
+        async move {
+            let Some(bytes) = read_filter(self, filter).await? else {
+                return Ok(false);
+            };
+            Ok(BloomFilter::from_bytes(&bytes)?.contains(item))
+        }
+    }
+
+    /// Create the Bloom filter `filter` sized for `capacity` items at `error_rate`, leaving an
+    /// existing filter untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    fn bf_reserve(
+        &self, filter: &str, capacity: u64, error_rate: f64,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let sized = BloomFilter::new(capacity, error_rate);
+        async move { update_filter(self, filter, sized, |_, existing| !existing).await.map(|_| ()) }
+    }
+
+    /// Retrieve the value stored under `key`, upgraded to the current version of
+    /// `migrations`. Values written at an older version, or before versioning was
+    /// adopted, are upgraded in memory; the stored value is left as it is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails, the value was written at a newer version, or an
+    /// upgrade step fails.
+    fn get_versioned(
+        &self, key: &str, migrations: &Migrations,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        use anyhow::Context;
+        async move {
+            let Some(stored) = self.get(key).await? else {
+                return Ok(None);
+            };
+            let value =
+                migrations.decode(&stored).with_context(|| format!("reading state `{key}`"))?;
+            Ok(Some(value))
+        }
+    }
+
+    /// Store `value` under `key`, tagged with the current version of `migrations`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    fn set_versioned(
+        &self, key: &str, value: &[u8], ttl_secs: Option<u64>, migrations: &Migrations,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let stored = migrations.encode(value);
+        async move { self.set(key, &stored, ttl_secs).await.map(|_| ()) }
+    }
+
+    /// Set fields of the hash stored under `key` (Redis `HSET`), creating it if absent, and
+    /// return how many fields were added. Other fields of the hash are left as they are.
+    ///
+    /// Off wasm32 the default reports that the store cannot, since a get followed by a set
+    /// would let two callers lose each other's fields. Stores with an atomic update should
+    /// override it, keeping the hash as the JSON map [`Self::hash_get`] reads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails, has no atomic hashes, or `key` holds a value
+    /// that is not a hash.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hash_set(
+        &self, key: &str, _fields: &[(&str, &[u8])],
+    ) -> impl Future<Output = Result<u32>> + Send {
+        async move { anyhow::bail!("cannot set fields of `{key}`: no atomic hashes") }
+    }
+
+    /// One field of the hash stored under `key` (Redis `HGET`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `key` holds a value that is not a hash.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hash_get(
+        &self, key: &str, field: &str,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        async move { Ok(read_hash(self, key).await?.remove(field)) }
+    }
+
+    /// Every field of the hash stored under `key` (Redis `HGETALL`); empty when absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `key` holds a value that is not a hash.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hash_get_all(
+        &self, key: &str,
+    ) -> impl Future<Output = Result<BTreeMap<String, Vec<u8>>>> + Send {
+        read_hash(self, key)
+    }
+
+    /// Delete fields of the hash stored under `key` (Redis `HDEL`), deleting the hash once it
+    /// is empty, and return how many fields were deleted.
+    ///
+    /// Off wasm32 the default reports that the store cannot, as [`Self::hash_set`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails, has no atomic hashes, or `key` holds a value
+    /// that is not a hash.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hash_delete(&self, key: &str, _fields: &[&str]) -> impl Future<Output = Result<u32>> + Send {
+        async move { anyhow::bail!("cannot delete fields of `{key}`: no atomic hashes") }
+    }
+
+    /// Set fields of the hash stored under `key` (Redis `HSET`), creating it if absent, and
+    /// return how many fields were added. Other fields of the hash are left as they are, so
+    /// concurrent writers of different fields do not overwrite each other.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `key` holds a value that is not a hash.
+    #[cfg(target_arch = "wasm32")]
+    fn hash_set(
+        &self, key: &str, fields: &[(&str, &[u8])],
+    ) -> impl Future<Output = Result<u32>> + Send {
+        use anyhow::Context;
+        let fields =
+            fields.iter().map(|(name, value)| ((*name).to_string(), value.to_vec())).collect();
+        async move {
+            let bucket = omnia_wasi_keyvalue::store::open("cache".to_string())
+                .await
+                .context("opening cache")?;
+            omnia_wasi_keyvalue::hash::set_fields(&bucket, key.to_string(), fields)
+                .await
+                .context("setting hash fields")
+        }
+    }
+
+    /// One field of the hash stored under `key` (Redis `HGET`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `key` holds a value that is not a hash.
+    #[cfg(target_arch = "wasm32")]
+    fn hash_get(
+        &self, key: &str, field: &str,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        use anyhow::Context;
+        async move {
+            let bucket = omnia_wasi_keyvalue::store::open("cache".to_string())
+                .await
+                .context("opening cache")?;
+            omnia_wasi_keyvalue::hash::get_field(&bucket, key.to_string(), field.to_string())
+                .await
+                .context("reading hash field")
+        }
+    }
+
+    /// Every field of the hash stored under `key` (Redis `HGETALL`); empty when absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `key` holds a value that is not a hash.
+    #[cfg(target_arch = "wasm32")]
+    fn hash_get_all(
+        &self, key: &str,
+    ) -> impl Future<Output = Result<BTreeMap<String, Vec<u8>>>> + Send {
+        use anyhow::Context;
+        async move {
+            let bucket = omnia_wasi_keyvalue::store::open("cache".to_string())
+                .await
+                .context("opening cache")?;
+            let fields = omnia_wasi_keyvalue::hash::get_all_fields(&bucket, key.to_string())
+                .await
+                .context("reading hash fields")?;
+            Ok(fields.into_iter().collect())
+        }
+    }
+
+    /// Delete fields of the hash stored under `key` (Redis `HDEL`), deleting the hash once it
+    /// is empty, and return how many fields were deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or `key` holds a value that is not a hash.
+    #[cfg(target_arch = "wasm32")]
+    fn hash_delete(&self, key: &str, fields: &[&str]) -> impl Future<Output = Result<u32>> + Send {
+        use anyhow::Context;
+        let fields = fields.iter().map(|name| (*name).to_string()).collect();
+        async move {
+            let bucket = omnia_wasi_keyvalue::store::open("cache".to_string())
+                .await
+                .context("opening cache")?;
+            omnia_wasi_keyvalue::hash::delete_fields(&bucket, key.to_string(), fields)
+                .await
+                .context("deleting hash fields")
+        }
+    }
+}
+
+/// The counter `value` holds: eight bytes read as a big-endian `i64`.
+#[cfg(not(target_arch = "wasm32"))]
+fn counter_value(key: &str, value: &[u8]) -> Result<i64> {
+    use anyhow::Context;
+
+    let bytes = <[u8; 8]>::try_from(value)
+        .with_context(|| format!("`{key}` holds {} bytes, not a counter", value.len()))?;
+    Ok(i64::from_be_bytes(bytes))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_hash<S: StateStore + ?Sized>(
+    store: &S, key: &str,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    match store.get(key).await? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_filter<S: StateStore + ?Sized>(store: &S, key: &str) -> Result<Option<Vec<u8>>> {
+    store.get(key).await
+}
+
+// Filters bypass the TTL envelope of the cache and are read from the bucket directly.
+#[cfg(target_arch = "wasm32")]
+async fn read_filter<S: StateStore + ?Sized>(_store: &S, key: &str) -> Result<Option<Vec<u8>>> {
+    use anyhow::Context;
+    let bucket =
+        omnia_wasi_keyvalue::store::open("cache".to_string()).await.context("opening cache")?;
+    bucket.get(key.to_string()).await.context("reading bloom filter")
+}
+
+/// Apply `update` to the filter under `key` (or to `initial` when absent) and persist it if
+/// `update` reports a change. `update` also receives whether the filter already existed.
+#[cfg(not(target_arch = "wasm32"))]
+async fn update_filter<S, F>(store: &S, key: &str, initial: BloomFilter, update: F) -> Result<bool>
+where
+    S: StateStore + ?Sized,
+    F: Fn(&mut BloomFilter, bool) -> bool + Send,
+{
+    let (mut bloom, existing) = match store.get(key).await? {
+        Some(bytes) => (BloomFilter::from_bytes(&bytes)?, true),
+        None => (initial, false),
+    };
+    let changed = update(&mut bloom, existing);
+    if changed {
+        store.set(key, &bloom.to_bytes(), None).await?;
+    }
+    Ok(changed)
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn update_filter<S, F>(_store: &S, key: &str, initial: BloomFilter, update: F) -> Result<bool>
+where
+    S: StateStore + ?Sized,
+    F: Fn(&mut BloomFilter, bool) -> bool + Send,
+{
+    use anyhow::{Context, anyhow};
+    use omnia_wasi_keyvalue::atomics::{self, Cas, CasError};
+
+    let bucket =
+        omnia_wasi_keyvalue::store::open("cache".to_string()).await.context("opening cache")?;
+    let mut cas = Cas::new(&bucket, key.to_string()).await.context("reading bloom filter")?;
+    loop {
+        let (mut bloom, existing) = match cas.current().await.context("reading bloom filter")? {
+            Some(bytes) => (BloomFilter::from_bytes(&bytes)?, true),
+            None => (initial.clone(), false),
+        };
+        if !update(&mut bloom, existing) {
+            return Ok(false);
+        }
+        match atomics::swap(cas, bloom.to_bytes()).await {
+            Ok(()) => return Ok(true),
+            // Another writer got in first; retry against the value it wrote.
+            Err(CasError::CasFailed(fresh)) => cas = fresh,
+            Err(CasError::StoreError(e)) => return Err(anyhow!("writing bloom filter: {e:?}")),
+        }
+    }
+}
+
+/// An in-memory [`StateStore`] for unit tests. It ignores TTLs.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore(std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>);
+
+#[cfg(test)]
+impl StateStore for MemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.0.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set(
+        &self, key: &str, value: &[u8], _ttl_secs: Option<u64>,
+    ) -> Result<Option<Vec<u8>>> {
+        Ok(self.0.lock().unwrap().insert(key.to_string(), value.to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.0.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn increments_count_from_zero() {
+        let store = MemoryStore::default();
+        assert_eq!(store.increment("hits", 2).await.unwrap(), 2);
+        assert_eq!(store.increment("hits", -5).await.unwrap(), -3);
+    }
+
+    #[tokio::test]
+    async fn increments_refuse_values_that_are_not_counters() {
+        let store = MemoryStore::default();
+        store.set("hits", &[1], None).await.unwrap();
+        store.increment("hits", 1).await.unwrap_err();
+        assert_eq!(store.get("hits").await.unwrap(), Some(vec![1]));
+    }
 }
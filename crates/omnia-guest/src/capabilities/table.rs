@@ -1,9 +1,16 @@
 //! Relational (SQL) table capability backing the ORM.
 
 use std::future::Future;
+use std::time::Instant;
 
-use anyhow::Result;
-use omnia_wasi_sql::{DataType, Row};
+use anyhow::{Result, ensure};
+use futures::{Stream, TryFutureExt};
+use omnia_wasi_sql::{DataType, ExecResult, Row};
+use tracing::{Instrument, Span};
+
+use crate::orm::Query;
+#[cfg(target_arch = "wasm32")]
+use crate::orm::sql_error;
 
 /// Types that provide ORM database access.
 ///
@@ -32,9 +39,7 @@ pub trait TableStore: Send + Sync {
     ) -> impl Future<Output = Result<Vec<Row>>> + Send {
         async move {
             let (conn, stmt) = prepare(conn_name, query, params).await?;
-            omnia_wasi_sql::readwrite::query(&conn, &stmt)
-                .await
-                .map_err(|e| anyhow::anyhow!("query failed: {}", e.trace()))
+            omnia_wasi_sql::readwrite::query(&conn, &stmt).await.map_err(sql_error("query failed"))
         }
     }
 
@@ -49,11 +54,193 @@ pub trait TableStore: Send + Sync {
     ) -> impl Future<Output = Result<u32>> + Send {
         async move {
             let (conn, stmt) = prepare(conn_name, query, params).await?;
-            omnia_wasi_sql::readwrite::exec(&conn, &stmt)
+            omnia_wasi_sql::readwrite::exec(&conn, &stmt).await.map_err(sql_error("exec failed"))
+        }
+    }
+
+    /// Executes a statement and reports its effects: the affected row count,
+    /// the last insert id, and any `RETURNING` rows.
+    ///
+    /// Off wasm32 the default reports only the count from [`Self::exec`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`Self::exec`] fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn exec_with_result(
+        &self, conn_name: String, query: String, params: Vec<DataType>,
+    ) -> impl Future<Output = Result<ExecResult>> + Send {
+        self.exec(conn_name, query, params).map_ok(|affected| ExecResult {
+            affected_rows: u64::from(affected),
+            last_insert_id: None,
+            rows: Vec::new(),
+        })
+    }
+
+    /// Executes a statement and reports its effects: the affected row count,
+    /// the last insert id, and any `RETURNING` rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails, statement preparation fails, or execution fails.
+    #[cfg(target_arch = "wasm32")]
+    fn exec_with_result(
+        &self, conn_name: String, query: String, params: Vec<DataType>,
+    ) -> impl Future<Output = Result<ExecResult>> + Send {
+        async move {
+            let (conn, stmt) = prepare(conn_name, query, params).await?;
+            omnia_wasi_sql::results::exec_with_result(&conn, &stmt)
+                .await
+                .map_err(sql_error("exec failed"))
+        }
+    }
+
+    /// Runs a built query that yields rows (a `SELECT`, or a write with `RETURNING`).
+    ///
+    /// Execution is recorded in an `orm.execute` span with the table, statement
+    /// kind, row count, and elapsed time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query yields no rows, or if [`Self::query`] fails.
+    fn fetch(
+        &self, conn_name: String, query: Query,
+    ) -> impl Future<Output = Result<Vec<Row>>> + Send {
+        async move {
+            ensure!(
+                query.expects_rows,
+                "{} statement returns no rows; use `apply` instead of `fetch`",
+                query.kind.as_str()
+            );
+            let span = execute_span(&query);
+            let started = Instant::now();
+            let rows =
+                self.query(conn_name, query.sql, query.params).instrument(span.clone()).await;
+            record(&span, started, rows.as_ref().map(Vec::len).ok());
+            rows
+        }
+    }
+
+    /// Streams the rows of a built query that yields rows, fetching at most
+    /// `batch_size` rows from the host at a time.
+    ///
+    /// Off wasm32 the rows come from a single [`Self::query`] call.
+    ///
+    /// # Errors
+    ///
+    /// The stream yields an error if the query yields no rows, or if opening
+    /// the cursor or fetching a batch fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn stream(
+        &self, conn_name: String, query: Query, _batch_size: u32,
+    ) -> impl Stream<Item = Result<Row>> + Send {
+        self.fetch(conn_name, query)
+            .map_ok(|rows| futures::stream::iter(rows.into_iter().map(Ok)))
+            .try_flatten_stream()
+    }
+
+    /// Streams the rows of a built query that yields rows, fetching at most
+    /// `batch_size` rows from the host at a time, so a large result set never
+    /// has to fit in guest memory.
+    ///
+    /// # Errors
+    ///
+    /// The stream yields an error if the query yields no rows, or if opening
+    /// the cursor or fetching a batch fails.
+    #[cfg(target_arch = "wasm32")]
+    fn stream(
+        &self, conn_name: String, query: Query, batch_size: u32,
+    ) -> impl Stream<Item = Result<Row>> + Send {
+        async move {
+            ensure!(
+                query.expects_rows,
+                "{} statement returns no rows; use `apply` instead of `stream`",
+                query.kind.as_str()
+            );
+            let (conn, stmt) = prepare(conn_name, query.sql, query.params).await?;
+            let cursor = omnia_wasi_sql::cursors::Cursor::open(&conn, &stmt, batch_size)
                 .await
-                .map_err(|e| anyhow::anyhow!("exec failed: {}", e.trace()))
+                .map_err(sql_error("failed to open cursor"))?;
+            Ok(omnia_wasi_sql::rows(cursor))
+        }
+        .try_flatten_stream()
+    }
+
+    /// Runs a built statement that yields no rows and returns the number of affected rows.
+    ///
+    /// Execution is recorded in an `orm.execute` span like [`Self::fetch`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query yields rows, or if [`Self::exec`] fails.
+    fn apply(&self, conn_name: String, query: Query) -> impl Future<Output = Result<u32>> + Send {
+        async move {
+            ensure!(
+                !query.expects_rows,
+                "{} statement returns rows; use `fetch` instead of `apply`",
+                query.kind.as_str()
+            );
+            let span = execute_span(&query);
+            let started = Instant::now();
+            let affected =
+                self.exec(conn_name, query.sql, query.params).instrument(span.clone()).await;
+            record(
+                &span,
+                started,
+                affected.as_ref().ok().map(|&n| usize::try_from(n).unwrap_or(usize::MAX)),
+            );
+            affected
         }
     }
+
+    /// Runs a built write and reports its effects, including the rows of a
+    /// `RETURNING` clause, so the caller can verify what an update or delete
+    /// touched.
+    ///
+    /// Execution is recorded in an `orm.execute` span like [`Self::fetch`],
+    /// with the affected row count as `rows`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`Self::exec_with_result`] fails.
+    fn apply_with_result(
+        &self, conn_name: String, query: Query,
+    ) -> impl Future<Output = Result<ExecResult>> + Send {
+        async move {
+            let span = execute_span(&query);
+            let started = Instant::now();
+            let outcome = self
+                .exec_with_result(conn_name, query.sql, query.params)
+                .instrument(span.clone())
+                .await;
+            record(
+                &span,
+                started,
+                outcome
+                    .as_ref()
+                    .ok()
+                    .map(|outcome| usize::try_from(outcome.affected_rows).unwrap_or(usize::MAX)),
+            );
+            outcome
+        }
+    }
+}
+
+fn execute_span(query: &Query) -> Span {
+    tracing::info_span!(
+        "orm.execute",
+        table = query.table,
+        kind = query.kind.as_str(),
+        rows = tracing::field::Empty,
+        elapsed_ms = tracing::field::Empty,
+        error = tracing::field::Empty,
+    )
+}
+
+/// Record the outcome of an execution; `rows` is `None` when it failed.
+fn record(span: &Span, started: Instant, rows: Option<usize>) {
+    span.record("elapsed_ms", u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX));
+    rows.map_or_else(|| span.record("error", true), |rows| span.record("rows", rows));
 }
 
 /// Open the connection and prepare the statement shared by `query` and `exec`.
@@ -63,11 +250,9 @@ async fn prepare(
 ) -> Result<(omnia_wasi_sql::types::Connection, omnia_wasi_sql::types::Statement)> {
     use omnia_wasi_sql::types::{Connection, Statement};
 
-    let conn = Connection::open(conn_name)
-        .await
-        .map_err(|e| anyhow::anyhow!("failed to open connection: {}", e.trace()))?;
+    let conn = Connection::open(conn_name).await.map_err(sql_error("failed to open connection"))?;
     let stmt = Statement::prepare(query, params)
         .await
-        .map_err(|e| anyhow::anyhow!("failed to prepare statement: {}", e.trace()))?;
+        .map_err(sql_error("failed to prepare statement"))?;
     Ok((conn, stmt))
 }
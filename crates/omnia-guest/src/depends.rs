@@ -0,0 +1,319 @@
+//! Declared outbound HTTP dependencies.
+//!
+//! [`depends!`](crate::depends!) declares the upstream services a component
+//! calls, each with the configuration key holding its base URL, and generates
+//! a struct with one typed [`Dependency`] client per upstream. A client
+//! resolves its base URL from [`Config`] on each call, joins the request path
+//! onto it, injects a bearer token when the dependency declares one, and
+//! records per-dependency metrics (`dependency_requests`,
+//! `dependency_failures`, `dependency_duration_ms`, labelled with the
+//! dependency), so handlers no longer concatenate URL strings by hand.
+//!
+//! ```rust,ignore
+//! omnia_guest::depends! {
+//!     pub struct Upstreams {
+//!         fleet: FLEET_URL,
+//!         gtfs: GTFS_STATIC_URL [bearer = GTFS_TOKEN],
+//!     }
+//! }
+//!
+//! let vehicles: Vec<Vehicle> = Upstreams::new(&provider).fleet().get_json("/vehicles").await?;
+//! ```
+
+use std::any::Any;
+use std::error::Error;
+use std::time::Instant;
+
+use anyhow::{Context, Result, bail};
+use axum::body::Body as AxumBody;
+use bytes::Bytes;
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{Method, Request, Response};
+use http_body::Body;
+use serde::de::DeserializeOwned;
+
+use crate::{Config, HttpRequest};
+
+/// A typed client for one declared upstream, generated by
+/// [`depends!`](crate::depends!).
+#[derive(Debug)]
+pub struct Dependency<'a, P> {
+    provider: &'a P,
+    name: &'static str,
+    url_key: &'static str,
+    bearer_key: Option<&'static str>,
+}
+
+impl<'a, P> Dependency<'a, P> {
+    /// A client for the upstream `name`, whose base URL is the configuration
+    /// value `url_key`.
+    #[must_use]
+    pub const fn new(provider: &'a P, name: &'static str, url_key: &'static str) -> Self {
+        Self {
+            provider,
+            name,
+            url_key,
+            bearer_key: None,
+        }
+    }
+
+    /// Send the configuration value `token_key` as a bearer token on every
+    /// request.
+    #[must_use]
+    pub const fn bearer(mut self, token_key: &'static str) -> Self {
+        self.bearer_key = Some(token_key);
+        self
+    }
+
+    /// The dependency's declared name.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<P: Config + HttpRequest> Dependency<'_, P> {
+    /// The full URL of `path` (with any query) on this upstream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base URL is not configured.
+    pub async fn url(&self, path: &str) -> Result<String> {
+        let base = self.provider.get(self.url_key).await.with_context(|| {
+            format!("resolving base URL of dependency `{}` from `{}`", self.name, self.url_key)
+        })?;
+        Ok(format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/')))
+    }
+
+    /// A request builder for `method` on `path`, with the URL resolved and
+    /// credentials attached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base URL or the bearer token is not configured.
+    pub async fn request(&self, method: Method, path: &str) -> Result<http::request::Builder> {
+        let mut builder = Request::builder().method(method).uri(self.url(path).await?);
+        if let Some(token_key) = self.bearer_key {
+            let token = self.provider.get(token_key).await.with_context(|| {
+                format!("resolving bearer token of dependency `{}` from `{token_key}`", self.name)
+            })?;
+            builder = builder.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        Ok(builder)
+    }
+
+    /// Send `request`, recording it against this dependency's metrics.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn send<T>(&self, request: Request<T>) -> Result<Response<Bytes>>
+    where
+        T: Body + Any + Send,
+        T::Data: Into<Vec<u8>>,
+        T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+    {
+        let started = Instant::now();
+        let outcome = self.provider.fetch(request).await;
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        match &outcome {
+            Ok(response) => tracing::debug!(
+                monotonic_counter.dependency_requests = 1,
+                histogram.dependency_duration_ms = duration_ms,
+                dependency = self.name,
+                status = response.status().as_u16(),
+            ),
+            Err(error) => tracing::warn!(
+                monotonic_counter.dependency_requests = 1,
+                monotonic_counter.dependency_failures = 1,
+                histogram.dependency_duration_ms = duration_ms,
+                dependency = self.name,
+                %error,
+                "dependency request failed",
+            ),
+        }
+        outcome.with_context(|| format!("calling dependency `{}`", self.name))
+    }
+
+    /// `GET` `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be built or fails.
+    pub async fn get(&self, path: &str) -> Result<Response<Bytes>> {
+        let request = self.request(Method::GET, path).await?.body(AxumBody::empty())?;
+        self.send(request).await
+    }
+
+    /// `POST` `body` to `path` as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be built or fails.
+    pub async fn post(&self, path: &str, body: Bytes) -> Result<Response<Bytes>> {
+        let request = self
+            .request(Method::POST, path)
+            .await?
+            .header(CONTENT_TYPE, "application/json")
+            .body(AxumBody::from(body))?;
+        self.send(request).await
+    }
+
+    /// `GET` `path` and deserialize the JSON response body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the upstream answers with a
+    /// non-success status, or the body is not a `T`.
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self.get(path).await?;
+        if !response.status().is_success() {
+            bail!("dependency `{}` answered {} for `{path}`", self.name, response.status());
+        }
+        serde_json::from_slice(response.body())
+            .with_context(|| format!("decoding response of dependency `{}`", self.name))
+    }
+}
+
+/// Declares a component's upstream HTTP dependencies and generates a struct
+/// with one [`Dependency`](crate::depends::Dependency) client per upstream.
+///
+/// Each entry names the dependency and the configuration key holding its base
+/// URL; `[bearer = KEY]` sends the configuration value `KEY` as a bearer token.
+/// The generated struct borrows the guest's provider, which must implement
+/// [`Config`](crate::Config) and [`HttpRequest`](crate::HttpRequest).
+///
+/// # Examples
+///
+/// ```ignore
+/// omnia_guest::depends! {
+///     /// Services the journey planner calls.
+///     pub struct Upstreams {
+///         fleet: FLEET_URL,
+///         gtfs: GTFS_STATIC_URL [bearer = GTFS_TOKEN],
+///     }
+/// }
+///
+/// let response = Upstreams::new(&provider).gtfs().get("/feeds/static").await?;
+/// ```
+#[macro_export]
+macro_rules! depends {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$dep_meta:meta])*
+                $dep:ident : $url_key:ident $([bearer = $bearer_key:ident])?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        $vis struct $name<'a, P> {
+            provider: &'a P,
+        }
+
+        impl<'a, P> $name<'a, P> {
+            /// Clients for every declared dependency, calling out through `provider`.
+            #[must_use]
+            $vis const fn new(provider: &'a P) -> Self {
+                Self { provider }
+            }
+
+            $(
+                $(#[$dep_meta])*
+                #[doc = concat!(
+                    "Client for the `", stringify!($dep), "` dependency, based at `",
+                    stringify!($url_key), "`."
+                )]
+                #[must_use]
+                $vis const fn $dep(&self) -> $crate::depends::Dependency<'a, P> {
+                    $crate::depends::Dependency::new(
+                        self.provider,
+                        stringify!($dep),
+                        stringify!($url_key),
+                    )
+                    $(.bearer(stringify!($bearer_key)))?
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Provider {
+        config: HashMap<&'static str, &'static str>,
+        sent: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    impl Config for Provider {
+        async fn get(&self, key: &str) -> Result<String> {
+            self.config.get(key).map(ToString::to_string).ok_or_else(|| anyhow!("no `{key}`"))
+        }
+    }
+
+    impl HttpRequest for Provider {
+        async fn fetch<T>(&self, request: Request<T>) -> Result<Response<Bytes>>
+        where
+            T: Body + Any + Send,
+            T::Data: Into<Vec<u8>>,
+            T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+        {
+            let auth = request.headers().get(AUTHORIZATION).map(|v| v.to_str().unwrap().into());
+            self.sent.lock().unwrap().push((request.uri().to_string(), auth));
+            Ok(Response::new(Bytes::from_static(b"[1,2]")))
+        }
+    }
+
+    depends! {
+        struct Upstreams {
+            fleet: FLEET_URL,
+            gtfs: GTFS_URL [bearer = GTFS_TOKEN],
+        }
+    }
+
+    fn provider() -> Provider {
+        Provider {
+            config: HashMap::from([
+                ("FLEET_URL", "https://fleet.example/api/"),
+                ("GTFS_URL", "https://gtfs.example"),
+                ("GTFS_TOKEN", "secret"),
+            ]),
+            ..Provider::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn dependencies_resolve_urls_and_credentials() {
+        let provider = provider();
+        let upstreams = Upstreams::new(&provider);
+
+        let ids: Vec<u32> = upstreams.fleet().get_json("/vehicles?route=42").await.unwrap();
+        assert_eq!(ids, [1, 2]);
+        upstreams.gtfs().get("feeds").await.unwrap();
+
+        let sent = provider.sent.lock().unwrap();
+        assert_eq!(sent[0], ("https://fleet.example/api/vehicles?route=42".to_string(), None));
+        assert_eq!(
+            sent[1],
+            ("https://gtfs.example/feeds".to_string(), Some("Bearer secret".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_base_url_names_the_key() {
+        let provider = Provider::default();
+        let error = Upstreams::new(&provider).fleet().get("/").await.unwrap_err();
+        assert!(format!("{error:#}").contains("FLEET_URL"));
+    }
+}
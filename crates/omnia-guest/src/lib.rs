@@ -1,10 +1,18 @@
 #![doc = include_str!("../README.md")]
 
 pub mod api;
+pub mod bloom;
 mod capabilities;
+pub mod depends;
 mod error;
+pub mod liveness;
 pub mod mcp;
+pub mod migrate;
 pub mod orm;
+pub mod relay;
+pub mod shed;
+pub mod state_machine;
+pub mod validate;
 
 /// Document store types and helpers (from `omnia-wasi-docstore`).
 pub mod document_store {
@@ -0,0 +1,606 @@
+//! Monotonic sequences, heartbeats, and leases persisted in a [`StateStore`].
+//!
+//! Each component instance serves a single request or message, so anything
+//! that must outlive it lives in the state store:
+//!
+//! - a [`Sequence`] hands out numbers that never repeat or go backwards, either
+//!   as a plain counter or as millisecond timestamps that keep advancing when
+//!   the wall clock steps back;
+//! - [`Heartbeats`] record when each subject (a feed, a vehicle) last reported
+//!   fresh data, flag source clocks that drift from the local one, and report
+//!   subjects that have gone quiet as [`Liveness::Stale`];
+//! - a [`Lease`] gives one holder exclusive use of a key until it stops
+//!   renewing.
+//!
+//! Sequences and leases are updated with the store's atomic
+//! [`increment`](StateStore::increment) and
+//! [`compare_and_swap`](StateStore::compare_and_swap), so concurrent instances
+//! never share a number or a lease. Heartbeats are read-modify-write without a
+//! lock, like [`LoadShedder`](crate::shed::LoadShedder): two instances beating
+//! the same subject at once can both read the old beat. Give each subject a
+//! single writer (one ingester per feed) where that matters.
+//!
+//! ```rust,ignore
+//! let heartbeats = Heartbeats::new(&provider, HeartbeatPolicy::default());
+//! for vehicle in &feed.vehicles {
+//!     heartbeats.beat(&vehicle.id, vehicle.timestamp, Utc::now()).await?;
+//! }
+//! if let Liveness::Stale { silent_for } = heartbeats.status(&vehicle_id, Utc::now()).await? {
+//!     mark_inactive(&vehicle_id, silent_for).await?;
+//! }
+//! ```
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::StateStore;
+
+/// A monotonic sequence stored under a single key.
+#[derive(Debug)]
+pub struct Sequence<'a, S> {
+    store: &'a S,
+    key: String,
+}
+
+impl<'a, S: StateStore> Sequence<'a, S> {
+    /// Create a sequence persisted under `key`.
+    pub fn new(store: &'a S, key: impl Into<String>) -> Self {
+        Self {
+            store,
+            key: key.into(),
+        }
+    }
+
+    /// The last number handed out, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable value.
+    pub async fn current(&self) -> Result<Option<u64>> {
+        self.store.get(&self.key).await?.as_deref().map(counter).transpose()
+    }
+
+    /// The next number in the sequence, starting at 1. The number is taken with
+    /// one atomic increment, so concurrent callers never share it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable value.
+    pub async fn next(&self) -> Result<u64> {
+        let next = self.store.increment(&self.key, 1).await?;
+        Ok(next.cast_unsigned())
+    }
+
+    /// The next time-based token: `now` in Unix milliseconds, or one more than
+    /// the previous token when the clock has not moved past it. Tokens stay
+    /// strictly increasing when the clock steps back or two calls share a
+    /// millisecond. The token is stored with a compare-and-swap, retried when
+    /// another caller gets in first, so concurrent callers never share it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable value.
+    pub async fn next_after(&self, now: DateTime<Utc>) -> Result<u64> {
+        let now = u64::try_from(now.timestamp_millis()).unwrap_or_default();
+        loop {
+            let stored = self.store.get(&self.key).await?;
+            let next = stored
+                .as_deref()
+                .map(counter)
+                .transpose()?
+                .map_or(now, |last| now.max(last.saturating_add(1)));
+            let bytes = next.cast_signed().to_be_bytes();
+            if self.store.compare_and_swap(&self.key, stored.as_deref(), &bytes).await? {
+                return Ok(next);
+            }
+        }
+    }
+}
+
+/// How quickly a subject goes stale and how far its clock may drift.
+#[derive(Clone, Debug)]
+pub struct HeartbeatPolicy {
+    /// Prefix of the state-store key holding each subject's last beat.
+    pub prefix: String,
+    /// How long a subject may go without fresh data before it is stale.
+    pub timeout: TimeDelta,
+    /// How far a beat's source timestamp may differ from the local clock
+    /// before the beat is flagged as drifted.
+    pub max_skew: TimeDelta,
+    /// How long a beat record outlives its last update. Subjects whose record
+    /// has expired report [`Liveness::Unknown`].
+    pub retention: Duration,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            prefix: "heartbeat:".to_string(),
+            timeout: TimeDelta::minutes(2),
+            max_skew: TimeDelta::seconds(30),
+            retention: Duration::from_hours(24),
+        }
+    }
+}
+
+/// The last fresh beat recorded for a subject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Beat {
+    /// Counts the beats that advanced the source timestamp, starting at 1.
+    pub sequence: u64,
+    /// When the source says the data was produced.
+    pub source_time: DateTime<Utc>,
+    /// When the beat was received, by the local clock.
+    pub received_at: DateTime<Utc>,
+}
+
+impl Beat {
+    /// How far the local clock is ahead of the source clock. Negative when the
+    /// source reports a time in the local future.
+    #[must_use]
+    pub fn skew(&self) -> TimeDelta {
+        self.received_at - self.source_time
+    }
+}
+
+/// The outcome of recording a beat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Observation {
+    /// The subject's last fresh beat: the new one when it advanced, otherwise
+    /// the one already stored.
+    pub beat: Beat,
+    /// Whether the beat carried a newer source timestamp than the last one.
+    /// A feed replaying an old snapshot does not advance, and does not keep
+    /// its subject alive.
+    pub advanced: bool,
+    /// Whether the source clock is further from the local clock than the
+    /// policy's `max_skew`.
+    pub drifted: bool,
+}
+
+/// Whether a subject is still reporting fresh data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Liveness {
+    /// No beat is recorded for the subject.
+    Unknown,
+    /// The last fresh beat arrived within the policy's timeout.
+    Alive,
+    /// The last fresh beat is older than the policy's timeout.
+    Stale {
+        /// Time since the last fresh beat arrived.
+        silent_for: TimeDelta,
+    },
+}
+
+/// Records heartbeats for many subjects in a state store.
+#[derive(Debug)]
+pub struct Heartbeats<'a, S> {
+    store: &'a S,
+    policy: HeartbeatPolicy,
+}
+
+impl<'a, S: StateStore> Heartbeats<'a, S> {
+    /// Create a heartbeat recorder over `store`.
+    pub const fn new(store: &'a S, policy: HeartbeatPolicy) -> Self {
+        Self { store, policy }
+    }
+
+    /// The last fresh beat recorded for `subject`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable record.
+    pub async fn last(&self, subject: &str) -> Result<Option<Beat>> {
+        let record: Option<BeatRecord> = load(self.store, &self.key(subject)).await?;
+        Ok(record.map(BeatRecord::into_beat))
+    }
+
+    /// Record that `subject` reported data produced at `source_time`, received
+    /// at `now`. Only a beat newer than the last one is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable record.
+    pub async fn beat(
+        &self, subject: &str, source_time: DateTime<Utc>, now: DateTime<Utc>,
+    ) -> Result<Observation> {
+        let last = self.last(subject).await?;
+        let beat = Beat {
+            sequence: last.map_or(0, |last| last.sequence).saturating_add(1),
+            source_time,
+            received_at: now,
+        };
+        let drifted = beat.skew().abs() > self.policy.max_skew;
+        if drifted {
+            tracing::warn!(
+                monotonic_counter.heartbeat_drift = 1,
+                subject,
+                skew_ms = beat.skew().num_milliseconds(),
+                "source clock drifted",
+            );
+        }
+
+        if let Some(last) = last.filter(|last| last.source_time >= source_time) {
+            tracing::debug!(subject, sequence = last.sequence, "heartbeat did not advance");
+            return Ok(Observation {
+                beat: last,
+                advanced: false,
+                drifted,
+            });
+        }
+
+        let ttl = self.policy.retention.as_secs();
+        save(self.store, &self.key(subject), &BeatRecord::from_beat(&beat), Some(ttl)).await?;
+        Ok(Observation {
+            beat,
+            advanced: true,
+            drifted,
+        })
+    }
+
+    /// Whether `subject` has reported fresh data within the timeout of `now`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable record.
+    pub async fn status(&self, subject: &str, now: DateTime<Utc>) -> Result<Liveness> {
+        let Some(last) = self.last(subject).await? else {
+            return Ok(Liveness::Unknown);
+        };
+        let silent_for = now - last.received_at;
+        if silent_for > self.policy.timeout {
+            return Ok(Liveness::Stale { silent_for });
+        }
+        Ok(Liveness::Alive)
+    }
+
+    fn key(&self, subject: &str) -> String {
+        format!("{}{subject}", self.policy.prefix)
+    }
+}
+
+/// Exclusive use of a key by one holder until the holder stops renewing.
+///
+/// Every change to the lease record is a compare-and-swap, so two holders
+/// racing for a free lease cannot both take it.
+///
+/// ```rust,ignore
+/// let lease = Lease::new(&provider, "lease:gtfs-poller", instance_id, Duration::from_secs(30));
+/// if lease.acquire(Utc::now()).await? {
+///     lease.hold(poll_feed(&provider)).await??;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Lease<'a, S> {
+    store: &'a S,
+    key: String,
+    holder: String,
+    ttl: Duration,
+}
+
+impl<'a, S: StateStore> Lease<'a, S> {
+    /// Create a lease on `key` for `holder`, valid for `ttl` after each renewal.
+    pub fn new(
+        store: &'a S, key: impl Into<String>, holder: impl Into<String>, ttl: Duration,
+    ) -> Self {
+        Self {
+            store,
+            key: key.into(),
+            holder: holder.into(),
+            ttl,
+        }
+    }
+
+    /// Take the lease if it is free, expired, or already held by this holder.
+    /// Returns whether the lease is now held.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable record.
+    pub async fn acquire(&self, now: DateTime<Utc>) -> Result<bool> {
+        let now_ms = now.timestamp_millis();
+        let free =
+            |record: &LeaseRecord| record.holder == self.holder || record.expires_at_ms <= now_ms;
+        self.swap(|record| record.is_none_or(free).then(|| self.record_at(now))).await
+    }
+
+    /// Extend the lease by its TTL from `now`. Returns `false`, leaving the
+    /// record untouched, if another holder has taken the lease meanwhile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable record.
+    pub async fn renew(&self, now: DateTime<Utc>) -> Result<bool> {
+        let ours = |record: &LeaseRecord| record.holder == self.holder;
+        self.swap(|record| record.is_none_or(ours).then(|| self.record_at(now))).await
+    }
+
+    /// Give the lease up, if this holder still has it, by marking it lapsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails or holds an unreadable record.
+    pub async fn release(&self) -> Result<()> {
+        let lapsed = || LeaseRecord {
+            holder: self.holder.clone(),
+            expires_at_ms: i64::MIN,
+        };
+        let ours = |record: &LeaseRecord| record.holder == self.holder;
+        self.swap(|record| record.is_some_and(ours).then(lapsed)).await?;
+        Ok(())
+    }
+
+    /// Run `work` while renewing the lease every third of its TTL.
+    ///
+    /// Off wasm32 there is no runtime-agnostic timer, so the lease is renewed
+    /// once before `work` starts and not again while it runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, abandoning `work`, if a renewal fails or another
+    /// holder takes the lease.
+    pub async fn hold<F>(&self, work: F) -> Result<F::Output>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        self.renew_during(work).await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn renew_during<F>(&self, work: F) -> Result<F::Output>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        use futures::future::{Either, select};
+
+        let renewals = async {
+            loop {
+                if let Err(error) = self.renew_or_bail().await {
+                    return error;
+                }
+                let nanos = u64::try_from((self.ttl / 3).as_nanos()).unwrap_or(u64::MAX);
+                wasip3::clocks::monotonic_clock::wait_for(nanos).await;
+            }
+        };
+        let (work, renewals) = (std::pin::pin!(work), std::pin::pin!(renewals));
+        match select(work, renewals).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right((error, _)) => Err(error),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn renew_during<F>(&self, work: F) -> Result<F::Output>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        self.renew_or_bail().await?;
+        Ok(work.await)
+    }
+
+    async fn renew_or_bail(&self) -> Result<()> {
+        if !self.renew(Utc::now()).await? {
+            bail!("lease {} lost to another holder", self.key);
+        }
+        Ok(())
+    }
+
+    /// Replace the lease record with the one `update` makes of it, retrying
+    /// when another writer changes the record first. Returns `false`, leaving
+    /// the record untouched, when `update` returns `None`.
+    async fn swap<F>(&self, update: F) -> Result<bool>
+    where
+        F: Fn(Option<&LeaseRecord>) -> Option<LeaseRecord> + Send + Sync,
+    {
+        loop {
+            let stored = self.store.get(&self.key).await?;
+            let record: Option<LeaseRecord> =
+                stored.as_deref().map(serde_json::from_slice).transpose()?;
+            let Some(next) = update(record.as_ref()) else {
+                return Ok(false);
+            };
+            let bytes = serde_json::to_vec(&next)?;
+            if self.store.compare_and_swap(&self.key, stored.as_deref(), &bytes).await? {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn record_at(&self, now: DateTime<Utc>) -> LeaseRecord {
+        let ttl = TimeDelta::from_std(self.ttl).unwrap_or(TimeDelta::MAX);
+        LeaseRecord {
+            holder: self.holder.clone(),
+            expires_at_ms: now.checked_add_signed(ttl).unwrap_or(now).timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BeatRecord {
+    sequence: u64,
+    source_ms: i64,
+    received_ms: i64,
+}
+
+impl BeatRecord {
+    const fn from_beat(beat: &Beat) -> Self {
+        Self {
+            sequence: beat.sequence,
+            source_ms: beat.source_time.timestamp_millis(),
+            received_ms: beat.received_at.timestamp_millis(),
+        }
+    }
+
+    fn into_beat(self) -> Beat {
+        let time = |ms| DateTime::from_timestamp_millis(ms).unwrap_or_default();
+        Beat {
+            sequence: self.sequence,
+            source_time: time(self.source_ms),
+            received_at: time(self.received_ms),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LeaseRecord {
+    holder: String,
+    expires_at_ms: i64,
+}
+
+/// The number a sequence key holds, stored as a big-endian `i64` counter.
+fn counter(bytes: &[u8]) -> Result<u64> {
+    let Ok(bytes) = <[u8; 8]>::try_from(bytes) else {
+        bail!("sequence holds {} bytes, not a counter", bytes.len());
+    };
+    Ok(i64::from_be_bytes(bytes).cast_unsigned())
+}
+
+async fn load<S: StateStore, T: DeserializeOwned>(store: &S, key: &str) -> Result<Option<T>> {
+    let Some(bytes) = store.get(key).await? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_slice(&bytes)?))
+}
+
+async fn save<S: StateStore, T: Serialize + Sync>(
+    store: &S, key: &str, value: &T, ttl_secs: Option<u64>,
+) -> Result<()> {
+    let bytes = serde_json::to_vec(value)?;
+    store.set(key, &bytes, ttl_secs).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::capabilities::MemoryStore;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sequences_never_go_backwards() {
+        let store = MemoryStore::default();
+        let sequence = Sequence::new(&store, "seq:trips");
+        assert_eq!(sequence.next().await.unwrap(), 1);
+        assert_eq!(sequence.next().await.unwrap(), 2);
+
+        let tokens = Sequence::new(&store, "seq:tokens");
+        let first = tokens.next_after(at(10)).await.unwrap();
+        // the clock steps back
+        assert_eq!(tokens.next_after(at(5)).await.unwrap(), first + 1);
+        assert_eq!(tokens.next_after(at(20)).await.unwrap(), first + 10_000);
+    }
+
+    /// A store where another writer gets in ahead of the first swap.
+    struct Contended {
+        inner: MemoryStore,
+        rival: Mutex<Option<Vec<u8>>>,
+    }
+
+    impl Contended {
+        fn new(rival: &[u8]) -> Self {
+            Self {
+                inner: MemoryStore::default(),
+                rival: Mutex::new(Some(rival.to_vec())),
+            }
+        }
+    }
+
+    impl StateStore for Contended {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.inner.get(key).await
+        }
+
+        async fn set(
+            &self, key: &str, value: &[u8], ttl_secs: Option<u64>,
+        ) -> Result<Option<Vec<u8>>> {
+            self.inner.set(key, value, ttl_secs).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.inner.delete(key).await
+        }
+
+        async fn compare_and_swap(
+            &self, key: &str, expected: Option<&[u8]>, new: &[u8],
+        ) -> Result<bool> {
+            let rival = self.rival.lock().unwrap().take();
+            if let Some(rival) = rival {
+                self.inner.set(key, &rival, None).await?;
+            }
+            self.inner.compare_and_swap(key, expected, new).await
+        }
+    }
+
+    #[tokio::test]
+    async fn sequences_retry_past_a_rival_token() {
+        let rival = u64::try_from(at(50).timestamp_millis()).unwrap();
+        let store = Contended::new(&rival.cast_signed().to_be_bytes());
+        let tokens = Sequence::new(&store, "seq:tokens");
+        assert_eq!(tokens.next_after(at(10)).await.unwrap(), rival + 1);
+    }
+
+    #[tokio::test]
+    async fn leases_lose_to_a_rival_that_takes_them_first() {
+        let rival = LeaseRecord {
+            holder: "b".to_string(),
+            expires_at_ms: at(30).timestamp_millis(),
+        };
+        let store = Contended::new(&serde_json::to_vec(&rival).unwrap());
+        let lease = Lease::new(&store, "lease:poller", "a", Duration::from_secs(30));
+        assert!(!lease.acquire(at(0)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn quiet_subjects_go_stale() {
+        let store = MemoryStore::default();
+        let heartbeats = Heartbeats::new(&store, HeartbeatPolicy::default());
+        assert_eq!(heartbeats.status("bus-12", at(0)).await.unwrap(), Liveness::Unknown);
+
+        let fresh = heartbeats.beat("bus-12", at(0), at(1)).await.unwrap();
+        assert!(fresh.advanced && !fresh.drifted);
+
+        // a feed replaying its last snapshot does not keep the vehicle alive
+        let replay = heartbeats.beat("bus-12", at(0), at(100)).await.unwrap();
+        assert!(!replay.advanced);
+        assert_eq!(replay.beat.sequence, 1);
+        assert!(replay.drifted);
+
+        assert_eq!(heartbeats.status("bus-12", at(60)).await.unwrap(), Liveness::Alive);
+        assert_eq!(
+            heartbeats.status("bus-12", at(181)).await.unwrap(),
+            Liveness::Stale {
+                silent_for: TimeDelta::seconds(180)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn leases_exclude_other_holders_until_expiry() {
+        let store = MemoryStore::default();
+        let ttl = Duration::from_secs(30);
+        let first = Lease::new(&store, "lease:poller", "a", ttl);
+        let second = Lease::new(&store, "lease:poller", "b", ttl);
+
+        assert!(first.acquire(at(0)).await.unwrap());
+        assert!(!second.acquire(at(10)).await.unwrap());
+        assert!(!second.renew(at(10)).await.unwrap());
+        assert!(second.acquire(at(31)).await.unwrap());
+        assert!(!first.renew(at(32)).await.unwrap());
+
+        second.release().await.unwrap();
+        assert!(first.acquire(at(33)).await.unwrap());
+    }
+}
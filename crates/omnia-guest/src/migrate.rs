@@ -0,0 +1,144 @@
+//! Schema-versioned state.
+//!
+//! When the shape of a cached value changes between component versions, a
+//! [`Migrations`] registry lets the new component read values the old one
+//! wrote instead of flushing the cache. Values written through
+//! [`StateStore::set_versioned`](crate::StateStore::set_versioned) carry a
+//! two-byte header: a marker byte, then the schema version. On read,
+//! [`StateStore::get_versioned`](crate::StateStore::get_versioned) runs the
+//! upgrade steps from the stored version to the current one, so old values are
+//! migrated lazily, one read at a time. The upgraded value is not written back;
+//! the stored value keeps its old shape until it is next written or expires.
+//!
+//! A value without the header is version 0: the shape written before the
+//! registry was introduced. Adopting migrations therefore needs no flush
+//! either.
+//!
+//! ```rust,ignore
+//! // v0 stored `{"name": ...}`; v1 splits it into first and last names.
+//! static MIGRATIONS: LazyLock<Migrations> =
+//!     LazyLock::new(|| Migrations::new().step(split_name));
+//!
+//! let profile = provider.get_versioned(&key, &MIGRATIONS).await?;
+//! provider.set_versioned(&key, &serde_json::to_vec(&profile)?, None, &MIGRATIONS).await?;
+//! ```
+
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+
+/// First byte of a versioned value. Never the first byte of UTF-8 text, so
+/// JSON and other text values written before versioning stay unambiguous.
+const MARKER: u8 = 0xFF;
+
+type Upgrade = Box<dyn Fn(Vec<u8>) -> Result<Vec<u8>> + Send + Sync>;
+
+/// Ordered upgrade steps for one kind of stored value.
+///
+/// Step `n` upgrades a version-`n` value to version `n + 1`; the current
+/// version is the number of steps.
+#[derive(Default)]
+pub struct Migrations {
+    steps: Vec<Upgrade>,
+}
+
+impl Migrations {
+    /// A registry with no steps, whose current version is 0.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the step upgrading the current version to the next.
+    ///
+    /// # Panics
+    ///
+    /// Panics when adding a 256th step; the version is stored as one byte.
+    #[must_use]
+    pub fn step(
+        mut self, upgrade: impl Fn(Vec<u8>) -> Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        assert!(self.steps.len() < usize::from(u8::MAX), "at most 255 migration steps");
+        self.steps.push(Box::new(upgrade));
+        self
+    }
+
+    /// The version values are written at.
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        u8::try_from(self.steps.len()).unwrap_or(u8::MAX)
+    }
+
+    /// Prefix `value` with the current version's header.
+    #[must_use]
+    pub fn encode(&self, value: &[u8]) -> Vec<u8> {
+        let mut stored = Vec::with_capacity(value.len() + 2);
+        stored.extend_from_slice(&[MARKER, self.version()]);
+        stored.extend_from_slice(value);
+        stored
+    }
+
+    /// Strip the header from `stored` and upgrade the value to the current
+    /// version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `stored` was written at a newer version than this
+    /// registry knows, or an upgrade step fails.
+    pub fn decode(&self, stored: &[u8]) -> Result<Vec<u8>> {
+        let (version, value) = match stored {
+            [MARKER, version, value @ ..] => (*version, value),
+            _ => (0, stored),
+        };
+        if version > self.version() {
+            bail!(
+                "value is at schema version {version}, newer than the current version {}",
+                self.version()
+            );
+        }
+
+        let mut value = value.to_vec();
+        for (from, upgrade) in self.steps.iter().enumerate().skip(usize::from(version)) {
+            value = upgrade(value)
+                .with_context(|| format!("upgrading value from schema version {from}"))?;
+        }
+        Ok(value)
+    }
+}
+
+impl fmt::Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migrations").field("version", &self.version()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrations() -> Migrations {
+        Migrations::new()
+            .step(|value| Ok([value.as_slice(), b"+v1"].concat()))
+            .step(|value| Ok([value.as_slice(), b"+v2"].concat()))
+    }
+
+    #[test]
+    fn old_values_are_upgraded_on_read() {
+        let migrations = migrations();
+        assert_eq!(migrations.version(), 2);
+
+        // Written before versioning was adopted.
+        assert_eq!(migrations.decode(b"{}").unwrap(), b"{}+v1+v2");
+        // Written by the v1 component.
+        let v1 = Migrations::new().step(Ok).encode(b"{}");
+        assert_eq!(migrations.decode(&v1).unwrap(), b"{}+v2");
+        // Written at the current version.
+        assert_eq!(migrations.decode(&migrations.encode(b"{}")).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn newer_values_are_refused() {
+        let newer = migrations().step(Ok).encode(b"{}");
+        migrations().decode(&newer).unwrap_err();
+    }
+}
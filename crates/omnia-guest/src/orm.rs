@@ -6,22 +6,42 @@
 //!
 //! It re-exports `Row` and `DataType` from `omnia-wasi-sql` for convenience.
 
+mod cache;
 mod delete;
 mod entity;
+mod error;
 mod filter;
+mod geo;
 mod insert;
+mod interval;
 mod join;
+mod projection;
 mod query;
+mod retry;
 mod select;
+mod transaction;
 mod update;
 
+pub use cache::cached_query;
 pub use delete::DeleteBuilder;
-pub use entity::{Entity, EntityValues, FetchValue};
+pub use entity::{ColumnIndex, Entity, EntityValues, FetchValue};
+pub use error::SqlError;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use error::sql_error;
 pub use filter::{CmpOp, ColRef, Filter};
+pub use geo::Point;
 pub use insert::{ConflictSet, InsertBuilder, NoConflict};
+pub use interval::Interval;
 pub use join::{Join, JoinKind};
-pub use omnia_wasi_sql::{DataType, Field, Row};
+pub use omnia_wasi_sql::{DataType, ExecResult, Field, Row};
+pub use projection::Projection;
+pub use query::{Dialect, Query, StatementKind};
+#[cfg(target_arch = "wasm32")]
+pub use retry::retry_serializable;
 pub use select::SelectBuilder;
+#[cfg(target_arch = "wasm32")]
+pub use transaction::Transaction;
+pub use transaction::{IsolationLevel, TransactionOptions};
 pub use update::UpdateBuilder;
 
 #[doc(hidden)]
@@ -29,8 +49,36 @@ pub mod __private {
     pub use sea_query::Value;
 }
 
+/// Validates an entity against the schema file when `schema-check` is enabled.
+///
+/// Defined per feature so the check follows `omnia-guest`'s features rather
+/// than those of the crate invoking `entity!`.
+#[cfg(feature = "schema-check")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __check_entity {
+    ($($decl:tt)*) => {
+        $crate::check_entity!($($decl)*);
+    };
+}
+
+#[cfg(not(feature = "schema-check"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __check_entity {
+    ($($decl:tt)*) => {};
+}
+
 /// Declares an ORM entity with automatic `Entity` trait implementation.
 ///
+/// The struct and its fields may use any visibility, may declare type parameters, and carry
+/// arbitrary attributes (`#[derive]`, `#[serde(..)]`, docs). A field followed by `= expr` falls
+/// back to that value when its column is absent from the row.
+///
+/// With the `schema-check` feature, the table and every column are checked at compile time
+/// against `schema.json` or `schema.sql` in the crate root (or the file named by
+/// `OMNIA_SCHEMA`).
+///
 /// # Examples
 ///
 /// ```ignore
@@ -41,34 +89,55 @@ pub mod __private {
 ///     pub struct Post {
 ///         pub id: i32,
 ///         pub title: String,
+///         #[serde(default)]
+///         views: i64 = 0,
 ///     }
 /// }
 /// ```
 #[macro_export]
 macro_rules! entity {
+    // Field fetch with a fallback for absent columns
+    (@fetch $index:ident, $row:ident, $field_name:ident, $field_type:ty, $default:expr) => {
+        $index.fetch_or::<$field_type>($row, stringify!($field_name), || $default)?
+    };
+
+    // Field fetch that requires the column
+    (@fetch $index:ident, $row:ident, $field_name:ident, $field_type:ty) => {
+        $index.fetch::<$field_type>($row, stringify!($field_name))?
+    };
+
     // Full form: columns + joins + struct (single code-generation arm)
     (
         table = $table:literal,
         columns = [$( ($col_table:literal, $col_name:literal, $col_field:literal) ),* $(,)?],
         joins = [$($join:expr),* $(,)?],
         $(#[$meta:meta])*
-        pub struct $struct_name:ident {
+        $vis:vis struct $struct_name:ident $(<$($generic:ident),+ $(,)?>)? {
             $(
                 $(#[$field_meta:meta])*
-                pub $field_name:ident : $field_type:ty
+                $field_vis:vis $field_name:ident : $field_type:ty $(= $default:expr)?
             ),* $(,)?
         }
     ) => {
+        $crate::__check_entity! {
+            table = $table,
+            columns = [$( ($col_table, $col_name, $col_field) ),*],
+            fields = [$($field_name),*],
+        }
+
         $(#[$meta])*
         /// Entity table row generated by [`entity!`].
-        pub struct $struct_name {
+        $vis struct $struct_name $(<$($generic),+>)? {
             $(
                 $(#[$field_meta])*
-                pub $field_name : $field_type
+                $field_vis $field_name : $field_type
             ),*
         }
 
-        impl $crate::orm::Entity for $struct_name {
+        impl $(<$($generic),+>)? $crate::orm::Entity for $struct_name $(<$($generic),+>)?
+        where
+            $($field_type: $crate::orm::FetchValue,)*
+        {
             const TABLE: &'static str = $table;
 
             fn projection() -> &'static [&'static str] {
@@ -84,15 +153,24 @@ macro_rules! entity {
             }
 
             fn from_row(row: &$crate::orm::Row) -> anyhow::Result<Self> {
+                Self::from_indexed(row, &$crate::orm::ColumnIndex::new(row))
+            }
+
+            fn from_indexed(
+                row: &$crate::orm::Row, index: &$crate::orm::ColumnIndex,
+            ) -> anyhow::Result<Self> {
                 Ok(Self {
                     $(
-                        $field_name: <$field_type as $crate::orm::FetchValue>::fetch(row, stringify!($field_name))?,
+                        $field_name: $crate::entity!(@fetch index, row, $field_name, $field_type $(, $default)?),
                     )*
                 })
             }
         }
 
-        impl $crate::orm::EntityValues for $struct_name {
+        impl $(<$($generic),+>)? $crate::orm::EntityValues for $struct_name $(<$($generic),+>)?
+        where
+            $($field_type: Clone + Into<$crate::orm::__private::Value>,)*
+        {
             fn __to_values(&self) -> Vec<(&'static str, $crate::orm::__private::Value)> {
                 vec![
                     $(
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+use anyhow::{Result, ensure};
+
+use super::DataType;
+use super::query::{Query, StatementKind};
+
+/// The rendered shape of a cached query: everything but its parameter values.
+#[derive(Clone)]
+struct Shape {
+    sql: String,
+    table: &'static str,
+    kind: StatementKind,
+    expects_rows: bool,
+}
+
+/// A cached shape is found by its name and the types of its parameters, so a
+/// name reused with differently typed parameters renders its own entry.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    name: &'static str,
+    types: Vec<&'static str>,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<Key, Shape>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Reuse the SQL rendered for a query shape, binding fresh `params` on each call.
+///
+/// `build` runs only the first time `name` is seen with parameters of these
+/// types; its parameter values are placeholders and are replaced by `params`,
+/// whose types must match them. The cache lives as long as the guest instance,
+/// so it saves work for shapes built repeatedly within one invocation (batch
+/// ingest loops) or in hosts that reuse instances.
+///
+/// ```rust,ignore
+/// let query = cached_query("agency_by_id", vec![DataType::Int64(Some(id))], || {
+///     SelectBuilder::<Agency>::new().r#where(Filter::eq("agency_id", 0_i64)).build()
+/// })?;
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `build` fails or renders placeholders whose types do not
+/// match `params`.
+pub fn cached_query<F>(name: &'static str, params: Vec<DataType>, build: F) -> Result<Query>
+where
+    F: FnOnce() -> Result<Query>,
+{
+    let key = Key {
+        name,
+        types: params.iter().map(type_name).collect(),
+    };
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let shape = match cache.entry(key) {
+        Entry::Occupied(entry) => entry.get().clone(),
+        Entry::Vacant(entry) => {
+            let query = build()?;
+            let rendered: Vec<_> = query.params.iter().map(type_name).collect();
+            ensure!(
+                rendered == entry.key().types,
+                "cached query '{name}' binds parameters {rendered:?}, got {:?}",
+                entry.key().types
+            );
+            entry
+                .insert(Shape {
+                    sql: query.sql,
+                    table: query.table,
+                    kind: query.kind,
+                    expects_rows: query.expects_rows,
+                })
+                .clone()
+        }
+    };
+    drop(cache);
+    Ok(Query {
+        sql: shape.sql,
+        params,
+        table: shape.table,
+        kind: shape.kind,
+        expects_rows: shape.expects_rows,
+    })
+}
+
+const fn type_name(value: &DataType) -> &'static str {
+    match value {
+        DataType::Int32(_) => "int32",
+        DataType::Int64(_) => "int64",
+        DataType::Uint32(_) => "uint32",
+        DataType::Uint64(_) => "uint64",
+        DataType::Float(_) => "float",
+        DataType::Double(_) => "double",
+        DataType::Str(_) => "str",
+        DataType::Boolean(_) => "boolean",
+        DataType::Date(_) => "date",
+        DataType::Time(_) => "time",
+        DataType::Timestamp(_) => "timestamp",
+        DataType::Binary(_) => "binary",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_once_per_key() {
+        let build = || {
+            Ok(Query {
+                sql: "SELECT 1 WHERE id = $1".to_string(),
+                params: vec![DataType::Int64(Some(0))],
+                table: "t",
+                kind: StatementKind::Select,
+                expects_rows: true,
+            })
+        };
+
+        let first = cached_query("builds_once", vec![DataType::Int64(Some(1))], build).unwrap();
+        let second = cached_query("builds_once", vec![DataType::Int64(Some(2))], || {
+            panic!("shape already cached")
+        })
+        .unwrap();
+
+        assert_eq!(first.sql, second.sql);
+        assert!(matches!(second.params[..], [DataType::Int64(Some(2))]));
+    }
+
+    #[test]
+    fn rejects_param_count_mismatch() {
+        let build = || {
+            Ok(Query {
+                sql: "SELECT 1 WHERE a = $1 AND b = $2".to_string(),
+                params: vec![DataType::Int32(Some(0)), DataType::Int32(Some(0))],
+                table: "t",
+                kind: StatementKind::Select,
+                expects_rows: true,
+            })
+        };
+
+        cached_query("mismatch", vec![DataType::Int32(Some(1))], build).unwrap_err();
+    }
+
+    #[test]
+    fn parameter_types_are_part_of_the_key() {
+        let build = |sql: &str, param: DataType| {
+            let sql = sql.to_string();
+            move || -> Result<Query> {
+                Ok(Query {
+                    sql,
+                    params: vec![param],
+                    table: "t",
+                    kind: StatementKind::Select,
+                    expects_rows: true,
+                })
+            }
+        };
+
+        let by_id = cached_query(
+            "lookup",
+            vec![DataType::Int64(Some(7))],
+            build("SELECT 1 WHERE id = $1", DataType::Int64(Some(0))),
+        )
+        .unwrap();
+        let by_name = cached_query(
+            "lookup",
+            vec![DataType::Str(Some("NX1".to_string()))],
+            build("SELECT 1 WHERE name = $1", DataType::Str(Some(String::new()))),
+        )
+        .unwrap();
+
+        assert_eq!(by_id.sql, "SELECT 1 WHERE id = $1");
+        assert_eq!(by_name.sql, "SELECT 1 WHERE name = $1");
+    }
+}
@@ -5,7 +5,8 @@ use sea_query::{Alias, SimpleExpr};
 
 use super::entity::Entity;
 use super::filter::Filter;
-use super::query::{Query, finish};
+use super::query::{Dialect, Query, StatementKind, finish};
+use super::select::table_ref;
 
 /// Builder for constructing DELETE queries.
 pub struct DeleteBuilder<M: Entity> {
@@ -53,21 +54,33 @@ impl<M: Entity> DeleteBuilder<M> {
     /// remove every row), or if a query value cannot be converted to a WASI data
     /// type.
     pub fn build(self) -> Result<Query> {
+        self.build_for(Dialect::Postgres)
+    }
+
+    /// Build the DELETE query in `dialect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no `WHERE` filter was set, if `RETURNING` columns were
+    /// requested for MySQL, or if a query value cannot be converted to a WASI data
+    /// type.
+    pub fn build_for(self, dialect: Dialect) -> Result<Query> {
         if self.filters.is_empty() {
             anyhow::bail!("refusing to build an unfiltered DELETE; add a `.where(...)` clause");
         }
 
         let mut statement = sea_query::Query::delete();
-        statement.from_table(Alias::new(M::TABLE));
+        statement.from_table(table_ref(M::TABLE));
 
         for filter in self.filters {
             statement.and_where(filter);
         }
 
+        let expects_rows = !self.returning.is_empty();
         for column in self.returning {
             statement.returning_col(Alias::new(column));
         }
 
-        finish(&statement, M::TABLE, "delete")
+        finish(&statement, M::TABLE, StatementKind::Delete, expects_rows, dialect)
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::{Result, anyhow, bail};
 use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use sea_query::{Value, Values};
@@ -10,12 +12,89 @@ use super::{DataType, Row};
 /// This trait is implemented for all standard Rust types that can be
 /// fetched from a database row (`i32`, `String`, `DateTime`, etc.).
 pub trait FetchValue: Sized {
+    /// Convert a column value to this type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value cannot be converted to the target type.
+    fn decode(value: &DataType) -> anyhow::Result<Self>;
+
+    /// The result when the column is absent from the row.
+    ///
+    /// # Errors
+    ///
+    /// Returns a "missing column" error unless the type tolerates absence (as `Option` does).
+    fn missing(col: &str) -> anyhow::Result<Self> {
+        bail!("missing column '{col}'")
+    }
+
     /// Fetch a value from a row by column name.
     ///
     /// # Errors
     ///
     /// Returns an error if the column is missing or the value cannot be converted to the target type.
-    fn fetch(row: &Row, col: &str) -> anyhow::Result<Self>;
+    fn fetch(row: &Row, col: &str) -> anyhow::Result<Self> {
+        find_field(row, col).map_or_else(|| Self::missing(col), Self::decode)
+    }
+
+    /// Fetch a value from a row by column name, using `default` when the column is absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column is present but its value cannot be converted to the target type.
+    fn fetch_or(row: &Row, col: &str, default: impl FnOnce() -> Self) -> anyhow::Result<Self> {
+        find_field(row, col).map_or_else(|| Ok(default()), Self::decode)
+    }
+}
+
+/// Column name to position lookup, built once per row or result set.
+///
+/// Rows from one result set share a column layout, so an index built from the
+/// first row decodes the rest without scanning their fields by name.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnIndex {
+    positions: HashMap<String, usize>,
+}
+
+impl ColumnIndex {
+    /// Index the columns of `row`.
+    #[must_use]
+    pub fn new(row: &Row) -> Self {
+        let positions =
+            row.fields.iter().enumerate().map(|(i, field)| (field.name.clone(), i)).collect();
+        Self { positions }
+    }
+
+    /// Look up a column value, falling back to a scan if `row` has a different layout.
+    #[must_use]
+    pub fn value<'r>(&self, row: &'r Row, col: &str) -> Option<&'r DataType> {
+        self.positions
+            .get(col)
+            .and_then(|&i| row.fields.get(i))
+            .filter(|field| field.name == col)
+            .map(|field| &field.value)
+            .or_else(|| find_field(row, col))
+    }
+
+    /// Fetch a value by column name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column is missing or the value cannot be converted to the target type.
+    pub fn fetch<T: FetchValue>(&self, row: &Row, col: &str) -> Result<T> {
+        self.value(row, col).map_or_else(|| T::missing(col), T::decode)
+    }
+
+    /// Fetch a value by column name, using `default` when the column is absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column is present but its value cannot be converted to the target type.
+    pub fn fetch_or<T: FetchValue>(
+        &self, row: &Row, col: &str, default: impl FnOnce() -> T,
+    ) -> Result<T> {
+        self.value(row, col).map_or_else(|| Ok(default()), T::decode)
+    }
 }
 
 /// Trait for database entities with metadata for query building.
@@ -44,10 +123,36 @@ pub trait Entity: Sized {
 
     /// Construct an entity instance from a database row.
     ///
+    /// This indexes the row's columns first; decode a result set with
+    /// [`from_rows`](Self::from_rows), or [`from_indexed`](Self::from_indexed)
+    /// with one shared [`ColumnIndex`], to index its layout once.
+    ///
     /// # Errors
     ///
     /// Returns an error if any required column is missing or cannot be converted to the expected type.
     fn from_row(row: &Row) -> Result<Self>;
+
+    /// Construct an entity instance from a row using a prebuilt column index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any required column is missing or cannot be converted to the expected type.
+    fn from_indexed(row: &Row, _index: &ColumnIndex) -> Result<Self> {
+        Self::from_row(row)
+    }
+
+    /// Construct entities from a result set, indexing its column layout once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any row cannot be converted.
+    fn from_rows(rows: &[Row]) -> Result<Vec<Self>> {
+        let Some(first) = rows.first() else {
+            return Ok(Vec::new());
+        };
+        let index = ColumnIndex::new(first);
+        rows.iter().map(|row| Self::from_indexed(row, &index)).collect()
+    }
 }
 
 /// Internal trait for extracting entity values. Automatically implemented by the `entity!` macro.
@@ -90,8 +195,8 @@ fn value_to_wasi_datatype(value: Value) -> Result<DataType> {
 macro_rules! fetch {
     ($($ty:ty => $variant:ident),* $(,)?) => {$(
         impl FetchValue for $ty {
-            fn fetch(row: &Row, col: &str) -> anyhow::Result<Self> {
-                match row_field(row, col)? {
+            fn decode(value: &DataType) -> anyhow::Result<Self> {
+                match value {
                     DataType::$variant(Some(v)) => Ok(v.clone()),
                     _ => bail!(concat!("expected ", stringify!($variant), " data type")),
                 }
@@ -113,38 +218,39 @@ fetch! {
 }
 
 impl FetchValue for DateTime<Utc> {
-    fn fetch(row: &Row, col: &str) -> anyhow::Result<Self> {
-        parse_timestamp(row_field(row, col)?)
+    fn decode(value: &DataType) -> anyhow::Result<Self> {
+        parse_timestamp(value)
     }
 }
 
 impl FetchValue for NaiveDate {
-    fn fetch(row: &Row, col: &str) -> anyhow::Result<Self> {
-        parse_date(row_field(row, col)?)
+    fn decode(value: &DataType) -> anyhow::Result<Self> {
+        parse_date(value)
     }
 }
 
 impl FetchValue for serde_json::Value {
-    fn fetch(row: &Row, col: &str) -> anyhow::Result<Self> {
-        parse_json(row_field(row, col)?)
+    fn decode(value: &DataType) -> anyhow::Result<Self> {
+        parse_json(value)
     }
 }
 
 impl<T: FetchValue> FetchValue for Option<T> {
-    fn fetch(row: &Row, col: &str) -> anyhow::Result<Self> {
-        match row_field(row, col) {
-            Ok(field) if !is_null(field) => Ok(Some(T::fetch(row, col)?)),
-            _ => Ok(None),
-        }
+    fn decode(value: &DataType) -> anyhow::Result<Self> {
+        if is_null(value) { Ok(None) } else { T::decode(value).map(Some) }
+    }
+
+    fn missing(_col: &str) -> anyhow::Result<Self> {
+        Ok(None)
     }
 }
 
-fn row_field<'a>(row: &'a Row, name: &str) -> Result<&'a DataType> {
-    row.fields
-        .iter()
-        .find(|field| field.name == name)
-        .map(|field| &field.value)
-        .ok_or_else(|| anyhow!("missing column '{name}'"))
+fn find_field<'a>(row: &'a Row, name: &str) -> Option<&'a DataType> {
+    row.fields.iter().find(|field| field.name == name).map(|field| &field.value)
+}
+
+pub(super) fn row_field<'a>(row: &'a Row, name: &str) -> Result<&'a DataType> {
+    find_field(row, name).ok_or_else(|| anyhow!("missing column '{name}'"))
 }
 
 const fn is_null(value: &DataType) -> bool {
@@ -281,4 +387,45 @@ mod tests {
         serde_json::Value::fetch(&one_field_row(DataType::Str(Some("not json".to_string()))), "x")
             .unwrap_err();
     }
+
+    #[test]
+    fn column_index_tolerates_layout_drift() {
+        use omnia_wasi_sql::Field;
+
+        let field = |name: &str, v: i32| Field {
+            name: name.to_string(),
+            value: DataType::Int32(Some(v)),
+        };
+        let first = Row {
+            fields: vec![field("a", 1), field("b", 2)],
+            index: "0".to_string(),
+        };
+        let swapped = Row {
+            fields: vec![field("b", 4), field("a", 3)],
+            index: "1".to_string(),
+        };
+
+        let index = ColumnIndex::new(&first);
+        assert_eq!(index.fetch::<i32>(&first, "b").unwrap(), 2);
+        assert_eq!(index.fetch::<i32>(&swapped, "a").unwrap(), 3);
+        assert_eq!(index.fetch::<Option<i32>>(&swapped, "c").unwrap(), None);
+        index.fetch::<i32>(&swapped, "c").unwrap_err();
+    }
+
+    #[test]
+    fn fetch_or_defaults_only_when_column_absent() {
+        use omnia_wasi_sql::Field;
+
+        let row = Row {
+            fields: vec![Field {
+                name: "x".to_string(),
+                value: DataType::Int32(Some(7)),
+            }],
+            index: "0".to_string(),
+        };
+
+        assert_eq!(i32::fetch_or(&row, "x", || 0).unwrap(), 7);
+        assert_eq!(i32::fetch_or(&row, "y", || 42).unwrap(), 42);
+        String::fetch_or(&row, "x", String::new).unwrap_err();
+    }
 }
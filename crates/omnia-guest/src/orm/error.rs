@@ -0,0 +1,74 @@
+use std::fmt;
+
+/// A failure reported by the SQL host.
+///
+/// Statement errors from [`Transaction`](super::Transaction) and the
+/// [`TableStore`](crate::TableStore) defaults carry this type in their `anyhow`
+/// chain, so callers can match on the SQLSTATE code rather than the message:
+///
+/// ```rust,ignore
+/// let conflict = error
+///     .chain()
+///     .filter_map(|cause| cause.downcast_ref::<SqlError>())
+///     .any(|sql| sql.sqlstate() == Some("23000"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SqlError {
+    sqlstate: Option<String>,
+    trace: String,
+}
+
+impl SqlError {
+    /// A failure with the given SQLSTATE code and trace.
+    pub fn new(sqlstate: Option<String>, trace: impl Into<String>) -> Self {
+        Self {
+            sqlstate,
+            trace: trace.into(),
+        }
+    }
+
+    /// The SQLSTATE code the database reported, if any.
+    #[must_use]
+    pub fn sqlstate(&self) -> Option<&str> {
+        self.sqlstate.as_deref()
+    }
+
+    /// The host's description of the failure.
+    #[must_use]
+    pub fn trace(&self) -> &str {
+        &self.trace
+    }
+
+    /// Whether the database rolled the transaction back because it conflicted
+    /// with a concurrent one (SQLSTATE `40001`).
+    #[must_use]
+    pub fn is_serialization_failure(&self) -> bool {
+        self.sqlstate() == Some("40001")
+    }
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.trace)
+    }
+}
+
+impl std::error::Error for SqlError {}
+
+#[cfg(target_arch = "wasm32")]
+impl From<omnia_wasi_sql::types::Error> for SqlError {
+    fn from(err: omnia_wasi_sql::types::Error) -> Self {
+        Self {
+            sqlstate: omnia_wasi_sql::errors::sqlstate(&err),
+            trace: err.trace(),
+        }
+    }
+}
+
+/// Wrap a host error in `context`, keeping the [`SqlError`] in the chain.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn sql_error(
+    context: &'static str,
+) -> impl FnOnce(omnia_wasi_sql::types::Error) -> anyhow::Error {
+    move |err| anyhow::Error::new(SqlError::from(err)).context(context)
+}
@@ -2,6 +2,7 @@ use std::ops::Not;
 
 use sea_query::{Expr, ExprTrait, SimpleExpr, Value};
 
+use super::geo::Point;
 use super::select::table_column;
 
 /// A column reference, optionally qualified with a table name.
@@ -77,6 +78,8 @@ enum FilterKind {
     Like(ColRef, String, bool),
     /// `col BETWEEN low AND high` or `col NOT BETWEEN low AND high` when negated.
     Between(ColRef, Value, Value, bool),
+    /// Geography column within a distance (meters) of a point.
+    WithinRadius(ColRef, Point, f64),
     /// Column-to-column comparison, e.g. `table1.col1 <op> table2.col2`.
     ColCompare(ColRef, CmpOp, ColRef),
     /// Logical AND of multiple filters.
@@ -118,6 +121,10 @@ impl Filter {
             FilterKind::Between(col, low, high, true) => {
                 col.resolve(default_table).not_between(low, high)
             }
+            FilterKind::WithinRadius(col, center, meters) => Expr::cust_with_exprs(
+                "ST_DWithin($1::geography, ST_GeogFromText($2), $3)",
+                [col.resolve(default_table), Expr::value(center.to_string()), Expr::value(meters)],
+            ),
             FilterKind::ColCompare(left, op, right) => {
                 apply_cmp(left.resolve(default_table), op, right.resolve(default_table))
             }
@@ -165,6 +172,9 @@ impl Filter {
             FilterKind::Null(col, neg) => FilterKind::Null(set(col), neg),
             FilterKind::Like(col, pat, neg) => FilterKind::Like(set(col), pat, neg),
             FilterKind::Between(col, lo, hi, neg) => FilterKind::Between(set(col), lo, hi, neg),
+            FilterKind::WithinRadius(col, center, meters) => {
+                FilterKind::WithinRadius(set(col), center, meters)
+            }
             FilterKind::And(filters) => {
                 FilterKind::And(filters.into_iter().map(|f| f.in_table(table)).collect())
             }
@@ -254,6 +264,15 @@ impl Filter {
         Self(FilterKind::Between(ColRef::unqualified(col), low.into(), high.into(), true))
     }
 
+    /// Creates a filter matching rows whose geography column lies within `meters` of `center`.
+    ///
+    /// Renders PostGIS `ST_DWithin` over `geography`, so it needs a PostGIS backend; refine
+    /// results elsewhere with [`Point::distance_to`].
+    #[must_use]
+    pub const fn within_radius(col: &'static str, center: Point, meters: f64) -> Self {
+        Self(FilterKind::WithinRadius(ColRef::unqualified(col), center, meters))
+    }
+
     /// Combines filters with logical AND. Empty list evaluates to `true`.
     #[must_use]
     pub fn and(filters: impl IntoIterator<Item = Self>) -> Self {
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use sea_query::Value;
+
+use super::DataType;
+use super::entity::FetchValue;
+
+/// Mean Earth radius used for great-circle distances, in meters.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// A WGS 84 longitude/latitude position.
+///
+/// Binds as WKT text (`POINT(lon lat)`); decodes from WKT, EWKT (`SRID=4326;POINT(..)`), or a
+/// GeoJSON `Point`, so a PostGIS column selected with `ST_AsText` or `ST_AsGeoJSON` maps
+/// straight onto an entity field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    /// Longitude in degrees.
+    pub lon: f64,
+    /// Latitude in degrees.
+    pub lat: f64,
+}
+
+impl Point {
+    /// Create a point from longitude and latitude in degrees.
+    #[must_use]
+    pub const fn new(lon: f64, lat: f64) -> Self {
+        Self { lon, lat }
+    }
+
+    /// Great-circle (haversine) distance to `other`, in meters.
+    #[must_use]
+    pub fn distance_to(self, other: Self) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+        let (sin_lat, sin_lon) = ((d_lat / 2.0).sin(), (d_lon / 2.0).sin());
+        let a = (lat1.cos() * lat2.cos()).mul_add(sin_lon.powi(2), sin_lat.powi(2));
+        2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+    }
+
+    /// Render as a GeoJSON `Point` geometry.
+    #[must_use]
+    pub fn to_geojson(self) -> serde_json::Value {
+        serde_json::json!({ "type": "Point", "coordinates": [self.lon, self.lat] })
+    }
+
+    fn from_geojson(value: &serde_json::Value) -> Result<Self> {
+        if value.get("type").and_then(serde_json::Value::as_str) != Some("Point") {
+            bail!("expected a GeoJSON Point");
+        }
+        let coordinates = value
+            .get("coordinates")
+            .and_then(serde_json::Value::as_array)
+            .ok_or_else(|| anyhow!("GeoJSON Point has no coordinates"))?;
+        match coordinates[..] {
+            [ref lon, ref lat, ..] => Ok(Self::new(
+                lon.as_f64().ok_or_else(|| anyhow!("invalid longitude"))?,
+                lat.as_f64().ok_or_else(|| anyhow!("invalid latitude"))?,
+            )),
+            _ => bail!("GeoJSON Point needs two coordinates"),
+        }
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "POINT({} {})", self.lon, self.lat)
+    }
+}
+
+impl FromStr for Point {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.starts_with('{') {
+            return Self::from_geojson(&serde_json::from_str(s)?);
+        }
+
+        // Drop an EWKT SRID prefix; only WGS 84 coordinates are meaningful here.
+        let wkt = s.split_once(';').map_or(s, |(_, wkt)| wkt).trim();
+        let body = wkt
+            .get(..5)
+            .filter(|tag| tag.eq_ignore_ascii_case("POINT"))
+            .and_then(|_| wkt[5..].trim().strip_prefix('('))
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| anyhow!("unsupported point: {s}; expected WKT or GeoJSON"))?;
+        let mut coordinates = body.split_whitespace().map(str::parse::<f64>);
+        match (coordinates.next(), coordinates.next()) {
+            (Some(lon), Some(lat)) => Ok(Self::new(lon?, lat?)),
+            _ => bail!("unsupported point: {s}; expected two coordinates"),
+        }
+    }
+}
+
+impl FetchValue for Point {
+    fn decode(value: &DataType) -> Result<Self> {
+        match value {
+            DataType::Str(Some(raw)) => raw.parse(),
+            DataType::Binary(Some(bytes)) => Self::from_geojson(&serde_json::from_slice(bytes)?),
+            _ => bail!("expected WKT or GeoJSON point data type"),
+        }
+    }
+}
+
+impl From<Point> for Value {
+    fn from(point: Point) -> Self {
+        Self::String(Some(point.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orm::{Filter, SelectBuilder};
+
+    crate::entity! {
+        table = "vehicles",
+        struct Vehicle {
+            id: i64,
+            location: Point,
+        }
+    }
+
+    #[test]
+    fn parses_wkt_ewkt_and_geojson() {
+        let expected = Point::new(174.7633, -36.8485);
+
+        assert_eq!("POINT(174.7633 -36.8485)".parse::<Point>().unwrap(), expected);
+        assert_eq!("SRID=4326;point (174.7633 -36.8485)".parse::<Point>().unwrap(), expected);
+        assert_eq!(
+            r#"{"type":"Point","coordinates":[174.7633,-36.8485]}"#.parse::<Point>().unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn round_trips_through_wkt() {
+        let point = Point::new(-0.1276, 51.5072);
+        assert_eq!(point.to_string().parse::<Point>().unwrap(), point);
+    }
+
+    #[test]
+    fn haversine_distance() {
+        let auckland = Point::new(174.7633, -36.8485);
+        let wellington = Point::new(174.7762, -41.2865);
+
+        let km = auckland.distance_to(wellington) / 1000.0;
+        assert!((km - 493.5).abs() < 1.0, "got {km} km");
+    }
+
+    #[test]
+    fn within_radius_renders_st_dwithin() {
+        let query = SelectBuilder::<Vehicle>::new()
+            .r#where(Filter::within_radius("location", Point::new(174.7633, -36.8485), 500.0))
+            .build()
+            .unwrap();
+
+        assert!(query.sql.ends_with(
+            r#"WHERE ST_DWithin("vehicles"."location"::geography, ST_GeogFromText($1), $2)"#
+        ));
+        assert!(
+            matches!(query.params.as_slice(), [DataType::Str(Some(wkt)), DataType::Double(Some(_))]
+            if wkt == "POINT(174.7633 -36.8485)")
+        );
+    }
+}
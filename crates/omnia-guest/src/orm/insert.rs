@@ -4,7 +4,8 @@ use anyhow::Result;
 use sea_query::{Alias, OnConflict, SimpleExpr, Value};
 
 use super::entity::{Entity, EntityValues};
-use super::query::{Query, finish};
+use super::query::{Dialect, Query, StatementKind, finish};
+use super::select::table_ref;
 
 /// Marker: no `ON CONFLICT` target has been set.
 pub struct NoConflict;
@@ -95,6 +96,14 @@ impl<M: Entity, C> InsertBuilder<M, C> {
         self.values.push((column, value.into()));
         self
     }
+
+    /// Omit columns the database generates itself (serial or identity keys, defaulted
+    /// timestamps) from the values populated so far, typically by [`InsertBuilder::from_entity`].
+    #[must_use]
+    pub fn skip_columns(mut self, columns: &[&str]) -> Self {
+        self.values.retain(|(column, _)| !columns.contains(column));
+        self
+    }
 }
 
 impl<M: Entity> InsertBuilder<M, ConflictSet> {
@@ -135,8 +144,17 @@ impl<M: Entity, C> InsertBuilder<M, C> {
     ///
     /// Returns an error if any query values cannot be converted to WASI data types.
     pub fn build(self) -> Result<Query> {
+        self.build_for(Dialect::Postgres)
+    }
+
+    /// Build the INSERT query in `dialect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any query values cannot be converted to WASI data types.
+    pub fn build_for(self, dialect: Dialect) -> Result<Query> {
         let mut statement = sea_query::Query::insert();
-        statement.into_table(Alias::new(M::TABLE));
+        statement.into_table(table_ref(M::TABLE));
 
         let columns: Vec<_> = self.values.iter().map(|(column, _)| Alias::new(*column)).collect();
         let row: Vec<SimpleExpr> =
@@ -158,6 +176,53 @@ impl<M: Entity, C> InsertBuilder<M, C> {
             statement.on_conflict(on_conflict);
         }
 
-        finish(&statement, M::TABLE, "insert")
+        finish(&statement, M::TABLE, StatementKind::Insert, false, dialect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::entity! {
+        table = "trips",
+        #[derive(Clone)]
+        struct Trip {
+            id: i64,
+            route: String,
+        }
+    }
+
+    #[test]
+    fn skip_columns_omits_generated_id() {
+        let trip = Trip {
+            id: 0,
+            route: "NX1".to_string(),
+        };
+        let query =
+            InsertBuilder::<Trip>::from_entity(&trip).skip_columns(&["id"]).build().unwrap();
+
+        assert_eq!(query.sql, r#"INSERT INTO "trips" ("route") VALUES ($1)"#);
+        assert_eq!(query.params.len(), 1);
+    }
+
+    #[test]
+    fn mysql_dialect() {
+        let trip = Trip {
+            id: 7,
+            route: "NX1".to_string(),
+        };
+        let query = InsertBuilder::<Trip>::from_entity(&trip)
+            .skip_columns(&["id"])
+            .build_for(Dialect::MySql)
+            .unwrap();
+        assert_eq!(query.sql, "INSERT INTO `trips` (`route`) VALUES (?)");
+
+        let upsert = InsertBuilder::<Trip>::from_entity(&trip)
+            .on_conflict("id")
+            .do_update_all()
+            .build_for(Dialect::MySql)
+            .unwrap();
+        assert!(upsert.sql.contains("ON DUPLICATE KEY UPDATE"), "{}", upsert.sql);
     }
 }
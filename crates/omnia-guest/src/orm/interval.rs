@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail, ensure};
+use chrono::TimeDelta;
+use sea_query::Value;
+
+use super::DataType;
+use super::entity::FetchValue;
+
+const MINUTE: f64 = 60.0;
+const HOUR: f64 = 60.0 * MINUTE;
+const DAY: f64 = 24.0 * HOUR;
+const WEEK: f64 = 7.0 * DAY;
+
+const DATE_UNITS: [(char, f64); 2] = [('W', WEEK), ('D', DAY)];
+const TIME_UNITS: [(char, f64); 3] = [('H', HOUR), ('M', MINUTE), ('S', 1.0)];
+
+/// A signed span of time held in an SQL interval or integer-seconds column.
+///
+/// Binds as whole seconds (`BIGINT`). Decodes from integer or fractional seconds, or from
+/// interval text in Postgres (`1 day 02:30:00`) or ISO 8601 (`P1DT2H30M`) form. Month and
+/// year units are rejected because their length is not fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Interval(pub TimeDelta);
+
+impl Interval {
+    /// Create an interval of whole seconds.
+    #[must_use]
+    pub const fn seconds(secs: i64) -> Self {
+        Self(TimeDelta::seconds(secs))
+    }
+
+    /// The interval as a `std::time::Duration`, or `None` if it is negative.
+    #[must_use]
+    pub fn to_std(self) -> Option<Duration> {
+        self.0.to_std().ok()
+    }
+}
+
+impl From<TimeDelta> for Interval {
+    fn from(delta: TimeDelta) -> Self {
+        Self(delta)
+    }
+}
+
+impl From<Interval> for TimeDelta {
+    fn from(interval: Interval) -> Self {
+        interval.0
+    }
+}
+
+impl TryFrom<Duration> for Interval {
+    type Error = chrono::OutOfRangeError;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        TimeDelta::from_std(duration).map(Self)
+    }
+}
+
+impl FetchValue for Interval {
+    #[allow(clippy::cast_precision_loss)]
+    fn decode(value: &DataType) -> Result<Self> {
+        let secs = match value {
+            DataType::Int32(Some(v)) => f64::from(*v),
+            DataType::Int64(Some(v)) => *v as f64,
+            DataType::Uint32(Some(v)) => f64::from(*v),
+            DataType::Uint64(Some(v)) => *v as f64,
+            DataType::Double(Some(v)) => *v,
+            DataType::Float(Some(v)) => f64::from(*v),
+            DataType::Str(Some(raw)) | DataType::Time(Some(raw)) => parse_interval(raw)?,
+            _ => bail!("expected interval or integer seconds data type"),
+        };
+        from_secs(secs).map(Self)
+    }
+}
+
+impl FetchValue for TimeDelta {
+    fn decode(value: &DataType) -> Result<Self> {
+        Interval::decode(value).map(|interval| interval.0)
+    }
+}
+
+impl FetchValue for Duration {
+    fn decode(value: &DataType) -> Result<Self> {
+        Interval::decode(value)?.to_std().ok_or_else(|| anyhow!("negative interval"))
+    }
+}
+
+impl From<Interval> for Value {
+    fn from(interval: Interval) -> Self {
+        Self::BigInt(Some(interval.0.num_seconds()))
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn from_secs(secs: f64) -> Result<TimeDelta> {
+    let nanos = (secs * 1e9).round();
+    ensure!(nanos.abs() < 9.2e18, "interval out of range: {secs} seconds");
+    Ok(TimeDelta::nanoseconds(nanos as i64))
+}
+
+/// Seconds in a bare number, a Postgres interval, or an ISO 8601 duration.
+fn parse_interval(raw: &str) -> Result<f64> {
+    let text = raw.trim();
+    if let Ok(secs) = text.parse::<f64>() {
+        return Ok(secs);
+    }
+    let parsed = text.strip_prefix("-P").map_or_else(
+        || text.strip_prefix('P').map_or_else(|| parse_postgres(text), parse_iso8601),
+        |iso| parse_iso8601(iso).map(|secs| -secs),
+    );
+    parsed.filter(|_| !text.is_empty()).ok_or_else(|| {
+        anyhow!("unsupported interval: {raw}; expected seconds, Postgres, or ISO 8601 format")
+    })
+}
+
+/// `[nW][nD][T[nH][nM][nS]]`, the part after `P`.
+fn parse_iso8601(body: &str) -> Option<f64> {
+    let (date, time) = body.split_once('T').unwrap_or((body, ""));
+    let mut total = 0.0;
+    for (part, units) in [(date, &DATE_UNITS[..]), (time, &TIME_UNITS[..])] {
+        let mut rest = part;
+        for &(unit, scale) in units {
+            if let Some((amount, tail)) = rest.split_once(unit) {
+                total = amount.parse::<f64>().ok()?.mul_add(scale, total);
+                rest = tail;
+            }
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+    }
+    Some(total)
+}
+
+/// Postgres output such as `3 days`, `-1 days +02:00:00`, or `00:01:30.5`.
+fn parse_postgres(text: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token.contains(':') {
+            total += parse_clock(token)?;
+            continue;
+        }
+        let amount = token.parse::<f64>().ok()?;
+        let scale = match tokens.next()?.trim_end_matches('s') {
+            "week" => WEEK,
+            "day" => DAY,
+            "hour" => HOUR,
+            "min" | "minute" => MINUTE,
+            "sec" | "second" => 1.0,
+            _ => return None,
+        };
+        total = amount.mul_add(scale, total);
+    }
+    Some(total)
+}
+
+fn parse_clock(token: &str) -> Option<f64> {
+    let (sign, clock) = token
+        .strip_prefix('-')
+        .map_or_else(|| (1.0, token.trim_start_matches('+')), |clock| (-1.0, clock));
+    let mut parts = clock.split(':');
+    let hours = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next()?.parse::<f64>().ok()?;
+    let seconds = parts.next().map_or(Some(0.0), |s| s.parse::<f64>().ok())?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(sign * hours.mul_add(HOUR, minutes.mul_add(MINUTE, seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orm::InsertBuilder;
+
+    crate::entity! {
+        table = "schedules",
+        struct Schedule {
+            id: i64,
+            offset: Interval,
+        }
+    }
+
+    fn decode(raw: &str) -> TimeDelta {
+        TimeDelta::decode(&DataType::Str(Some(raw.to_string()))).unwrap()
+    }
+
+    #[test]
+    fn decodes_interval_text() {
+        assert_eq!(decode("90"), TimeDelta::seconds(90));
+        assert_eq!(decode("00:01:30.5"), TimeDelta::milliseconds(90_500));
+        assert_eq!(decode("1 day 02:00:00"), TimeDelta::hours(26));
+        assert_eq!(decode("-1 days +02:00:00"), TimeDelta::hours(-22));
+        assert_eq!(decode("3 mins"), TimeDelta::minutes(3));
+        assert_eq!(decode("P1DT2H30M"), TimeDelta::minutes(1590));
+        assert_eq!(decode("-PT45S"), TimeDelta::seconds(-45));
+    }
+
+    #[test]
+    fn rejects_variable_length_units() {
+        for raw in ["1 mon", "P1Y", "", "soon"] {
+            Interval::decode(&DataType::Str(Some(raw.to_string()))).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn decodes_integer_seconds() {
+        assert_eq!(
+            Duration::decode(&DataType::Int64(Some(300))).unwrap(),
+            Duration::from_secs(300)
+        );
+        Duration::decode(&DataType::Int32(Some(-5))).unwrap_err();
+    }
+
+    #[test]
+    fn binds_as_whole_seconds() {
+        let schedule = Schedule {
+            id: 1,
+            offset: Interval::seconds(-600),
+        };
+        let query = InsertBuilder::<Schedule>::from_entity(&schedule).build().unwrap();
+
+        assert!(matches!(
+            query.params[..],
+            [DataType::Int64(Some(1)), DataType::Int64(Some(-600))]
+        ));
+    }
+}
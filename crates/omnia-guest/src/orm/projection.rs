@@ -0,0 +1,160 @@
+use anyhow::Result;
+use base64ct::{Base64, Encoding};
+use serde_json::{Map, Number, Value};
+
+use super::entity::row_field;
+use super::{DataType, Row};
+
+type Compute = Box<dyn Fn(&Row) -> Result<Value> + Send + Sync>;
+
+/// Maps selected rows straight into compact JSON objects for client payloads.
+///
+/// Use a projection when rows are only re-shaped for broadcast: it renames
+/// columns and adds computed fields without decoding a full [`Entity`](super::Entity)
+/// first.
+///
+/// ```rust,ignore
+/// let projection = Projection::new()
+///     .rename("trip_id", "id")
+///     .field("route")
+///     .computed("late", |row| Ok(json!(i64::fetch(row, "delay_secs")? > 300)));
+/// Provider.send("vehicles", &projection.to_bytes(&rows)?, None).await?;
+/// ```
+#[derive(Default)]
+pub struct Projection {
+    columns: Vec<(String, String)>,
+    computed: Vec<(String, Compute)>,
+}
+
+impl Projection {
+    /// Create an empty projection.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Include `column` under its own name.
+    #[must_use]
+    pub fn field(self, column: impl Into<String>) -> Self {
+        let column = column.into();
+        self.rename(column.clone(), column)
+    }
+
+    /// Include `column` under the output key `key`.
+    #[must_use]
+    pub fn rename(mut self, column: impl Into<String>, key: impl Into<String>) -> Self {
+        self.columns.push((column.into(), key.into()));
+        self
+    }
+
+    /// Include a value computed from the whole row under `key`.
+    #[must_use]
+    pub fn computed<F>(mut self, key: impl Into<String>, compute: F) -> Self
+    where
+        F: Fn(&Row) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.computed.push((key.into(), Box::new(compute)));
+        self
+    }
+
+    /// Project one row into a JSON object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a projected column is missing or a computed field fails.
+    pub fn project(&self, row: &Row) -> Result<Value> {
+        let mut object = Map::with_capacity(self.columns.len() + self.computed.len());
+        for (column, key) in &self.columns {
+            object.insert(key.clone(), to_json(row_field(row, column)?));
+        }
+        for (key, compute) in &self.computed {
+            object.insert(key.clone(), compute(row)?);
+        }
+        Ok(Value::Object(object))
+    }
+
+    /// Project every row into a JSON array.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any row fails to project.
+    pub fn project_all(&self, rows: &[Row]) -> Result<Value> {
+        rows.iter().map(|row| self.project(row)).collect::<Result<_>>().map(Value::Array)
+    }
+
+    /// Project every row and serialize the array, ready to broadcast.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any row fails to project.
+    pub fn to_bytes(&self, rows: &[Row]) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&self.project_all(rows)?)?)
+    }
+}
+
+fn to_json(value: &DataType) -> Value {
+    match value {
+        DataType::Int32(Some(v)) => Value::from(*v),
+        DataType::Int64(Some(v)) => Value::from(*v),
+        DataType::Uint32(Some(v)) => Value::from(*v),
+        DataType::Uint64(Some(v)) => Value::from(*v),
+        DataType::Float(Some(v)) => {
+            Number::from_f64(f64::from(*v)).map_or(Value::Null, Value::Number)
+        }
+        DataType::Double(Some(v)) => Number::from_f64(*v).map_or(Value::Null, Value::Number),
+        DataType::Str(Some(v))
+        | DataType::Date(Some(v))
+        | DataType::Time(Some(v))
+        | DataType::Timestamp(Some(v)) => Value::String(v.clone()),
+        DataType::Boolean(Some(v)) => Value::Bool(*v),
+        DataType::Binary(Some(v)) => Value::String(Base64::encode_string(v)),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use omnia_wasi_sql::Field;
+    use serde_json::json;
+
+    use super::*;
+    use crate::orm::FetchValue;
+
+    fn row() -> Row {
+        Row {
+            fields: vec![
+                Field {
+                    name: "trip_id".to_string(),
+                    value: DataType::Str(Some("t1".to_string())),
+                },
+                Field {
+                    name: "delay_secs".to_string(),
+                    value: DataType::Int64(Some(420)),
+                },
+                Field {
+                    name: "note".to_string(),
+                    value: DataType::Str(None),
+                },
+            ],
+            index: "0".to_string(),
+        }
+    }
+
+    #[test]
+    fn renames_and_computes() {
+        let projection = Projection::new()
+            .rename("trip_id", "id")
+            .field("note")
+            .computed("late", |row| Ok(json!(i64::fetch(row, "delay_secs")? > 300)));
+
+        assert_eq!(
+            projection.project(&row()).unwrap(),
+            json!({"id": "t1", "note": null, "late": true})
+        );
+    }
+
+    #[test]
+    fn missing_column_fails() {
+        Projection::new().field("route").project(&row()).unwrap_err();
+    }
+}
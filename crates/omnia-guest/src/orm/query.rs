@@ -1,38 +1,119 @@
+use std::time::Instant;
+
 use anyhow::Result;
 use sea_query::backend::{
     EscapeBuilder, OperLeftAssocDecider, PrecedenceDecider, QuotedBuilder, TableRefBuilder,
 };
 use sea_query::prepare::SqlWriter;
 use sea_query::{
-    BinOper, ExplainStatement, Oper, QueryStatementBuilder, Quote, SelectInto, SimpleExpr,
-    SubQueryStatement, Value,
+    BinOper, ExplainStatement, MysqlQueryBuilder, Oper, QueryStatementBuilder, Quote, SelectInto,
+    SimpleExpr, SubQueryStatement, Value,
 };
 
 use super::DataType;
 use super::entity::values_to_wasi_datatypes;
 
+/// A rendered statement with its bound parameters.
+#[derive(Debug)]
 pub struct Query {
+    /// The SQL text with numbered placeholders.
     pub sql: String,
+    /// Parameter values, in placeholder order.
     pub params: Vec<DataType>,
+    /// The table the statement targets, as recorded on tracing spans.
+    pub table: &'static str,
+    /// The kind of statement.
+    pub kind: StatementKind,
+    /// Whether executing the statement yields rows (a `SELECT`, or a write with `RETURNING`).
+    pub expects_rows: bool,
+}
+
+/// The kind of SQL statement a [`Query`] holds.
+///
+/// Only the builders produce a [`Query`], so there is no kind for schema
+/// changes; run DDL through `wasi:sql` directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatementKind {
+    /// `SELECT`.
+    Select,
+    /// `INSERT`.
+    Insert,
+    /// `UPDATE`.
+    Update,
+    /// `DELETE`.
+    Delete,
 }
 
-/// Finalises a `SeaQuery` statement into a [`Query`]: renders the SQL, converts the bound
-/// values to WASI [`DataType`]s, and emits a uniform `tracing::debug!` event.
+impl StatementKind {
+    /// Lower-case statement name, as used in tracing events.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Select => "select",
+            Self::Insert => "insert",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// The SQL dialect a statement is rendered in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// PostgreSQL and SQLite: double-quoted identifiers and numbered placeholders (`$1`).
+    #[default]
+    Postgres,
+    /// MySQL and MariaDB: backtick-quoted identifiers and positional placeholders (`?`).
+    /// Upserts render as `ON DUPLICATE KEY UPDATE`, and `RETURNING` is unsupported.
+    MySql,
+}
+
+impl Dialect {
+    /// Lower-case dialect name, as used in tracing events.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Postgres => "postgres",
+            Self::MySql => "mysql",
+        }
+    }
+}
+
+/// Finalises a `SeaQuery` statement into a [`Query`]: renders the SQL in `dialect`, converts
+/// the bound values to WASI [`DataType`]s, and records the build inside an `orm.build` span.
 pub fn finish<S: QueryStatementBuilder>(
-    stmt: &S, table: &'static str, kind: &'static str,
+    stmt: &S, table: &'static str, kind: StatementKind, expects_rows: bool, dialect: Dialect,
 ) -> Result<Query> {
-    let (sql, values) = stmt.build_any(&QueryBuilder);
-    let params = values_to_wasi_datatypes(values)?;
+    if dialect == Dialect::MySql && expects_rows && kind != StatementKind::Select {
+        anyhow::bail!("MySQL does not support RETURNING on {}", kind.as_str());
+    }
 
-    tracing::debug!(
+    let span = tracing::debug_span!(
+        "orm.build",
         table,
-        kind,
-        sql = %sql,
-        param_count = params.len(),
-        "ORM query built",
+        kind = kind.as_str(),
+        dialect = dialect.as_str(),
+        build_us = tracing::field::Empty,
     );
+    let _entered = span.enter();
+    let started = Instant::now();
 
-    Ok(Query { sql, params })
+    let (sql, values) = match dialect {
+        Dialect::Postgres => stmt.build_any(&QueryBuilder),
+        Dialect::MySql => stmt.build_any(&MysqlQueryBuilder),
+    };
+    let params = values_to_wasi_datatypes(values)?;
+
+    span.record("build_us", u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX));
+    tracing::debug!(sql = %sql, param_count = params.len(), "ORM query built");
+
+    Ok(Query {
+        sql,
+        params,
+        table,
+        kind,
+        expects_rows,
+    })
 }
 
 /// Backend-agnostic `SeaQuery` query builder configured for Postgres/SQLite dialects:
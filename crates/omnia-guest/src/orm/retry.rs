@@ -0,0 +1,177 @@
+// `retry_serializable` needs a host transaction, so off wasm32 only the tests
+// drive the retry loop.
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+
+use super::error::SqlError;
+#[cfg(target_arch = "wasm32")]
+use super::transaction::{IsolationLevel, Transaction, TransactionOptions};
+
+const BASE_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Run `op` in a `SERIALIZABLE` transaction on `conn_name`, starting over in a
+/// fresh transaction while it fails with a serialization failure (SQLSTATE
+/// `40001`).
+///
+/// `op` receives the attempt's transaction and must perform the whole unit of
+/// work through it, because a conflicting transaction is rolled back in full.
+/// The transaction is committed once `op` succeeds and rolled back if it
+/// fails. Attempts are separated by jittered exponential backoff; any other
+/// error is returned immediately.
+///
+/// ```rust,ignore
+/// let count = retry_serializable("db", 5, async |tx| {
+///     let debited = tx.apply(debit.clone()).await?;
+///     tx.apply(credit.clone()).await?;
+///     Ok(debited)
+/// })
+/// .await?;
+/// ```
+///
+/// # Errors
+///
+/// Returns the first non-serialization error, or the last serialization
+/// failure once `max_attempts` is exhausted.
+#[cfg(target_arch = "wasm32")]
+pub async fn retry_serializable<T>(
+    conn_name: &str, max_attempts: u32, mut op: impl AsyncFnMut(&Transaction) -> Result<T>,
+) -> Result<T> {
+    let options = TransactionOptions::new().isolation(IsolationLevel::Serializable);
+    retry(max_attempts, async || {
+        let tx = Transaction::begin(conn_name, options).await?;
+        match op(&tx).await {
+            Ok(value) => {
+                tx.commit().await?;
+                Ok(value)
+            }
+            Err(error) => {
+                if let Err(rollback) = tx.rollback().await {
+                    tracing::debug!("rollback after failed attempt failed: {rollback:#}");
+                }
+                Err(error)
+            }
+        }
+    })
+    .await
+}
+
+async fn retry<T>(max_attempts: u32, mut attempt: impl AsyncFnMut() -> Result<T>) -> Result<T> {
+    if max_attempts == 0 {
+        bail!("retry_serializable requires at least one attempt");
+    }
+    let mut attempts = 1;
+    loop {
+        match attempt().await {
+            Err(error) if attempts < max_attempts && is_serialization_failure(&error) => {
+                tracing::debug!(attempts, "serialization failure, retrying: {error:#}");
+                sleep(backoff(attempts)).await;
+                attempts += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether `error` carries a [`SqlError`] with SQLSTATE `40001`.
+fn is_serialization_failure(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<SqlError>())
+        .any(SqlError::is_serialization_failure)
+}
+
+fn backoff(attempt: u32) -> Duration {
+    let exponential = BASE_DELAY.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    let capped = exponential.min(MAX_DELAY);
+    // Full jitter keeps conflicting writers from retrying in lockstep.
+    capped.mul_f64(rand::random::<f64>())
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn sleep(duration: Duration) {
+    let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+    wasip3::clocks::monotonic_clock::wait_for(nanos).await;
+}
+
+// Off wasm32 there is no runtime-agnostic timer, so a thread wakes the task.
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(duration: Duration) {
+    let (wake, woken) = futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        let _ = wake.send(());
+    });
+    let _ = woken.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn sql_error(sqlstate: Option<&str>, trace: &str) -> anyhow::Error {
+        anyhow::Error::new(SqlError::new(sqlstate.map(ToString::to_string), trace))
+            .context("commit failed")
+    }
+
+    #[tokio::test]
+    async fn retries_serialization_failures_until_success() {
+        let mut calls = 0;
+        let result = retry(3, async || {
+            calls += 1;
+            if calls < 3 {
+                Err(sql_error(Some("40001"), "could not serialize access"))
+            } else {
+                Ok(calls)
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let mut calls = 0;
+        retry(3, async || {
+            calls += 1;
+            Err::<(), _>(sql_error(Some("23505"), "unique constraint violated"))
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn messages_that_mention_the_code_are_not_retried() {
+        let mut calls = 0;
+        retry(3, async || {
+            calls += 1;
+            Err::<(), _>(anyhow!("order 40001 not found"))
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert!(backoff(30) <= MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn sleep_waits() {
+        let started = Instant::now();
+        sleep(Duration::from_millis(20)).await;
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+}
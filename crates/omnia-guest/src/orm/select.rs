@@ -1,11 +1,11 @@
 use std::marker::PhantomData;
 
-use sea_query::{Alias, ColumnRef, Order, SimpleExpr};
+use sea_query::{Alias, ColumnRef, IntoColumnRef, IntoTableRef, Order, SimpleExpr, TableRef};
 
 use super::entity::Entity;
 use super::filter::Filter;
 use super::join::{Join, JoinSpec};
-use super::query::{Query, finish};
+use super::query::{Dialect, Query, StatementKind, finish};
 
 /// Builder for constructing SELECT queries.
 pub struct SelectBuilder<M: Entity> {
@@ -89,6 +89,15 @@ impl<M: Entity> SelectBuilder<M> {
     ///
     /// Returns an error if query values cannot be converted to WASI data types.
     pub fn build(self) -> anyhow::Result<Query> {
+        self.build_for(Dialect::Postgres)
+    }
+
+    /// Build the SELECT query in `dialect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query values cannot be converted to WASI data types.
+    pub fn build_for(self, dialect: Dialect) -> anyhow::Result<Query> {
         let mut statement = sea_query::Query::select();
 
         let column_specs = M::column_specs();
@@ -102,7 +111,7 @@ impl<M: Entity> SelectBuilder<M> {
             }
         }
 
-        statement.from(Alias::new(M::TABLE));
+        statement.from(table_ref(M::TABLE));
 
         for JoinSpec {
             table,
@@ -111,7 +120,7 @@ impl<M: Entity> SelectBuilder<M> {
             kind,
         } in self.joins
         {
-            let table_alias = Alias::new(table);
+            let table_alias = table_ref(table);
             if let Some(alias) = alias {
                 statement.join_as(kind, table_alias, Alias::new(alias), on);
             } else {
@@ -135,10 +144,116 @@ impl<M: Entity> SelectBuilder<M> {
             statement.order_by(column, order);
         }
 
-        finish(&statement, M::TABLE, "select")
+        finish(&statement, M::TABLE, StatementKind::Select, true, dialect)
+    }
+}
+
+/// A table reference, quoting the schema and table of `schema.table` separately.
+pub fn table_ref(table: &str) -> TableRef {
+    match table.split_once('.') {
+        Some((schema, table)) => (Alias::new(schema), Alias::new(table)).into_table_ref(),
+        None => Alias::new(table).into_table_ref(),
     }
 }
 
+/// A column of `table`, which may be schema-qualified.
 pub fn table_column(table: &str, column: &str) -> ColumnRef {
-    (Alias::new(table), Alias::new(column)).into()
+    match table.split_once('.') {
+        Some((schema, table)) => {
+            (Alias::new(schema), Alias::new(table), Alias::new(column)).into_column_ref()
+        }
+        None => (Alias::new(table), Alias::new(column)).into_column_ref(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orm::{DeleteBuilder, InsertBuilder, StatementKind, UpdateBuilder};
+
+    crate::entity! {
+        table = "analytics.events",
+        columns = [("auth.users", "name", "user_name")],
+        joins = [Join::left("auth.users", Filter::col_eq("analytics.events", "user_id", "auth.users", "id"))],
+        struct Event {
+            id: i64,
+            user_name: String,
+        }
+    }
+
+    #[test]
+    fn quotes_schema_and_table_separately() {
+        let query = SelectBuilder::<Event>::new().r#where(Filter::eq("id", 7)).build().unwrap();
+
+        assert_eq!(
+            query.sql,
+            r#"SELECT "analytics"."events"."id", "auth"."users"."name" AS "user_name" FROM "analytics"."events" LEFT JOIN "auth"."users" ON ("analytics"."events"."user_id") = ("auth"."users"."id") WHERE ("analytics"."events"."id") = ($1)"#
+        );
+    }
+
+    #[test]
+    fn qualified_tables_in_writes() {
+        let event = Event {
+            id: 1,
+            user_name: "ana".to_string(),
+        };
+        let insert = InsertBuilder::<Event>::from_entity(&event).build().unwrap();
+        let delete = DeleteBuilder::<Event>::new().r#where(Filter::eq("id", 1)).build().unwrap();
+
+        assert!(insert.sql.starts_with(r#"INSERT INTO "analytics"."events""#));
+        assert!(delete.sql.starts_with(r#"DELETE FROM "analytics"."events""#));
+    }
+
+    #[test]
+    fn builders_record_kind_and_expected_rows() {
+        let event = Event {
+            id: 1,
+            user_name: "ana".to_string(),
+        };
+        let one = || Filter::eq("id", 1);
+        let queries = [
+            SelectBuilder::<Event>::new().build().unwrap(),
+            InsertBuilder::<Event>::from_entity(&event).build().unwrap(),
+            UpdateBuilder::<Event>::new().set("id", 2).r#where(one()).build().unwrap(),
+            UpdateBuilder::<Event>::new()
+                .set("id", 2)
+                .r#where(one())
+                .returning("id")
+                .build()
+                .unwrap(),
+            DeleteBuilder::<Event>::new().r#where(one()).build().unwrap(),
+            DeleteBuilder::<Event>::new().r#where(one()).returning("id").build().unwrap(),
+        ];
+
+        let described: Vec<_> =
+            queries.iter().map(|query| (query.kind, query.expects_rows, query.table)).collect();
+        assert_eq!(
+            described,
+            [
+                (StatementKind::Select, true, "analytics.events"),
+                (StatementKind::Insert, false, "analytics.events"),
+                (StatementKind::Update, false, "analytics.events"),
+                (StatementKind::Update, true, "analytics.events"),
+                (StatementKind::Delete, false, "analytics.events"),
+                (StatementKind::Delete, true, "analytics.events"),
+            ]
+        );
+    }
+
+    #[test]
+    fn mysql_quotes_with_backticks() {
+        let query = SelectBuilder::<Event>::new()
+            .r#where(Filter::eq("id", 7))
+            .build_for(Dialect::MySql)
+            .unwrap();
+        assert!(query.sql.ends_with("WHERE `analytics`.`events`.`id` = ?"), "{}", query.sql);
+
+        let error = DeleteBuilder::<Event>::new()
+            .r#where(Filter::eq("id", 1))
+            .returning("id")
+            .build_for(Dialect::MySql)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("RETURNING"));
+    }
 }
@@ -0,0 +1,258 @@
+use std::fmt;
+
+#[cfg(target_arch = "wasm32")]
+use anyhow::{Result, ensure};
+#[cfg(target_arch = "wasm32")]
+use omnia_wasi_sql::{ExecResult, Row, transactional, types};
+
+#[cfg(target_arch = "wasm32")]
+use super::error::sql_error;
+use super::query::Dialect;
+#[cfg(target_arch = "wasm32")]
+use super::query::Query;
+
+/// The isolation level a transaction runs at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// `READ UNCOMMITTED` (Postgres treats it as `READ COMMITTED`).
+    ReadUncommitted,
+    /// `READ COMMITTED`, the Postgres default.
+    ReadCommitted,
+    /// `REPEATABLE READ`, the MySQL default.
+    RepeatableRead,
+    /// `SERIALIZABLE`; pair with [`retry_serializable`](super::retry_serializable).
+    Serializable,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ReadUncommitted => "READ UNCOMMITTED",
+            Self::ReadCommitted => "READ COMMITTED",
+            Self::RepeatableRead => "REPEATABLE READ",
+            Self::Serializable => "SERIALIZABLE",
+        })
+    }
+}
+
+/// Characteristics of a transaction: isolation level and access mode.
+///
+/// Unset characteristics fall back to the database defaults, in which case the
+/// transaction opens with a bare `BEGIN` that SQLite also accepts.
+///
+/// ```rust
+/// use omnia_guest::orm::{Dialect, IsolationLevel, TransactionOptions};
+///
+/// let reporting =
+///     TransactionOptions::new().isolation(IsolationLevel::Serializable).read_only().deferrable();
+/// assert_eq!(
+///     reporting.begin_statements(Dialect::Postgres),
+///     ["BEGIN", "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE, READ ONLY, DEFERRABLE"],
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactionOptions {
+    isolation: Option<IsolationLevel>,
+    read_only: bool,
+    deferrable: bool,
+}
+
+impl TransactionOptions {
+    /// Options that keep every database default.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run at `level` instead of the database default.
+    #[must_use]
+    pub const fn isolation(mut self, level: IsolationLevel) -> Self {
+        self.isolation = Some(level);
+        self
+    }
+
+    /// Reject writes for the rest of the transaction.
+    #[must_use]
+    pub const fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Postgres only: let a `SERIALIZABLE READ ONLY` transaction wait for a safe
+    /// snapshot rather than risk a serialization failure. Ignored for MySQL.
+    #[must_use]
+    pub const fn deferrable(mut self) -> Self {
+        self.deferrable = true;
+        self
+    }
+
+    /// The statements that open a transaction with these characteristics, in
+    /// execution order. All of them must run on the same connection.
+    #[must_use]
+    pub fn begin_statements(&self, dialect: Dialect) -> Vec<String> {
+        let mut characteristics = Vec::new();
+        if let Some(level) = self.isolation {
+            characteristics.push(format!("ISOLATION LEVEL {level}"));
+        }
+        if self.read_only {
+            characteristics.push("READ ONLY".to_string());
+        }
+        if self.deferrable && dialect == Dialect::Postgres {
+            characteristics.push("DEFERRABLE".to_string());
+        }
+
+        let begin = match dialect {
+            Dialect::Postgres => "BEGIN",
+            Dialect::MySql => "START TRANSACTION",
+        };
+        if characteristics.is_empty() {
+            return vec![begin.to_string()];
+        }
+        let set = format!("SET TRANSACTION {}", characteristics.join(", "));
+        match dialect {
+            // Postgres applies `SET TRANSACTION` to the transaction already open.
+            Dialect::Postgres => vec![begin.to_string(), set],
+            // MySQL applies it to the next transaction, so it comes first.
+            Dialect::MySql => vec![set, begin.to_string()],
+        }
+    }
+}
+
+/// A transaction held open across several statements on one connection.
+///
+/// The host pins the transaction to a single database session, so every
+/// [`fetch`](Self::fetch) and [`apply`](Self::apply) sees the writes before it.
+/// Dropping the transaction without calling [`commit`](Self::commit) rolls it
+/// back.
+///
+/// ```rust,ignore
+/// let tx = Transaction::begin("db", TransactionOptions::new()).await?;
+/// tx.apply(UpdateBuilder::<Account>::new().set("balance", 90).build()?).await?;
+/// tx.apply(InsertBuilder::<Transfer>::from_entity(&transfer).build()?).await?;
+/// tx.commit().await?;
+/// ```
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug)]
+pub struct Transaction {
+    inner: transactional::Transaction,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Transaction {
+    /// Open the connection `conn_name` and begin a transaction on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be opened or the backend
+    /// refuses to begin a transaction with `options`.
+    pub async fn begin(conn_name: impl Into<String>, options: TransactionOptions) -> Result<Self> {
+        let conn = types::Connection::open(conn_name.into())
+            .await
+            .map_err(sql_error("failed to open connection"))?;
+        let options = transactional::TransactionOptions {
+            isolation: options.isolation.map(|level| match level {
+                IsolationLevel::ReadUncommitted => transactional::IsolationLevel::ReadUncommitted,
+                IsolationLevel::ReadCommitted => transactional::IsolationLevel::ReadCommitted,
+                IsolationLevel::RepeatableRead => transactional::IsolationLevel::RepeatableRead,
+                IsolationLevel::Serializable => transactional::IsolationLevel::Serializable,
+            }),
+            read_only: options.read_only,
+            deferrable: options.deferrable,
+        };
+        let inner = transactional::Transaction::begin(&conn, options)
+            .await
+            .map_err(sql_error("failed to begin transaction"))?;
+        Ok(Self { inner })
+    }
+
+    /// Runs a built query that yields rows within the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query yields no rows or fails to execute.
+    pub async fn fetch(&self, query: Query) -> Result<Vec<Row>> {
+        ensure!(
+            query.expects_rows,
+            "{} statement returns no rows; use `apply` instead of `fetch`",
+            query.kind.as_str()
+        );
+        let stmt = prepare(query).await?;
+        transactional::query(&self.inner, &stmt).await.map_err(sql_error("query failed"))
+    }
+
+    /// Runs a built statement that yields no rows within the transaction and
+    /// returns the number of affected rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query yields rows or fails to execute.
+    pub async fn apply(&self, query: Query) -> Result<u32> {
+        ensure!(
+            !query.expects_rows,
+            "{} statement returns rows; use `fetch` instead of `apply`",
+            query.kind.as_str()
+        );
+        let stmt = prepare(query).await?;
+        transactional::exec(&self.inner, &stmt).await.map_err(sql_error("exec failed"))
+    }
+
+    /// Runs a built write within the transaction and reports its effects:
+    /// the affected row count, the last insert id, and any `RETURNING` rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statement fails to execute.
+    pub async fn apply_with_result(&self, query: Query) -> Result<ExecResult> {
+        let stmt = prepare(query).await?;
+        transactional::exec_with_result(&self.inner, &stmt).await.map_err(sql_error("exec failed"))
+    }
+
+    /// Commit the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the commit fails, in which case the transaction is
+    /// rolled back.
+    pub async fn commit(self) -> Result<()> {
+        transactional::Transaction::commit(self.inner).await.map_err(sql_error("commit failed"))
+    }
+
+    /// Roll the transaction back.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the rollback fails.
+    pub async fn rollback(self) -> Result<()> {
+        transactional::Transaction::rollback(self.inner).await.map_err(sql_error("rollback failed"))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn prepare(query: Query) -> Result<types::Statement> {
+    types::Statement::prepare(query.sql, query.params)
+        .await
+        .map_err(sql_error("failed to prepare statement"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_open_with_a_bare_begin() {
+        let options = TransactionOptions::new();
+
+        assert_eq!(options.begin_statements(Dialect::Postgres), ["BEGIN"]);
+        assert_eq!(options.begin_statements(Dialect::MySql), ["START TRANSACTION"]);
+    }
+
+    #[test]
+    fn mysql_sets_characteristics_before_starting() {
+        let ingest = TransactionOptions::new().isolation(IsolationLevel::Serializable).deferrable();
+
+        assert_eq!(
+            ingest.begin_statements(Dialect::MySql),
+            ["SET TRANSACTION ISOLATION LEVEL SERIALIZABLE", "START TRANSACTION"]
+        );
+    }
+}
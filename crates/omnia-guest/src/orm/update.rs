@@ -5,7 +5,8 @@ use sea_query::{Alias, SimpleExpr, Value};
 
 use super::entity::Entity;
 use super::filter::Filter;
-use super::query::{Query, finish};
+use super::query::{Dialect, Query, StatementKind, finish};
+use super::select::table_ref;
 
 /// Builder for constructing UPDATE queries.
 pub struct UpdateBuilder<M: Entity> {
@@ -65,12 +66,23 @@ impl<M: Entity> UpdateBuilder<M> {
     /// rewrite every row), or if a query value cannot be converted to a WASI data
     /// type.
     pub fn build(self) -> Result<Query> {
+        self.build_for(Dialect::Postgres)
+    }
+
+    /// Build the UPDATE query in `dialect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no `WHERE` filter was set, if `RETURNING` columns were
+    /// requested for MySQL, or if a query value cannot be converted to a WASI data
+    /// type.
+    pub fn build_for(self, dialect: Dialect) -> Result<Query> {
         if self.filters.is_empty() {
             anyhow::bail!("refusing to build an unfiltered UPDATE; add a `.where(...)` clause");
         }
 
         let mut statement = sea_query::Query::update();
-        statement.table(Alias::new(M::TABLE));
+        statement.table(table_ref(M::TABLE));
 
         for (column, value) in self.set_clauses {
             statement.value(Alias::new(column), value);
@@ -80,10 +92,11 @@ impl<M: Entity> UpdateBuilder<M> {
             statement.and_where(expr);
         }
 
+        let expects_rows = !self.returning.is_empty();
         for column in self.returning {
             statement.returning_col(Alias::new(column));
         }
 
-        finish(&statement, M::TABLE, "update")
+        finish(&statement, M::TABLE, StatementKind::Update, expects_rows, dialect)
     }
 }
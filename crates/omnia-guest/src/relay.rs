@@ -0,0 +1,222 @@
+//! Relay a streaming upstream HTTP response into WebSocket broadcasts.
+//!
+//! A [`Relay`] opens an upstream response with
+//! [`HttpRequest::fetch_stream`] and broadcasts it through [`Broadcast::send`]
+//! as it arrives, so a long-lived feed (server-sent events, a chunked
+//! GTFS-RT stream) reaches WebSocket peers without the guest holding the whole
+//! body. With [`Framing::Chunks`] every body chunk is sent as it is read; with
+//! [`Framing::Events`] the body is parsed as server-sent events and each
+//! event's `data` is sent once the event is complete, so only a partial event
+//! is ever held.
+//!
+//! ```rust,ignore
+//! let sent = Relay::new(&provider, "vehicles")
+//!     .groups(["route:42"])
+//!     .framing(Framing::Events)
+//!     .run(Request::get(feed_url).body(Empty::<Bytes>::new())?)
+//!     .await?;
+//! ```
+
+use std::any::Any;
+use std::error::Error;
+
+use anyhow::{Result, bail};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use http::Request;
+use http_body::Body;
+
+use crate::{Broadcast, HttpRequest};
+
+/// How a relayed body is cut into broadcasts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Send each body chunk as it is read.
+    #[default]
+    Chunks,
+    /// Parse the body as server-sent events and send each event's `data`.
+    /// Events without data, such as keep-alive comments, are skipped.
+    Events,
+}
+
+/// Pipes an upstream response into broadcasts on one WebSocket channel.
+#[derive(Debug)]
+pub struct Relay<'a, P> {
+    provider: &'a P,
+    channel: String,
+    sockets: Option<Vec<String>>,
+    framing: Framing,
+}
+
+impl<'a, P: Broadcast> Relay<'a, P> {
+    /// Relay to every peer of the WebSocket `channel`, one broadcast per
+    /// chunk.
+    pub fn new(provider: &'a P, channel: impl Into<String>) -> Self {
+        Self {
+            provider,
+            channel: channel.into(),
+            sockets: None,
+            framing: Framing::Chunks,
+        }
+    }
+
+    /// Send only to the listed groups or sockets instead of every peer.
+    #[must_use]
+    pub fn groups<I, T>(mut self, groups: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.sockets = Some(groups.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Cut the body into broadcasts with `framing`.
+    #[must_use]
+    pub const fn framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Broadcast `chunks` until the stream ends, returning the number of
+    /// broadcasts sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a broadcast fails; chunks read before it have
+    /// already been sent.
+    pub async fn forward(&self, chunks: impl Stream<Item = Bytes> + Send) -> Result<u64> {
+        let mut chunks = std::pin::pin!(chunks);
+        let mut events = EventParser::default();
+        let mut sent = 0;
+
+        while let Some(chunk) = chunks.next().await {
+            match self.framing {
+                Framing::Chunks => {
+                    self.send(&chunk).await?;
+                    sent += 1;
+                }
+                Framing::Events => {
+                    for data in events.push(&chunk) {
+                        self.send(&data).await?;
+                        sent += 1;
+                    }
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    async fn send(&self, data: &[u8]) -> Result<()> {
+        self.provider.send(&self.channel, data, self.sockets.clone()).await
+    }
+}
+
+impl<P: Broadcast + HttpRequest> Relay<'_, P> {
+    /// Send `request` upstream and broadcast its response body until the
+    /// upstream closes it, returning the number of broadcasts sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the upstream answers with a
+    /// non-success status, or a broadcast fails.
+    pub async fn run<T>(&self, request: Request<T>) -> Result<u64>
+    where
+        T: Body + Any + Send,
+        T::Data: Into<Vec<u8>>,
+        T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+    {
+        let response = self.provider.fetch_stream(request).await?;
+        if !response.status().is_success() {
+            bail!("upstream answered {} for relay to `{}`", response.status(), self.channel);
+        }
+        self.forward(response.into_body()).await
+    }
+}
+
+/// Incremental server-sent events parser holding at most one partial event.
+#[derive(Debug, Default)]
+struct EventParser {
+    pending: Vec<u8>,
+}
+
+impl EventParser {
+    /// Append `chunk` and return the data of every event it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        // `\r\n` and `\n` end lines alike; dropping `\r` leaves only `\n`.
+        self.pending.extend(chunk.iter().filter(|byte| **byte != b'\r'));
+
+        let mut complete = Vec::new();
+        while let Some(end) = self.pending.windows(2).position(|pair| pair == b"\n\n") {
+            let event: Vec<u8> = self.pending.drain(..end + 2).collect();
+            let data: Vec<&[u8]> = event
+                .split(|byte| *byte == b'\n')
+                .filter_map(|line| line.strip_prefix(b"data:"))
+                .map(|value| value.strip_prefix(b" ").unwrap_or(value))
+                .collect();
+            if !data.is_empty() {
+                complete.push(data.join(&b'\n'));
+            }
+        }
+        complete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::stream;
+
+    use super::*;
+
+    /// A broadcast's event name, data, and target sockets.
+    type Sent = (String, Vec<u8>, Option<Vec<String>>);
+
+    #[derive(Default)]
+    struct Sink(Mutex<Vec<Sent>>);
+
+    impl Broadcast for Sink {
+        async fn send(&self, name: &str, data: &[u8], sockets: Option<Vec<String>>) -> Result<()> {
+            self.0.lock().unwrap().push((name.to_string(), data.to_vec(), sockets));
+            Ok(())
+        }
+    }
+
+    fn chunks(parts: &[&'static str]) -> impl Stream<Item = Bytes> {
+        stream::iter(parts.iter().map(|part| Bytes::from_static(part.as_bytes())))
+    }
+
+    #[tokio::test]
+    async fn chunks_are_sent_as_read() {
+        let sink = Sink::default();
+        let relay = Relay::new(&sink, "feed").groups(["route:42"]);
+
+        let sent = relay.forward(chunks(&["ab", "cd"])).await.unwrap();
+
+        assert_eq!(sent, 2);
+        let sends = sink.0.lock().unwrap();
+        assert_eq!(
+            sends[1],
+            ("feed".to_string(), b"cd".to_vec(), Some(vec!["route:42".to_string()]))
+        );
+    }
+
+    #[tokio::test]
+    async fn events_are_sent_once_complete() {
+        let sink = Sink::default();
+        let relay = Relay::new(&sink, "feed").framing(Framing::Events);
+
+        let body = [
+            ": keep-alive\n\nda",
+            "ta: {\"id\":1}\r\n\r\ndata: a\ndata: b\n",
+            "\nevent: partial\ndata: c",
+        ];
+        let sent = relay.forward(chunks(&body)).await.unwrap();
+
+        assert_eq!(sent, 2);
+        let sends = sink.0.lock().unwrap();
+        assert_eq!(sends[0].1, b"{\"id\":1}");
+        assert_eq!(sends[1].1, b"a\nb");
+    }
+}
@@ -0,0 +1,299 @@
+//! Load shedding for saturated components.
+//!
+//! Each component instance serves a single request or message, so load cannot
+//! be measured in guest memory. A [`LoadShedder`] keeps the [`Load`] in the
+//! state store, as a counter of the work in flight and a record of the
+//! smoothed handler latency, which every instance updates on entry and exit.
+//! From it the shedder derives a [`Pressure`] and refuses work whose
+//! [`Priority`] the pressure no longer admits.
+//!
+//! The in-flight counter moves with [`StateStore::increment`], so concurrent
+//! instances never lose each other's updates. The latency record is
+//! read-modify-write: a sample lost under contention only nudges the average.
+//! Both expire shortly after the last update, so counts left behind by
+//! instances that trapped mid-request decay on their own.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::StateStore;
+
+/// Weight given to the newest latency sample in the moving average.
+const SMOOTHING: f64 = 0.2;
+
+/// How long the load record outlives its last update, in seconds.
+const RECORD_TTL_SECS: u64 = 60;
+
+/// How important a unit of work is when the component is under pressure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Shed as soon as the component is saturated.
+    Low,
+    /// Shed only when the component is overloaded.
+    #[default]
+    Normal,
+    /// Never shed.
+    Critical,
+}
+
+/// How loaded the component is relative to its [`ShedPolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pressure {
+    /// Below both thresholds.
+    Normal,
+    /// At or above a threshold; low-priority work is shed.
+    Saturated,
+    /// At or above twice a threshold; all but critical work is shed.
+    Overloaded,
+}
+
+impl Pressure {
+    /// Whether work of `priority` should run under this pressure.
+    #[must_use]
+    pub fn admits(self, priority: Priority) -> bool {
+        match self {
+            Self::Normal => true,
+            Self::Saturated => priority >= Priority::Normal,
+            Self::Overloaded => priority == Priority::Critical,
+        }
+    }
+}
+
+/// Saturation thresholds and the state-store key the load record lives under.
+#[derive(Clone, Debug)]
+pub struct ShedPolicy {
+    /// State-store key of the shared latency record. The in-flight counter
+    /// lives under the same key with an `:in-flight` suffix.
+    pub key: String,
+    /// Work in flight at which the component counts as saturated.
+    pub max_in_flight: u32,
+    /// Smoothed handler latency at which the component counts as saturated.
+    pub target_latency: Duration,
+}
+
+impl Default for ShedPolicy {
+    fn default() -> Self {
+        Self {
+            key: "shed:load".to_string(),
+            max_in_flight: 64,
+            target_latency: Duration::from_millis(500),
+        }
+    }
+}
+
+impl ShedPolicy {
+    /// The pressure `load` represents under this policy.
+    #[must_use]
+    pub fn pressure(&self, load: &Load) -> Pressure {
+        let target_ms = self.target_latency.as_secs_f64() * 1000.0;
+        let over = |factor: u32| {
+            load.in_flight >= self.max_in_flight.saturating_mul(factor)
+                || load.latency_ms >= target_ms * f64::from(factor)
+        };
+        if over(2) {
+            Pressure::Overloaded
+        } else if over(1) {
+            Pressure::Saturated
+        } else {
+            Pressure::Normal
+        }
+    }
+}
+
+/// The shared load.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Load {
+    /// Work admitted and not yet released.
+    pub in_flight: u32,
+    /// Exponentially weighted handler latency, in milliseconds.
+    pub latency_ms: f64,
+}
+
+/// Admission for one unit of work; hand it back to [`LoadShedder::release`].
+#[derive(Debug)]
+#[must_use = "release the permit when the work finishes"]
+pub struct Permit {
+    started: Instant,
+}
+
+/// Admits or sheds work against the load record in a state store.
+#[derive(Debug)]
+pub struct LoadShedder<'a, S> {
+    store: &'a S,
+    policy: ShedPolicy,
+}
+
+impl<'a, S: StateStore> LoadShedder<'a, S> {
+    /// Create a shedder over `store`.
+    pub const fn new(store: &'a S, policy: ShedPolicy) -> Self {
+        Self { store, policy }
+    }
+
+    /// The current load.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state store fails or holds an unreadable record.
+    pub async fn load(&self) -> Result<Load> {
+        let in_flight = match self.store.get(&self.counter_key()).await? {
+            Some(bytes) => {
+                let bytes = <[u8; 8]>::try_from(bytes.as_slice())
+                    .map_err(|_e| anyhow!("in-flight count holds {} bytes", bytes.len()))?;
+                clamp(i64::from_be_bytes(bytes))
+            }
+            None => 0,
+        };
+        Ok(Load {
+            in_flight,
+            latency_ms: self.latency().await?.latency_ms,
+        })
+    }
+
+    /// The current pressure, for degrading work (say, serving a cached
+    /// response) rather than refusing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the load cannot be read.
+    pub async fn pressure(&self) -> Result<Pressure> {
+        Ok(self.policy.pressure(&self.load().await?))
+    }
+
+    /// Admit work of `priority`, or return `None` if it should be shed.
+    ///
+    /// The work is counted before the pressure is judged, so of two instances
+    /// racing for the last slot, only one sees room for itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the load cannot be read or written.
+    pub async fn admit(&self, priority: Priority) -> Result<Option<Permit>> {
+        let counted = self.count(1).await?;
+        let load = Load {
+            in_flight: counted.saturating_sub(1),
+            latency_ms: self.latency().await?.latency_ms,
+        };
+        let pressure = self.policy.pressure(&load);
+        if !pressure.admits(priority) {
+            self.count(-1).await?;
+            tracing::warn!(
+                monotonic_counter.shed = 1,
+                ?priority,
+                ?pressure,
+                in_flight = load.in_flight,
+                latency_ms = load.latency_ms,
+                "shedding work",
+            );
+            return Ok(None);
+        }
+        Ok(Some(Permit {
+            started: Instant::now(),
+        }))
+    }
+
+    /// Release `permit`, folding the work's latency into the latency record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the load cannot be read or written.
+    pub async fn release(&self, permit: Permit) -> Result<()> {
+        let elapsed_ms = permit.started.elapsed().as_secs_f64() * 1000.0;
+        self.count(-1).await?;
+        let mut latency = self.latency().await?;
+        latency.latency_ms =
+            (elapsed_ms - latency.latency_ms).mul_add(SMOOTHING, latency.latency_ms);
+        let bytes = serde_json::to_vec(&latency)?;
+        self.store.set(&self.policy.key, &bytes, Some(RECORD_TTL_SECS)).await?;
+        Ok(())
+    }
+
+    /// Move the in-flight counter by `delta`, restart its expiry, and return
+    /// the new count.
+    async fn count(&self, delta: i64) -> Result<u32> {
+        let key = self.counter_key();
+        let count = self.store.increment(&key, delta).await?;
+        self.store.get_ex(&key, RECORD_TTL_SECS).await?;
+        Ok(clamp(count))
+    }
+
+    async fn latency(&self) -> Result<Latency> {
+        let Some(bytes) = self.store.get(&self.policy.key).await? else {
+            return Ok(Latency::default());
+        };
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    fn counter_key(&self) -> String {
+        format!("{}:in-flight", self.policy.key)
+    }
+}
+
+/// The smoothed handler latency, as stored.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Latency {
+    latency_ms: f64,
+}
+
+/// A stored count as work in flight. Releases of work whose count already
+/// expired can drive the counter below zero.
+fn clamp(count: i64) -> u32 {
+    u32::try_from(count.max(0)).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::MemoryStore;
+
+    fn policy() -> ShedPolicy {
+        ShedPolicy {
+            max_in_flight: 2,
+            ..ShedPolicy::default()
+        }
+    }
+
+    #[test]
+    fn pressure_tracks_thresholds() {
+        let policy = policy();
+        let load = |in_flight, latency_ms| Load {
+            in_flight,
+            latency_ms,
+        };
+
+        assert_eq!(policy.pressure(&load(1, 100.0)), Pressure::Normal);
+        assert_eq!(policy.pressure(&load(2, 100.0)), Pressure::Saturated);
+        assert_eq!(policy.pressure(&load(0, 600.0)), Pressure::Saturated);
+        assert_eq!(policy.pressure(&load(4, 100.0)), Pressure::Overloaded);
+    }
+
+    #[tokio::test]
+    async fn sheds_low_priority_when_saturated() {
+        let store = MemoryStore::default();
+        let shedder = LoadShedder::new(&store, policy());
+
+        let first = shedder.admit(Priority::Low).await.unwrap().unwrap();
+        let _second = shedder.admit(Priority::Normal).await.unwrap().unwrap();
+
+        assert!(shedder.admit(Priority::Low).await.unwrap().is_none());
+        let _third = shedder.admit(Priority::Normal).await.unwrap().unwrap();
+        assert_eq!(shedder.pressure().await.unwrap(), Pressure::Saturated);
+
+        shedder.release(first).await.unwrap();
+        assert_eq!(shedder.load().await.unwrap().in_flight, 2);
+    }
+
+    #[tokio::test]
+    async fn shed_work_leaves_the_count_alone() {
+        let store = MemoryStore::default();
+        let shedder = LoadShedder::new(&store, policy());
+        for _ in 0..4 {
+            let _permit = shedder.admit(Priority::Critical).await.unwrap().unwrap();
+        }
+
+        assert!(shedder.admit(Priority::Normal).await.unwrap().is_none());
+        assert_eq!(shedder.load().await.unwrap().in_flight, 4);
+        assert_eq!(store.increment("shed:load:in-flight", 0).await.unwrap(), 4);
+    }
+}
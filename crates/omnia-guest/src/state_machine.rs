@@ -0,0 +1,329 @@
+//! Typed lifecycle state machines persisted in a [`StateStore`].
+//!
+//! A [`Lifecycle`] declares which transitions are legal; a [`StateMachine`]
+//! persists the current state with a version so concurrent writers that read
+//! a stale version fail with [`TransitionError::Conflict`] instead of silently
+//! overwriting each other. Each write is a compare-and-swap against the value
+//! that was read, so of two concurrent transitions only one succeeds.
+//!
+//! ```rust,ignore
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+//! enum Trip { Scheduled, Active, Completed, Canceled }
+//!
+//! impl Lifecycle for Trip {
+//!     fn transitions(self) -> &'static [Self] {
+//!         match self {
+//!             Self::Scheduled => &[Self::Active, Self::Canceled],
+//!             Self::Active => &[Self::Completed, Self::Canceled],
+//!             Self::Completed | Self::Canceled => &[],
+//!         }
+//!     }
+//! }
+//!
+//! let machine = StateMachine::<_, Trip>::new(&provider, format!("trip:{trip_id}"));
+//! machine.advance(Trip::Active).await?;
+//! ```
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::StateStore;
+
+/// A set of states with declared legal transitions.
+pub trait Lifecycle:
+    Copy + Eq + Debug + Serialize + DeserializeOwned + Send + Sync + 'static
+{
+    /// States reachable from `self` in a single transition.
+    fn transitions(self) -> &'static [Self];
+
+    /// Whether `self -> next` is a declared transition.
+    fn can_transition(self, next: Self) -> bool {
+        self.transitions().contains(&next)
+    }
+}
+
+/// A persisted state and the version it was written at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Versioned<S> {
+    /// The current state.
+    pub state: S,
+    /// Incremented on every successful write.
+    pub version: u64,
+}
+
+/// Errors returned by [`StateMachine`] operations.
+#[derive(Error, Debug)]
+pub enum TransitionError {
+    /// The transition is not declared by the lifecycle.
+    #[error("invalid transition from {from} to {to}")]
+    Invalid {
+        /// The current state.
+        from: String,
+        /// The requested state.
+        to: String,
+    },
+
+    /// The stored version differs from the one the caller expected.
+    #[error("version conflict: expected {expected:?}, found {actual:?}")]
+    Conflict {
+        /// The version the caller read.
+        expected: Option<u64>,
+        /// The version currently stored.
+        actual: Option<u64>,
+    },
+
+    /// No state has been initialized for the key.
+    #[error("no state stored for {0}")]
+    NotFound(String),
+
+    /// The state store failed or held an undecodable value.
+    #[error(transparent)]
+    Store(#[from] anyhow::Error),
+}
+
+/// A lifecycle state machine stored under a single key.
+pub struct StateMachine<'a, P, S> {
+    store: &'a P,
+    key: String,
+    _state: PhantomData<S>,
+}
+
+impl<'a, P: StateStore, S: Lifecycle> StateMachine<'a, P, S> {
+    /// Create a state machine persisted under `key`.
+    pub fn new(store: &'a P, key: impl Into<String>) -> Self {
+        Self {
+            store,
+            key: key.into(),
+            _state: PhantomData,
+        }
+    }
+
+    /// Read the current state, if one has been initialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransitionError::Store`] if the store fails or the stored value cannot be decoded.
+    pub async fn current(&self) -> Result<Option<Versioned<S>>, TransitionError> {
+        Ok(self.stored().await?.map(|(_, current)| current))
+    }
+
+    /// Initialize the machine in `initial` state at version 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransitionError::Conflict`] if a state already exists, or
+    /// [`TransitionError::Store`] if the store fails.
+    pub async fn init(&self, initial: S) -> Result<Versioned<S>, TransitionError> {
+        if let Some((_, existing)) = self.stored().await? {
+            return Err(TransitionError::Conflict {
+                expected: None,
+                actual: Some(existing.version),
+            });
+        }
+        let value = Versioned {
+            state: initial,
+            version: 1,
+        };
+        self.swap(None, value, None).await
+    }
+
+    /// Move to `next`, provided the stored version still equals `expected_version`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransitionError::NotFound`] if the machine is uninitialized,
+    /// [`TransitionError::Conflict`] if another writer has moved it on,
+    /// [`TransitionError::Invalid`] if the transition is not declared, or
+    /// [`TransitionError::Store`] if the store fails.
+    pub async fn transition(
+        &self, expected_version: u64, next: S,
+    ) -> Result<Versioned<S>, TransitionError> {
+        let (bytes, current) =
+            self.stored().await?.ok_or_else(|| TransitionError::NotFound(self.key.clone()))?;
+        if current.version != expected_version {
+            return Err(TransitionError::Conflict {
+                expected: Some(expected_version),
+                actual: Some(current.version),
+            });
+        }
+        if !current.state.can_transition(next) {
+            return Err(TransitionError::Invalid {
+                from: format!("{:?}", current.state),
+                to: format!("{next:?}"),
+            });
+        }
+        let value = Versioned {
+            state: next,
+            version: current.version + 1,
+        };
+        self.swap(Some(&bytes), value, Some(expected_version)).await
+    }
+
+    /// Move to `next` from whatever version is currently stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::transition`].
+    pub async fn advance(&self, next: S) -> Result<Versioned<S>, TransitionError> {
+        let current =
+            self.current().await?.ok_or_else(|| TransitionError::NotFound(self.key.clone()))?;
+        self.transition(current.version, next).await
+    }
+
+    /// The stored bytes and the state they decode to.
+    async fn stored(&self) -> Result<Option<(Vec<u8>, Versioned<S>)>, TransitionError> {
+        let Some(bytes) = self.store.get(&self.key).await? else {
+            return Ok(None);
+        };
+        let current = serde_json::from_slice(&bytes).map_err(anyhow::Error::from)?;
+        Ok(Some((bytes, current)))
+    }
+
+    /// Write `value` only if the key still holds `read`, the bytes the caller
+    /// based it on, reporting a conflict if another writer got in first.
+    async fn swap(
+        &self, read: Option<&[u8]>, value: Versioned<S>, expected: Option<u64>,
+    ) -> Result<Versioned<S>, TransitionError> {
+        let bytes = serde_json::to_vec(&value).map_err(anyhow::Error::from)?;
+        if self.store.compare_and_swap(&self.key, read, &bytes).await? {
+            return Ok(value);
+        }
+        Err(TransitionError::Conflict {
+            expected,
+            actual: self.current().await?.map(|current| current.version),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::capabilities::MemoryStore;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    enum Trip {
+        Scheduled,
+        Active,
+        Completed,
+    }
+
+    impl Lifecycle for Trip {
+        fn transitions(self) -> &'static [Self] {
+            match self {
+                Self::Scheduled => &[Self::Active],
+                Self::Active => &[Self::Completed],
+                Self::Completed => &[],
+            }
+        }
+    }
+
+    /// A store where another writer gets in between each read and swap.
+    #[derive(Default)]
+    struct Racing {
+        inner: MemoryStore,
+        rival: Mutex<Option<Vec<u8>>>,
+    }
+
+    impl StateStore for Racing {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.inner.get(key).await
+        }
+
+        async fn set(
+            &self, key: &str, value: &[u8], ttl_secs: Option<u64>,
+        ) -> Result<Option<Vec<u8>>> {
+            self.inner.set(key, value, ttl_secs).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.inner.delete(key).await
+        }
+
+        async fn compare_and_swap(
+            &self, key: &str, expected: Option<&[u8]>, new: &[u8],
+        ) -> Result<bool> {
+            let rival = self.rival.lock().unwrap().take();
+            if let Some(rival) = rival {
+                self.inner.set(key, &rival, None).await?;
+            }
+            self.inner.compare_and_swap(key, expected, new).await
+        }
+    }
+
+    #[tokio::test]
+    async fn declared_transitions_bump_version() {
+        let store = MemoryStore::default();
+        let machine = StateMachine::<_, Trip>::new(&store, "trip:1");
+
+        machine.init(Trip::Scheduled).await.unwrap();
+        machine.advance(Trip::Active).await.unwrap();
+        let done = machine.advance(Trip::Completed).await.unwrap();
+
+        assert_eq!(
+            done,
+            Versioned {
+                state: Trip::Completed,
+                version: 3
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn undeclared_transition_is_rejected() {
+        let store = MemoryStore::default();
+        let machine = StateMachine::<_, Trip>::new(&store, "trip:1");
+        machine.init(Trip::Scheduled).await.unwrap();
+
+        let err = machine.advance(Trip::Completed).await.unwrap_err();
+        assert!(matches!(err, TransitionError::Invalid { .. }));
+    }
+
+    #[tokio::test]
+    async fn stale_version_conflicts() {
+        let store = MemoryStore::default();
+        let machine = StateMachine::<_, Trip>::new(&store, "trip:1");
+        machine.init(Trip::Scheduled).await.unwrap();
+        machine.advance(Trip::Active).await.unwrap();
+
+        let err = machine.transition(1, Trip::Active).await.unwrap_err();
+        assert!(matches!(
+            err,
+            TransitionError::Conflict {
+                expected: Some(1),
+                actual: Some(2)
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn concurrent_transition_conflicts() {
+        let store = Racing::default();
+        let machine = StateMachine::<_, Trip>::new(&store, "trip:1");
+        machine.init(Trip::Scheduled).await.unwrap();
+
+        // Another writer activates the trip after this one has read version 1.
+        let rival = Versioned {
+            state: Trip::Active,
+            version: 2,
+        };
+        *store.rival.lock().unwrap() = Some(serde_json::to_vec(&rival).unwrap());
+
+        let err = machine.transition(1, Trip::Active).await.unwrap_err();
+        assert!(matches!(
+            err,
+            TransitionError::Conflict {
+                expected: Some(1),
+                actual: Some(2)
+            }
+        ));
+        assert_eq!(machine.current().await.unwrap(), Some(rival));
+    }
+}
@@ -0,0 +1,305 @@
+//! Data-quality checks for inbound feeds.
+//!
+//! Checks record [`Violation`]s into a [`Report`] instead of failing fast, so an
+//! ingester sees every problem in a batch before deciding whether to reject or
+//! sanitize it.
+//!
+//! ```rust,ignore
+//! let mut report = Report::new();
+//! report
+//!     .coordinates("position", lat, lon)
+//!     .timestamp("observed_at", observed_at, Utc::now(), TimeWindow::default())
+//!     .monotonic("sequence", &[seq_prev, seq]);
+//! report.into_result()?;
+//! ```
+
+use std::fmt::{self, Display};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// The constraint a value failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// A value fell outside its permitted range.
+    OutOfRange,
+    /// A timestamp was older than the accepted window.
+    Stale,
+    /// A timestamp was further in the future than the accepted window.
+    Future,
+    /// A sequence went backwards (or repeated, for strict checks).
+    NonMonotonic,
+}
+
+/// A single failed check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Name of the offending field.
+    pub field: String,
+    /// The constraint that failed.
+    pub kind: ViolationKind,
+    /// Human-readable detail.
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Accepted distance of a timestamp from "now".
+#[derive(Debug, Clone, Copy)]
+pub struct TimeWindow {
+    /// How far in the past a timestamp may be.
+    pub max_past: TimeDelta,
+    /// How far in the future a timestamp may be, to tolerate clock skew.
+    pub max_future: TimeDelta,
+}
+
+impl Default for TimeWindow {
+    fn default() -> Self {
+        Self {
+            max_past: TimeDelta::hours(24),
+            max_future: TimeDelta::minutes(5),
+        }
+    }
+}
+
+/// Collected violations for one record or batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Create an empty report.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            violations: Vec::new(),
+        }
+    }
+
+    /// Whether every check so far has passed.
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// The violations recorded so far.
+    #[must_use]
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Record a violation.
+    pub fn push(
+        &mut self, field: impl Into<String>, kind: ViolationKind, message: impl Into<String>,
+    ) -> &mut Self {
+        self.violations.push(Violation {
+            field: field.into(),
+            kind,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Check that `value` lies within `min..=max`.
+    pub fn range<T>(&mut self, field: &str, value: T, min: T, max: T) -> &mut Self
+    where
+        T: PartialOrd + Display + Copy,
+    {
+        if value < min || value > max {
+            self.push(field, ViolationKind::OutOfRange, format!("{value} not in {min}..={max}"));
+        }
+        self
+    }
+
+    /// Check that a WGS84 coordinate pair is finite and within latitude/longitude bounds.
+    pub fn coordinates(&mut self, field: &str, lat: f64, lon: f64) -> &mut Self {
+        if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+            self.push(field, ViolationKind::OutOfRange, format!("latitude {lat} not in -90..=90"));
+        }
+        if !lon.is_finite() || !(-180.0..=180.0).contains(&lon) {
+            self.push(
+                field,
+                ViolationKind::OutOfRange,
+                format!("longitude {lon} not in -180..=180"),
+            );
+        }
+        self
+    }
+
+    /// Check that `value` lies within `window` of `now`. A bound reaching
+    /// past the range of [`DateTime`] saturates at the earliest or latest
+    /// representable instant.
+    pub fn timestamp(
+        &mut self, field: &str, value: DateTime<Utc>, now: DateTime<Utc>, window: TimeWindow,
+    ) -> &mut Self {
+        let earliest = now.checked_sub_signed(window.max_past).unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let latest = now.checked_add_signed(window.max_future).unwrap_or(DateTime::<Utc>::MAX_UTC);
+        if value < earliest {
+            self.push(
+                field,
+                ViolationKind::Stale,
+                format!("{value} is older than {}", window.max_past),
+            );
+        } else if value > latest {
+            self.push(
+                field,
+                ViolationKind::Future,
+                format!("{value} is more than {} in the future", window.max_future),
+            );
+        }
+        self
+    }
+
+    /// Check that `values` never decrease.
+    pub fn monotonic<T>(&mut self, field: &str, values: &[T]) -> &mut Self
+    where
+        T: PartialOrd + Display,
+    {
+        for (i, pair) in values.windows(2).enumerate() {
+            if pair[1] < pair[0] {
+                self.push(
+                    field,
+                    ViolationKind::NonMonotonic,
+                    format!("{} at {} follows {}", pair[1], i + 1, pair[0]),
+                );
+            }
+        }
+        self
+    }
+
+    /// Check that `values` strictly increase, rejecting repeats as well as regressions.
+    pub fn strictly_increasing<T>(&mut self, field: &str, values: &[T]) -> &mut Self
+    where
+        T: PartialOrd + Display,
+    {
+        for (i, pair) in values.windows(2).enumerate() {
+            if pair[1] <= pair[0] {
+                self.push(
+                    field,
+                    ViolationKind::NonMonotonic,
+                    format!("{} at {} does not exceed {}", pair[1], i + 1, pair[0]),
+                );
+            }
+        }
+        self
+    }
+
+    /// Convert the report into a `BadRequest` error carrying every violation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BadRequest`] with code `invalid_data` when any check failed.
+    pub fn into_result(self) -> crate::Result<()> {
+        if self.is_valid() {
+            return Ok(());
+        }
+        let description = self.violations.iter().map(ToString::to_string).collect::<Vec<_>>();
+        Err(Error::BadRequest {
+            code: "invalid_data".to_string(),
+            description: description.join("; "),
+        })
+    }
+}
+
+/// Trim surrounding whitespace and drop control characters from free-text feed fields.
+#[must_use]
+pub fn sanitize_text(value: &str) -> String {
+    value.trim().chars().filter(|ch| !ch.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_out_of_bounds() {
+        let mut report = Report::new();
+        report.coordinates("pos", -36.85, 174.76).coordinates("pos", 91.0, f64::NAN);
+
+        let kinds = report.violations().iter().map(|v| v.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [ViolationKind::OutOfRange, ViolationKind::OutOfRange]);
+    }
+
+    #[test]
+    fn timestamp_outside_window() {
+        let now = Utc::now();
+        let mut report = Report::new();
+        report
+            .timestamp("t", now - TimeDelta::minutes(1), now, TimeWindow::default())
+            .timestamp("t", now - TimeDelta::days(2), now, TimeWindow::default())
+            .timestamp("t", now + TimeDelta::hours(1), now, TimeWindow::default());
+
+        let kinds = report.violations().iter().map(|v| v.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [ViolationKind::Stale, ViolationKind::Future]);
+    }
+
+    #[test]
+    fn unrepresentable_past_bound_saturates() {
+        let now = Utc::now();
+        let window = TimeWindow {
+            max_past: TimeDelta::MAX,
+            ..TimeWindow::default()
+        };
+        let mut report = Report::new();
+        report
+            .timestamp("t", now, now, window)
+            .timestamp("t", DateTime::<Utc>::MIN_UTC, now, window)
+            .timestamp("t", now + TimeDelta::hours(1), now, window);
+
+        let kinds = report.violations().iter().map(|v| v.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [ViolationKind::Future]);
+    }
+
+    #[test]
+    fn unrepresentable_future_bound_saturates() {
+        let now = Utc::now();
+        let window = TimeWindow {
+            max_future: TimeDelta::MAX,
+            ..TimeWindow::default()
+        };
+        let mut report = Report::new();
+        report.timestamp("t", DateTime::<Utc>::MAX_UTC, now, window).timestamp(
+            "t",
+            now - TimeDelta::days(2),
+            now,
+            window,
+        );
+
+        let kinds = report.violations().iter().map(|v| v.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [ViolationKind::Stale]);
+    }
+
+    #[test]
+    fn monotonic_allows_repeats_strict_does_not() {
+        let mut report = Report::new();
+        report.monotonic("seq", &[1, 2, 2, 3]);
+        assert!(report.is_valid());
+
+        report.strictly_increasing("seq", &[1, 2, 2, 1]);
+        assert_eq!(report.violations().len(), 2);
+    }
+
+    #[test]
+    fn into_result_reports_every_violation() {
+        let mut report = Report::new();
+        report.range("speed", 250, 0, 200).monotonic("seq", &[3, 1]);
+
+        let err = report.into_result().unwrap_err();
+        assert_eq!(err.code(), "invalid_data");
+        assert!(err.description().contains("speed"));
+        assert!(err.description().contains("seq"));
+    }
+
+    #[test]
+    fn sanitize_text_strips_controls() {
+        assert_eq!(sanitize_text("  Route\u{0} 12\n "), "Route 12");
+    }
+}
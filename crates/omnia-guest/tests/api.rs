@@ -6,12 +6,14 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use axum::body::{Body, to_bytes};
 use axum::response::{IntoResponse, Response};
 use http::{Method, Request, StatusCode};
+use omnia_guest::api::command::CommandResponse;
 use omnia_guest::api::http::{Projector, Router, get, get_with, post};
+use omnia_guest::api::local::Harness;
 use omnia_guest::api::messaging::{
     Delivery, DeliveryError, Outcome as DeliveryOutcome, Projector as DeliveryProjector,
     Router as MessagingRouter, consume,
 };
-use omnia_guest::api::{CallContext, Invocation, Invoker, Metadata, Operation, Provider};
+use omnia_guest::api::{CallContext, Invocation, Invoker, Metadata, Operation, Provider, Tenancy};
 use serde::{Deserialize, Serialize};
 use tower::ServiceExt as _;
 
@@ -27,6 +29,8 @@ struct EchoOutput {
     count: u32,
     owner: String,
     correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
 }
 
 struct Echo;
@@ -44,6 +48,7 @@ impl<P: Provider> Operation<P> for Echo {
             count: input.count.unwrap_or(1),
             owner: context.owner.to_owned(),
             correlation_id: context.metadata.correlation_id.clone(),
+            tenant: context.tenant().map(|tenant| tenant.key("echo")),
         })
     }
 }
@@ -330,6 +335,51 @@ async fn messaging_exact_topic() {
     );
 }
 
+struct Headers;
+
+impl<P: Provider> Operation<P> for Headers {
+    type Error = omnia_guest::Error;
+    type Input = EchoInput;
+    type Output = Vec<(String, String)>;
+
+    async fn call(
+        _input: Self::Input, context: CallContext<'_, P>,
+    ) -> Result<Self::Output, Self::Error> {
+        Ok(context.metadata.headers.clone())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ExpectHeaders;
+
+impl DeliveryProjector<Vec<(String, String)>, omnia_guest::Error, serde_json::Error>
+    for ExpectHeaders
+{
+    fn project(
+        &self,
+        outcome: DeliveryOutcome<Vec<(String, String)>, omnia_guest::Error, serde_json::Error>,
+    ) -> Result<(), DeliveryError> {
+        let expected = [
+            ("correlation-id".to_string(), "delivery-1".to_string()),
+            ("x-source".to_string(), "fleet".to_string()),
+        ];
+        match outcome {
+            DeliveryOutcome::Output(headers) if headers == expected => Ok(()),
+            _ => Err(DeliveryError::Rejected("headers not passed through".to_string())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn messaging_headers_reach_the_operation() {
+    let router = MessagingRouter::new(Invoker::new("messages", ()))
+        .route("events", consume::<Headers>().project_with(ExpectHeaders));
+
+    let mut delivery = delivery(Some("events"), br#"{"name":"message"}"#);
+    delivery.metadata.push(("x-source".to_string(), "fleet".to_string()));
+    router.handle(delivery).await.expect("headers reach the operation");
+}
+
 #[tokio::test]
 async fn messaging_failures() {
     let router =
@@ -361,3 +411,74 @@ fn messaging_duplicate_topic() {
         .route("events", consume::<Echo>())
         .route("events", consume::<Echo>());
 }
+
+#[tokio::test]
+async fn tenant_from_path() {
+    let router = Router::new(Invoker::new("test", ()))
+        .route("/tenants/{tenant}/echo", post::<Echo, ()>())
+        .tenants(Tenancy::path_param("tenant").allow(["acme"]))
+        .into_axum();
+
+    let request = |uri: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .body(Body::from(r#"{"name":"scoped"}"#))
+            .expect("build request")
+    };
+    let response = router.clone().oneshot(request("/tenants/acme/echo")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["tenant"], "acme:echo");
+
+    let response = router.oneshot(request("/tenants/globex/echo")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tenant_from_delivery_metadata() {
+    let router = MessagingRouter::new(Invoker::new("messages", ()))
+        .route("events", consume::<Echo>())
+        .tenants(Tenancy::header("tenant-id"));
+
+    let mut tenanted = delivery(Some("events"), br#"{"name":"message"}"#);
+    tenanted.metadata.push(("Tenant-Id".to_string(), "acme".to_string()));
+    assert_eq!(router.handle(tenanted).await, Ok(()));
+
+    assert!(matches!(
+        router.handle(delivery(Some("events"), br#"{"name":"message"}"#)).await,
+        Err(DeliveryError::Rejected(error)) if error.contains("missing_tenant")
+    ));
+}
+
+#[tokio::test]
+async fn local_harness_http() {
+    let harness = Harness::new()
+        .http(Router::new(Invoker::new("local", ())).route("/echo/{name}", post::<Echo, ()>()));
+
+    let response =
+        harness.execute(["local", "http", "post", "/echo/harness", r#"{"count":3}"#]).await;
+
+    assert_eq!(response.exit, 0);
+    let stdout = String::from_utf8(response.stdout).expect("utf-8 output");
+    let (status, body) = stdout.split_once('\n').expect("status line");
+    assert_eq!(status, "200 OK");
+    let body: serde_json::Value = serde_json::from_str(body).expect("json body");
+    assert_eq!(body["name"], "harness");
+    assert_eq!(body["count"], 3);
+}
+
+#[tokio::test]
+async fn local_harness_message() {
+    let harness = Harness::new().messaging(
+        MessagingRouter::new(Invoker::new("local", ())).route("events", consume::<Echo>()),
+    );
+
+    let response = harness.execute(["local", "message", "events", r#"{"name":"local"}"#]).await;
+    assert_eq!(response, CommandResponse::success("ack\n"));
+
+    let harness: Harness<()> = Harness::new();
+    let response = harness.execute(["local", "message", "events"]).await;
+    assert_eq!(response.exit, 1);
+}
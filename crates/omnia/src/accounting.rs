@@ -0,0 +1,234 @@
+//! # Invocation accounting
+//!
+//! Guests from several teams share one host, so the platform needs to know
+//! what each of them costs. [`Accounting`] tallies, per guest, the
+//! invocations run, the time spent executing guest code, and the imports the
+//! guest called. Each invocation's store is metered through a wasmtime call
+//! hook: wall-clock time between entering wasm and leaving it (returning, or
+//! calling out to the host) is guest time, so time a guest spends awaiting a
+//! backend is not billed. Guest time is not CPU time: it also counts time the
+//! thread running the guest was descheduled.
+//!
+//! Every call from wasm into the host is one import call, whatever it does,
+//! so reading a clock counts the same as a database query.
+//!
+//! Every invocation is emitted as metrics (`guest_invocations`,
+//! `guest_import_calls`, `guest_time_us`, `guest_wall_us`, labelled with the
+//! guest) when its store drops, and folded into the current window. The runtime closes a window
+//! every `USAGE_REPORT_INTERVAL_MS`, logging one usage line per guest.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use wasmtime::CallHook;
+
+use crate::registry::GuestId;
+
+/// Per-guest usage over the current reporting window.
+#[derive(Debug)]
+pub struct Accounting {
+    // Guard is never held across an await.
+    window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    guests: HashMap<GuestId, Usage>,
+}
+
+/// Resources a guest consumed, for one invocation or summed over a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Invocations run.
+    pub invocations: u64,
+    /// Wall-clock time spent executing guest code, excluding host calls.
+    pub guest_time: Duration,
+    /// Wall-clock time from store creation to store drop.
+    pub wall: Duration,
+    /// Calls from guest code into host imports, of any kind.
+    pub import_calls: u64,
+}
+
+/// Usage per guest over one closed window, from [`Accounting::rotate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// How long the window ran.
+    pub window: Duration,
+    /// Usage per guest, ordered by guest id; idle guests are absent.
+    pub guests: Vec<(GuestId, Usage)>,
+}
+
+impl Default for Accounting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accounting {
+    /// An empty accounting whose first window starts now.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                guests: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Add one invocation's `usage` to `id`'s window and emit it as metrics.
+    pub fn record(&self, id: &GuestId, usage: Usage) {
+        tracing::debug!(
+            monotonic_counter.guest_invocations = usage.invocations,
+            monotonic_counter.guest_import_calls = usage.import_calls,
+            histogram.guest_time_us = duration_us(usage.guest_time),
+            histogram.guest_wall_us = duration_us(usage.wall),
+            guest = %id,
+        );
+
+        let mut window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        window.guests.entry(id.clone()).or_default().add(usage);
+    }
+
+    /// Usage in the current window, without closing it.
+    #[must_use]
+    pub fn usage(&self) -> Report {
+        let window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        window.report(window.guests.iter().map(|(id, usage)| (id.clone(), *usage)).collect())
+    }
+
+    /// Close the current window, returning its usage, and start the next.
+    #[must_use]
+    pub fn rotate(&self) -> Report {
+        let mut window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        let guests = window.guests.drain().collect();
+        let report = window.report(guests);
+        window.started = Instant::now();
+        report
+    }
+
+    /// A meter charging one invocation of `id` to this accounting when it
+    /// drops; [`Runtime::meter`](crate::Runtime::meter) installs one on a
+    /// guest's store.
+    #[must_use]
+    pub fn meter(self: &Arc<Self>, id: &GuestId) -> Meter {
+        Meter {
+            accounting: Arc::clone(self),
+            guest: id.clone(),
+            created: Instant::now(),
+            entered: None,
+            usage: Usage {
+                invocations: 1,
+                ..Usage::default()
+            },
+        }
+    }
+}
+
+impl Window {
+    fn report(&self, mut guests: Vec<(GuestId, Usage)>) -> Report {
+        guests.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        Report {
+            window: self.started.elapsed(),
+            guests,
+        }
+    }
+}
+
+impl Usage {
+    const fn add(&mut self, other: Self) {
+        self.invocations = self.invocations.saturating_add(other.invocations);
+        self.guest_time = self.guest_time.saturating_add(other.guest_time);
+        self.wall = self.wall.saturating_add(other.wall);
+        self.import_calls = self.import_calls.saturating_add(other.import_calls);
+    }
+}
+
+/// One invocation's tally, driven by its store's call hook and recorded when
+/// the store (and with it the hook) drops.
+#[derive(Debug)]
+pub struct Meter {
+    accounting: Arc<Accounting>,
+    guest: GuestId,
+    created: Instant,
+    // When execution last entered guest code, while it is still there.
+    entered: Option<Instant>,
+    usage: Usage,
+}
+
+impl Meter {
+    /// Account for one wasm/host transition.
+    pub fn transition(&mut self, hook: CallHook) {
+        match hook {
+            CallHook::CallingWasm | CallHook::ReturningFromHost => {
+                self.entered = Some(Instant::now());
+            }
+            CallHook::ReturningFromWasm => self.leave(),
+            CallHook::CallingHost => {
+                self.leave();
+                self.usage.import_calls += 1;
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        if let Some(entered) = self.entered.take() {
+            self.usage.guest_time += entered.elapsed();
+        }
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        self.leave();
+        self.usage.wall = self.created.elapsed();
+        self.accounting.record(&self.guest, self.usage);
+    }
+}
+
+fn duration_us(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meters_fold_into_the_window() {
+        let accounting = Arc::new(Accounting::new());
+        let id = GuestId::from("orders");
+
+        for _ in 0..2 {
+            let mut meter = accounting.meter(&id);
+            meter.transition(CallHook::CallingWasm);
+            meter.transition(CallHook::CallingHost);
+            meter.transition(CallHook::ReturningFromHost);
+            meter.transition(CallHook::CallingHost);
+            meter.transition(CallHook::ReturningFromHost);
+            meter.transition(CallHook::ReturningFromWasm);
+        }
+        drop(accounting.meter(&GuestId::from("billing")));
+
+        let report = accounting.usage();
+        let ids: Vec<_> = report.guests.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["billing", "orders"]);
+        let orders = report.guests[1].1;
+        assert_eq!(orders.invocations, 2);
+        assert_eq!(orders.import_calls, 4);
+        assert!(orders.guest_time <= orders.wall);
+    }
+
+    #[test]
+    fn rotate_starts_a_fresh_window() {
+        let accounting = Arc::new(Accounting::new());
+        drop(accounting.meter(&GuestId::from("orders")));
+
+        let closed = accounting.rotate();
+        assert_eq!(closed.guests.len(), 1);
+        assert_eq!(accounting.usage().guests, Vec::new());
+        assert_eq!(accounting.rotate().guests, Vec::new());
+    }
+}
@@ -24,7 +24,7 @@ use crate::dispatch::{
     DispatchHandle, FirstArgSelector, GuestResolver, GuestSelector, HttpFallback,
 };
 use crate::mount::{MountRegistry, ResolvedPreopen};
-use crate::registry::{GuestId, Registry, Routes};
+use crate::registry::{GuestId, LinkedHost, Registry, Routes};
 use crate::{Host, Mode, RuntimeOptions, Server, Telemetry};
 
 /// Typestate for [`DeploymentBuilder`]: only raw `.wasm` sources load (the
@@ -297,6 +297,8 @@ pub struct Deployment<T: WasiView + 'static> {
     linker: Linker<T>,
     options: RuntimeOptions,
     guests: Vec<LoadedGuest>,
+    // Hosts linked so far, in link order, for capability reports.
+    hosts: Vec<LinkedHost>,
     routes: Routes,
     // Guest links — the host-mediated interfaces.
     links: BTreeSet<Box<str>>,
@@ -349,6 +351,7 @@ impl<T: WasiView + 'static> Deployment<T> {
             linker,
             options,
             guests,
+            hosts: Vec::new(),
             routes: plan.routes,
             links: plan.links,
             selector: Arc::new(FirstArgSelector),
@@ -374,9 +377,22 @@ impl<T: WasiView> Deployment<T> {
         H: Host<T> + Server<B>,
     {
         H::add_to_linker(&mut self.linker)?;
+        self.hosts.push(LinkedHost {
+            host: std::any::type_name::<H>(),
+            backend: None,
+        });
         Ok(self)
     }
 
+    /// Name `D` as the backend serving the most recently linked host, for
+    /// capability reports. Chainable after [`host`](Self::host).
+    pub fn backend<D>(&mut self) -> &mut Self {
+        if let Some(linked) = self.hosts.last_mut() {
+            linked.backend = Some(std::any::type_name::<D>());
+        }
+        self
+    }
+
     /// Override the host-mediated dispatch [`GuestSelector`].
     ///
     /// Defaults to [`FirstArgSelector`] — the runtime core's "first call argument is the
@@ -436,7 +452,7 @@ impl<T: WasiView> Deployment<T> {
             self.options.guest_timeout,
         );
 
-        Registry::assemble(
+        let registry = Registry::assemble(
             self.engine,
             self.linker,
             self.options,
@@ -444,7 +460,12 @@ impl<T: WasiView> Deployment<T> {
             self.routes,
             dispatch,
             self.allow_empty,
-        )
+        )?
+        .with_hosts(self.hosts);
+        for guest in registry.guests() {
+            registry.write_capability_report(&guest)?;
+        }
+        Ok(registry)
     }
 }
 
@@ -72,6 +72,7 @@ where
     let func_owned = func.to_owned();
     let results = tokio::spawn(async move {
         let mut store = task_runtime.build_store(task_runtime.store());
+        task_runtime.meter(&target_owned, &mut store);
         let instance = task_runtime
             .instantiate(&instance_pre, &mut store)
             .await
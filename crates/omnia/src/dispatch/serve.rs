@@ -99,7 +99,12 @@ where
             let server =
                 Arc::clone(server.get_or_insert_with(|| Arc::new(InProcServer::default())));
             let runtime = state.clone();
-            let factory = move || runtime.build_store(runtime.store());
+            let id = guest.id().clone();
+            let factory = move || {
+                let mut store = runtime.build_store(runtime.store());
+                runtime.meter(&id, &mut store);
+                store
+            };
             let stream = server
                 .serve_function(
                     factory,
@@ -2,12 +2,14 @@
 #![cfg(not(target_arch = "wasm32"))]
 #![allow(unsafe_code)] // wasmtime component deserialization and deployment hooks
 
+mod accounting;
 mod cli;
 mod deployment;
 mod dispatch;
 mod host;
 mod mount;
 mod options;
+mod quarantine;
 mod registry;
 mod runtime;
 mod store;
@@ -22,6 +24,7 @@ pub use wrpc_wasmtime::{WrpcCtxView, WrpcView};
 #[doc(hidden)]
 pub use {anyhow, futures, tokio, wasmtime, wasmtime_wasi};
 
+pub use self::accounting::{Accounting, Meter, Report, Usage};
 pub use self::cli::{Cli, Command};
 pub use self::deployment::{
     Deployment, DeploymentBuilder, GuestArtifact, GuestEntry, HttpRoute, Manifest, Mount,
@@ -36,8 +39,10 @@ pub use self::mount::{MountRegistry, ResolvedPreopen};
 pub use self::options::RuntimeOptions;
 #[cfg(feature = "jit")]
 pub use self::options::compile;
+pub use self::quarantine::{Crash, Quarantine, Quarantined};
 pub use self::registry::{
-    CliRoutes, Guest, GuestId, HttpRoutes, PatternRoutes, Registry, Routes, TriggerRouter,
+    CapabilityReport, CliRoutes, Guest, GuestId, HttpRoutes, ImportReport, PatternRoutes, Provider,
+    Registry, Routes, TriggerRouter,
 };
 pub use self::runtime::{Backends, ExitStatus, Mode, Runtime, Wiring};
 #[doc(hidden)]
@@ -88,7 +93,7 @@ macro_rules! host_error {
 /// repeats.
 ///
 /// Emits the `Wasi<Service>View` accessor trait, the `Wasi<Service>CtxView`
-/// borrowed `(ctx, table)` view, the `Has<Service>` backend-accessor trait, and
+/// borrowed `(ctx, table, guest)` view, the `Has<Service>` backend-accessor trait, and
 /// the blanket `Wasi<Service>View for omnia::StoreCtx<B>` impl.
 ///
 /// Pass the service stem (the part after `Wasi` in the host struct name); every
@@ -120,6 +125,8 @@ macro_rules! wasi_view {
                 pub ctx: &'a mut dyn [<Wasi $name Ctx>],
                 /// Mutable reference to the table used to manage resources.
                 pub table: &'a mut $crate::wasmtime_wasi::ResourceTable,
+                /// The guest the store runs, when the runtime has attributed it.
+                pub guest: Option<&'a $crate::GuestId>,
             }
 
             #[doc = concat!("A backend bundle that yields the WASI ", stringify!($name), " context for a store.")]
@@ -137,6 +144,7 @@ macro_rules! wasi_view {
                     [<Wasi $name CtxView>] {
                         ctx: self.backends.[<$name:lower _ ctx>](),
                         table: &mut self.base.table,
+                        guest: self.base.guest.as_ref(),
                     }
                 }
             }
@@ -18,6 +18,7 @@
 #[cfg(feature = "jit")]
 mod compile;
 
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::{Result, bail};
@@ -84,6 +85,18 @@ pub struct RuntimeOptions {
     /// Maximum host-mediated guest-to-guest dispatch nesting depth (`MAX_DISPATCH_DEPTH`, default 8).
     #[env(from = "MAX_DISPATCH_DEPTH", default = "8")]
     pub max_dispatch_depth: usize,
+    /// Consecutive crashed event invocations before a guest is quarantined; `0` disables (`CRASH_LOOP_THRESHOLD`, default 5).
+    #[env(from = "CRASH_LOOP_THRESHOLD", default = "5")]
+    pub crash_loop_threshold: u32,
+    /// How long a crash-looping guest is quarantined before a probe invocation (`QUARANTINE_MS`, default 60s).
+    #[env(from = "QUARANTINE_MS", default = "60000", with = parse_millis)]
+    pub quarantine_period: Duration,
+    /// How long trigger servers have to drain after a shutdown signal before the runtime exits (`SHUTDOWN_GRACE_MS`, default 10s).
+    #[env(from = "SHUTDOWN_GRACE_MS", default = "10000", with = parse_millis)]
+    pub shutdown_grace: Duration,
+    /// Length of each per-guest usage window, logged as a usage report when it closes; `0` disables reports (`USAGE_REPORT_INTERVAL_MS`, default 60s).
+    #[env(from = "USAGE_REPORT_INTERVAL_MS", default = "60000", with = parse_millis)]
+    pub usage_report_interval: Duration,
     /// Enable the pooling instance allocator (`POOLING`, default `true`).
     #[env(from = "POOLING", default = "true")]
     pub pooling: bool,
@@ -159,6 +172,9 @@ pub struct RuntimeOptions {
     /// Honour WebAssembly branch hints during compilation; compile-affecting (`BRANCH_HINTING`, default `false`).
     #[env(from = "BRANCH_HINTING", default = "false")]
     pub branch_hinting: bool,
+    /// Directory each guest's capability report is written to at load (`CAPABILITY_REPORT_DIR`, unset: no reports).
+    #[env(from = "CAPABILITY_REPORT_DIR")]
+    pub capability_report_dir: Option<PathBuf>,
 }
 
 /// Build the [`Config`] shared by [`crate::compile`] and [`crate::DeploymentBuilder`].
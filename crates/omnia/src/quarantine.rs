@@ -0,0 +1,242 @@
+//! # Crash-loop quarantine
+//!
+//! A guest that crashes on every event (a trap from bad configuration, say)
+//! would otherwise be re-instantiated for each delivery forever. [`Quarantine`]
+//! counts consecutive crashed invocations per guest; at the threshold the
+//! guest is quarantined for a cooling-off period, during which event triggers
+//! stop feeding it. Once the period lapses the next invocation is a probe: an
+//! invocation that runs to completion releases the guest, a crash quarantines
+//! it again straight away.
+//!
+//! Only errors marked [`Crash`] count: traps, which is also how a guest panic
+//! surfaces. An error the guest returns, or a timeout, shows the guest runs
+//! and resets its count.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::registry::GuestId;
+
+/// Per-guest crash-loop detector shared by the event triggers.
+#[derive(Debug)]
+pub struct Quarantine {
+    threshold: u32,
+    period: Duration,
+    // Guard is never held across an await.
+    guests: Mutex<HashMap<GuestId, Health>>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    failures: u32,
+    last_error: String,
+    until: Option<Instant>,
+}
+
+impl Quarantine {
+    /// Quarantine a guest for `period` after `threshold` consecutive failures;
+    /// a zero threshold disables detection.
+    #[must_use]
+    pub fn new(threshold: u32, period: Duration) -> Self {
+        Self {
+            threshold,
+            period,
+            guests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether `id` may be invoked now.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Quarantined`] while the guest's cooling-off period runs.
+    pub fn admit(&self, id: &GuestId) -> Result<(), Quarantined> {
+        let guests = self.guests.lock().unwrap_or_else(PoisonError::into_inner);
+        match guests.get(id) {
+            Some(health) if health.until.is_some_and(|until| Instant::now() < until) => {
+                Err(health.quarantined(id))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the outcome of one invocation of `id`.
+    pub fn record<T>(&self, id: &GuestId, outcome: &anyhow::Result<T>) {
+        if self.threshold == 0 {
+            return;
+        }
+        let mut guests = self.guests.lock().unwrap_or_else(PoisonError::into_inner);
+        match outcome {
+            Err(error) if error.downcast_ref::<Crash>().is_some() => {
+                let health = guests.entry(id.clone()).or_default();
+                health.failures = health.failures.saturating_add(1);
+                health.last_error = format!("{error:#}");
+                if health.failures >= self.threshold {
+                    health.until = Some(Instant::now() + self.period);
+                    tracing::error!(
+                        monotonic_counter.guest_quarantined = 1,
+                        guest = %id,
+                        failures = health.failures,
+                        error = %health.last_error,
+                        "crash loop detected; guest quarantined",
+                    );
+                }
+            }
+            _ => {
+                if guests.remove(id).is_some_and(|health| health.until.is_some()) {
+                    tracing::info!(guest = %id, "guest released from quarantine");
+                }
+            }
+        }
+    }
+
+    /// Guests currently in quarantine, for health reporting.
+    #[must_use]
+    pub fn quarantined(&self) -> Vec<Quarantined> {
+        let now = Instant::now();
+        let guests = self.guests.lock().unwrap_or_else(PoisonError::into_inner);
+        guests
+            .iter()
+            .filter(|(_, health)| health.until.is_some_and(|until| now < until))
+            .map(|(id, health)| health.quarantined(id))
+            .collect()
+    }
+}
+
+impl Health {
+    fn quarantined(&self, id: &GuestId) -> Quarantined {
+        Quarantined {
+            guest: id.clone(),
+            failures: self.failures,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Marks an invocation error as a guest crash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crash;
+
+impl Crash {
+    /// Convert an error from instantiating or calling a guest, marking traps
+    /// as crashes.
+    #[must_use]
+    pub fn classify(error: wasmtime::Error) -> anyhow::Error {
+        let trapped = error.downcast_ref::<wasmtime::Trap>().is_some();
+        let error = anyhow::Error::from(error);
+        if trapped { error.context(Self) } else { error }
+    }
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("guest crashed")
+    }
+}
+
+/// A guest refused because it is in quarantine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Quarantined {
+    /// The quarantined guest.
+    pub guest: GuestId,
+    /// Consecutive failures that led to the quarantine.
+    pub failures: u32,
+    /// The most recent failure.
+    pub last_error: String,
+}
+
+impl fmt::Display for Quarantined {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "guest `{}` is quarantined after {} consecutive failures: {}",
+            self.guest, self.failures, self.last_error
+        )
+    }
+}
+
+impl std::error::Error for Quarantined {}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn failure() -> anyhow::Result<()> {
+        Err(anyhow!("wasm trap: unreachable").context(Crash))
+    }
+
+    #[test]
+    fn quarantines_after_consecutive_failures() {
+        let quarantine = Quarantine::new(3, Duration::from_secs(60));
+        let id = GuestId::from("orders");
+
+        quarantine.record(&id, &failure());
+        quarantine.record(&id, &failure());
+        quarantine.admit(&id).unwrap();
+
+        quarantine.record(&id, &failure());
+        let refused = quarantine.admit(&id).unwrap_err();
+        assert_eq!(refused.failures, 3);
+        assert_eq!(refused.last_error, "guest crashed: wasm trap: unreachable");
+        assert_eq!(quarantine.quarantined(), [refused]);
+    }
+
+    #[test]
+    fn success_resets_the_count() {
+        let quarantine = Quarantine::new(2, Duration::from_secs(60));
+        let id = GuestId::from("orders");
+
+        quarantine.record(&id, &failure());
+        quarantine.record(&id, &Ok(()));
+        quarantine.record(&id, &failure());
+        quarantine.admit(&id).unwrap();
+    }
+
+    #[test]
+    fn probe_after_period_requarantines_on_failure() {
+        let quarantine = Quarantine::new(1, Duration::ZERO);
+        let id = GuestId::from("orders");
+
+        quarantine.record(&id, &failure());
+        quarantine.admit(&id).unwrap();
+        quarantine.record(&id, &failure());
+        assert!(quarantine.guests.lock().unwrap()[&id].until.is_some());
+
+        quarantine.record(&id, &Ok(()));
+        assert!(quarantine.guests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn guest_errors_and_timeouts_do_not_count() {
+        let quarantine = Quarantine::new(1, Duration::from_secs(60));
+        let id = GuestId::from("orders");
+
+        quarantine.record::<()>(&id, &Err(anyhow!("invalid order")));
+        quarantine.record::<()>(&id, &Err(anyhow!("messaging handler timed out")));
+        quarantine.admit(&id).unwrap();
+    }
+
+    #[test]
+    fn classifies_traps_as_crashes() {
+        let trap = Crash::classify(wasmtime::Error::new(wasmtime::Trap::UnreachableCodeReached));
+        assert!(trap.downcast_ref::<Crash>().is_some());
+
+        let other = Crash::classify(wasmtime::Error::msg("missing export"));
+        assert!(other.downcast_ref::<Crash>().is_none());
+    }
+
+    #[test]
+    fn zero_threshold_disables() {
+        let quarantine = Quarantine::new(0, Duration::from_secs(60));
+        let id = GuestId::from("orders");
+
+        for _ in 0..10 {
+            quarantine.record(&id, &failure());
+        }
+        quarantine.admit(&id).unwrap();
+    }
+}
@@ -9,6 +9,7 @@
 //! The runtime core treats identities as opaque keys; consumers project their own
 //! scheme onto them. Omnia never parses a [`GuestId`].
 
+mod report;
 mod routing;
 
 use std::collections::{BTreeMap, BTreeSet, btree_map};
@@ -16,6 +17,7 @@ use std::fmt;
 use std::sync::{Arc, PoisonError, RwLock};
 
 use anyhow::{Context as _, Result, bail, ensure};
+pub use report::{CapabilityReport, ImportReport, LinkedHost, Provider};
 pub use routing::{CliRoutes, HttpRoutes, PatternRoutes, Routes, TriggerRouter};
 use wasmtime::Engine;
 use wasmtime::component::{Component, InstancePre, Linker};
@@ -133,6 +135,8 @@ pub struct Registry<T: 'static> {
     // Link interfaces polyfilled onto the shared linker at bootstrap; a late
     // guest's remaining allow-listed imports are polyfilled on a linker clone.
     wired_links: BTreeSet<Box<str>>,
+    // Hosts linked before assembly, for capability reports.
+    hosts: Vec<LinkedHost>,
     routes: Routes,
     dispatch: Arc<DispatchHandle>,
 }
@@ -165,10 +169,7 @@ impl<T: WasiView + 'static> Registry<T> {
 
         let mut guests = BTreeMap::new();
         for guest in loaded {
-            let instance_pre = linker
-                .instantiate_pre(&guest.component)
-                .map_err(anyhow::Error::from)
-                .with_context(|| format!("pre-instantiating guest `{}`", guest.id))?;
+            let instance_pre = pre_instantiate(&engine, &linker, &guest.id, &guest.component)?;
             let id = guest.id.clone();
             if guests
                 .insert(guest.id.clone(), Arc::new(Guest::local(guest.id, instance_pre)))
@@ -194,11 +195,18 @@ impl<T: WasiView + 'static> Registry<T> {
             guests: RwLock::new(guests),
             static_ids,
             wired_links,
+            hosts: Vec::new(),
             routes,
             dispatch,
         })
     }
 
+    /// Attribute imports to the hosts linked before assembly.
+    pub(crate) fn with_hosts(mut self, hosts: Vec<LinkedHost>) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
     /// Pre-instantiate a late (dynamically registered) component against the
     /// shared host set.
     ///
@@ -222,13 +230,78 @@ impl<T: WasiView + 'static> Registry<T> {
             &self.dispatch,
             &self.wired_links,
         )?;
-        linker
-            .instantiate_pre(component)
-            .map_err(anyhow::Error::from)
-            .with_context(|| format!("pre-instantiating guest `{id}`"))
+        pre_instantiate(&self.engine, &linker, id, component)
     }
 }
 
+/// Pre-instantiate `component`, listing the imports no linked host or `link`
+/// entry provides and the host that would provide each — wasmtime's own error
+/// names only the first missing import, not what to link to provide it.
+fn pre_instantiate<T: 'static>(
+    engine: &Engine, linker: &Linker<T>, id: &GuestId, component: &Component,
+) -> Result<InstancePre<T>> {
+    linker.instantiate_pre(component).map_err(anyhow::Error::from).with_context(|| {
+        let missing = unsatisfied(engine, linker, component)
+            .into_iter()
+            .map(|name| match provider_hint(&name) {
+                Some(host) => format!("{name} (link {host})"),
+                None => name,
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            format!(
+                "pre-instantiating guest `{id}`: an import does not match the type its provider \
+                 defines"
+            )
+        } else {
+            format!(
+                "pre-instantiating guest `{id}`: no linked host or `link` entry provides {}",
+                missing.join(", ")
+            )
+        }
+    })
+}
+
+/// The imports of `component` that `linker` defines nothing for.
+///
+/// A linker cannot be queried by name, so each import is probed on a copy:
+/// with shadowing off, defining a name the linker already holds fails.
+fn unsatisfied<T: 'static>(
+    engine: &Engine, linker: &Linker<T>, component: &Component,
+) -> Vec<String> {
+    component
+        .component_type()
+        .imports(engine)
+        .filter(|(name, _)| {
+            let mut probe = linker.clone();
+            probe.allow_shadowing(false);
+            probe.root().instance(name).is_ok()
+        })
+        .map(|(name, _)| name.to_owned())
+        .collect()
+}
+
+/// The Omnia host type that links an interface import, by WIT package.
+fn provider_hint(import: &str) -> Option<&'static str> {
+    let package = import.split(['/', '@']).next()?;
+    let host = match package {
+        "wasi:blobstore" => "omnia_wasi_blobstore::WasiBlobstore",
+        "wasi:config" => "omnia_wasi_config::WasiConfig",
+        "wasi:docstore" => "omnia_wasi_docstore::WasiDocStore",
+        "wasi:http" => "omnia_wasi_http::WasiHttp",
+        "wasi:keyvalue" => "omnia_wasi_keyvalue::WasiKeyValue",
+        "wasi:messaging" => "omnia_wasi_messaging::WasiMessaging",
+        "wasi:sql" => "omnia_wasi_sql::WasiSql",
+        "omnia:identity" => "omnia_wasi_identity::WasiIdentity",
+        "omnia:model" => "omnia_wasi_model::WasiModel",
+        "omnia:otel" => "omnia_wasi_otel::WasiOtel",
+        "omnia:vault" => "omnia_wasi_vault::WasiVault",
+        "omnia:websocket" => "omnia_wasi_websocket::WasiWebSocket",
+        _ => return None,
+    };
+    Some(host)
+}
+
 impl<T: 'static> Registry<T> {
     /// Returns the shared engine every guest is instantiated against.
     #[must_use]
@@ -319,6 +392,37 @@ impl<T: 'static> Registry<T> {
         &self.dispatch
     }
 
+    /// Everything guest `id` imports and what satisfies each import, or
+    /// `None` if the guest is not registered.
+    #[must_use]
+    pub fn capability_report(&self, id: &GuestId) -> Option<CapabilityReport> {
+        self.get(id).map(|guest| self.report(&guest))
+    }
+
+    /// Capability reports for every registered guest, ordered by identity.
+    #[must_use]
+    pub fn capability_reports(&self) -> Vec<CapabilityReport> {
+        self.guests().map(|guest| self.report(&guest)).collect()
+    }
+
+    /// Write `guest`'s capability report when `CAPABILITY_REPORT_DIR` is set.
+    pub(crate) fn write_capability_report(&self, guest: &Guest<T>) -> Result<()> {
+        let Some(dir) = &self.options.capability_report_dir else {
+            return Ok(());
+        };
+        self.report(guest).write(dir)
+    }
+
+    fn report(&self, guest: &Guest<T>) -> CapabilityReport {
+        CapabilityReport::new(
+            &self.engine,
+            guest.id(),
+            guest.component(),
+            &self.hosts,
+            self.dispatch.links(),
+        )
+    }
+
     /// Returns the number of registered guests.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -377,4 +481,17 @@ mod tests {
         let registry = assemble_empty(true).expect("a dynamic deployment may start with no guests");
         assert!(registry.is_empty());
     }
+
+    #[test]
+    fn provider_hint_maps_versioned_interfaces() {
+        assert_eq!(
+            provider_hint("wasi:keyvalue/store@0.2.0-draft2"),
+            Some("omnia_wasi_keyvalue::WasiKeyValue")
+        );
+        assert_eq!(
+            provider_hint("omnia:websocket/client@0.1.0"),
+            Some("omnia_wasi_websocket::WasiWebSocket")
+        );
+        assert_eq!(provider_hint("acme:billing/ledger"), None);
+    }
 }
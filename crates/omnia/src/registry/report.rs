@@ -0,0 +1,167 @@
+//! # Capability reports
+//!
+//! A machine-readable record of everything a guest imports and what satisfies
+//! each import: an Omnia host (and the backend behind it), the WASI platform
+//! the runtime always links, or another guest through a `link` entry. Reports
+//! are built from the component's import types, so a security review of a
+//! third-party component reads the report instead of its WIT.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use serde::Serialize;
+use wasmtime::Engine;
+use wasmtime::component::Component;
+
+use super::{GuestId, provider_hint};
+
+/// WASI packages `wasmtime-wasi` links into every deployment.
+const PLATFORM: &[&str] =
+    &["wasi:cli", "wasi:clocks", "wasi:filesystem", "wasi:io", "wasi:random", "wasi:sockets"];
+
+/// A host linked into the deployment, with the backend serving it when the
+/// deployment named one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkedHost {
+    /// The host type name.
+    pub host: &'static str,
+    /// The backend type name.
+    pub backend: Option<&'static str>,
+}
+
+/// Everything one guest imports and what satisfies each import.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CapabilityReport {
+    /// The guest identity.
+    pub guest: String,
+    /// Every import, ordered by name.
+    pub imports: Vec<ImportReport>,
+}
+
+/// One import and its provider.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    /// The imported interface, with its version when the component has one.
+    pub interface: String,
+    /// What satisfies the import.
+    pub provider: Provider,
+}
+
+/// What satisfies an import.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Provider {
+    /// An Omnia host.
+    Host {
+        /// The host type, e.g. `omnia_wasi_sql::WasiSql`.
+        host: String,
+        /// The backend type behind the host, when the deployment named it.
+        backend: Option<String>,
+    },
+    /// The WASI platform (`wasi:cli`, `wasi:io`, ...) linked into every
+    /// deployment.
+    Platform,
+    /// Another guest, through host-mediated dispatch.
+    Link,
+    /// Linked by a host the runtime cannot attribute, such as a
+    /// deployment-specific host outside the Omnia crates.
+    Unattributed,
+}
+
+impl CapabilityReport {
+    /// Build the report for `component`.
+    pub(crate) fn new(
+        engine: &Engine, id: &GuestId, component: &Component, hosts: &[LinkedHost],
+        links: &BTreeSet<Box<str>>,
+    ) -> Self {
+        let mut imports: Vec<_> = component
+            .component_type()
+            .imports(engine)
+            .map(|(name, _)| ImportReport {
+                interface: name.to_owned(),
+                provider: provider(name, hosts, links),
+            })
+            .collect();
+        imports.sort_by(|a, b| a.interface.cmp(&b.interface));
+
+        Self {
+            guest: id.to_string(),
+            imports,
+        }
+    }
+
+    /// Write the report to `<dir>/<guest>.json`, replacing characters that are
+    /// not safe in a file name with `_`.
+    pub(crate) fn write(&self, dir: &Path) -> Result<()> {
+        let file: String = self
+            .guest
+            .chars()
+            .map(
+                |c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' },
+            )
+            .collect();
+        let path = dir.join(format!("{file}.json"));
+
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating capability report directory {}", dir.display()))?;
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("writing capability report {}", path.display()))?;
+        tracing::info!(guest = %self.guest, path = %path.display(), "capability report written");
+        Ok(())
+    }
+}
+
+fn provider(import: &str, hosts: &[LinkedHost], links: &BTreeSet<Box<str>>) -> Provider {
+    if links.contains(import) {
+        return Provider::Link;
+    }
+
+    // Hosts are matched by type name, so a re-exported path still matches.
+    let linked = provider_hint(import).and_then(|hint| {
+        let name = short_name(hint);
+        hosts.iter().find(|linked| short_name(linked.host) == name)
+    });
+    if let Some(linked) = linked {
+        return Provider::Host {
+            host: linked.host.to_owned(),
+            backend: linked.backend.map(str::to_owned),
+        };
+    }
+
+    let package = import.split(['/', '@']).next().unwrap_or_default();
+    if PLATFORM.contains(&package) {
+        return Provider::Platform;
+    }
+    Provider::Unattributed
+}
+
+fn short_name(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_resolve_to_providers() {
+        let hosts = [LinkedHost {
+            host: "omnia_wasi_sql::host::WasiSql",
+            backend: Some("omnia_wasi_sql::host::default_impl::SqlDefault"),
+        }];
+        let links = BTreeSet::from([Box::from("omnia:link/echo")]);
+
+        assert_eq!(
+            provider("wasi:sql/readwrite@0.2.0-draft", &hosts, &links),
+            Provider::Host {
+                host: "omnia_wasi_sql::host::WasiSql".to_owned(),
+                backend: Some("omnia_wasi_sql::host::default_impl::SqlDefault".to_owned()),
+            }
+        );
+        assert_eq!(provider("omnia:link/echo", &hosts, &links), Provider::Link);
+        assert_eq!(provider("wasi:io/streams@0.2.6", &hosts, &links), Provider::Platform);
+        assert_eq!(provider("wasi:keyvalue/store", &hosts, &links), Provider::Unattributed);
+    }
+}
@@ -14,16 +14,19 @@ use anyhow::{Context as _, Result};
 pub use entry::{MainOptions, ManifestSource};
 use futures::FutureExt as _;
 use futures::future::{BoxFuture, Shared};
+use tokio_util::sync::CancellationToken;
 use wasmtime::component::types::ComponentItem;
 use wasmtime::component::{Component, Instance, InstancePre};
 use wasmtime::{Engine, Store};
 
+use crate::accounting::Accounting;
 use crate::deployment::GuestArtifact;
 use crate::dispatch::{
     EnsureError, GuestResolver, HttpFallback, ResolveHook, serve_guest, serve_links,
 };
 use crate::host::FutureResult;
 use crate::mount::MountRegistry;
+use crate::quarantine::{Crash, Quarantine};
 use crate::registry::{Guest, GuestId};
 use crate::store::HasLimits;
 use crate::{
@@ -192,6 +195,10 @@ where
     let epoch = drive_epoch(runtime.registry().engine().clone(), runtime.options().epoch_tick);
     let pool =
         sample_pool(runtime.registry().engine().clone(), runtime.options().pool_metrics_interval);
+    let usage = report_usage(
+        Arc::clone(&runtime.inner.accounting),
+        runtime.options().usage_report_interval,
+    );
 
     log_bootstrap_complete(&runtime, mode);
 
@@ -205,16 +212,84 @@ where
             });
             command::drive(&runtime).await
         }
-        Mode::Server => H::serve(&runtime).await.map(|()| ExitStatus::SUCCESS),
+        Mode::Server => serve_until_signal::<B, H>(&runtime).await,
     };
 
     epoch.abort();
     if let Some(pool) = pool {
         pool.abort();
     }
+    if let Some(usage) = usage {
+        usage.abort();
+    }
     outcome
 }
 
+/// Run every trigger server until they exit or a shutdown signal (SIGTERM or
+/// Ctrl-C) arrives. On a signal, servers are told to drain through
+/// [`Runtime::shutting_down`] and given `shutdown_grace` to return; servers
+/// still running after that, or after a second signal, are dropped.
+async fn serve_until_signal<B, H>(runtime: &Runtime<B>) -> Result<ExitStatus>
+where
+    B: Backends,
+    H: Wiring<B>,
+{
+    let serve = H::serve(runtime);
+    tokio::pin!(serve);
+
+    tokio::select! {
+        outcome = &mut serve => return outcome.map(|()| ExitStatus::SUCCESS),
+        () = shutdown_signal() => {}
+    }
+    tracing::info!("shutdown signal received; draining trigger servers");
+    runtime.shutdown();
+
+    let grace = runtime.options().shutdown_grace;
+    tokio::select! {
+        drained = tokio::time::timeout(grace, &mut serve) => drained.map_or_else(
+            |_| {
+                tracing::warn!("trigger servers still running after {grace:?}; exiting");
+                Ok(ExitStatus::SUCCESS)
+            },
+            |outcome| outcome.map(|()| ExitStatus::SUCCESS),
+        ),
+        () = shutdown_signal() => {
+            tracing::warn!("second shutdown signal received; exiting without draining");
+            Ok(ExitStatus::SUCCESS)
+        }
+    }
+}
+
+/// Resolves on SIGTERM (Unix) or Ctrl-C. Never resolves if no handler can be
+/// installed.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let terminate = async {
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(_) => std::future::pending::<()>().await,
+            }
+        };
+        tokio::select! {
+            () = ctrl_c => {}
+            () = terminate => {}
+        }
+    }
+    #[cfg(not(unix))]
+    ctrl_c.await;
+}
+
 fn log_bootstrap_complete<B>(runtime: &Runtime<B>, mode: Mode)
 where
     B: Clone + Send + Sync + 'static,
@@ -266,6 +341,39 @@ fn sample_pool(engine: Engine, interval: Duration) -> Option<tokio::task::JoinHa
     Some(handle)
 }
 
+fn report_usage(
+    accounting: Arc<Accounting>, interval: Duration,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if interval.is_zero() {
+        return None;
+    }
+
+    let handle = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately; the first window closes a full
+        // interval after start-up.
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+
+            let report = accounting.rotate();
+            for (guest, usage) in &report.guests {
+                tracing::info!(
+                    guest = %guest,
+                    window_secs = report.window.as_secs(),
+                    invocations = usage.invocations,
+                    guest_time_ms =
+                        u64::try_from(usage.guest_time.as_millis()).unwrap_or(u64::MAX),
+                    wall_ms = u64::try_from(usage.wall.as_millis()).unwrap_or(u64::MAX),
+                    import_calls = usage.import_calls,
+                    "guest usage",
+                );
+            }
+        }
+    });
+    Some(handle)
+}
+
 /// Guest exit code. [`code_u8`](Self::code_u8) and [`ExitCode`](std::process::ExitCode)
 /// keep only the low byte (POSIX semantics).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -334,6 +442,9 @@ struct RuntimeInner<B: 'static> {
     // its flight: inserted when the flight starts, removed when its outcome
     // is computed — nothing is cached across flights.
     flights: Mutex<HashMap<GuestId, Flight<B>>>,
+    quarantine: Quarantine,
+    accounting: Arc<Accounting>,
+    shutdown: CancellationToken,
 }
 
 impl<B: 'static> RuntimeInner<B> {
@@ -341,6 +452,8 @@ impl<B: 'static> RuntimeInner<B> {
         registry: Arc<Registry<StoreCtx<B>>>, args: Arc<Vec<String>>, mounts: Arc<MountRegistry>,
         backends: B,
     ) -> Self {
+        let options = registry.options();
+        let quarantine = Quarantine::new(options.crash_loop_threshold, options.quarantine_period);
         Self {
             registry,
             args,
@@ -350,6 +463,9 @@ impl<B: 'static> RuntimeInner<B> {
             http_fallback: OnceLock::new(),
             command_guest: OnceLock::new(),
             flights: Mutex::new(HashMap::new()),
+            quarantine,
+            accounting: Arc::new(Accounting::new()),
+            shutdown: CancellationToken::new(),
         }
     }
 }
@@ -577,11 +693,69 @@ impl<B: Clone + Send + Sync + 'static> Runtime<B> {
     pub async fn instantiate(
         &self, instance_pre: &InstancePre<StoreCtx<B>>, store: &mut Store<StoreCtx<B>>,
     ) -> Result<Instance> {
-        let instance = instance_pre.instantiate_async(store).await?;
+        let instance = instance_pre.instantiate_async(store).await.map_err(Crash::classify)?;
         tracing::debug!("component instantiated");
         Ok(instance)
     }
 
+    /// Crash-loop detector for the runtime's guests.
+    #[must_use]
+    pub fn quarantine(&self) -> &Quarantine {
+        &self.inner.quarantine
+    }
+
+    /// Resolves once the runtime begins shutting down. Trigger servers that
+    /// can drain wait on it, finish their in-flight work within
+    /// `shutdown_grace`, and return.
+    pub async fn shutting_down(&self) {
+        self.inner.shutdown.cancelled().await;
+    }
+
+    /// Begin shutting down: every [`shutting_down`](Self::shutting_down)
+    /// waiter resolves. The generated `main` calls this on SIGTERM or Ctrl-C;
+    /// embedders driving servers themselves call it directly.
+    pub fn shutdown(&self) {
+        self.inner.shutdown.cancel();
+    }
+
+    /// Per-guest invocation accounting for the runtime's guests.
+    #[must_use]
+    pub fn accounting(&self) -> &Accounting {
+        &self.inner.accounting
+    }
+
+    /// Charge everything `store` runs to `id`: the store counts as one
+    /// invocation, and its guest time and import calls are tallied until it
+    /// drops. Call once per store, right after [`build_store`](Self::build_store).
+    ///
+    /// The store is also attributed to `id` (see [`StoreBase::guest`]), so
+    /// host bindings can tell which guest is calling.
+    pub fn meter(&self, id: &GuestId, store: &mut Store<StoreCtx<B>>) {
+        store.data_mut().base.guest = Some(id.clone());
+        let mut meter = self.inner.accounting.meter(id);
+        store.call_hook(move |_, hook| {
+            meter.transition(hook);
+            Ok(())
+        });
+    }
+
+    /// Run one event-triggered invocation of `id` under crash-loop
+    /// supervision: a quarantined guest is refused without instantiating it,
+    /// and the outcome counts towards the guest's consecutive failures.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Quarantined`](crate::Quarantined) while the guest is quarantined, otherwise the
+    /// invocation's own error.
+    pub async fn supervise<T>(
+        &self, id: &GuestId, invocation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        self.inner.quarantine.admit(id)?;
+        let outcome = invocation.await;
+        self.inner.quarantine.record(id, &outcome);
+        outcome
+    }
+
     /// Register a guest at run time: load `artifact`, pre-instantiate it
     /// against the shared host set, wire its host-mediated link serve side,
     /// then publish entry and endpoint as one atomic lifecycle transition —
@@ -636,7 +810,11 @@ impl<B: Clone + Send + Sync + 'static> Runtime<B> {
         let server = serve_guest(self, &guest)
             .await
             .with_context(|| format!("serving guest `{id}` link exports"))?;
+        let report = registry.write_capability_report(&guest);
         registry.publish(guest, server)?;
+        if let Err(error) = report {
+            tracing::warn!(guest = %id, "capability report not written: {error:#}");
+        }
 
         tracing::info!(guest = %id, "guest registered");
         Ok(())
@@ -67,6 +67,7 @@ where
     tracing::info!(guest = %guest_id, "running wasi:cli/run");
 
     let mut store = runtime.build_store(runtime.store());
+    runtime.meter(guest_id, &mut store);
     let instance = runtime.instantiate(guest.instance_pre(), &mut store).await?;
     let command = Command::new(&mut store, &instance)?;
 
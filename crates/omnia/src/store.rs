@@ -11,7 +11,7 @@ use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiCtxView, WasiVie
 use wasmtime_wasi_http::p3::{WasiHttpCtxView, WasiHttpView};
 use wrpc_wasmtime::{WrpcCtxView, WrpcView};
 
-use crate::{Dispatcher, LinkClient, MountRegistry, RuntimeOptions, WrpcState};
+use crate::{Dispatcher, GuestId, LinkClient, MountRegistry, RuntimeOptions, WrpcState};
 
 /// Exposes a store context's [`StoreLimits`] so the runtime can install a
 /// per-guest resource limiter on every [`Store`](wasmtime::Store) it creates.
@@ -155,6 +155,7 @@ impl StoreBaseBuilder<Set<&RuntimeOptions>, Set<Arc<dyn Dispatcher>>> {
             wrpc: WrpcState::new(),
             dispatcher,
             mounts,
+            guest: None,
         }
     }
 }
@@ -185,6 +186,9 @@ pub struct StoreBase {
     /// `descriptor` back to its mount by directory identity. Empty unless the
     /// deployment configures `[[mount]]`s.
     pub mounts: Arc<MountRegistry>,
+    /// The guest the store runs, once [`Runtime::meter`](crate::Runtime::meter)
+    /// attributes it; host bindings read it to apply per-guest policy.
+    pub guest: Option<GuestId>,
 }
 
 impl StoreBase {
@@ -265,13 +269,15 @@ impl<B: Send + 'static> HasLimits for StoreCtx<B> {
 /// impl of this trait directly.
 pub trait HasHttp: Send {
     /// Borrow the `wasi:http` context as the linker-facing view, threading in
-    /// the store's [`ResourceTable`].
-    fn http_view<'a>(&'a mut self, table: &'a mut ResourceTable) -> WasiHttpCtxView<'a>;
+    /// the store's [`ResourceTable`] and the guest it runs, if attributed.
+    fn http_view<'a>(
+        &'a mut self, table: &'a mut ResourceTable, guest: Option<&GuestId>,
+    ) -> WasiHttpCtxView<'a>;
 }
 
 impl<B: HasHttp + Send + 'static> WasiHttpView for StoreCtx<B> {
     fn http(&mut self) -> WasiHttpCtxView<'_> {
-        self.backends.http_view(&mut self.base.table)
+        self.backends.http_view(&mut self.base.table, self.base.guest.as_ref())
     }
 }
 
@@ -33,8 +33,10 @@ struct Bundle {
 }
 
 impl HasHttp for Bundle {
-    fn http_view<'a>(&'a mut self, table: &'a mut ResourceTable) -> WasiHttpCtxView<'a> {
-        self.http.as_view(table)
+    fn http_view<'a>(
+        &'a mut self, table: &'a mut ResourceTable, guest: Option<&GuestId>,
+    ) -> WasiHttpCtxView<'a> {
+        self.http.as_view(table, guest)
     }
 }
 
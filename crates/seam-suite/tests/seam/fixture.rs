@@ -7,11 +7,12 @@
 
 use std::net::TcpListener;
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
 
 use anyhow::{Context as _, Result};
 use futures::FutureExt as _;
 use omnia::wasmtime_wasi::ResourceTable;
-use omnia::{Backend, FutureResult, HasHttp, Runtime};
+use omnia::{Backend, FutureResult, GuestId, HasHttp, Runtime};
 use omnia_testkit::single_guest;
 use omnia_wasi_blobstore::{BlobstoreDefault, HasBlobstore, WasiBlobstore, WasiBlobstoreCtx};
 use omnia_wasi_config::{ConfigDefault, HasConfig, WasiConfig, WasiConfigCtx};
@@ -19,13 +20,15 @@ use omnia_wasi_docstore::{DocStoreDefault, HasDocStore, WasiDocStore, WasiDocSto
 use omnia_wasi_http::{HttpDefault, WasiHttp, WasiHttpCtxView};
 use omnia_wasi_identity::{HasIdentity, IdentityStub, WasiIdentity, WasiIdentityCtx};
 use omnia_wasi_keyvalue::{HasKeyValue, KeyValueDefault, WasiKeyValue, WasiKeyValueCtx};
-use omnia_wasi_messaging::{HasMessaging, MessagingDefault, WasiMessaging, WasiMessagingCtx};
+use omnia_wasi_messaging::{
+    HasMessaging, Message, MessagingDefault, WasiMessaging, WasiMessagingCtx,
+};
 use omnia_wasi_otel::{HasOtel, WasiOtel, WasiOtelCtx};
 use omnia_wasi_sql::{HasSql, SqlDefault, WasiSql, WasiSqlCtx};
 use omnia_wasi_vault::{HasVault, VaultDefault, WasiVault, WasiVaultCtx};
 use omnia_wasi_websocket::{
-    ConnectOptions as WsConnectOptions, HasWebSocket, WasiWebSocket, WasiWebSocketCtx,
-    WebSocketDefault,
+    ConnectOptions as WsConnectOptions, HasWebSocket, Listener as WsListener,
+    RateLimit as WsRateLimit, WasiWebSocket, WasiWebSocketCtx, WebSocketDefault,
 };
 use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
 use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
@@ -104,8 +107,10 @@ pub struct Bundle {
 }
 
 impl HasHttp for Bundle {
-    fn http_view<'a>(&'a mut self, table: &'a mut ResourceTable) -> WasiHttpCtxView<'a> {
-        self.http.as_view(table)
+    fn http_view<'a>(
+        &'a mut self, table: &'a mut ResourceTable, guest: Option<&GuestId>,
+    ) -> WasiHttpCtxView<'a> {
+        self.http.as_view(table, guest)
     }
 }
 
@@ -183,6 +188,12 @@ pub struct Conformance {
     pub otel: CapturingOtel,
     /// The port the default WebSocket backend's server listens on.
     pub websocket_port: u16,
+    /// The port of the same server's listener that has the guest authorize
+    /// each connection.
+    pub websocket_authorized_port: u16,
+    /// The port of the same server's listener that allows each peer one
+    /// message a second.
+    pub websocket_limited_port: u16,
 }
 
 /// The conformance fixture, built once per suite process.
@@ -193,6 +204,20 @@ pub async fn conformance() -> Result<&'static Conformance> {
 
 async fn build() -> Result<Conformance> {
     let websocket_port = free_port()?;
+    let websocket_authorized_port = free_port()?;
+    let websocket_limited_port = free_port()?;
+    let mut websocket_options = WsConnectOptions::single(format!("127.0.0.1:{websocket_port}"));
+    websocket_options.listeners.push(WsListener {
+        authorize: true,
+        ..WsListener::new("authorized", format!("127.0.0.1:{websocket_authorized_port}"))
+    });
+    websocket_options.listeners.push(WsListener {
+        rate_limit: Some(WsRateLimit {
+            per_second: 1,
+            burst: 1,
+        }),
+        ..WsListener::new("limited", format!("127.0.0.1:{websocket_limited_port}"))
+    });
     let bundle = Bundle {
         http: HttpDefault::connect().await.context("connecting http")?,
         otel: CapturingOtel::default(),
@@ -206,11 +231,9 @@ async fn build() -> Result<Conformance> {
         messaging: <MessagingDefault as Backend>::connect()
             .await
             .context("connecting messaging")?,
-        websocket: WebSocketDefault::connect_with(WsConnectOptions {
-            socket_addr: format!("127.0.0.1:{websocket_port}"),
-        })
-        .await
-        .context("connecting websocket")?,
+        websocket: WebSocketDefault::connect_with(websocket_options)
+            .await
+            .context("connecting websocket")?,
     };
 
     let keyvalue = bundle.keyvalue.clone();
@@ -245,6 +268,15 @@ async fn build() -> Result<Conformance> {
         }
     });
 
+    // Likewise the messaging trigger loop, which delivers messages sent on the
+    // shared broker to the guest's incoming handler.
+    let trigger = runtime.clone();
+    tokio::spawn(async move {
+        if let Err(e) = omnia::Server::run(&WasiMessaging, &trigger).await {
+            eprintln!("messaging trigger loop failed: {e}");
+        }
+    });
+
     Ok(Conformance {
         runtime,
         keyvalue,
@@ -255,9 +287,38 @@ async fn build() -> Result<Conformance> {
         messaging,
         otel,
         websocket_port,
+        websocket_authorized_port,
+        websocket_limited_port,
     })
 }
 
+impl Conformance {
+    /// Send `message`, whose payload is a unique key, to `topic` on the shared
+    /// broker until the guest's incoming handler records what it observed
+    /// under that key, and return the record.
+    ///
+    /// The messaging trigger loop subscribes in the background, so a message
+    /// sent before it has subscribed is never delivered; sending again until
+    /// the record appears covers that window.
+    pub async fn deliver(&self, topic: &str, message: Message) -> Result<serde_json::Value> {
+        let key = String::from_utf8(message.payload.clone()).context("payload is the key")?;
+        let client = self.messaging.connect().await.context("connect broker client")?;
+        let bucket = self.keyvalue.open_bucket("omnia_bucket".to_owned()).await?;
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while tokio::time::Instant::now() < deadline {
+            client.send(topic.to_owned(), message.clone()).await.context("send message")?;
+            for _ in 0..10 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                if let Some(record) = bucket.get(key.clone()).await.context("read record")? {
+                    return serde_json::from_slice(&record).context("parse record");
+                }
+            }
+        }
+        anyhow::bail!("the guest never handled the message sent to `{topic}`")
+    }
+}
+
 /// Reserve a free localhost port (for the WebSocket backend's server).
 fn free_port() -> Result<u16> {
     let listener = TcpListener::bind("127.0.0.1:0").context("reserving a port")?;
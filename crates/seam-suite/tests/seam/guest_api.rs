@@ -5,7 +5,7 @@
 use anyhow::{Context as _, Result};
 use bytes::Bytes;
 use omnia::wasmtime_wasi::ResourceTable;
-use omnia::{Backend as _, HasHttp, Runtime};
+use omnia::{Backend as _, GuestId, HasHttp, Runtime};
 use omnia_testkit::{http, single_guest};
 use omnia_wasi_http::{HttpDefault, WasiHttp, WasiHttpCtxView};
 
@@ -18,8 +18,10 @@ struct Bundle {
 }
 
 impl HasHttp for Bundle {
-    fn http_view<'a>(&'a mut self, table: &'a mut ResourceTable) -> WasiHttpCtxView<'a> {
-        self.http.as_view(table)
+    fn http_view<'a>(
+        &'a mut self, table: &'a mut ResourceTable, guest: Option<&GuestId>,
+    ) -> WasiHttpCtxView<'a> {
+        self.http.as_view(table, guest)
     }
 }
 
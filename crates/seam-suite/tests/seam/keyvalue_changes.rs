@@ -0,0 +1,30 @@
+//! `omnia:keyvalue/changes` seam: a feed the guest opens reports, in order,
+//! the writes and deletes under its prefix and nothing outside it.
+
+use anyhow::Result;
+use omnia_testkit::http;
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn feeds_see_changes_under_their_prefix() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let prefix = format!("{}:", unique("toggle"));
+
+        let response =
+            http::post(&fx.runtime, &format!("/keyvalue/changes?prefix={prefix}"), "").await?;
+        assert!(response.status().is_success(), "guest reads its feed: {:?}", response.body());
+
+        let body: serde_json::Value = serde_json::from_slice(response.body())?;
+        let key = format!("{prefix}dark");
+        assert_eq!(
+            body["changes"],
+            json!([{ "key": key, "value": "on" }, { "key": key, "value": null }]),
+            "the feed reports the set, then the delete"
+        );
+
+        Ok(())
+    })
+}
@@ -0,0 +1,59 @@
+//! Atomic keyvalue seam: concurrent guest increments of one counter all land,
+//! and `omnia:keyvalue/exchange` compare-and-swap and get-and-delete behave per
+//! the WIT contract across the boundary.
+
+use anyhow::{Context as _, Result};
+use futures::future;
+use omnia_testkit::http;
+use omnia_wasi_keyvalue::WasiKeyValueCtx as _;
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn concurrent_increments_all_land() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let key = unique("kv-counter");
+        let path = format!("/keyvalue/counter?key={key}&by=2");
+
+        let responses =
+            future::try_join_all((0..20).map(|_| http::post(&fx.runtime, &path, ""))).await?;
+        assert!(
+            responses.iter().all(|response| response.status().is_success()),
+            "every guest increment succeeds"
+        );
+
+        let bucket =
+            fx.keyvalue.open_bucket("omnia_bucket".to_owned()).await.context("open bucket")?;
+        let count = bucket.increment(key, 0).await.context("read counter")?;
+        assert_eq!(count, 40, "no increment was lost");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn swaps_and_takes_are_atomic() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let key = unique("kv-exchange");
+
+        let response =
+            http::post(&fx.runtime, &format!("/keyvalue/exchange?key={key}"), "").await?;
+        assert!(
+            response.status().is_success(),
+            "guest completes the exchange: {:?}",
+            response.body()
+        );
+
+        let body: serde_json::Value = serde_json::from_slice(response.body())?;
+        assert_eq!(body, json!({ "swapped": [true, false, true], "taken": "v2", "again": false }));
+
+        let bucket =
+            fx.keyvalue.open_bucket("omnia_bucket".to_owned()).await.context("open bucket")?;
+        assert!(!bucket.exists(key).await.context("check key")?, "the taken key is gone");
+
+        Ok(())
+    })
+}
@@ -0,0 +1,33 @@
+//! `omnia:keyvalue/expiry` seam: the guest stores a key with a TTL, and a probe
+//! on the shared backend finds it until the TTL runs out and not after.
+
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use omnia_testkit::http;
+use omnia_wasi_keyvalue::WasiKeyValueCtx as _;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn keys_expire_after_their_ttl() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let key = unique("kv-ttl");
+
+        let response =
+            http::post(&fx.runtime, &format!("/keyvalue/expiry?key={key}&ttl=1"), "session")
+                .await?;
+        assert!(response.status().is_success(), "guest stores the key with a ttl");
+
+        let bucket =
+            fx.keyvalue.open_bucket("omnia_bucket".to_owned()).await.context("open bucket")?;
+        let stored = bucket.get(key.clone()).await.context("read key")?;
+        assert_eq!(stored.as_deref(), Some(b"session".as_slice()), "the write reached the host");
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(bucket.get(key).await.context("read expired key")?, None, "the key expired");
+
+        Ok(())
+    })
+}
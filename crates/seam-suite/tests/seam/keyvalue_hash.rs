@@ -0,0 +1,35 @@
+//! `omnia:keyvalue/hash` seam: the guest sets, updates, reads, and deletes
+//! fields of a hash, and a probe on the shared backend finds exactly the field
+//! it left behind.
+
+use anyhow::{Context as _, Result};
+use omnia_testkit::http;
+use omnia_wasi_keyvalue::WasiKeyValueCtx as _;
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn fields_update_independently() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let key = unique("vehicle");
+
+        let response = http::post(&fx.runtime, &format!("/keyvalue/hash?key={key}"), "").await?;
+        assert!(
+            response.status().is_success(),
+            "guest completes the hash operations: {:?}",
+            response.body()
+        );
+
+        let body: serde_json::Value = serde_json::from_slice(response.body())?;
+        assert_eq!(body, json!({ "added": [2, 0], "lat": "3", "deleted": 1 }));
+
+        let bucket =
+            fx.keyvalue.open_bucket("omnia_bucket".to_owned()).await.context("open bucket")?;
+        let fields = bucket.get_all_fields(key).await.context("read hash")?;
+        assert_eq!(fields, [("lat".to_string(), b"3".to_vec())], "only `lat` is left on the host");
+
+        Ok(())
+    })
+}
@@ -31,11 +31,22 @@ mod guest_link;
 mod http;
 mod identity;
 mod keyvalue;
+mod keyvalue_changes;
+mod keyvalue_exchange;
+mod keyvalue_expiry;
+mod keyvalue_hash;
 mod mcp;
 mod messaging;
+mod messaging_batch;
+mod messaging_headers;
+mod messaging_settlement;
 mod model;
 mod otel;
 mod routing;
 mod sql;
+mod sql_cursors;
+mod sql_errors;
+mod sql_results;
+mod sql_transactional;
 mod vault;
 mod websocket;
@@ -0,0 +1,32 @@
+//! `omnia:messaging/batch` seam: the guest sends several messages in one call,
+//! and a replay of the topic on the shared broker finds them all, in order.
+
+use anyhow::{Context as _, Result};
+use futures::StreamExt as _;
+use omnia_testkit::http;
+use omnia_wasi_messaging::WasiMessagingCtx as _;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn batches_arrive_in_order() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let topic = unique("telemetry");
+
+        let response = http::post_json(
+            &fx.runtime,
+            &format!("/messaging/batch?topic={topic}"),
+            r#"["one","two","three"]"#,
+        )
+        .await?;
+        assert!(response.status().is_success(), "guest publishes the batch: {:?}", response.body());
+
+        let client = fx.messaging.connect().await.context("connect broker client")?;
+        let replayed = client.replay(topic).await.context("replay topic")?;
+        let payloads: Vec<_> = replayed.map(|message| message.payload).collect().await;
+        assert_eq!(payloads, [b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,34 @@
+//! Messaging delivery seam: a message sent on the shared broker reaches the
+//! guest's incoming handler through the messaging trigger loop, with every
+//! header it was sent with.
+
+use anyhow::Result;
+use omnia_wasi_messaging::{Message, Metadata};
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn headers_reach_the_handler() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let key = unique("msg-headers");
+
+        let mut metadata = Metadata::new();
+        metadata.insert("correlation-id".to_owned(), key.clone());
+        metadata.insert("x-source".to_owned(), "fleet".to_owned());
+        let mut message = Message::new(key.clone().into_bytes());
+        message.metadata = Some(metadata);
+
+        let record = fx.deliver("conformance.headers", message).await?;
+        let mut headers: Vec<(String, String)> = serde_json::from_value(record["headers"].clone())?;
+        headers.sort();
+        assert_eq!(
+            json!(headers),
+            json!([["correlation-id", key], ["x-source", "fleet"]]),
+            "the handler sees every header"
+        );
+
+        Ok(())
+    })
+}
@@ -0,0 +1,25 @@
+//! `omnia:messaging/settlement` seam: the guest's incoming handler extends the
+//! deadline of and acknowledges the message delivered to it, while a second
+//! acknowledgement, and one of a message it built itself, are refused.
+
+use anyhow::Result;
+use omnia_wasi_messaging::Message;
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn handlers_settle_their_delivery_once() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let key = unique("msg-settle");
+
+        let record = fx.deliver("conformance.settlement", Message::new(key.into_bytes())).await?;
+        assert_eq!(
+            record,
+            json!({ "extended": true, "acked": true, "acked_again": false, "foreign": false }),
+        );
+
+        Ok(())
+    })
+}
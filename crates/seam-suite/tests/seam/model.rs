@@ -169,6 +169,7 @@ async fn call_run(runtime: &Runtime<TestBundle>) -> Result<String> {
             wrpc: WrpcState::new(),
             dispatcher: Arc::clone(&template.base.dispatcher),
             mounts,
+            guest: None,
         },
         backends: template.backends.clone(),
     };
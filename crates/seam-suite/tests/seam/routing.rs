@@ -5,7 +5,9 @@ use std::sync::Arc;
 
 use anyhow::{Context as _, Result};
 use omnia::wasmtime_wasi::ResourceTable;
-use omnia::{Backend as _, DeploymentBuilder, HasHttp, Manifest, MountRegistry, Runtime, StoreCtx};
+use omnia::{
+    Backend as _, DeploymentBuilder, GuestId, HasHttp, Manifest, MountRegistry, Runtime, StoreCtx,
+};
 use omnia_testkit::{find_guest, http, temp_manifest};
 use omnia_wasi_http::{HttpDefault, WasiHttp, WasiHttpCtxView};
 use omnia_wasi_otel::{HasOtel, OtelDefault, WasiOtel, WasiOtelCtx};
@@ -20,8 +22,10 @@ struct Bundle {
 }
 
 impl HasHttp for Bundle {
-    fn http_view<'a>(&'a mut self, table: &'a mut ResourceTable) -> WasiHttpCtxView<'a> {
-        self.http.as_view(table)
+    fn http_view<'a>(
+        &'a mut self, table: &'a mut ResourceTable, guest: Option<&GuestId>,
+    ) -> WasiHttpCtxView<'a> {
+        self.http.as_view(table, guest)
     }
 }
 
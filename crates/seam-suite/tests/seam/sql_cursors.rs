@@ -0,0 +1,26 @@
+//! `omnia:sql/cursors` seam: the guest reads a result set through a cursor,
+//! and every batch crosses the WIT boundary in order and no larger than the
+//! batch size it asked for.
+
+use anyhow::Result;
+use omnia_testkit::http;
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn rows_arrive_in_batches() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let tag = unique("trip");
+
+        let response =
+            http::post(&fx.runtime, &format!("/sql/cursors?tag={tag}&rows=5&batch=2"), "").await?;
+        assert!(response.status().is_success(), "guest drains the cursor: {:?}", response.body());
+
+        let body: serde_json::Value = serde_json::from_slice(response.body())?;
+        assert_eq!(body["batches"], json!([[0, 1], [2, 3], [4]]), "rows arrive two at a time");
+
+        Ok(())
+    })
+}
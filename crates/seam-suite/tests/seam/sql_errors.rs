@@ -0,0 +1,30 @@
+//! `omnia:sql/errors` seam: a constraint violation the guest provokes carries
+//! its SQLSTATE across the WIT boundary, and a failure the database reports no
+//! code for carries none.
+
+use anyhow::Result;
+use omnia_testkit::http;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn constraint_violation_has_a_sqlstate() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let code = unique("registration");
+
+        let response =
+            http::post_json(&fx.runtime, "/sql/errors", format!(r#"{{"code":"{code}"}}"#)).await?;
+        assert!(
+            response.status().is_success(),
+            "guest provokes both failures: {:?}",
+            response.body()
+        );
+
+        let body: serde_json::Value = serde_json::from_slice(response.body())?;
+        assert_eq!(body["duplicate"], "23000", "the primary key violation is an integrity error");
+        assert!(body["missing"].is_null(), "a missing table has no SQLSTATE: {body}");
+
+        Ok(())
+    })
+}
@@ -0,0 +1,47 @@
+//! `omnia:sql/results` seam: the effects the guest's writes report across the
+//! WIT boundary — affected rows, the last insert id, and `RETURNING` rows —
+//! match what a probe on the shared `SQLite` backend finds.
+
+use anyhow::{Context as _, Result};
+use omnia_testkit::http;
+use omnia_wasi_sql::{DataType, WasiSqlCtx as _};
+use serde_json::json;
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn writes_report_their_effects() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let route = unique("route");
+
+        let response =
+            http::post_json(&fx.runtime, "/sql/results", format!(r#"{{"route":"{route}"}}"#))
+                .await?;
+        assert!(
+            response.status().is_success(),
+            "guest completes both writes: {:?}",
+            response.body()
+        );
+
+        let body: serde_json::Value = serde_json::from_slice(response.body())?;
+        assert_eq!(body["inserted"], json!({ "affected": 2, "returned": 0 }));
+        assert_eq!(body["updated"], json!({ "affected": 1, "returned": [format!("{route}-renamed")] }));
+
+        // The reported insert id names the renamed trip on the host.
+        let trip_id = body["trip_id"].as_i64().context("trip id is a number")?;
+        let connection = fx.sql.open("db".to_owned()).await.context("open probe connection")?;
+        let rows = connection
+            .query(format!("SELECT route FROM trip WHERE trip_id = {trip_id}"), Vec::new())
+            .await
+            .context("query trips")?;
+        assert_eq!(rows.len(), 1, "the last insert id names a stored trip");
+        assert!(
+            matches!(&rows[0].fields[0].value, DataType::Str(Some(stored)) if *stored == format!("{route}-renamed")),
+            "the update reached the host store: {:?}",
+            rows[0].fields
+        );
+
+        Ok(())
+    })
+}
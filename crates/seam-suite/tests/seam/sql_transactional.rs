@@ -0,0 +1,47 @@
+//! `omnia:sql/transactional` seam: the guest writes one entry in a transaction
+//! it rolls back and another in one it commits, and a probe on the shared
+//! `SQLite` backend proves only the committed entry reached the host store.
+
+use anyhow::{Context as _, Result};
+use omnia_testkit::http;
+use omnia_wasi_sql::{DataType, WasiSqlCtx as _};
+
+use crate::fixture::{self, unique};
+
+#[test]
+fn commit_keeps_and_rollback_discards() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let kept = unique("kept");
+        let discarded = unique("discarded");
+
+        let response = http::post_json(
+            &fx.runtime,
+            "/sql/transactions",
+            format!(r#"{{"kept":"{kept}","discarded":"{discarded}"}}"#),
+        )
+        .await?;
+        assert!(
+            response.status().is_success(),
+            "guest settles both transactions: {:?}",
+            response.body()
+        );
+
+        let connection = fx.sql.open("db".to_owned()).await.context("open probe connection")?;
+        let rows = connection
+            .query(
+                format!("SELECT entry FROM ledger WHERE entry IN ('{kept}', '{discarded}')"),
+                Vec::new(),
+            )
+            .await
+            .context("query ledger")?;
+        assert_eq!(rows.len(), 1, "only one entry reached the host store");
+        assert!(
+            matches!(&rows[0].fields[0].value, DataType::Str(Some(entry)) if *entry == kept),
+            "the committed entry reached the host store: {:?}",
+            rows[0].fields
+        );
+
+        Ok(())
+    })
+}
@@ -1,35 +1,31 @@
 //! `wasi:websocket` seam, both directions: the guest's `connect` + `send`
 //! crosses into the host and reaches a connected external peer, and a peer
 //! message travels back through the host into the guest's event handler.
+//! The guest's group, peer, and close calls, and its subscription and
+//! connection authorizers, are driven the same way.
 
 use std::time::Duration;
 
-use anyhow::{Context as _, Result};
+use anyhow::{Context as _, Result, bail};
 use futures::{SinkExt as _, StreamExt as _};
 use omnia_testkit::http;
 use omnia_wasi_keyvalue::WasiKeyValueCtx as _;
+use serde_json::json;
+use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
-use crate::fixture;
+use crate::fixture::{self, Conformance};
+
+type Peer = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 #[test]
 fn send_reaches_connected_peer() -> Result<()> {
     fixture::RT.block_on(async {
         let fx = fixture::conformance().await?;
 
-        // The backend's server starts on a spawned task; retry until it accepts.
-        let url = format!("ws://127.0.0.1:{}", fx.websocket_port);
-        let mut peer = None;
-        for _ in 0..50 {
-            match tokio_tungstenite::connect_async(&url).await {
-                Ok((stream, _)) => {
-                    peer = Some(stream);
-                    break;
-                }
-                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
-            }
-        }
-        let mut peer = peer.context("websocket server did not accept a connection")?;
+        let mut peer = connect(&format!("ws://127.0.0.1:{}", fx.websocket_port)).await?;
 
         // The guest's send only reaches peers registered before it fires; the
         // handshake and registration race, so retry the request until delivery.
@@ -86,3 +82,211 @@ fn send_reaches_connected_peer() -> Result<()> {
         Ok(())
     })
 }
+
+#[test]
+fn subscribe_is_authorized_by_guest() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let mut peer = connect(&format!("ws://127.0.0.1:{}", fx.websocket_port)).await?;
+
+        let public = fixture::unique("news");
+        let private = fixture::unique("private.ops");
+        subscribe(&mut peer, &[&public, &private]).await?;
+        let granted = subscribed(&mut peer).await?;
+        assert_eq!(granted, vec![public], "the guest's authorizer refused the private group");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_is_authorized_by_guest() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let url = format!("ws://127.0.0.1:{}", fx.websocket_authorized_port);
+
+        let mut refused = connect(&url).await?;
+        let message = expect(&mut refused, |message| matches!(message, Message::Close(_))).await?;
+        let Message::Close(Some(frame)) = message else {
+            bail!("refused peer was closed without a close frame");
+        };
+        assert_eq!(frame.code, CloseCode::Policy, "a peer without the token is refused");
+
+        let mut admitted = connect(&format!("{url}/?token=conformance-token")).await?;
+        let group = fixture::unique("admitted");
+        subscribe(&mut admitted, &[&group]).await?;
+        assert_eq!(
+            subscribed(&mut admitted).await?,
+            vec![group],
+            "a peer with the token is admitted and served"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn guest_joins_and_removes_peer_from_group() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let mut peer = connect(&format!("ws://127.0.0.1:{}", fx.websocket_port)).await?;
+        let addr = local_addr(&peer)?;
+        let group = fixture::unique("private.crew");
+
+        // Joining directly skips the authorizer, which refuses `private.` groups.
+        guest_call(fx, &format!("/websocket/peers/{addr}/groups/{group}"), "").await?;
+        guest_call(fx, &format!("/websocket/groups/{group}"), "to the crew").await?;
+        let message = expect(&mut peer, |message| *message == Message::text("to the crew")).await?;
+        assert_eq!(message, Message::text("to the crew"), "the joined peer got the group event");
+
+        let left =
+            http::delete(&fx.runtime, &format!("/websocket/peers/{addr}/groups/{group}")).await?;
+        assert!(left.status().is_success(), "guest removes the peer from the group");
+        guest_call(fx, &format!("/websocket/groups/{group}"), "after leaving").await?;
+        assert!(
+            quiet(&mut peer, |message| *message == Message::text("after leaving")).await,
+            "a peer that left the group no longer gets its events"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn guest_replies_to_one_peer() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let url = format!("ws://127.0.0.1:{}", fx.websocket_port);
+        let mut addressed = connect(&url).await?;
+        let mut bystander = connect(&url).await?;
+        let addr = local_addr(&addressed)?;
+        let reply = fixture::unique("reply");
+
+        guest_call(fx, &format!("/websocket/peers/{addr}"), reply.clone()).await?;
+        let message =
+            expect(&mut addressed, |message| *message == Message::text(reply.as_str())).await?;
+        assert_eq!(message, Message::text(reply.as_str()), "the addressed peer got the reply");
+        assert!(
+            quiet(&mut bystander, |message| *message == Message::text(reply.as_str())).await,
+            "no other peer got the reply"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn guest_closes_peer_with_code() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let mut peer = connect(&format!("ws://127.0.0.1:{}", fx.websocket_port)).await?;
+        let addr = local_addr(&peer)?;
+
+        guest_call(fx, &format!("/websocket/peers/{addr}/close"), "conversation over").await?;
+        let message = expect(&mut peer, |message| matches!(message, Message::Close(_))).await?;
+        let Message::Close(Some(frame)) = message else {
+            bail!("peer was closed without a close frame");
+        };
+        assert_eq!(u16::from(frame.code), 4000, "the guest's close code reached the peer");
+        assert_eq!(frame.reason.as_str(), "conversation over", "and so did its reason");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn rate_limited_peer_is_sent_a_close_frame() -> Result<()> {
+    fixture::RT.block_on(async {
+        let fx = fixture::conformance().await?;
+        let mut peer = connect(&format!("ws://127.0.0.1:{}", fx.websocket_limited_port)).await?;
+
+        // Control frames count against the limit without reaching the guest.
+        for _ in 0..2 {
+            subscribe(&mut peer, &[]).await?;
+        }
+        let message = expect(&mut peer, |message| matches!(message, Message::Close(_))).await?;
+        let Message::Close(Some(frame)) = message else {
+            bail!("rate-limited peer was closed without a close frame");
+        };
+        assert_eq!(frame.code, CloseCode::Policy, "the peer learns it broke the policy");
+        assert_eq!(frame.reason.as_str(), "rate limit exceeded");
+
+        Ok(())
+    })
+}
+
+/// Connect to `url`, retrying while the backend's server, which starts on a
+/// spawned task, does not yet accept.
+async fn connect(url: &str) -> Result<Peer> {
+    for _ in 0..50 {
+        match tokio_tungstenite::connect_async(url).await {
+            Ok((stream, _)) => return Ok(stream),
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    bail!("websocket server did not accept a connection on {url}")
+}
+
+/// The peer's own address, which the host knows it by.
+fn local_addr(peer: &Peer) -> Result<String> {
+    let MaybeTlsStream::Plain(stream) = peer.get_ref() else {
+        bail!("expected a plain TCP stream");
+    };
+    Ok(stream.local_addr()?.to_string())
+}
+
+/// POST `body` to the guest route at `path` until it succeeds; calls that
+/// name the peer fail until the host has registered its connection.
+async fn guest_call(fx: &Conformance, path: &str, body: impl Into<bytes::Bytes>) -> Result<()> {
+    let body = body.into();
+    for _ in 0..20 {
+        let response = http::post(&fx.runtime, path, body.clone()).await?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    bail!("guest route {path} did not succeed")
+}
+
+/// The next frame that `wanted` accepts, skipping others (such as broadcasts
+/// from concurrent tests), or an error if none arrives in time. The wait
+/// covers a guest instantiation for the authorizers.
+async fn expect(peer: &mut Peer, wanted: impl Fn(&Message) -> bool) -> Result<Message> {
+    within(peer, Duration::from_secs(5), wanted).await
+}
+
+/// Whether no frame `wanted` accepts arrives for half a second.
+async fn quiet(peer: &mut Peer, wanted: impl Fn(&Message) -> bool) -> bool {
+    within(peer, Duration::from_millis(500), wanted).await.is_err()
+}
+
+async fn within(
+    peer: &mut Peer, wait: Duration, wanted: impl Fn(&Message) -> bool,
+) -> Result<Message> {
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        let message = tokio::time::timeout_at(deadline, peer.next())
+            .await
+            .context("no expected frame")?
+            .context("connection closed before the expected frame")??;
+        if wanted(&message) {
+            return Ok(message);
+        }
+    }
+}
+
+async fn subscribe(peer: &mut Peer, groups: &[&str]) -> Result<()> {
+    let request = json!({ "type": "subscribe", "groups": groups });
+    peer.send(Message::text(request.to_string())).await.context("sending subscribe")
+}
+
+/// The groups the host's `subscribed` reply says the peer joined.
+async fn subscribed(peer: &mut Peer) -> Result<Vec<String>> {
+    let is_reply = |message: &Message| {
+        message.to_text().is_ok_and(|text| text.contains(r#""type":"subscribed""#))
+    };
+    let reply = expect(peer, is_reply).await?;
+    let reply = serde_json::from_str::<serde_json::Value>(reply.to_text()?)?;
+    Ok(serde_json::from_value(reply["groups"].clone())?)
+}
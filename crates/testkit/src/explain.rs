@@ -0,0 +1,148 @@
+//! Query-plan regression checks for critical ORM queries.
+//!
+//! A [`PlanGuard`] holds the queries a component cannot afford to run as a
+//! full table scan, and the tables large enough for a scan to matter. Its
+//! [`check`](PlanGuard::check) runs `EXPLAIN` for every query through a
+//! [`TableStore`] and fails, listing each offending plan, when any of them
+//! scans a large table sequentially. Point it at [`Tables`](crate::sql::Tables)
+//! for `SQLite` plans, or at a store over a live development database for
+//! `PostgreSQL` plans; `PostgreSQL` seq-scans small tables whatever their
+//! indexes, so its plans are only meaningful against realistic row counts.
+//!
+//! ```rust,ignore
+//! PlanGuard::new(Planner::Sqlite)
+//!     .large_table("stop_times")
+//!     .query("departures", departures_query(stop_id)?)
+//!     .check(&tables, "db")
+//!     .await?;
+//! ```
+
+use std::collections::BTreeSet;
+
+use anyhow::{Result, bail};
+use omnia_guest::TableStore;
+use omnia_guest::orm::Query;
+use omnia_wasi_sql::{DataType, Row};
+
+/// The database whose `EXPLAIN` output a guard reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Planner {
+    /// `EXPLAIN QUERY PLAN`, where a full scan reads `SCAN <table>`.
+    Sqlite,
+    /// `EXPLAIN`, where a full scan reads `Seq Scan on <table>`.
+    Postgres,
+}
+
+/// Critical queries that must not scan large tables.
+pub struct PlanGuard {
+    planner: Planner,
+    large_tables: BTreeSet<String>,
+    queries: Vec<(String, Query)>,
+}
+
+impl PlanGuard {
+    /// Create a guard reading plans from `planner`.
+    #[must_use]
+    pub const fn new(planner: Planner) -> Self {
+        Self {
+            planner,
+            large_tables: BTreeSet::new(),
+            queries: Vec::new(),
+        }
+    }
+
+    /// Fail when any query scans `table` sequentially.
+    #[must_use]
+    pub fn large_table(mut self, table: impl Into<String>) -> Self {
+        self.large_tables.insert(table.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Register a critical query under `name`, used in failure messages.
+    #[must_use]
+    pub fn query(mut self, name: impl Into<String>, query: Query) -> Self {
+        self.queries.push((name.into(), query));
+        self
+    }
+
+    /// Explain every registered query on the connection `conn_name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a query cannot be explained, or one error naming
+    /// every query whose plan scans a large table, with that plan.
+    pub async fn check<S: TableStore>(&self, store: &S, conn_name: &str) -> Result<()> {
+        let mut failures = Vec::new();
+        for (name, query) in &self.queries {
+            let plan = self.explain(store, conn_name, query).await?;
+            let scanned: Vec<_> = plan
+                .iter()
+                .filter_map(|line| self.scanned_table(line))
+                .filter(|table| self.large_tables.contains(table))
+                .collect();
+            if !scanned.is_empty() {
+                failures.push(format!(
+                    "`{name}` scans {}:\n  {}\n  {}",
+                    scanned.join(", "),
+                    query.sql,
+                    plan.join("\n  ")
+                ));
+            }
+        }
+        if !failures.is_empty() {
+            bail!("critical queries scan large tables:\n{}", failures.join("\n"));
+        }
+        Ok(())
+    }
+
+    /// The plan lines for `query`.
+    async fn explain<S: TableStore>(
+        &self, store: &S, conn_name: &str, query: &Query,
+    ) -> Result<Vec<String>> {
+        let prefix = match self.planner {
+            Planner::Sqlite => "EXPLAIN QUERY PLAN",
+            Planner::Postgres => "EXPLAIN",
+        };
+        let rows = store
+            .query(conn_name.to_string(), format!("{prefix} {}", query.sql), query.params.clone())
+            .await?;
+        Ok(rows.iter().filter_map(|row| self.plan_line(row)).collect())
+    }
+
+    /// The plan text of one `EXPLAIN` row: `detail` for `SQLite`, `QUERY PLAN`
+    /// for `PostgreSQL`.
+    fn plan_line(&self, row: &Row) -> Option<String> {
+        let column = match self.planner {
+            Planner::Sqlite => "detail",
+            Planner::Postgres => "QUERY PLAN",
+        };
+        row.fields.iter().find(|field| field.name.eq_ignore_ascii_case(column)).and_then(|field| {
+            match &field.value {
+                DataType::Str(Some(text)) => Some(text.clone()),
+                _ => None,
+            }
+        })
+    }
+
+    /// The table a plan line scans sequentially, lower-cased and without
+    /// quotes or schema.
+    fn scanned_table(&self, line: &str) -> Option<String> {
+        let table = match self.planner {
+            Planner::Sqlite => {
+                let rest = line.trim().strip_prefix("SCAN ")?;
+                let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+                // `SCAN t USING [COVERING] INDEX i` walks an index, not the table.
+                if rest.contains(" USING ") {
+                    return None;
+                }
+                rest.split_whitespace().next()?
+            }
+            Planner::Postgres => {
+                let (_, rest) = line.split_once("Seq Scan on ")?;
+                rest.split_whitespace().next()?
+            }
+        };
+        let table = table.rsplit('.').next().unwrap_or(table);
+        Some(table.trim_matches(['"', '`']).to_ascii_lowercase())
+    }
+}
@@ -9,13 +9,22 @@
 //!   removes it on drop.
 //! - [`single_guest`] assembles a single-guest [`omnia::Runtime`] over a
 //!   backend bundle, absorbing the deployment/link/registry boilerplate.
+//! - [`messaging`] — [`messaging::Broker`] publishes to and reads back from
+//!   a private in-memory broker.
+//! - [`sql`] — [`sql::Tables`] runs ORM queries natively against a private
+//!   in-memory `SQLite` database.
+//! - [`explain`] — [`explain::PlanGuard`] fails when a critical query's plan
+//!   switches to a sequential scan of a large table.
 //! - [`http`] drives a guest's `wasi:http/handler` export in-process, without
 //!   binding a TCP socket.
 
 #![cfg(not(target_arch = "wasm32"))]
 
+pub mod explain;
 pub mod http;
+pub mod messaging;
 pub mod model;
+pub mod sql;
 
 mod guest;
 mod manifest;
@@ -0,0 +1,91 @@
+//! An in-memory broker for native messaging tests.
+//!
+//! [`Broker`] implements the guest-side [`Publish`] over a
+//! [`MessagingDefault`], so handlers that publish run natively without a
+//! component or any infrastructure, and tests read back what was published.
+//! The same `MessagingDefault` can back a runtime for end-to-end tests of a
+//! built guest.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::StreamExt;
+use omnia::Backend;
+use omnia_guest::{Message, Publish};
+use omnia_wasi_messaging::{Client, MessagingDefault, Metadata, WasiMessagingCtx};
+
+/// A [`Publish`] over a private in-memory broker.
+#[derive(Clone, Debug)]
+pub struct Broker {
+    messaging: MessagingDefault,
+    client: Arc<dyn Client>,
+}
+
+impl Broker {
+    /// Start an empty broker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the broker cannot be connected.
+    pub async fn new() -> Result<Self> {
+        let messaging = <MessagingDefault as Backend>::connect().await?;
+        let client = WasiMessagingCtx::connect(&messaging).await?;
+        Ok(Self { messaging, client })
+    }
+
+    /// The backing broker, to hand to a runtime.
+    #[must_use]
+    pub const fn backend(&self) -> &MessagingDefault {
+        &self.messaging
+    }
+
+    /// The messages published to `topic` so far, oldest first, up to the
+    /// broker's retention.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the broker cannot replay the topic.
+    pub async fn published(&self, topic: &str) -> Result<Vec<Message>> {
+        let replay = self.client.replay(topic.to_string()).await?;
+        let messages = replay
+            .map(|message| Message {
+                payload: message.payload,
+                headers: message.metadata.map(|metadata| metadata.inner).unwrap_or_default(),
+            })
+            .collect()
+            .await;
+        Ok(messages)
+    }
+}
+
+impl Publish for Broker {
+    async fn send(&self, topic: &str, message: &Message) -> Result<()> {
+        let mut sent = omnia_wasi_messaging::Message::new(message.payload.clone());
+        if !message.headers.is_empty() {
+            sent.metadata = Some(Metadata {
+                inner: message.headers.clone(),
+            });
+        }
+        self.client.send(topic.to_string(), sent).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn published_messages_are_read_back_by_topic() {
+        let broker = Broker::new().await.unwrap();
+        let mut message = Message::new(b"{\"id\":1}");
+        message.headers.insert("tenant".to_string(), "acme".to_string());
+
+        broker.send("orders.created", &message).await.unwrap();
+        broker.send("orders.cancelled", &Message::new(b"{}")).await.unwrap();
+
+        let published = broker.published("orders.created").await.unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].payload, b"{\"id\":1}");
+        assert_eq!(published[0].headers["tenant"], "acme");
+    }
+}
@@ -0,0 +1,66 @@
+//! An in-memory SQL backend for native ORM tests.
+//!
+//! [`Tables`] implements the guest-side [`TableStore`] over an isolated
+//! in-memory [`SqlDefault`], so ORM queries built with `omnia_guest::orm` run
+//! against real `SQLite` without a component or any infrastructure. The same
+//! `SqlDefault` can back a runtime for end-to-end tests of a built guest.
+
+use anyhow::Result;
+use omnia_guest::TableStore;
+use omnia_wasi_sql::{DataType, ExecResult, Row, SqlDefault, WasiSqlCtx};
+
+/// A [`TableStore`] over a private in-memory database.
+///
+/// Every connection name resolves to the same database.
+#[derive(Clone, Debug)]
+pub struct Tables {
+    sql: SqlDefault,
+}
+
+impl Tables {
+    /// Open an empty database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `SQLite` cannot open the database.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            sql: SqlDefault::in_memory()?,
+        })
+    }
+
+    /// Open a database and run `schema` against it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or `schema` fails.
+    pub fn with_schema(schema: &str) -> Result<Self> {
+        let tables = Self::new()?;
+        tables.sql.execute_batch(schema)?;
+        Ok(tables)
+    }
+
+    /// The backing database, to seed fixtures or hand to a runtime.
+    #[must_use]
+    pub const fn backend(&self) -> &SqlDefault {
+        &self.sql
+    }
+}
+
+impl TableStore for Tables {
+    async fn query(
+        &self, conn_name: String, query: String, params: Vec<DataType>,
+    ) -> Result<Vec<Row>> {
+        self.sql.open(conn_name).await?.query(query, params).await
+    }
+
+    async fn exec(&self, conn_name: String, query: String, params: Vec<DataType>) -> Result<u32> {
+        self.sql.open(conn_name).await?.exec(query, params).await
+    }
+
+    async fn exec_with_result(
+        &self, conn_name: String, query: String, params: Vec<DataType>,
+    ) -> Result<ExecResult> {
+        self.sql.open(conn_name).await?.exec_with_result(query, params).await
+    }
+}
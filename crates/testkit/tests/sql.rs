@@ -0,0 +1,102 @@
+//! ORM round trips against the in-memory `SQLite` test backend.
+
+use omnia_guest::TableStore;
+use omnia_guest::orm::{
+    DeleteBuilder, Entity, Filter, InsertBuilder, SelectBuilder, UpdateBuilder,
+};
+use omnia_testkit::explain::{PlanGuard, Planner};
+use omnia_testkit::sql::Tables;
+
+omnia_guest::entity! {
+    table = "stops",
+    #[derive(Clone, Debug, PartialEq)]
+    struct Stop {
+        id: i64,
+        name: String,
+    }
+}
+
+const SCHEMA: &str = "CREATE TABLE stops (id INTEGER PRIMARY KEY, name TEXT NOT NULL);";
+
+#[tokio::test]
+async fn insert_then_select() {
+    let tables = Tables::with_schema(SCHEMA).unwrap();
+    let britomart = Stop {
+        id: 1,
+        name: "Britomart".to_string(),
+    };
+    let query = InsertBuilder::<Stop>::from_entity(&britomart).build().unwrap();
+    assert_eq!(tables.apply("db".to_string(), query).await.unwrap(), 1);
+
+    let query = SelectBuilder::<Stop>::new().r#where(Filter::eq("id", 1)).build().unwrap();
+    let rows = tables.fetch("db".to_string(), query).await.unwrap();
+    assert_eq!(Stop::from_rows(&rows).unwrap(), [britomart]);
+}
+
+#[tokio::test]
+async fn databases_are_private() {
+    let seeded = Tables::with_schema(SCHEMA).unwrap();
+    seeded.backend().execute_batch("INSERT INTO stops VALUES (1, 'Newmarket');").unwrap();
+    let empty = Tables::with_schema(SCHEMA).unwrap();
+
+    let query = DeleteBuilder::<Stop>::new().r#where(Filter::eq("id", 1)).build().unwrap();
+    assert_eq!(empty.apply("db".to_string(), query).await.unwrap(), 0);
+
+    let query = SelectBuilder::<Stop>::new().build().unwrap();
+    assert_eq!(seeded.fetch("db".to_string(), query).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn writes_report_their_effects() {
+    let tables = Tables::with_schema(SCHEMA).unwrap();
+    tables.backend().execute_batch("INSERT INTO stops (name) VALUES ('Parnell');").unwrap();
+
+    let query = InsertBuilder::<Stop>::new().set("name", "Grafton").build().unwrap();
+    let inserted = tables.apply_with_result("db".to_string(), query).await.unwrap();
+    assert_eq!((inserted.affected_rows, inserted.last_insert_id), (1, Some(2)));
+
+    let query = UpdateBuilder::<Stop>::new()
+        .set("name", "Grafton Gully")
+        .r#where(Filter::eq("id", 2))
+        .returning("name")
+        .build()
+        .unwrap();
+    let updated = tables.apply_with_result("db".to_string(), query).await.unwrap();
+    assert_eq!(updated.affected_rows, 1);
+    assert_eq!(updated.last_insert_id, None);
+    assert_eq!(updated.rows.len(), 1);
+}
+
+#[tokio::test]
+async fn plan_guard_rejects_table_scans() {
+    let tables = Tables::with_schema(SCHEMA).unwrap();
+    let by_id = || SelectBuilder::<Stop>::new().r#where(Filter::eq("id", 1)).build().unwrap();
+    let by_name =
+        || SelectBuilder::<Stop>::new().r#where(Filter::eq("name", "Britomart")).build().unwrap();
+
+    PlanGuard::new(Planner::Sqlite)
+        .large_table("stops")
+        .query("stop by id", by_id())
+        .check(&tables, "db")
+        .await
+        .unwrap();
+
+    let error = PlanGuard::new(Planner::Sqlite)
+        .large_table("stops")
+        .query("stop by id", by_id())
+        .query("stop by name", by_name())
+        .check(&tables, "db")
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("`stop by name` scans stops"), "{error}");
+    assert!(!error.contains("`stop by id`"), "{error}");
+
+    tables.backend().execute_batch("CREATE INDEX stops_name ON stops (name);").unwrap();
+    PlanGuard::new(Planner::Sqlite)
+        .large_table("stops")
+        .query("stop by name", by_name())
+        .check(&tables, "db")
+        .await
+        .unwrap();
+}
@@ -4,9 +4,13 @@
 
 mod cache;
 mod incoming;
+mod multipart;
 mod outgoing;
+mod sse;
 
 pub use axum;
 
 pub use self::incoming::*;
+pub use self::multipart::{FORM_PART_LIMIT, FilePart, Form, FormOptions};
 pub use self::outgoing::*;
+pub use self::sse::{Event, Sse};
@@ -0,0 +1,160 @@
+//! Multipart form extraction for guest handlers.
+
+use std::collections::HashMap;
+
+use axum::extract::multipart::Field;
+use axum::extract::{FromRequest, Multipart, Request};
+use axum::response::{IntoResponse, Response};
+use bytes::{Bytes, BytesMut};
+use http::StatusCode;
+
+/// The largest part [`Form`] reads, by default.
+pub const FORM_PART_LIMIT: usize = 1024 * 1024;
+
+/// A `multipart/form-data` body read in full: its text fields by name, and its
+/// file parts in order.
+///
+/// Use it as the last extractor of a handler, in place of [`Multipart`] when
+/// the whole form fits in memory:
+///
+/// ```rust,ignore
+/// async fn upload(form: Form) -> HttpResult<String> {
+///     let title = form.fields.get("title").cloned().unwrap_or_default();
+///     Ok(format!("{title}: {} files", form.files.len()))
+/// }
+/// ```
+///
+/// A part larger than [`FormOptions::max_part`] is answered with
+/// `413 Payload Too Large`.
+#[derive(Debug, Default)]
+pub struct Form {
+    /// Text fields, by name. A repeated name keeps its last value.
+    pub fields: HashMap<String, String>,
+    /// Parts sent with a file name.
+    pub files: Vec<FilePart>,
+}
+
+/// A file uploaded in a multipart form.
+#[derive(Debug, Clone)]
+pub struct FilePart {
+    /// The form field the file was sent as.
+    pub name: String,
+    /// The file name the client sent.
+    pub file_name: String,
+    /// The part's `Content-Type`, if given.
+    pub content_type: Option<String>,
+    /// The file's contents.
+    pub data: Bytes,
+}
+
+/// Request extension used to change how [`Form`] reads a body.
+#[derive(Clone, Debug)]
+pub struct FormOptions {
+    /// The largest part, in bytes, that is read into memory.
+    pub max_part: usize,
+}
+
+impl Default for FormOptions {
+    fn default() -> Self {
+        Self {
+            max_part: FORM_PART_LIMIT,
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequest<S> for Form {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let options = req
+            .extensions()
+            .get::<FormOptions>()
+            .map_or_else(FormOptions::default, Clone::clone);
+        let mut multipart =
+            Multipart::from_request(req, state).await.map_err(IntoResponse::into_response)?;
+
+        let mut form = Self::default();
+        while let Some(field) = multipart.next_field().await.map_err(IntoResponse::into_response)? {
+            let name = field.name().unwrap_or_default().to_string();
+            let file_name = field.file_name().map(ToString::to_string);
+            let content_type = field.content_type().map(ToString::to_string);
+            let data = read(field, options.max_part).await?;
+            if let Some(file_name) = file_name {
+                form.files.push(FilePart {
+                    name,
+                    file_name,
+                    content_type,
+                    data,
+                });
+            } else {
+                let value = String::from_utf8(data.into()).map_err(|_e| {
+                    (StatusCode::BAD_REQUEST, "form field is not valid UTF-8").into_response()
+                })?;
+                form.fields.insert(name, value);
+            }
+        }
+        Ok(form)
+    }
+}
+
+// Read a part, refusing it once it passes `max` bytes.
+async fn read(mut field: Field<'_>, max: usize) -> Result<Bytes, Response> {
+    let mut data = BytesMut::new();
+    while let Some(chunk) = field.chunk().await.map_err(IntoResponse::into_response)? {
+        if data.len() + chunk.len() > max {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, "form part is too large").into_response());
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use futures::executor::block_on;
+    use http::header::CONTENT_TYPE;
+
+    use super::*;
+
+    const BODY: &str = "--XYZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+        Route 7\r\n\
+        --XYZ\r\n\
+        Content-Disposition: form-data; name=\"timetable\"; filename=\"route-7.csv\"\r\n\
+        Content-Type: text/csv\r\n\r\n\
+        stop,time\r\n\
+        --XYZ--\r\n";
+
+    fn request(options: Option<FormOptions>) -> Request {
+        let mut request = Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=XYZ")
+            .body(Body::from(BODY))
+            .unwrap();
+        if let Some(options) = options {
+            request.extensions_mut().insert(options);
+        }
+        request
+    }
+
+    #[test]
+    fn parses_fields_and_files() {
+        let form = block_on(Form::from_request(request(None), &())).unwrap();
+
+        assert_eq!(form.fields["title"], "Route 7");
+        assert_eq!(form.files.len(), 1);
+        let file = &form.files[0];
+        assert_eq!(file.name, "timetable");
+        assert_eq!(file.file_name, "route-7.csv");
+        assert_eq!(file.content_type.as_deref(), Some("text/csv"));
+        assert_eq!(file.data, "stop,time");
+    }
+
+    #[test]
+    fn rejects_parts_over_the_limit() {
+        let options = FormOptions { max_part: 8 };
+        let rejection = block_on(Form::from_request(request(Some(options)), &())).unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -3,6 +3,7 @@ use std::error::Error;
 
 use anyhow::{Context, Result};
 use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
 use http::HeaderValue;
 use http::header::ETAG;
 use http_body::Body;
@@ -12,6 +13,9 @@ use wasip3::wit_future;
 
 pub use crate::guest::cache::{Cache, CacheOptions};
 
+/// Largest chunk read from a streamed response body.
+const STREAM_CHUNK: usize = 16 * 1024;
+
 /// Send an HTTP request using the WASI HTTP proxy handler.
 ///
 /// # Errors
@@ -63,3 +67,42 @@ where
 
     Ok(response)
 }
+
+/// Send an HTTP request and return the response with its body still streaming.
+///
+/// Use this to relay long-lived (SSE or chunked) responses as they arrive.
+/// Chunks are yielded as the host delivers them, at most 16,384 bytes each.
+/// Streamed responses bypass the response cache.
+///
+/// # Errors
+///
+/// Returns an error if the request could not be sent.
+pub async fn stream<T>(
+    request: http::Request<T>,
+) -> Result<http::Response<BoxStream<'static, Bytes>>>
+where
+    T: Body + Any,
+    T::Data: Into<Vec<u8>>,
+    T::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+{
+    tracing::debug!("streaming request through proxy: {:?}", request.headers());
+    let wasi_req = http_into_wasi_request(request).context("Issue converting request")?;
+    let wasi_resp = client::send(wasi_req).await.context("Issue calling proxy")?;
+    let http_resp = http_from_wasi_response(wasi_resp).context("Issue converting response")?;
+
+    let (parts, mut body) = http_resp.into_parts();
+    let reader = body.take_unstarted().map(|response| {
+        let (_, body_rx) = wit_future::new(|| Ok(()));
+        let (stream, _trailers) = response.consume_body(body_rx);
+        stream
+    });
+
+    // An empty read means the upstream closed the body.
+    let chunks = stream::unfold(reader, |reader| async move {
+        let mut reader = reader?;
+        let (_, chunk) = reader.read(Vec::with_capacity(STREAM_CHUNK)).await;
+        (!chunk.is_empty()).then(|| (Bytes::from(chunk), Some(reader)))
+    });
+
+    Ok(http::Response::from_parts(parts, chunks.boxed()))
+}
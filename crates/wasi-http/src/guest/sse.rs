@@ -0,0 +1,211 @@
+//! Server-sent events for guest handlers.
+
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::pin::pin;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+use futures::future::{self, Either};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use serde::Serialize;
+
+/// How often an idle stream sends a keep-alive comment, by default.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A single server-sent event.
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    name: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    data: String,
+}
+
+impl Event {
+    /// An unnamed event carrying `data`. Multi-line data is sent as one
+    /// `data:` line per line.
+    pub fn data(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Self::default()
+        }
+    }
+
+    /// An unnamed event carrying `value` as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` cannot be serialized.
+    pub fn json<T: Serialize>(value: &T) -> serde_json::Result<Self> {
+        Ok(Self::data(serde_json::to_string(value)?))
+    }
+
+    /// Name the event, so browsers dispatch it to listeners for `name`.
+    #[must_use]
+    pub fn event(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the event's id, which a reconnecting client sends back as
+    /// `Last-Event-ID`.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Ask the client to wait `retry` before reconnecting.
+    #[must_use]
+    pub const fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn encode(&self) -> Bytes {
+        let mut frame = String::new();
+        if let Some(name) = &self.name {
+            let _ = writeln!(frame, "event: {}", single_line(name));
+        }
+        if let Some(id) = &self.id {
+            let _ = writeln!(frame, "id: {}", single_line(id));
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(frame, "retry: {}", retry.as_millis());
+        }
+        for line in self.data.lines() {
+            let _ = writeln!(frame, "data: {line}");
+        }
+        if self.data.is_empty() {
+            frame.push_str("data:\n");
+        }
+        frame.push('\n');
+        Bytes::from(frame)
+    }
+}
+
+// A line break in a field would end the field early.
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// A `text/event-stream` response that sends each event as the stream yields
+/// it.
+///
+/// While no event is ready the response sends a comment every 15 seconds, so
+/// proxies and browsers keep an idle connection open:
+///
+/// ```rust,ignore
+/// async fn updates() -> Sse<impl Stream<Item = Event>> {
+///     let ticks = stream::iter(1..=3).map(|n| Event::data(n.to_string()).event("tick"));
+///     Sse::new(ticks).keep_alive(Duration::from_secs(5))
+/// }
+/// ```
+pub struct Sse<S> {
+    events: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    /// Stream `events`, ending the response when the stream ends.
+    pub const fn new(events: S) -> Self {
+        Self {
+            events,
+            keep_alive: Some(KEEP_ALIVE),
+        }
+    }
+
+    /// Send a keep-alive comment after `interval` without an event.
+    #[must_use]
+    pub const fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Send no keep-alive comments.
+    #[must_use]
+    pub const fn without_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+
+    fn frames(self) -> BoxStream<'static, Bytes> {
+        let events = self.events.map(|event| event.encode()).boxed();
+        let Some(interval) = self.keep_alive else {
+            return events;
+        };
+
+        stream::unfold(events, move |mut events| async move {
+            let frame = match future::select(events.next(), pin!(sleep(interval))).await {
+                Either::Left((Some(frame), _)) => frame,
+                Either::Left((None, _)) => return None,
+                Either::Right(((), _)) => Bytes::from_static(b":\n\n"),
+            };
+            Some((frame, events))
+        })
+        .boxed()
+    }
+}
+
+impl<S> IntoResponse for Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let frames = self.frames().map(Ok::<_, Infallible>);
+        (
+            [(CONTENT_TYPE, "text/event-stream"), (CACHE_CONTROL, "no-cache")],
+            Body::from_stream(frames),
+        )
+            .into_response()
+    }
+}
+
+async fn sleep(duration: Duration) {
+    let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+    wasip3::clocks::monotonic_clock::wait_for(nanos).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn sends_one_data_line_per_line() {
+        let frame = Event::data("first\nsecond").encode();
+        assert_eq!(frame, "data: first\ndata: second\n\n");
+    }
+
+    #[test]
+    fn writes_fields_before_data() {
+        let event = Event::data("7").event("tick").id("42").retry(Duration::from_secs(3));
+        assert_eq!(event.encode(), "event: tick\nid: 42\nretry: 3000\ndata: 7\n\n");
+    }
+
+    #[test]
+    fn empty_data_is_still_an_event() {
+        assert_eq!(Event::data("").encode(), "data:\n\n");
+    }
+
+    #[test]
+    fn line_breaks_cannot_end_fields_early() {
+        let event = Event::data("x").event("a\r\nb").id("1\n2");
+        assert_eq!(event.encode(), "event: a  b\nid: 1 2\ndata: x\n\n");
+    }
+
+    #[test]
+    fn idle_streams_send_keep_alive_comments() {
+        let sse = Sse::new(stream::pending()).keep_alive(Duration::from_millis(1));
+        let frame = block_on(sse.frames().next());
+        assert_eq!(frame.as_deref(), Some(&b":\n\n"[..]));
+    }
+}
@@ -2,12 +2,22 @@
 //!
 //! This module implements a host-side service for `wasi:http`
 
+mod compression;
 mod default_impl;
+mod egress;
+mod identity;
+mod pool;
+mod protocol;
+mod redirect;
+mod resolve;
+mod retry;
 mod server;
+mod upstream;
 
 use anyhow::Result;
 pub use default_impl::HttpDefault;
 use omnia::{Host, Runtime, Server, StoreCtx};
+pub use pool::{HostPoolStats, pool_stats};
 use wasmtime::component::Linker;
 pub use wasmtime_wasi_http::WasiHttpCtx;
 pub use wasmtime_wasi_http::p3::{WasiHttpCtxView, WasiHttpView};
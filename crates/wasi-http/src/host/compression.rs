@@ -0,0 +1,276 @@
+//! Transparent decompression of outbound responses.
+//!
+//! With `HTTP_DECOMPRESS` set, the host advertises the listed encodings in
+//! `Accept-Encoding`, unless the guest sets its own, and decodes matching
+//! responses before the guest reads them. A decoded response loses its
+//! `Content-Encoding` and `Content-Length` and ends with a
+//! [`DECODED_LENGTH_TRAILER`] giving the bytes the guest read.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder};
+use bytes::Bytes;
+use fromenv::ParseResult;
+use futures::TryStreamExt;
+use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
+use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
+
+use crate::DECODED_LENGTH_TRAILER;
+
+/// Content encodings the host decodes.
+// One switch per encoding, set independently from the configured list.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Decompress {
+    gzip: bool,
+    brotli: bool,
+    zstd: bool,
+    deflate: bool,
+}
+
+impl Decompress {
+    /// Parse a comma-separated list of `gzip`, `br`, `zstd`, and `deflate`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming an unsupported encoding.
+    pub fn parse(value: &str) -> ParseResult<Self> {
+        let mut decompress = Self::default();
+        for encoding in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match encoding {
+                "gzip" => decompress.gzip = true,
+                "br" => decompress.brotli = true,
+                "zstd" => decompress.zstd = true,
+                "deflate" => decompress.deflate = true,
+                _ => return Err(format!("unsupported content encoding '{encoding}'").into()),
+            }
+        }
+        Ok(decompress)
+    }
+
+    /// Whether any encoding is decoded.
+    pub const fn any(self) -> bool {
+        self.gzip || self.brotli || self.zstd || self.deflate
+    }
+
+    /// Advertise the encodings in `Accept-Encoding`, unless the guest set the
+    /// header itself. Returns whether they were advertised, and so whether
+    /// the response should be decoded.
+    pub fn advertise(self, headers: &mut HeaderMap) -> bool {
+        if !self.any() || headers.contains_key(ACCEPT_ENCODING) {
+            return false;
+        }
+        let encodings = [
+            (self.gzip, "gzip"),
+            (self.brotli, "br"),
+            (self.zstd, "zstd"),
+            (self.deflate, "deflate"),
+        ];
+        let accepted: Vec<_> =
+            encodings.into_iter().filter_map(|(on, name)| on.then_some(name)).collect();
+        if let Ok(value) = HeaderValue::from_str(&accepted.join(", ")) {
+            headers.insert(ACCEPT_ENCODING, value);
+        }
+        true
+    }
+
+    /// Decode `body` when `headers` name one of the encodings, removing its
+    /// `Content-Encoding` and `Content-Length` and counting the decoded bytes
+    /// into a [`DECODED_LENGTH_TRAILER`]. Other bodies are returned as sent.
+    pub fn decode(
+        self, headers: &mut HeaderMap, body: UnsyncBoxBody<Bytes, ErrorCode>,
+    ) -> UnsyncBoxBody<Bytes, ErrorCode> {
+        let encoding = headers
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_ascii_lowercase());
+        let encoding = match encoding.as_deref() {
+            Some("gzip" | "x-gzip") if self.gzip => Encoding::Gzip,
+            Some("br") if self.brotli => Encoding::Brotli,
+            Some("zstd") if self.zstd => Encoding::Zstd,
+            Some("deflate") if self.deflate => Encoding::Deflate,
+            _ => return body,
+        };
+
+        let reader = StreamReader::new(
+            body.into_data_stream().map_err(|e| io::Error::other(format!("{e:?}"))),
+        );
+        let decoded: Pin<Box<dyn AsyncRead + Send>> = match encoding {
+            Encoding::Gzip => Box::pin(GzipDecoder::new(reader)),
+            Encoding::Brotli => Box::pin(BrotliDecoder::new(reader)),
+            Encoding::Zstd => Box::pin(ZstdDecoder::new(reader)),
+            // HTTP `deflate` is zlib-wrapped, despite its name.
+            Encoding::Deflate => Box::pin(ZlibDecoder::new(reader)),
+        };
+        headers.remove(CONTENT_ENCODING);
+        headers.remove(CONTENT_LENGTH);
+
+        let frames = ReaderStream::new(decoded)
+            .map_ok(Frame::data)
+            .map_err(|e| ErrorCode::InternalError(Some(format!("decoding response: {e}"))));
+        DecodedLength::new(StreamBody::new(frames)).boxed_unsync()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Gzip,
+    Brotli,
+    Zstd,
+    Deflate,
+}
+
+/// A response body that counts the bytes it yields and reports the total in
+/// a [`DECODED_LENGTH_TRAILER`] once the body ends.
+pub struct DecodedLength<B> {
+    body: B,
+    length: u64,
+    reported: bool,
+}
+
+impl<B> DecodedLength<B> {
+    /// Count the bytes of `body`.
+    pub const fn new(body: B) -> Self {
+        Self {
+            body,
+            length: 0,
+            reported: false,
+        }
+    }
+
+    fn trailer(&self) -> (HeaderName, HeaderValue) {
+        (HeaderName::from_static(DECODED_LENGTH_TRAILER), HeaderValue::from(self.length))
+    }
+}
+
+impl<B> Body for DecodedLength<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>, cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if this.reported {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut this.body).poll_frame(cx) {
+            Poll::Ready(Some(Ok(mut frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    this.length += data.len() as u64;
+                } else if let Some(trailers) = frame.trailers_mut() {
+                    let (name, value) = this.trailer();
+                    trailers.insert(name, value);
+                    this.reported = true;
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Poll::Ready(None) => {
+                let (name, value) = this.trailer();
+                let mut trailers = HeaderMap::new();
+                trailers.insert(name, value);
+                this.reported = true;
+                Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+            }
+            other => other,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.reported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_compression::tokio::bufread::GzipEncoder;
+    use http_body_util::Full;
+
+    use super::*;
+
+    fn body(bytes: Vec<u8>) -> UnsyncBoxBody<Bytes, ErrorCode> {
+        Full::new(Bytes::from(bytes)).map_err(|never| match never {}).boxed_unsync()
+    }
+
+    async fn gzip(data: &[u8]) -> Vec<u8> {
+        let chunks: Vec<Bytes> =
+            ReaderStream::new(GzipEncoder::new(data)).try_collect().await.unwrap();
+        chunks.concat()
+    }
+
+    #[test]
+    fn parses_encodings() {
+        let decompress = Decompress::parse("gzip, zstd").unwrap();
+        assert!(decompress.gzip && decompress.zstd && !decompress.brotli);
+        assert!(!Decompress::parse("").unwrap().any());
+        Decompress::parse("compress").unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn reports_length_in_trailer() {
+        let body = DecodedLength::new(Full::new(Bytes::from("decoded body")));
+        let collected = body.collect().await.unwrap();
+
+        assert_eq!(collected.trailers().unwrap()[DECODED_LENGTH_TRAILER], "12");
+        assert_eq!(collected.to_bytes(), "decoded body");
+    }
+
+    #[test]
+    fn advertises_unless_the_guest_chose() {
+        let decompress = Decompress::parse("gzip, br").unwrap();
+        let mut headers = HeaderMap::new();
+        assert!(decompress.advertise(&mut headers));
+        assert_eq!(headers[ACCEPT_ENCODING], "gzip, br");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        assert!(!decompress.advertise(&mut headers));
+        assert!(!Decompress::default().advertise(&mut HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn decodes_matching_responses() {
+        let encoded = gzip(b"decoded body").await;
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(encoded.len()));
+
+        let decompress = Decompress::parse("gzip").unwrap();
+        let collected = decompress.decode(&mut headers, body(encoded)).collect().await.unwrap();
+        assert!(headers.is_empty());
+        assert_eq!(collected.trailers().unwrap()[DECODED_LENGTH_TRAILER], "12");
+        assert_eq!(collected.to_bytes(), "decoded body");
+    }
+
+    #[tokio::test]
+    async fn passes_other_responses_through() {
+        let decompress = Decompress::parse("gzip").unwrap();
+        let mut headers = HeaderMap::new();
+        let collected =
+            decompress.decode(&mut headers, body(b"plain".to_vec())).collect().await.unwrap();
+        assert!(collected.trailers().is_none());
+        assert_eq!(collected.to_bytes(), "plain");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("br"));
+        let collected =
+            decompress.decode(&mut headers, body(b"opaque".to_vec())).collect().await.unwrap();
+        assert_eq!(headers[CONTENT_ENCODING], "br");
+        assert!(collected.trailers().is_none());
+    }
+}
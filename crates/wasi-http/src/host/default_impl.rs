@@ -1,30 +1,54 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
 use base64ct::{Base64, Encoding};
 use bytes::Bytes;
 use fromenv::FromEnv;
-use futures::Future;
+use futures::{Future, TryStreamExt};
 use http::header::{
     CONNECTION, HOST, HeaderName, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TRANSFER_ENCODING,
     UPGRADE,
 };
-use http::{Request, Response};
-use http_body_util::BodyExt;
+use http::{HeaderMap, HeaderValue, Request, Response};
 use http_body_util::combinators::UnsyncBoxBody;
-use omnia::Backend;
-use tracing::instrument;
+use http_body_util::{BodyDataStream, BodyExt};
+use moka::sync::Cache;
+use omnia::{Backend, GuestId};
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use tracing::{Instrument, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use wasmtime::component::ResourceTable;
 use wasmtime_wasi::TrappableError;
 use wasmtime_wasi_http::WasiHttpCtx;
 use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
 use wasmtime_wasi_http::p3::{self, RequestOptions, WasiHttpCtxView};
 
+use crate::host::compression::Decompress;
+use crate::host::egress::Egress;
+use crate::host::identity::{Identities, parse_identities};
+use crate::host::pool::{PooledBody, pool};
+use crate::host::protocol::{Protocol, Protocols};
+use crate::host::redirect::Redirects;
+use crate::host::resolve::{Resolve, UNIX_SCHEME, UnixSockets};
+use crate::host::retry::{RetryOn, RetryPolicy};
+use crate::host::upstream::{Upstreams, parse_upstreams};
+use crate::{REDIRECT_HEADER, RETRY_HEADER, UPSTREAM_HEADER};
+
 pub type HttpResult<T> = Result<T, HttpError>;
 pub type HttpError = TrappableError<ErrorCode>;
 pub type FutureResult<T> = Box<dyn Future<Output = Result<T, ErrorCode>> + Send>;
 
+/// W3C trace context headers added to outbound requests.
+const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+/// Most distinct one-off clients kept for reuse.
+const CLIENT_CACHE_CAPACITY: u64 = 64;
+
 /// Set of headers that are forbidden by `wasmtime-wasi-http`.
 pub const FORBIDDEN_HEADERS: [HeaderName; 9] = [
     CONNECTION,
@@ -44,6 +68,38 @@ pub struct ConnectOptions {
     pub addr: String,
     #[env(from = "HTTP_CONNECT_TIMEOUT", default = "10")]
     pub connect_timeout: u64,
+    #[env(from = "HTTP_UPSTREAMS", default = "", with = parse_upstreams)]
+    pub upstreams: Upstreams,
+    #[env(from = "HTTP_EGRESS", default = "", with = Egress::parse)]
+    pub egress: Egress,
+    #[env(from = "HTTP_CLIENT_IDENTITIES", default = "", with = parse_identities)]
+    pub identities: Identities,
+    #[env(from = "HTTP_POOL_IDLE_TIMEOUT", default = "90")]
+    pub pool_idle_timeout: u64,
+    #[env(from = "HTTP_POOL_MAX_IDLE_PER_HOST", default = "32")]
+    pub pool_max_idle_per_host: u32,
+    #[env(from = "HTTP_RESOLVE", default = "", with = Resolve::parse)]
+    pub resolve: Resolve,
+    #[env(from = "HTTP_UNIX_SOCKETS", default = "", with = UnixSockets::parse)]
+    pub unix_sockets: UnixSockets,
+    #[env(from = "HTTP_PROTOCOLS", default = "", with = Protocols::parse)]
+    pub protocols: Protocols,
+    #[env(from = "HTTP_DECOMPRESS", default = "", with = Decompress::parse)]
+    pub decompress: Decompress,
+    #[env(from = "HTTP_REDIRECT_LIMIT", default = "10")]
+    pub redirect_limit: usize,
+    #[env(from = "HTTP_REDIRECT_CROSS_ORIGIN", default = "true")]
+    pub redirect_cross_origin: bool,
+    #[env(from = "HTTP_RETRY_ATTEMPTS", default = "1")]
+    pub retry_attempts: u32,
+    #[env(from = "HTTP_RETRY_BACKOFF_MS", default = "100")]
+    pub retry_backoff_ms: u64,
+    #[env(from = "HTTP_RETRY_MAX_BACKOFF_MS", default = "5000")]
+    pub retry_max_backoff_ms: u64,
+    #[env(from = "HTTP_RETRY_ON", default = "502,503,504", with = RetryOn::parse)]
+    pub retry_on: RetryOn,
+    #[env(from = "HTTP_RETRY_IDEMPOTENT_ONLY", default = "true")]
+    pub retry_idempotent_only: bool,
 }
 
 impl omnia::FromEnv for ConnectOptions {
@@ -56,7 +112,108 @@ impl omnia::FromEnv for ConnectOptions {
 #[derive(Debug, Clone)]
 struct HttpHooks {
     client: reqwest::Client,
+    // Clients for requests the shared client cannot serve, kept so their
+    // connections and TLS sessions are reused too.
+    clients: Cache<ClientKey, reqwest::Client>,
     connect_timeout: Duration,
+    upstreams: Upstreams,
+    identities: Identities,
+    egress: Egress,
+    // The guest issuing requests, set each time the store borrows the view.
+    guest: Option<GuestId>,
+    redirects: Redirects,
+    decompress: Decompress,
+    resolve: Resolve,
+    unix_sockets: UnixSockets,
+    protocols: Protocols,
+    retry: RetryPolicy,
+}
+
+/// The settings a request needs that the shared client lacks: a client
+/// certificate, a Unix domain socket, a fixed protocol, or guest timeouts and
+/// redirect rules that `reqwest` only sets per client.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    // The PEM identity, compared whole so no two identities can share a client.
+    cert: Option<Arc<[u8]>>,
+    connect_timeout: Duration,
+    between_bytes_timeout: Option<Duration>,
+    redirects: Redirects,
+    unix_socket: Option<PathBuf>,
+    protocol: Protocol,
+}
+
+impl ClientKey {
+    fn build(&self, resolve: &Resolve) -> Result<reqwest::Client, ErrorCode> {
+        let builder = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .redirect(self.redirects.policy());
+        let builder = resolve.apply(self.protocol.apply(builder));
+        let builder = match self.between_bytes_timeout {
+            Some(between) => builder.read_timeout(between),
+            None => builder,
+        };
+        let builder = match &self.cert {
+            Some(pem) => {
+                tracing::debug!("using client certificate");
+                let identity = reqwest::Identity::from_pem(pem).map_err(internal_err)?;
+                builder.identity(identity)
+            }
+            None => builder,
+        };
+        #[cfg(unix)]
+        let builder = match &self.unix_socket {
+            Some(path) => builder.unix_socket(path.clone()),
+            None => builder,
+        };
+        #[cfg(not(unix))]
+        if self.unix_socket.is_some() {
+            return Err(internal_err("unix sockets are not supported on this platform"));
+        }
+
+        #[cfg(test)]
+        let builder = builder.no_proxy();
+
+        builder.build().map_err(reqwest_err)
+    }
+}
+
+// Keep the certificate out of logs.
+impl fmt::Debug for ClientKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientKey")
+            .field("cert", &self.cert.as_ref().map(|_| "[redacted]"))
+            .field("connect_timeout", &self.connect_timeout)
+            .field("between_bytes_timeout", &self.between_bytes_timeout)
+            .field("redirects", &self.redirects)
+            .field("unix_socket", &self.unix_socket)
+            .field("protocol", &self.protocol)
+            .finish()
+    }
+}
+
+/// An outbound request body: streamed when the request is sent once, and
+/// buffered when a retry may need to send it again.
+enum OutgoingBody {
+    Streamed(Option<UnsyncBoxBody<Bytes, ErrorCode>>),
+    Buffered(Bytes),
+}
+
+impl OutgoingBody {
+    /// The body for the next attempt.
+    fn next(&mut self) -> reqwest::Body {
+        match self {
+            Self::Streamed(body) => body.take().map_or_else(
+                || Bytes::new().into(),
+                |body| {
+                    let stream = BodyDataStream::new(body)
+                        .map_err(|e| io::Error::other(format!("request body: {e:?}")));
+                    reqwest::Body::wrap_stream(stream)
+                },
+            ),
+            Self::Buffered(bytes) => bytes.clone().into(),
+        }
+    }
 }
 
 /// Default implementation for `wasi:http`.
@@ -67,8 +224,12 @@ pub struct HttpDefault {
 }
 
 impl HttpDefault {
-    /// Produce a [`WasiHttpCtxView`] by splitting borrows on inner fields.
-    pub fn as_view<'a>(&'a mut self, table: &'a mut ResourceTable) -> WasiHttpCtxView<'a> {
+    /// Produce a [`WasiHttpCtxView`] by splitting borrows on inner fields,
+    /// attributing outbound requests to `guest` for the egress policy.
+    pub fn as_view<'a>(
+        &'a mut self, table: &'a mut ResourceTable, guest: Option<&GuestId>,
+    ) -> WasiHttpCtxView<'a> {
+        self.hooks.guest = guest.cloned();
         WasiHttpCtxView {
             hooks: &mut self.hooks,
             ctx: &mut self.ctx,
@@ -83,16 +244,45 @@ impl Backend for HttpDefault {
     #[instrument]
     async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
         let connect_timeout = Duration::from_secs(options.connect_timeout);
-        let builder = reqwest::Client::builder().connect_timeout(connect_timeout);
+        let idle_timeout = Duration::from_secs(options.pool_idle_timeout);
+        let redirects = Redirects {
+            limit: options.redirect_limit,
+            cross_origin: options.redirect_cross_origin,
+        };
+        let builder = reqwest::Client::builder()
+            .connect_timeout(connect_timeout)
+            .redirect(redirects.policy())
+            .pool_idle_timeout(idle_timeout)
+            .pool_max_idle_per_host(options.pool_max_idle_per_host as usize);
+        let builder = options.resolve.apply(builder);
 
         #[cfg(test)]
         let builder = builder.no_proxy();
 
         let client = builder.build().context("building HTTP client")?;
+        let clients =
+            Cache::builder().max_capacity(CLIENT_CACHE_CAPACITY).time_to_idle(idle_timeout).build();
         Ok(Self {
             hooks: HttpHooks {
                 client,
+                clients,
                 connect_timeout,
+                upstreams: options.upstreams,
+                identities: options.identities,
+                egress: options.egress,
+                guest: None,
+                redirects,
+                decompress: options.decompress,
+                resolve: options.resolve,
+                unix_sockets: options.unix_sockets,
+                protocols: options.protocols,
+                retry: RetryPolicy {
+                    attempts: options.retry_attempts.max(1),
+                    backoff: Duration::from_millis(options.retry_backoff_ms),
+                    max_backoff: Duration::from_millis(options.retry_max_backoff_ms),
+                    retry_on: options.retry_on,
+                    idempotent_only: options.retry_idempotent_only,
+                },
             },
             ctx: WasiHttpCtx::default(),
         })
@@ -100,6 +290,9 @@ impl Backend for HttpDefault {
 }
 
 impl p3::WasiHttpHooks for HttpHooks {
+    // Each step applies one piece of outbound policy, in the order the
+    // request needs it.
+    #[allow(clippy::too_many_lines)]
     fn send_request(
         &mut self, request: Request<UnsyncBoxBody<Bytes, ErrorCode>>,
         options: Option<RequestOptions>, fut: FutureResult<()>,
@@ -109,88 +302,242 @@ impl p3::WasiHttpHooks for HttpHooks {
             > + Send,
     > {
         let shared_client = self.client.clone();
+        let clients = self.clients.clone();
         let connect_timeout = self.connect_timeout;
+        let upstreams = self.upstreams.clone();
+        let identities = self.identities.clone();
+        let egress = self.egress.clone();
+        let guest = self.guest.clone();
+        let default_redirects = self.redirects;
+        let decompress = self.decompress;
+        let resolve = self.resolve.clone();
+        let unix_sockets = self.unix_sockets.clone();
+        let protocols = self.protocols.clone();
+        let mut policy = self.retry.clone();
 
         // guest-supplied timeouts from `wasi:http/types.request-options`
         let opt_connect = options.and_then(|o| o.connect_timeout);
         let opt_first_byte = options.and_then(|o| o.first_byte_timeout);
         let opt_between = options.and_then(|o| o.between_bytes_timeout);
 
-        Box::new(async move {
-            let (mut parts, body) = request.into_parts();
-
-            // remove "Host" headers (`reqwest` adds its own)
-            parts.headers.remove(HOST);
-
-            // A one-off client is required for a client certificate or whenever the
-            // guest overrides the connect/between-bytes timeouts (both are
-            // client-level in `reqwest`); otherwise reuse the shared client so
-            // connection pooling still applies on the common path.
-            let cert = parts.headers.remove("Client-Cert");
-            let client = if cert.is_some() || opt_connect.is_some() || opt_between.is_some() {
-                let builder = reqwest::Client::builder()
-                    .connect_timeout(opt_connect.unwrap_or(connect_timeout));
-                let builder = match opt_between {
-                    Some(between) => builder.read_timeout(between),
-                    None => builder,
+        let span = tracing::debug_span!(
+            "http-client",
+            otel.kind = "client",
+            http.request.method = %request.method(),
+            http.response.status_code = tracing::field::Empty,
+        );
+        let request_span = span.clone();
+
+        Box::new(
+            async move {
+                let (mut parts, body) = request.into_parts();
+
+                // remove "Host" headers (`reqwest` adds its own)
+                parts.headers.remove(HOST);
+
+                let upstream = match parts.headers.remove(UPSTREAM_HEADER) {
+                    Some(name) => {
+                        let name = name.to_str().map_err(internal_err)?;
+                        let upstream = upstreams
+                            .get(name)
+                            .ok_or_else(|| internal_err(format!("unknown upstream: {name}")))?;
+                        upstream.apply(&mut parts.headers);
+                        Some(upstream)
+                    }
+                    None => None,
+                };
+
+                // the upstream's attempts, then the guest's overrides, replace the host's
+                if let Some(retries) = upstream.as_ref().and_then(|u| u.retries()) {
+                    policy.attempts = retries.saturating_add(1);
+                }
+                if let Some(value) = parts.headers.remove(RETRY_HEADER) {
+                    let value = value.to_str().map_err(internal_err)?;
+                    policy = policy.with_overrides(value).map_err(internal_err)?;
+                }
+                let mut redirects = match parts.headers.remove(REDIRECT_HEADER) {
+                    Some(value) => {
+                        let value = value.to_str().map_err(internal_err)?;
+                        default_redirects.with_overrides(value).map_err(internal_err)?
+                    }
+                    None => default_redirects,
+                };
+
+                let url =
+                    upstream.as_ref().map_or_else(|| parts.uri.to_string(), |u| u.url(&parts.uri));
+                let target = reqwest::Url::parse(&url).ok();
+
+                // A restricted guest reaches only its allowed targets, and is not
+                // redirected to another origin it could not have requested itself.
+                if egress.restricts(guest.as_ref()) {
+                    if !target.as_ref().is_some_and(|target| egress.allows(guest.as_ref(), target))
+                    {
+                        let guest = guest.as_ref().map_or("-", GuestId::as_str);
+                        tracing::warn!(guest, "egress to {url} denied by policy");
+                        return Err(ErrorCode::HttpRequestDenied.into());
+                    }
+                    redirects.cross_origin = false;
+                }
+
+                // an `http+unix` target is requested over its socket as `localhost`
+                let (url, unix_socket) = match target.as_ref().filter(|t| t.scheme() == UNIX_SCHEME)
+                {
+                    Some(target) => {
+                        let (socket, url) = unix_sockets.target(target)?;
+                        (url, Some(socket))
+                    }
+                    None => (url, None),
                 };
-                let builder = match cert {
-                    Some(encoded_cert) => {
-                        tracing::debug!("using client certificate");
-                        let encoded = encoded_cert.to_str().map_err(internal_err)?;
-                        let bytes = Base64::decode_vec(encoded).map_err(internal_err)?;
-                        let identity = reqwest::Identity::from_pem(&bytes).map_err(internal_err)?;
-                        builder.identity(identity)
+
+                // The guest's `Client-Cert` header wins over the identity configured
+                // for the target host.
+                let cert = match parts.headers.remove("Client-Cert") {
+                    Some(encoded) => {
+                        let encoded = encoded.to_str().map_err(internal_err)?;
+                        Some(Arc::from(Base64::decode_vec(encoded).map_err(internal_err)?))
+                    }
+                    None => {
+                        target.as_ref().and_then(|u| u.host_str()).and_then(|h| identities.get(h))
                     }
-                    None => builder,
                 };
 
-                #[cfg(test)]
-                let builder = builder.no_proxy();
-
-                builder.build().map_err(reqwest_err)?
-            } else {
-                shared_client
-            };
-
-            let collected = body.collect().await.map_err(internal_err)?;
-
-            // make request
-            let url = parts.uri.to_string();
-            let send = client
-                .request(parts.method, &url)
-                .headers(parts.headers)
-                .body(collected.to_bytes())
-                .send();
-
-            // Bound time-to-response (connect + first byte). The response body is
-            // streamed downstream, so it is *not* part of this deadline; its
-            // pacing is governed by `between_bytes` (the read timeout above).
-            let resp = match opt_first_byte {
-                Some(first_byte) => {
-                    let budget = opt_connect.unwrap_or(connect_timeout).saturating_add(first_byte);
-                    match tokio::time::timeout(budget, send).await {
-                        Ok(result) => result.map_err(reqwest_err)?,
-                        Err(_elapsed) => return Err(ErrorCode::ConnectionTimeout.into()),
+                // A client of its own is required for a client certificate or whenever
+                // the guest overrides the connect/between-bytes timeouts or redirect
+                // policy (all client-level in `reqwest`). Such clients are cached by
+                // those settings; everything else uses the shared client.
+                let protocol = target
+                    .as_ref()
+                    .and_then(|u| u.host_str())
+                    .map(|h| protocols.get(h))
+                    .unwrap_or_default();
+                let pooled = cert.is_none()
+                    && unix_socket.is_none()
+                    && protocol == Protocol::Negotiate
+                    && opt_connect.is_none()
+                    && opt_between.is_none()
+                    && redirects == default_redirects;
+                let client = if pooled {
+                    shared_client
+                } else {
+                    let key = ClientKey {
+                        cert,
+                        connect_timeout: opt_connect.unwrap_or(connect_timeout),
+                        between_bytes_timeout: opt_between,
+                        redirects,
+                        unix_socket,
+                        protocol,
+                    };
+                    if let Some(client) = clients.get(&key) {
+                        client
+                    } else {
+                        let client = key.build(&resolve)?;
+                        clients.insert(key, client.clone());
+                        client
                     }
+                };
+
+                // make request
+                trace_headers(request_span.context().span().span_context(), &mut parts.headers);
+                let decode = decompress.advertise(&mut parts.headers);
+                let retries = policy.retries(&parts.method);
+                let mut body = if retries == 0 {
+                    OutgoingBody::Streamed(Some(body))
+                } else {
+                    OutgoingBody::Buffered(body.collect().await.map_err(internal_err)?.to_bytes())
+                };
+
+                // Only the shared client's pool is tracked.
+                let checkout = if pooled {
+                    target.and_then(|url| {
+                        let host = url.host_str()?;
+                        let port = url.port_or_known_default()?;
+                        Some(pool().checkout(format!("{host}:{port}")))
+                    })
+                } else {
+                    None
+                };
+
+                let mut attempt = 0;
+                let resp = loop {
+                    let request = client
+                        .request(parts.method.clone(), &url)
+                        .headers(parts.headers.clone())
+                        .body(body.next());
+                    let request = match upstream.as_ref().and_then(|u| u.timeout()) {
+                        Some(timeout) => request.timeout(timeout),
+                        None => request,
+                    };
+                    let send = request.send();
+
+                    // Bound time-to-response (connect + first byte). The response body is
+                    // streamed downstream, so it is *not* part of this deadline; its
+                    // pacing is governed by `between_bytes` (the read timeout above).
+                    let result = match opt_first_byte {
+                        Some(first_byte) => {
+                            let budget =
+                                opt_connect.unwrap_or(connect_timeout).saturating_add(first_byte);
+                            match tokio::time::timeout(budget, send).await {
+                                Ok(result) => result.map_err(reqwest_err),
+                                Err(_elapsed) => Err(ErrorCode::ConnectionTimeout),
+                            }
+                        }
+                        None => send.await.map_err(reqwest_err),
+                    };
+
+                    if attempt >= retries || !policy.retryable(&result) {
+                        break result?;
+                    }
+                    attempt += 1;
+                    let delay = policy.delay(attempt);
+                    tracing::debug!(attempt, "retrying request to {url} in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                };
+
+                // process response
+                request_span.record("http.response.status_code", resp.status().as_u16());
+                let converted: Response<reqwest::Body> = resp.into();
+                let (mut parts, body) = converted.into_parts();
+                let body = body.map_err(reqwest_err).boxed_unsync();
+                let body = match checkout {
+                    Some(checkout) => PooledBody::new(body, checkout).boxed_unsync(),
+                    None => body,
+                };
+                let body = if decode { decompress.decode(&mut parts.headers, body) } else { body };
+                let mut response = Response::from_parts(parts, body);
+
+                // remove forbidden headers (disallowed by `wasmtime-wasi-http`)
+                let headers = response.headers_mut();
+                for header in &FORBIDDEN_HEADERS {
+                    headers.remove(header);
                 }
-                None => send.await.map_err(reqwest_err)?,
-            };
-
-            // process response
-            let converted: Response<reqwest::Body> = resp.into();
-            let (parts, body) = converted.into_parts();
-            let body = body.map_err(reqwest_err).boxed_unsync();
-            let mut response = Response::from_parts(parts, body);
-
-            // remove forbidden headers (disallowed by `wasmtime-wasi-http`)
-            let headers = response.headers_mut();
-            for header in &FORBIDDEN_HEADERS {
-                headers.remove(header);
+
+                Ok((response, fut))
             }
+            .instrument(span),
+        )
+    }
+}
 
-            Ok((response, fut))
-        })
+/// Add W3C trace context headers for `span_context`, unless the guest set its
+/// own.
+fn trace_headers(span_context: &SpanContext, headers: &mut HeaderMap) {
+    if !span_context.is_valid() || headers.contains_key(TRACEPARENT) {
+        return;
+    }
+    let traceparent = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    if let Ok(value) = HeaderValue::from_str(&traceparent) {
+        headers.insert(TRACEPARENT, value);
+    }
+    let tracestate = span_context.trace_state().header();
+    if !tracestate.is_empty()
+        && let Ok(value) = HeaderValue::from_str(&tracestate)
+    {
+        headers.insert(TRACESTATE, value);
     }
 }
 
@@ -218,18 +565,38 @@ mod tests {
     use http::header::{AUTHORIZATION, CONTENT_TYPE};
     use http::{Method, StatusCode};
     use http_body_util::{Empty, Full};
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId, TraceState};
     use p3::WasiHttpHooks;
     use wiremock::matchers::{body_string, header, method};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     use super::*;
 
-    async fn test_client() -> HttpDefault {
-        let options = ConnectOptions {
+    fn test_options() -> ConnectOptions {
+        ConnectOptions {
             addr: String::new(),
             connect_timeout: 10,
-        };
-        HttpDefault::connect_with(options).await.unwrap()
+            upstreams: Upstreams::default(),
+            identities: Identities::default(),
+            egress: Egress::default(),
+            pool_idle_timeout: 90,
+            pool_max_idle_per_host: 32,
+            resolve: Resolve::default(),
+            unix_sockets: UnixSockets::default(),
+            protocols: Protocols::default(),
+            decompress: Decompress::default(),
+            redirect_limit: 10,
+            redirect_cross_origin: true,
+            retry_attempts: 1,
+            retry_backoff_ms: 1,
+            retry_max_backoff_ms: 10,
+            retry_on: RetryOn::parse("502,503,504").unwrap(),
+            retry_idempotent_only: true,
+        }
+    }
+
+    async fn test_client() -> HttpDefault {
+        HttpDefault::connect_with(test_options()).await.unwrap()
     }
 
     #[tokio::test]
@@ -282,6 +649,113 @@ mod tests {
         assert_eq!(response.status(), StatusCode::CREATED);
     }
 
+    #[tokio::test]
+    async fn retried_requests_resend_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(body_string("test body"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let request = Request::put(server.uri())
+            .header(RETRY_HEADER, "attempts=2")
+            .body(Full::new(Bytes::from("test body")).map_err(internal_err).boxed_unsync())
+            .unwrap();
+
+        let (response, _) = test_client().await.handle(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let requests = server.received_requests().await.expect("should have requests");
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn guest_can_decline_redirects() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/moved"))
+            .mount(&server)
+            .await;
+
+        let request = Request::get(server.uri())
+            .header(REDIRECT_HEADER, "none")
+            .body(Empty::new().map_err(internal_err).boxed_unsync())
+            .unwrap();
+
+        let (response, _) = test_client().await.handle(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()["location"], "/moved");
+    }
+
+    #[tokio::test]
+    async fn egress_policy_denies_other_targets() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let mut client = test_client().await;
+        client.hooks.egress = Egress::parse("reporting=https://*.example.com").unwrap();
+        client.hooks.guest = Some(GuestId::from("reporting"));
+
+        let request = Request::get(server.uri())
+            .body(Empty::new().map_err(internal_err).boxed_unsync())
+            .unwrap();
+
+        let Err(err) = client.handle(request).await else {
+            panic!("request should be denied");
+        };
+        assert!(matches!(err.downcast(), Ok(ErrorCode::HttpRequestDenied)));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resolve_overrides_dns() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let options = ConnectOptions {
+            resolve: Resolve::parse(&format!("fleet.internal={}", server.address())).unwrap(),
+            ..test_options()
+        };
+        let mut client = HttpDefault::connect_with(options).await.unwrap();
+
+        let request = Request::get("http://fleet.internal/vehicles")
+            .body(Empty::new().map_err(internal_err).boxed_unsync())
+            .unwrap();
+
+        let (response, _) = client.handle(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn adds_trace_context_headers() {
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::from_key_value([("vendor", "value")]).unwrap(),
+        );
+
+        let mut headers = HeaderMap::new();
+        trace_headers(&span_context, &mut headers);
+        assert_eq!(headers[TRACEPARENT], "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01");
+        assert_eq!(headers[TRACESTATE], "vendor=value");
+
+        // the guest's own trace context is kept
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, HeaderValue::from_static("00-guest"));
+        trace_headers(&span_context, &mut headers);
+        assert_eq!(headers[TRACEPARENT], "00-guest");
+        assert!(!headers.contains_key(TRACESTATE));
+    }
+
     #[tokio::test]
     async fn custom_headers() {
         let server = MockServer::start().await;
@@ -390,6 +864,31 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn one_off_clients_are_reused() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let mut client = test_client().await;
+        let options = RequestOptions {
+            connect_timeout: Some(Duration::from_secs(5)),
+            first_byte_timeout: None,
+            between_bytes_timeout: None,
+        };
+        for _ in 0..2 {
+            let request = Request::get(server.uri())
+                .body(Empty::new().map_err(internal_err).boxed_unsync())
+                .unwrap();
+            let boxed =
+                client.hooks.send_request(request, Some(options), Box::new(async { Ok(()) }));
+            let (response, _) = Pin::from(boxed).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        client.hooks.clients.run_pending_tasks();
+        assert_eq!(client.hooks.clients.entry_count(), 1);
+    }
+
     impl HttpDefault {
         async fn handle(
             &mut self, request: Request<UnsyncBoxBody<Bytes, ErrorCode>>,
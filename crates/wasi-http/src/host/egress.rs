@@ -0,0 +1,141 @@
+//! Per-guest egress policy.
+//!
+//! `HTTP_EGRESS` restricts which targets a guest's outbound requests may
+//! reach. It holds comma-separated rules of the form `guest=target`, where
+//! `target` is `[scheme://]host[:port]` and `host` may be `*` or start with
+//! `*.` to match any subdomain. A guest may reach every target in its own
+//! rules; a guest without rules falls back to the rules for `*`, and is
+//! unrestricted if there are none. `reporting=https://*.example.com` limits
+//! the `reporting` guest to HTTPS hosts under `example.com`.
+//!
+//! A restricted guest follows redirects only within the origin it requested,
+//! so a redirect cannot carry it past the policy.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use fromenv::ParseResult;
+use omnia::GuestId;
+use reqwest::Url;
+
+/// Allowed targets by guest.
+#[derive(Debug, Clone, Default)]
+pub struct Egress(Arc<HashMap<String, Vec<Target>>>);
+
+#[derive(Debug, PartialEq, Eq)]
+struct Target {
+    scheme: Option<String>,
+    host: String,
+    port: Option<u16>,
+}
+
+impl Egress {
+    /// Parse comma-separated `guest=target` rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rule is not of that form.
+    pub fn parse(value: &str) -> ParseResult<Self> {
+        let mut rules: HashMap<String, Vec<Target>> = HashMap::new();
+        for rule in value.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let (guest, target) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("egress rule `{rule}` is not `guest=target`"))?;
+            rules.entry(guest.trim().to_string()).or_default().push(Target::parse(target.trim())?);
+        }
+        Ok(Self(Arc::new(rules)))
+    }
+
+    fn targets(&self, guest: Option<&GuestId>) -> Option<&[Target]> {
+        guest
+            .and_then(|guest| self.0.get(guest.as_str()))
+            .or_else(|| self.0.get("*"))
+            .map(Vec::as_slice)
+    }
+
+    /// Whether any rule applies to `guest`.
+    pub fn restricts(&self, guest: Option<&GuestId>) -> bool {
+        self.targets(guest).is_some()
+    }
+
+    /// Whether `guest` may send a request to `url`.
+    pub fn allows(&self, guest: Option<&GuestId>, url: &Url) -> bool {
+        self.targets(guest).is_none_or(|targets| targets.iter().any(|target| target.matches(url)))
+    }
+}
+
+impl Target {
+    fn parse(value: &str) -> ParseResult<Self> {
+        let (scheme, authority) = match value.split_once("://") {
+            Some((scheme, authority)) => (Some(scheme.to_ascii_lowercase()), authority),
+            None => (None, value),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() => (host, port.parse().ok()),
+            _ => (authority, None),
+        };
+        if host.is_empty() || host.contains('/') {
+            return Err(format!("egress target `{value}` is not `[scheme://]host[:port]`").into());
+        }
+        Ok(Self {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port,
+        })
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host_matches = if self.host == "*" {
+            true
+        } else if let Some(domain) = self.host.strip_prefix("*.") {
+            host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.'))
+        } else {
+            host == self.host
+        };
+        host_matches
+            && self.scheme.as_ref().is_none_or(|scheme| scheme == url.scheme())
+            && self.port.is_none_or(|port| url.port_or_known_default() == Some(port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    #[test]
+    fn restricts_guests_to_their_targets() {
+        let egress =
+            Egress::parse("reporting=https://*.example.com, reporting=api.partner.io:8443")
+                .unwrap();
+        let guest = GuestId::from("reporting");
+
+        assert!(egress.allows(Some(&guest), &url("https://feeds.example.com/daily")));
+        assert!(egress.allows(Some(&guest), &url("http://api.partner.io:8443/")));
+        assert!(!egress.allows(Some(&guest), &url("http://feeds.example.com/")));
+        assert!(!egress.allows(Some(&guest), &url("https://example.com/")));
+        assert!(!egress.allows(Some(&guest), &url("https://api.partner.io/")));
+    }
+
+    #[test]
+    fn falls_back_to_wildcard_guest() {
+        let egress = Egress::parse("*=internal.svc, loader=*").unwrap();
+
+        assert!(egress.allows(Some(&GuestId::from("loader")), &url("https://anywhere.io")));
+        assert!(!egress.allows(Some(&GuestId::from("other")), &url("https://anywhere.io")));
+        assert!(egress.allows(None, &url("http://internal.svc/")));
+        assert!(!Egress::default().restricts(None));
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        Egress::parse("reporting").unwrap_err();
+        Egress::parse("reporting=https://example.com/path").unwrap_err();
+    }
+}
@@ -0,0 +1,63 @@
+//! Host-configured client identities for mutual TLS.
+//!
+//! Each entry maps a target host to a PEM file holding a certificate chain
+//! and its private key. Requests to that host present the identity without
+//! the guest ever seeing the key; a guest-supplied `Client-Cert` header still
+//! takes precedence.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use fromenv::ParseResult;
+
+/// PEM-encoded identities keyed by target host.
+#[derive(Clone, Default)]
+pub struct Identities(Arc<HashMap<String, Arc<[u8]>>>);
+
+impl Identities {
+    /// The identity to present to `host`.
+    pub fn get(&self, host: &str) -> Option<Arc<[u8]>> {
+        self.0.get(&host.to_ascii_lowercase()).cloned()
+    }
+}
+
+// Keep the keys out of logs.
+impl fmt::Debug for Identities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Load comma-separated `host=path` entries, checking each file holds a
+/// usable identity.
+pub fn parse_identities(value: &str) -> ParseResult<Identities> {
+    let mut identities = HashMap::new();
+    for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (host, path) =
+            entry.split_once('=').ok_or_else(|| format!("invalid client identity '{entry}'"))?;
+        let pem = std::fs::read(path.trim())
+            .map_err(|e| format!("reading client identity for {host}: {e}"))?;
+        reqwest::Identity::from_pem(&pem)
+            .map_err(|e| format!("invalid client identity for {host}: {e}"))?;
+        identities.insert(host.trim().to_ascii_lowercase(), Arc::from(pem));
+    }
+    Ok(Identities(Arc::new(identities)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_entries() {
+        parse_identities("fleet.example.com").unwrap_err();
+        parse_identities("fleet.example.com=/no/such/identity.pem").unwrap_err();
+    }
+
+    #[test]
+    fn empty_by_default() {
+        let identities = parse_identities("").unwrap();
+        assert!(identities.get("fleet.example.com").is_none());
+    }
+}
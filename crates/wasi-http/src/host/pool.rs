@@ -0,0 +1,166 @@
+//! Outbound request statistics.
+//!
+//! The host counts requests in flight through the shared client per upstream
+//! authority: a request is in flight until its response body is dropped.
+//! `reqwest` keeps its connection pool private, so open and idle connections
+//! are not reported.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::LazyLock;
+use std::task::{Context, Poll};
+
+use hyper::body::{Body, Frame, SizeHint};
+use parking_lot::Mutex;
+
+static POOL: LazyLock<Pool> = LazyLock::new(Pool::default);
+
+/// The process-wide tracker for the shared outbound client.
+#[must_use]
+pub fn pool() -> &'static Pool {
+    &POOL
+}
+
+/// A snapshot of requests in flight for every upstream authority, ordered by
+/// authority.
+#[must_use]
+pub fn pool_stats() -> Vec<HostPoolStats> {
+    pool().stats()
+}
+
+/// Requests in flight to one upstream authority.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPoolStats {
+    /// The upstream `host:port`.
+    pub host: String,
+    /// Requests in flight, each holding a connection.
+    pub in_flight: u32,
+}
+
+/// Per-authority requests in flight through the shared outbound client.
+#[derive(Debug, Default)]
+pub struct Pool {
+    in_flight: Mutex<HashMap<String, u32>>,
+}
+
+impl Pool {
+    /// Record a request to `host` leaving on a pooled connection. The request
+    /// stops counting when the [`Checkout`] is dropped.
+    #[must_use]
+    pub fn checkout(&self, host: String) -> Checkout<'_> {
+        let mut in_flight = self.in_flight.lock();
+        let count = in_flight.entry(host.clone()).or_default();
+        *count = count.saturating_add(1);
+        record(&host, *count);
+        drop(in_flight);
+
+        Checkout { pool: self, host }
+    }
+
+    fn release(&self, host: &str) {
+        let mut in_flight = self.in_flight.lock();
+        let Some(count) = in_flight.get_mut(host) else {
+            return;
+        };
+        *count = count.saturating_sub(1);
+        record(host, *count);
+        if *count == 0 {
+            in_flight.remove(host);
+        }
+    }
+
+    /// Requests in flight for every authority with any.
+    #[must_use]
+    pub fn stats(&self) -> Vec<HostPoolStats> {
+        let mut stats: Vec<_> = self
+            .in_flight
+            .lock()
+            .iter()
+            .map(|(host, &in_flight)| HostPoolStats {
+                host: host.clone(),
+                in_flight,
+            })
+            .collect();
+        stats.sort_by(|a, b| a.host.cmp(&b.host));
+        stats
+    }
+}
+
+fn record(host: &str, in_flight: u32) {
+    tracing::debug!(gauge.http_pool_in_flight = u64::from(in_flight), host);
+}
+
+/// A pooled connection in use; dropping it returns the connection to the pool.
+#[derive(Debug)]
+pub struct Checkout<'a> {
+    pool: &'a Pool,
+    host: String,
+}
+
+impl Drop for Checkout<'_> {
+    fn drop(&mut self) {
+        self.pool.release(&self.host);
+    }
+}
+
+/// A response body that holds its connection's [`Checkout`] until the body is
+/// dropped.
+pub struct PooledBody<B> {
+    body: B,
+    _checkout: Checkout<'static>,
+}
+
+impl<B> PooledBody<B> {
+    pub const fn new(body: B, checkout: Checkout<'static>) -> Self {
+        Self {
+            body,
+            _checkout: checkout,
+        }
+    }
+}
+
+impl<B> Body for PooledBody<B>
+where
+    B: Body + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>, cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.get_mut().body).poll_frame(cx)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_requests_until_released() {
+        let pool = Pool::default();
+
+        let first = pool.checkout("fleet:443".to_string());
+        let second = pool.checkout("fleet:443".to_string());
+        let billing = pool.checkout("billing:80".to_string());
+        let in_flight: Vec<_> =
+            pool.stats().into_iter().map(|stats| (stats.host, stats.in_flight)).collect();
+        assert_eq!(in_flight, [("billing:80".to_string(), 1), ("fleet:443".to_string(), 2)]);
+
+        drop(first);
+        drop(billing);
+        assert_eq!(pool.stats()[0].in_flight, 1);
+
+        drop(second);
+        assert_eq!(pool.stats(), Vec::<HostPoolStats>::new());
+    }
+}
@@ -0,0 +1,86 @@
+//! Per-host HTTP protocol selection.
+//!
+//! By default the host negotiates the protocol: HTTP/1.1 over plain TCP, and
+//! HTTP/2 over TLS when the server offers it. `HTTP_PROTOCOLS` names hosts
+//! that skip negotiation: `h2` speaks HTTP/2 from the first byte, including
+//! cleartext h2c for internal gRPC-style services, and `h3` speaks HTTP/3 over
+//! QUIC. HTTP/3 is experimental in `reqwest` and only available when the host
+//! is built with `RUSTFLAGS="--cfg reqwest_unstable"`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use fromenv::ParseResult;
+use reqwest::ClientBuilder;
+
+/// How a client picks the HTTP version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// Negotiate, by upgrade or ALPN.
+    #[default]
+    Negotiate,
+    /// HTTP/2 with prior knowledge.
+    Http2,
+    /// HTTP/3 with prior knowledge.
+    Http3,
+}
+
+impl Protocol {
+    /// Configure a client to use the protocol.
+    pub fn apply(self, builder: ClientBuilder) -> ClientBuilder {
+        match self {
+            Self::Negotiate => builder,
+            Self::Http2 => builder.http2_prior_knowledge(),
+            #[cfg(reqwest_unstable)]
+            Self::Http3 => builder.http3_prior_knowledge(),
+            #[cfg(not(reqwest_unstable))]
+            Self::Http3 => unreachable!("HTTP/3 is rejected when parsing HTTP_PROTOCOLS"),
+        }
+    }
+}
+
+/// Protocols keyed by target host.
+#[derive(Debug, Clone, Default)]
+pub struct Protocols(Arc<HashMap<String, Protocol>>);
+
+impl Protocols {
+    /// Parse comma-separated `host=h2` and `host=h3` entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not of that form, or names `h3` in a
+    /// build without HTTP/3 support.
+    pub fn parse(value: &str) -> ParseResult<Self> {
+        let mut protocols = HashMap::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (host, protocol) =
+                entry.split_once('=').ok_or_else(|| format!("invalid protocol entry '{entry}'"))?;
+            let protocol = match protocol.trim() {
+                "h2" => Protocol::Http2,
+                "h3" if cfg!(reqwest_unstable) => Protocol::Http3,
+                "h3" => return Err("h3 requires building with --cfg reqwest_unstable".into()),
+                other => return Err(format!("unknown protocol '{other}' for {host}").into()),
+            };
+            protocols.insert(host.trim().to_ascii_lowercase(), protocol);
+        }
+        Ok(Self(Arc::new(protocols)))
+    }
+
+    /// The protocol to use with `host`.
+    pub fn get(&self, host: &str) -> Protocol {
+        self.0.get(&host.to_ascii_lowercase()).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_protocol_by_host() {
+        let protocols = Protocols::parse("grpc.internal=h2").unwrap();
+        assert_eq!(protocols.get("GRPC.internal"), Protocol::Http2);
+        assert_eq!(protocols.get("example.com"), Protocol::Negotiate);
+        Protocols::parse("grpc.internal=spdy").unwrap_err();
+    }
+}
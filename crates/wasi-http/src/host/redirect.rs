@@ -0,0 +1,115 @@
+//! Redirect policy for outbound requests.
+//!
+//! `HTTP_REDIRECT_*` set how the host's clients follow redirects, and a guest
+//! can override them for one request with the [`REDIRECT_HEADER`], e.g.
+//! `none` for an API whose 302s should be handled by the guest. A redirect
+//! that is not followed is returned to the guest as is.
+//!
+//! Whatever the policy, `reqwest` drops `Authorization`, `Cookie`, and
+//! `Proxy-Authorization` when a redirect leaves the original host.
+
+use std::fmt::Display;
+
+use reqwest::redirect::Policy;
+
+use crate::REDIRECT_HEADER;
+
+/// Most redirects a guest may ask to follow in the [`REDIRECT_HEADER`].
+pub const MAX_REDIRECTS: usize = 20;
+
+/// How many redirects to follow, and whether to follow them to other origins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Redirects {
+    /// Redirects followed before the request fails; 0 follows none.
+    pub limit: usize,
+    /// Follow redirects to another scheme, host, or port.
+    pub cross_origin: bool,
+}
+
+impl Redirects {
+    /// The `reqwest` policy for a client.
+    pub fn policy(self) -> Policy {
+        if self.limit == 0 {
+            return Policy::none();
+        }
+        if self.cross_origin {
+            return Policy::limited(self.limit);
+        }
+        Policy::custom(move |attempt| {
+            let same_origin = attempt
+                .previous()
+                .last()
+                .is_none_or(|from| from.origin() == attempt.url().origin());
+            if !same_origin {
+                return attempt.stop();
+            }
+            if attempt.previous().len() > self.limit {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+
+    /// Apply the `;`-separated `name=value` overrides of a [`REDIRECT_HEADER`]
+    /// value: `limit` and `cross-origin`, or `none` to follow no redirects.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first directive that cannot be applied.
+    pub fn with_overrides(mut self, value: &str) -> Result<Self, String> {
+        for directive in value.split(';').map(str::trim).filter(|d| !d.is_empty()) {
+            if directive == "none" {
+                self.limit = 0;
+                continue;
+            }
+            let invalid = || format!("invalid {REDIRECT_HEADER} directive '{directive}'");
+            let malformed = |e: &dyn Display| format!("{}: {e}", invalid());
+            let (name, value) = directive.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match name.trim() {
+                "limit" => {
+                    self.limit = value.parse().map_err(|e| malformed(&e))?;
+                    if self.limit > MAX_REDIRECTS {
+                        return Err(format!(
+                            "{REDIRECT_HEADER} limit must be at most {MAX_REDIRECTS}"
+                        ));
+                    }
+                }
+                "cross-origin" => self.cross_origin = value.parse().map_err(|e| malformed(&e))?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOLLOW: Redirects = Redirects {
+        limit: 10,
+        cross_origin: true,
+    };
+
+    #[test]
+    fn applies_header_overrides() {
+        let overridden = FOLLOW.with_overrides("limit=3; cross-origin=false").unwrap();
+        assert_eq!(
+            overridden,
+            Redirects {
+                limit: 3,
+                cross_origin: false
+            }
+        );
+        assert_eq!(FOLLOW.with_overrides("none").unwrap().limit, 0);
+    }
+
+    #[test]
+    fn rejects_invalid_overrides() {
+        FOLLOW.with_overrides("limit=100").unwrap_err();
+        FOLLOW.with_overrides("keep-auth=true").unwrap_err();
+        FOLLOW.with_overrides("cross-origin").unwrap_err();
+    }
+}
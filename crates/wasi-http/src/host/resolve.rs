@@ -0,0 +1,134 @@
+//! Static DNS overrides and Unix domain socket targets.
+//!
+//! `HTTP_RESOLVE` pins hosts to addresses, bypassing DNS, so requests reach a
+//! sidecar or a test server whatever the URL says. `HTTP_UNIX_SOCKETS` lists
+//! the sockets guests may call with `http+unix://` URLs, whose host is the
+//! percent-encoded socket path: `http+unix://%2Frun%2Fsidecar.sock/status`.
+
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fromenv::ParseResult;
+use percent_encoding::percent_decode_str;
+use reqwest::{ClientBuilder, Url};
+use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
+
+/// URL scheme addressing a Unix domain socket.
+pub const UNIX_SCHEME: &str = "http+unix";
+
+/// Addresses that hosts resolve to instead of DNS.
+#[derive(Debug, Clone, Default)]
+pub struct Resolve(Arc<Vec<(String, SocketAddr)>>);
+
+impl Resolve {
+    /// Parse comma-separated `host=ip` or `host=ip:port` overrides. Without a
+    /// port, the request URL's port is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not of that form.
+    pub fn parse(value: &str) -> ParseResult<Self> {
+        let mut overrides = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (host, addr) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("invalid resolve override '{entry}'"))?;
+            let addr = addr.trim();
+            let addr = match addr.parse::<SocketAddr>() {
+                Ok(addr) => addr,
+                Err(_) => SocketAddr::new(addr.parse::<IpAddr>()?, 0),
+            };
+            overrides.push((host.trim().to_ascii_lowercase(), addr));
+        }
+        Ok(Self(Arc::new(overrides)))
+    }
+
+    /// Pin the overridden hosts on a client.
+    pub fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        self.0.iter().fold(builder, |builder, (host, addr)| builder.resolve(host, *addr))
+    }
+}
+
+/// Unix domain sockets guests may send requests to.
+#[derive(Debug, Clone, Default)]
+pub struct UnixSockets(Arc<HashSet<PathBuf>>);
+
+impl UnixSockets {
+    /// Parse a comma-separated list of socket paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a path is not absolute.
+    pub fn parse(value: &str) -> ParseResult<Self> {
+        let mut sockets = HashSet::new();
+        for path in value.split(',').map(str::trim).filter(|path| !path.is_empty()) {
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                return Err(
+                    format!("unix socket '{}' is not an absolute path", path.display()).into()
+                );
+            }
+            sockets.insert(path);
+        }
+        Ok(Self(Arc::new(sockets)))
+    }
+
+    /// The socket an `http+unix` URL names, and the URL to request over it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HttpRequestUriInvalid` if the URL names no socket, and
+    /// `HttpRequestDenied` if the socket is not configured.
+    pub fn target(&self, url: &Url) -> Result<(PathBuf, String), ErrorCode> {
+        let encoded = url.host_str().ok_or(ErrorCode::HttpRequestUriInvalid)?;
+        let path = percent_decode_str(encoded).decode_utf8().map_err(|e| {
+            tracing::warn!("unix socket path is not UTF-8: {e}");
+            ErrorCode::HttpRequestUriInvalid
+        })?;
+        let path = PathBuf::from(path.as_ref());
+        if !self.0.contains(&path) {
+            tracing::warn!("unix socket {} is not configured", path.display());
+            return Err(ErrorCode::HttpRequestDenied);
+        }
+
+        let mut rewritten = format!("http://localhost/{}", url.path().trim_start_matches('/'));
+        if let Some(query) = url.query() {
+            rewritten.push('?');
+            rewritten.push_str(query);
+        }
+        Ok((path, rewritten))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_overrides_with_and_without_port() {
+        let resolve = Resolve::parse("fleet.internal=10.0.0.5, billing=[::1]:8443").unwrap();
+        assert_eq!(resolve.0[0], ("fleet.internal".to_string(), "10.0.0.5:0".parse().unwrap()));
+        assert_eq!(resolve.0[1], ("billing".to_string(), "[::1]:8443".parse().unwrap()));
+        Resolve::parse("fleet.internal=not-an-ip").unwrap_err();
+    }
+
+    #[test]
+    fn rewrites_unix_targets() {
+        let sockets = UnixSockets::parse("/run/sidecar.sock").unwrap();
+        let url = Url::parse("http+unix://%2Frun%2Fsidecar.sock/status?verbose=1").unwrap();
+
+        let (path, rewritten) = sockets.target(&url).unwrap();
+        assert_eq!(path, PathBuf::from("/run/sidecar.sock"));
+        assert_eq!(rewritten, "http://localhost/status?verbose=1");
+    }
+
+    #[test]
+    fn denies_unconfigured_sockets() {
+        let url = Url::parse("http+unix://%2Fvar%2Frun%2Fdocker.sock/containers").unwrap();
+        let denied = UnixSockets::default().target(&url);
+        assert!(matches!(denied, Err(ErrorCode::HttpRequestDenied)));
+        UnixSockets::parse("relative.sock").unwrap_err();
+    }
+}
@@ -0,0 +1,194 @@
+//! Retry policy for outbound requests.
+//!
+//! A request is retried when it fails to connect, times out before a
+//! response, or gets a status in the policy's retry set, waiting twice as
+//! long before each attempt as the last. Only idempotent methods are retried
+//! unless the policy says otherwise, since others may already have taken
+//! effect.
+//!
+//! `HTTP_RETRY_*` set the host's policy, an upstream profile's `_RETRIES`
+//! overrides its attempts, and a guest can override it for one request with
+//! the [`RETRY_HEADER`], e.g. `attempts=3; on=5xx,429`.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use fromenv::ParseResult;
+use http::{Method, StatusCode};
+use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
+
+use crate::RETRY_HEADER;
+
+/// Most attempts a guest may ask for in the [`RETRY_HEADER`].
+pub const MAX_ATTEMPTS: u32 = 10;
+
+/// When and how often to retry a failed request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first; 1 disables retries.
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for each one after.
+    pub backoff: Duration,
+    /// Longest delay between attempts.
+    pub max_backoff: Duration,
+    /// Response statuses worth retrying.
+    pub retry_on: RetryOn,
+    /// Retry only idempotent methods.
+    pub idempotent_only: bool,
+}
+
+impl RetryPolicy {
+    /// How many times a `method` request may be retried.
+    pub fn retries(&self, method: &Method) -> u32 {
+        if self.idempotent_only && !method.is_idempotent() {
+            return 0;
+        }
+        self.attempts.saturating_sub(1)
+    }
+
+    /// Whether an attempt's outcome is worth retrying.
+    pub fn retryable(&self, result: &Result<reqwest::Response, ErrorCode>) -> bool {
+        match result {
+            Ok(resp) => self.retry_on.matches(resp.status()),
+            Err(code) => {
+                matches!(code, ErrorCode::ConnectionRefused | ErrorCode::ConnectionTimeout)
+            }
+        }
+    }
+
+    /// Delay before retry number `retry`, counting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1_u32.checked_shl(retry.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Apply the `;`-separated `name=value` overrides of a [`RETRY_HEADER`]
+    /// value: `attempts`, `backoff-ms`, `on`, and `idempotent-only`, or
+    /// `none` to disable retries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first directive that cannot be applied.
+    pub fn with_overrides(mut self, value: &str) -> Result<Self, String> {
+        for directive in value.split(';').map(str::trim).filter(|d| !d.is_empty()) {
+            if directive == "none" {
+                self.attempts = 1;
+                continue;
+            }
+            let invalid = || format!("invalid {RETRY_HEADER} directive '{directive}'");
+            let malformed = |e: &dyn Display| format!("{}: {e}", invalid());
+            let (name, value) = directive.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match name.trim() {
+                "attempts" => {
+                    self.attempts = value.parse().map_err(|e| malformed(&e))?;
+                    if !(1..=MAX_ATTEMPTS).contains(&self.attempts) {
+                        return Err(format!("{RETRY_HEADER} attempts must be 1 to {MAX_ATTEMPTS}"));
+                    }
+                }
+                "backoff-ms" => {
+                    self.backoff = Duration::from_millis(value.parse().map_err(|e| malformed(&e))?);
+                }
+                "on" => self.retry_on = RetryOn::parse(value).map_err(|e| malformed(&e))?,
+                "idempotent-only" => {
+                    self.idempotent_only = value.parse().map_err(|e| malformed(&e))?;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Response statuses to retry: exact codes or whole classes such as `5xx`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetryOn(Vec<Status>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Code(u16),
+    Class(u16),
+}
+
+impl RetryOn {
+    /// Parse a comma-separated list of codes (`503`) and classes (`5xx`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is neither a status code nor a class.
+    pub fn parse(value: &str) -> ParseResult<Self> {
+        let mut statuses = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let status = match entry.strip_suffix("xx") {
+                Some(class) => match class.parse() {
+                    Ok(class @ 1..=5) => Status::Class(class),
+                    _ => return Err(format!("invalid status class '{entry}'").into()),
+                },
+                None => Status::Code(StatusCode::from_bytes(entry.as_bytes())?.as_u16()),
+            };
+            statuses.push(status);
+        }
+        Ok(Self(statuses))
+    }
+
+    /// Whether `status` is in the set.
+    pub fn matches(&self, status: StatusCode) -> bool {
+        let code = status.as_u16();
+        self.0.iter().any(|s| match *s {
+            Status::Code(c) => c == code,
+            Status::Class(class) => code / 100 == class,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 4,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            retry_on: RetryOn::parse("502,503,504").unwrap(),
+            idempotent_only: true,
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_limit() {
+        let delays: Vec<_> = (1..=4).map(|n| policy().delay(n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 300, 300]);
+    }
+
+    #[test]
+    fn retries_only_idempotent_methods() {
+        assert_eq!(policy().retries(&Method::GET), 3);
+        assert_eq!(policy().retries(&Method::POST), 0);
+
+        let any_method = policy().with_overrides("idempotent-only=false").unwrap();
+        assert_eq!(any_method.retries(&Method::POST), 3);
+    }
+
+    #[test]
+    fn matches_codes_and_classes() {
+        let retry_on = RetryOn::parse("5xx, 429").unwrap();
+        assert!(retry_on.matches(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(retry_on.matches(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!retry_on.matches(StatusCode::NOT_FOUND));
+        RetryOn::parse("6xx").unwrap_err();
+    }
+
+    #[test]
+    fn applies_header_overrides() {
+        let overridden = policy().with_overrides("attempts=2; on=429; backoff-ms=10").unwrap();
+        assert_eq!(overridden.attempts, 2);
+        assert_eq!(overridden.backoff, Duration::from_millis(10));
+        assert!(overridden.retry_on.matches(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!overridden.retry_on.matches(StatusCode::BAD_GATEWAY));
+
+        assert_eq!(policy().with_overrides("none").unwrap().retries(&Method::GET), 0);
+        policy().with_overrides("attempts=50").unwrap_err();
+        policy().with_overrides("jitter=full").unwrap_err();
+    }
+}
@@ -9,15 +9,17 @@ use std::task::{Context as TaskContext, Poll};
 
 use anyhow::{Context, Result, anyhow};
 use bytes::Bytes;
-use http::StatusCode;
 use http::uri::{PathAndQuery, Uri};
+use http::{Method, StatusCode};
 use http_body_util::combinators::UnsyncBoxBody;
 use http_body_util::{BodyExt, Full};
 use hyper::body::{Body, Frame, Incoming, SizeHint};
 use hyper::header::{FORWARDED, HOST};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
-use omnia::{EnsureError, Guest, HttpRoutes, Runtime, StoreCtx, TriggerRouter};
+use omnia::{
+    CapabilityReport, EnsureError, Guest, HttpRoutes, Quarantined, Runtime, StoreCtx, TriggerRouter,
+};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio::time::timeout;
@@ -27,6 +29,8 @@ use wasmtime_wasi_http::p3::WasiHttpView;
 use wasmtime_wasi_http::p3::bindings::ServiceIndices;
 use wasmtime_wasi_http::p3::bindings::http::types::{self as wasi, ErrorCode};
 
+use crate::host::pool::pool_stats;
+
 type OutgoingBody = UnsyncBoxBody<Bytes, anyhow::Error>;
 
 const HTTP_ADDR: &str = "0.0.0.0:8080";
@@ -39,6 +43,19 @@ where
 {
     let component = env::var("COMPONENT").unwrap_or_else(|_| "unknown".into());
 
+    // Outbound pool statistics are useful whether or not this trigger serves.
+    if let Ok(addr) = env::var("HTTP_ADMIN_ADDR") {
+        let runtime = state.clone();
+        let reports: Reports = Arc::new(move || runtime.registry().capability_reports());
+        let runtime = state.clone();
+        let quarantined: Quarantines = Arc::new(move || runtime.quarantine().quarantined());
+        tokio::spawn(async move {
+            if let Err(error) = serve_admin(&addr, reports, quarantined).await {
+                tracing::error!("http admin server failed: {error:#}");
+            }
+        });
+    }
+
     // Capability probe: a guest exports `wasi:http/incoming-handler` exactly
     // when its typed `ServiceIndices` resolve. Build the per-guest indices and
     // the router that selects among them once, up front.
@@ -119,6 +136,100 @@ where
     }
 }
 
+/// Capability reports for every registered guest, read per request so late
+/// registrations show up.
+type Reports = Arc<dyn Fn() -> Vec<CapabilityReport> + Send + Sync>;
+
+/// Guests currently quarantined for crash-looping.
+type Quarantines = Arc<dyn Fn() -> Vec<Quarantined> + Send + Sync>;
+
+/// Serve host introspection on its own listener, away from guest routes.
+async fn serve_admin(addr: &str, reports: Reports, quarantined: Quarantines) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("http admin listening on: {addr}");
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(error) => {
+                tracing::error!(%error, "admin accept error");
+                continue;
+            }
+        };
+        let reports = Arc::clone(&reports);
+        let quarantined = Arc::clone(&quarantined);
+        tokio::spawn(async move {
+            let service = service_fn(|request| {
+                let response = admin(&request, &reports, &quarantined);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            if let Err(e) =
+                http1::Builder::new().serve_connection(TokioIo::new(stream), service).await
+            {
+                tracing::debug!("admin connection error: {e:?}");
+            }
+        });
+    }
+}
+
+// `GET /pool` lists outbound requests in flight per upstream authority;
+// `GET /capabilities` lists each guest's imports and what satisfies them;
+// `GET /health` lists quarantined guests, answering `503` while there are any.
+fn admin(
+    request: &hyper::Request<Incoming>, reports: &Reports, quarantined: &Quarantines,
+) -> hyper::Response<OutgoingBody> {
+    if request.method() != Method::GET {
+        return not_found();
+    }
+    let mut status = StatusCode::OK;
+    let json = match request.uri().path() {
+        "/health" => {
+            let guests: Vec<_> = quarantined()
+                .into_iter()
+                .map(|quarantined| {
+                    serde_json::json!({
+                        "guest": quarantined.guest.as_str(),
+                        "failures": quarantined.failures,
+                        "last_error": quarantined.last_error,
+                    })
+                })
+                .collect();
+            if !guests.is_empty() {
+                status = StatusCode::SERVICE_UNAVAILABLE;
+            }
+            let health = if guests.is_empty() { "ok" } else { "degraded" };
+            serde_json::json!({ "status": health, "quarantined": guests }).to_string()
+        }
+        "/pool" => {
+            let hosts: Vec<_> = pool_stats()
+                .into_iter()
+                .map(|stats| {
+                    serde_json::json!({
+                        "host": stats.host,
+                        "in_flight": stats.in_flight,
+                    })
+                })
+                .collect();
+            serde_json::Value::Array(hosts).to_string()
+        }
+        "/capabilities" => match serde_json::to_string(&reports()) {
+            Ok(json) => json,
+            Err(error) => {
+                tracing::error!(%error, "failed to serialize capability reports");
+                return internal_error();
+            }
+        },
+        _ => return not_found(),
+    };
+    let body = Full::new(Bytes::from(json)).map_err(Into::into).boxed_unsync();
+
+    hyper::Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .expect("should build admin response")
+}
+
 #[derive(Clone)]
 struct Handler<B>
 where
@@ -217,6 +328,7 @@ where
         // instantiate the selected guest fresh (instance-per-call)
         let store_data = self.state.store();
         let mut store = self.state.build_store(store_data);
+        self.state.meter(guest.id(), &mut store);
         let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
         let service = indices.load(&mut store, &instance)?;
 
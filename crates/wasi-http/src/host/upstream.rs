@@ -0,0 +1,170 @@
+//! Named upstream profiles for outbound requests.
+//!
+//! A guest addresses a profile by setting the [`UPSTREAM_HEADER`] on an
+//! outbound request; the host swaps in the profile's base URL and applies its
+//! headers, credentials, timeout, and retry attempts.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64ct::{Base64, Encoding};
+use fromenv::ParseResult;
+use http::header::AUTHORIZATION;
+use http::{HeaderMap, HeaderName, HeaderValue, Uri};
+
+use crate::{REDIRECT_HEADER, RETRY_HEADER, UPSTREAM_HEADER};
+
+/// Upstream profiles keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct Upstreams(Arc<HashMap<String, Arc<Upstream>>>);
+
+impl Upstreams {
+    /// The profile registered as `name`.
+    pub fn get(&self, name: &str) -> Option<Arc<Upstream>> {
+        self.0.get(name).cloned()
+    }
+}
+
+/// Endpoint policy for one upstream.
+#[derive(Debug)]
+pub struct Upstream {
+    base_url: String,
+    timeout: Option<Duration>,
+    retries: Option<u32>,
+    auth: Option<HeaderValue>,
+    headers: HeaderMap,
+}
+
+impl Upstream {
+    /// Rebase the guest's path and query onto the profile's base URL.
+    pub fn url(&self, uri: &Uri) -> String {
+        let path = uri.path_and_query().map_or("/", |p| p.as_str());
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+    }
+
+    /// Add the profile's headers, without overriding any set by the guest, and
+    /// its credentials, which always replace the guest's.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.headers {
+            if !headers.contains_key(name) {
+                headers.insert(name, value.clone());
+            }
+        }
+        if let Some(auth) = &self.auth {
+            headers.insert(AUTHORIZATION, auth.clone());
+        }
+    }
+
+    /// Total request timeout, if configured.
+    pub const fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// How many times a failed request is retried, overriding the host's policy.
+    pub const fn retries(&self) -> Option<u32> {
+        self.retries
+    }
+}
+
+/// Load the profiles named in `HTTP_UPSTREAMS` from their `HTTP_UPSTREAM_<NAME>_*` variables.
+pub fn parse_upstreams(names: &str) -> ParseResult<Upstreams> {
+    let mut profiles = HashMap::new();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        profiles.insert(name.to_string(), Arc::new(load(name)?));
+    }
+    Ok(Upstreams(Arc::new(profiles)))
+}
+
+fn load(name: &str) -> ParseResult<Upstream> {
+    let prefix = format!("HTTP_UPSTREAM_{}", name.to_ascii_uppercase().replace('-', "_"));
+    let var = |suffix: &str| std::env::var(format!("{prefix}_{suffix}")).ok();
+
+    let base_url = var("URL").ok_or_else(|| format!("{prefix}_URL is not set"))?;
+    let timeout = var("TIMEOUT_MS").map(|ms| ms.parse().map(Duration::from_millis)).transpose()?;
+    let retries = var("RETRIES").map(|n| n.parse()).transpose()?;
+    let auth = var("AUTH").map(|auth| parse_auth(&auth)).transpose()?.flatten();
+    let headers = var("HEADERS").map(|h| parse_headers(&h)).transpose()?.unwrap_or_default();
+
+    Ok(Upstream {
+        base_url,
+        timeout,
+        retries,
+        auth,
+        headers,
+    })
+}
+
+/// Parse `bearer:<token>`, `basic:<user>:<password>`, or `none`.
+fn parse_auth(value: &str) -> ParseResult<Option<HeaderValue>> {
+    let credentials = match value.split_once(':') {
+        Some(("bearer", token)) => format!("Bearer {token}"),
+        Some(("basic", user_pass)) => {
+            format!("Basic {}", Base64::encode_string(user_pass.as_bytes()))
+        }
+        None if value == "none" => return Ok(None),
+        _ => {
+            return Err(format!(
+                "invalid upstream auth '{value}': expected bearer:, basic:, or none"
+            )
+            .into());
+        }
+    };
+    let mut header = HeaderValue::from_str(&credentials)?;
+    header.set_sensitive(true);
+    Ok(Some(header))
+}
+
+/// Parse comma-separated `name=value` pairs.
+fn parse_headers(value: &str) -> ParseResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for pair in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (name, value) =
+            pair.split_once('=').ok_or_else(|| format!("invalid upstream header '{pair}'"))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())?;
+        if [UPSTREAM_HEADER, RETRY_HEADER, REDIRECT_HEADER].contains(&name.as_str()) {
+            return Err(format!("{name} cannot be an upstream header").into());
+        }
+        headers.insert(name, HeaderValue::from_str(value.trim())?);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstream() -> Upstream {
+        Upstream {
+            base_url: "https://fleet.example.com/api/".to_string(),
+            timeout: None,
+            retries: None,
+            auth: parse_auth("bearer:secret").unwrap(),
+            headers: parse_headers("x-tenant=acme, accept=application/json").unwrap(),
+        }
+    }
+
+    #[test]
+    fn rebases_path_and_query() {
+        let uri: Uri = "http://fleet/vehicles?active=true".parse().unwrap();
+        assert_eq!(upstream().url(&uri), "https://fleet.example.com/api/vehicles?active=true");
+    }
+
+    #[test]
+    fn applies_headers_without_overriding_guest() {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept", HeaderValue::from_static("text/csv"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer guest"));
+
+        upstream().apply(&mut headers);
+
+        assert_eq!(headers["accept"], "text/csv");
+        assert_eq!(headers["x-tenant"], "acme");
+        assert_eq!(headers[AUTHORIZATION], "Bearer secret");
+    }
+
+    #[test]
+    fn rejects_unknown_auth_mode() {
+        parse_auth("digest:abc").unwrap_err();
+    }
+}
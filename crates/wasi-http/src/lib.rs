@@ -5,6 +5,18 @@
 //! This module implements a runtime service for `wasi:http`
 //! (<https://github.com/WebAssembly/wasi-http>).
 
+/// Request header naming the host-configured upstream profile to send a request through.
+pub const UPSTREAM_HEADER: &str = "omnia-upstream";
+
+/// Request header overriding the host's retry policy for one outbound request.
+pub const RETRY_HEADER: &str = "omnia-retry";
+
+/// Request header overriding the host's redirect policy for one outbound request.
+pub const REDIRECT_HEADER: &str = "omnia-redirect";
+
+/// Response trailer giving the decoded length of a body the host decompressed.
+pub const DECODED_LENGTH_TRAILER: &str = "omnia-decoded-length";
+
 #[cfg(target_arch = "wasm32")]
 mod guest;
 #[cfg(target_arch = "wasm32")]
@@ -13,4 +13,5 @@ mod generated {
 
 pub mod cache;
 
+pub use self::generated::omnia::keyvalue::*;
 pub use self::generated::wasi::keyvalue::*;
@@ -5,8 +5,8 @@ use chrono::serde::ts_seconds;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::guest::store;
 use crate::guest::store::Bucket;
+use crate::guest::{exchange, expiry, store};
 
 /// Create a new Cache instance with the specified bucket name.
 ///
@@ -57,17 +57,100 @@ impl Cache {
     pub async fn set(
         &self, key: &str, value: &[u8], ttl_secs: Option<u64>,
     ) -> Result<Option<Vec<u8>>> {
-        let value = if let Some(ttl) = ttl_secs.map(|secs| Duration::seconds(secs.cast_signed())) {
-            let envelope = Cacheable::new(value, ttl);
-            &<Cacheable as TryInto<Vec<u8>>>::try_into(envelope)?
+        let previous = self.get(key).await?;
+
+        // The envelope hides the value once expired, even before the store evicts it.
+        if let Some(ttl_secs) = ttl_secs {
+            let envelope = Cacheable::new(value, Duration::seconds(ttl_secs.cast_signed()));
+            let value: Vec<u8> = envelope.try_into()?;
+            expiry::set_with_ttl(&self.bucket, key.to_string(), value, ttl_secs)
+                .await
+                .context("setting state with ttl")?;
         } else {
-            value
+            self.bucket.set(key.to_string(), value.to_vec()).await.context("setting state")?;
+        }
+
+        Ok(previous)
+    }
+
+    /// Get a value and delete it in one atomic operation, so at most one caller
+    /// observes it (Redis `GETDEL`). Suits one-shot tokens and nonces.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is an issue taking the value.
+    pub async fn get_del(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = exchange::get_and_delete(&self.bucket, key.to_string())
+            .await
+            .context("taking state")?
+        else {
+            return Ok(None);
         };
 
-        let previous = self.get(key).await?;
-        self.bucket.set(key.to_string(), value.to_vec()).await.context("setting state with ttl")?;
+        match Cacheable::try_from(&entry) {
+            Ok(ttl_val) if ttl_val.is_expired() => Ok(None),
+            Ok(ttl_val) => Ok(Some(ttl_val.value)),
+            Err(_) => Ok(Some(entry)),
+        }
+    }
 
-        Ok(previous)
+    /// Get a value and restart its expiry at `ttl_secs` from now (Redis
+    /// `GETEX`), for sliding sessions. Both the envelope and the store's own
+    /// TTL are restarted. The refresh is a compare-and-swap, so it never
+    /// resurrects a value another caller deleted or replaced meanwhile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is an issue reading or refreshing the value.
+    pub async fn get_ex(&self, key: &str, ttl_secs: u64) -> Result<Option<Vec<u8>>> {
+        let ttl = Duration::seconds(ttl_secs.cast_signed());
+        loop {
+            let Some(entry) = self.bucket.get(key.to_string()).await.context("reading state")?
+            else {
+                return Ok(None);
+            };
+            let value = match Cacheable::try_from(&entry) {
+                Ok(ttl_val) if ttl_val.is_expired() => return Ok(None),
+                Ok(ttl_val) => ttl_val.value,
+                Err(_) => entry.clone(),
+            };
+
+            let refreshed: Vec<u8> = Cacheable::new(&value, ttl).try_into()?;
+            let swapped = expiry::compare_and_swap_with_ttl(
+                &self.bucket,
+                key.to_string(),
+                Some(entry),
+                refreshed,
+                ttl_secs,
+            )
+            .await
+            .context("refreshing state")?;
+            if swapped {
+                return Ok(Some(value));
+            }
+            // Another writer got in first; retry against the value it wrote.
+        }
+    }
+
+    /// Store `new` under `key` only if it holds `expected` (`None`: the key is
+    /// absent or expired), in one atomic operation, and return whether it was
+    /// stored. The comparison is against the value as [`Self::get`] returns
+    /// it, and a value written with a TTL keeps its expiry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is an issue reading or swapping the value.
+    pub async fn compare_and_swap(
+        &self, key: &str, expected: Option<&[u8]>, new: &[u8],
+    ) -> Result<bool> {
+        let entry = self.bucket.get(key.to_string()).await.context("reading state")?;
+        let (current, new) = swap_in(entry.as_ref(), new)?;
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+        exchange::compare_and_swap(&self.bucket, key.to_string(), entry, new)
+            .await
+            .context("swapping state")
     }
 
     /// Delete a value from the cache.
@@ -80,6 +163,26 @@ impl Cache {
     }
 }
 
+/// The value `entry` holds, as [`Cache::get`] sees it, and the bytes to
+/// store in its place for `new`: wrapped in the same envelope when `entry`
+/// holds a live [`Cacheable`], so the swap keeps its expiry.
+fn swap_in(entry: Option<&Vec<u8>>, new: &[u8]) -> Result<(Option<Vec<u8>>, Vec<u8>)> {
+    let Some(entry) = entry else {
+        return Ok((None, new.to_vec()));
+    };
+    match Cacheable::try_from(entry) {
+        Ok(ttl_val) if ttl_val.is_expired() => Ok((None, new.to_vec())),
+        Ok(ttl_val) => {
+            let wrapped = Cacheable {
+                value: new.to_vec(),
+                expires_at: ttl_val.expires_at,
+            };
+            Ok((Some(ttl_val.value), wrapped.try_into()?))
+        }
+        Err(_) => Ok((Some(entry.clone()), new.to_vec())),
+    }
+}
+
 /// A type that allows for transfer of value types between guest and host where
 /// the implementation may be able to manage value lifetime for an individual
 /// key.
@@ -142,6 +245,24 @@ mod tests {
         assert!(err.contains("issue deserializing Cacheable"));
     }
 
+    #[test]
+    fn swaps_keep_the_envelope() {
+        let live: Vec<u8> = Cacheable::new(b"v1", Duration::seconds(60)).try_into().unwrap();
+        let (current, new) = swap_in(Some(&live), b"v2").unwrap();
+        assert_eq!(current.as_deref(), Some(&b"v1"[..]));
+        let new = Cacheable::try_from(&new).unwrap();
+        assert_eq!(new.value, b"v2");
+        assert_eq!(new.expires_at, Cacheable::try_from(&live).unwrap().expires_at);
+
+        let expired: Vec<u8> = Cacheable::new(b"v1", Duration::seconds(-1)).try_into().unwrap();
+        assert_eq!(swap_in(Some(&expired), b"v2").unwrap(), (None, b"v2".to_vec()));
+        assert_eq!(
+            swap_in(Some(&b"v1".to_vec()), b"v2").unwrap(),
+            (Some(b"v1".to_vec()), b"v2".to_vec())
+        );
+        assert_eq!(swap_in(None, b"v2").unwrap(), (None, b"v2".to_vec()));
+    }
+
     #[test]
     fn wrong_type() {
         // JSON for a different type (e.g., a string)
@@ -2,13 +2,18 @@
 
 mod atomics_impl;
 mod batch_impl;
+mod changes_impl;
 mod default_impl;
+mod exchange_impl;
+mod expiry_impl;
+mod hash_impl;
+mod policy;
 mod resource;
 mod store_impl;
 
 mod generated {
     pub use self::wasi::keyvalue::store::Error;
-    pub use super::{BucketProxy, Cas};
+    pub use super::{BucketProxy, Cas, Feed};
 
     wasmtime::component::bindgen!({
         world: "imports",
@@ -19,6 +24,7 @@ mod generated {
         with: {
             "wasi:keyvalue/store.bucket": BucketProxy,
             "wasi:keyvalue/atomics.cas": Cas,
+            "omnia:keyvalue/changes.feed": Feed,
         },
         trappable_error_type: {
             "wasi:keyvalue/store.error" => Error,
@@ -36,6 +42,7 @@ use wasmtime::component::{HasData, Linker};
 
 pub use self::default_impl::KeyValueDefault;
 use self::generated::wasi::keyvalue::store::Error;
+use self::generated::omnia::keyvalue::{changes, exchange, expiry, hash};
 use self::generated::wasi::keyvalue::{atomics, batch, store};
 pub use self::resource::*;
 
@@ -55,9 +62,14 @@ where
     T: WasiKeyValueView + 'static,
 {
     fn add_to_linker(linker: &mut Linker<T>) -> anyhow::Result<()> {
+        policy::validate()?;
         store::add_to_linker::<_, Self>(linker, T::keyvalue)?;
         atomics::add_to_linker::<_, Self>(linker, T::keyvalue)?;
-        Ok(batch::add_to_linker::<_, Self>(linker, T::keyvalue)?)
+        batch::add_to_linker::<_, Self>(linker, T::keyvalue)?;
+        expiry::add_to_linker::<_, Self>(linker, T::keyvalue)?;
+        exchange::add_to_linker::<_, Self>(linker, T::keyvalue)?;
+        hash::add_to_linker::<_, Self>(linker, T::keyvalue)?;
+        Ok(changes::add_to_linker::<_, Self>(linker, T::keyvalue)?)
     }
 }
 
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use anyhow::Context;
 use wasmtime::component::{Access, Accessor, Resource};
 
@@ -17,58 +19,45 @@ impl<T> HostWithStore<T> for WasiKeyValue {
     /// If the key does not exist in the store, it creates a new key-value pair
     /// with the value set to the given delta.
     ///
-    /// If any other error occurs, it returns an `Err(error)`.
+    /// Atomicity is the bucket's: see [`crate::Bucket::increment`].
     async fn increment(
         accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String, delta: i64,
     ) -> Result<i64> {
         let bucket = get_bucket(accessor, &bucket)?;
-
-        // A missing key starts from zero, so the increment creates it at `delta`.
-        let base =
-            bucket.get(key.clone()).await.context("issue getting value")?.map_or(0, |value| {
-                let mut buf = [0u8; 8];
-                let len = 8.min(value.len());
-                buf[..len].copy_from_slice(&value[..len]);
-                i64::from_be_bytes(buf)
-            });
-        let inc = base + delta;
-
-        bucket.set(key, inc.to_be_bytes().to_vec()).await.context("issue saving increment")?;
-        Ok(inc)
+        Ok(bucket.0.increment(key, delta).await.context("issue saving increment")?)
     }
 
     /// Perform the swap on a CAS operation. This consumes the CAS handle and
     /// returns an error if the CAS operation failed.
     ///
-    /// The default is read-compare-set on the [`crate::Bucket`] trait; backends
-    /// with a native compare-and-swap primitive can tighten the race window by
-    /// versioning inside their `Bucket` implementation.
+    /// The swap is [`crate::Bucket::compare_and_swap`] against the value the
+    /// handle observed, so it is as atomic as the bucket's implementation.
     async fn swap(
         accessor: &Accessor<T, Self>, cas: Resource<Cas>, value: Vec<u8>,
     ) -> anyhow::Result<anyhow::Result<(), CasError>, wasmtime::Error> {
         // The WIT consumes the handle, so remove it from the table up front.
         let cas = accessor.with(|mut store| store.get().table.delete(cas))?;
 
+        let swap = Arc::clone(&cas.bucket).compare_and_swap(cas.key.clone(), cas.current, value);
+        match swap.await {
+            Ok(true) => return Ok(Ok(())),
+            Ok(false) => {}
+            Err(error) => return Ok(Err(CasError::StoreError(Error::from(error)))),
+        }
+
+        // Stale snapshot: hand back a fresh handle at the latest value so the
+        // guest can retry, as the WIT contract requires.
         let observed = match cas.bucket.get(cas.key.clone()).await {
             Ok(observed) => observed,
             Err(error) => return Ok(Err(CasError::StoreError(Error::from(error)))),
         };
-        if observed != cas.current {
-            // Stale snapshot: hand back a fresh handle at the latest value so
-            // the guest can retry, as the WIT contract requires.
-            let fresh = Cas {
-                bucket: cas.bucket,
-                key: cas.key,
-                current: observed,
-            };
-            let resource = accessor.with(|mut store| store.get().table.push(fresh))?;
-            return Ok(Err(CasError::CasFailed(resource)));
-        }
-
-        match cas.bucket.set(cas.key, value).await {
-            Ok(()) => Ok(Ok(())),
-            Err(error) => Ok(Err(CasError::StoreError(Error::from(error)))),
-        }
+        let fresh = Cas {
+            bucket: cas.bucket,
+            key: cas.key,
+            current: observed,
+        };
+        let resource = accessor.with(|mut store| store.get().table.push(fresh))?;
+        Ok(Err(CasError::CasFailed(resource)))
     }
 }
 
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use futures::StreamExt;
+use futures::lock::Mutex;
+use wasmtime::component::{Access, Accessor, Resource};
+
+use crate::WasiKeyValueCtxView;
+use crate::host::generated::omnia::keyvalue::changes::{
+    Change, Host, HostFeed, HostFeedWithStore, HostWithStore,
+};
+use crate::host::generated::wasi::keyvalue::store::Error;
+use crate::host::resource::{BucketProxy, Feed};
+use crate::host::store_impl::get_bucket;
+use crate::host::{Result, WasiKeyValue};
+
+impl<T> HostWithStore<T> for WasiKeyValue {
+    /// Open a feed of changes to the keys starting with `prefix`.
+    ///
+    /// Whether the store can watch keys is the bucket's: see
+    /// [`crate::Bucket::watch`].
+    async fn watch(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, prefix: String,
+    ) -> Result<Resource<Feed>> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        let changes = bucket.watch(prefix).await.context("issue watching keys")?;
+        let feed = Feed(Arc::new(Mutex::new(changes)));
+        Ok(accessor.with(|mut store| store.get().table.push(feed))?)
+    }
+}
+
+impl<T> HostFeedWithStore<T> for WasiKeyValue {
+    /// Wait for the next change on the feed.
+    async fn next(accessor: &Accessor<T, Self>, self_: Resource<Feed>) -> Result<Option<Change>> {
+        let feed = accessor.with(|mut store| {
+            let feed = store.get().table.get(&self_).map_err(|_e| Error::NoSuchStore)?;
+            Ok::<_, Error>(feed.clone())
+        })?;
+        let change = feed.0.lock().await.next().await;
+        Ok(change.map(|change| Change {
+            key: change.key,
+            value: change.value,
+        }))
+    }
+
+    /// Drop the feed, ending the watch.
+    fn drop(mut accessor: Access<'_, T, Self>, rep: Resource<Feed>) -> wasmtime::Result<()> {
+        tracing::trace!("changes::HostFeed::drop");
+        Ok(accessor.get().table.delete(rep).map(|_| ())?)
+    }
+}
+
+impl Host for WasiKeyValueCtxView<'_> {}
+impl HostFeed for WasiKeyValueCtxView<'_> {}
@@ -1,34 +1,204 @@
 //! Default in-memory implementation for wasi-keyvalue
 //!
-//! This is a lightweight implementation for development use only.
+//! This is a lightweight implementation for development use only. Entries
+//! written with a TTL expire like they would in a production cache: reads stop
+//! seeing them once the TTL passes, and the cache evicts them in the
+//! background.
+//!
+//! Every write, delete, and expiry is broadcast to the bucket's watchers; a
+//! watcher that falls more than `CHANGE_CAPACITY` changes behind skips the
+//! ones it missed.
+//!
+//! Setting `KEYVALUE_SNAPSHOT` to a file path keeps state across restarts:
+//! the store is restored from the file at startup, saved to it every
+//! `KEYVALUE_SNAPSHOT_INTERVAL_MS`, and saved once more when the store is
+//! dropped. Entries written with a TTL are saved with their wall-clock
+//! deadline, so time spent stopped counts against them and entries that
+//! expired meanwhile are not restored.
+//!
+//! Buckets are unbounded unless `KEYVALUE_MAX_ENTRIES` or `KEYVALUE_MAX_BYTES`
+//! caps them; a capped bucket evicts its least recently used entries, and
+//! watchers see an evicted key as deleted.
+
+// `derive(FromEnv)` generates undocumented `from_env`/`requirements` associated
+// functions that would otherwise trip `missing_docs`.
+#![allow(missing_docs)]
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
 
-use anyhow::Result;
-use futures::FutureExt;
+use anyhow::{Context, Result, anyhow};
+use fromenv::{FromEnv, ParseResult};
+use futures::{FutureExt, StreamExt};
+use moka::Expiry;
+use moka::notification::RemovalCause;
+use moka::ops::compute::Op;
+use moka::policy::EvictionPolicy;
 use moka::sync::Cache;
 use omnia::Backend;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, Sender};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tracing::instrument;
 
 use crate::host::WasiKeyValueCtx;
-use crate::host::resource::{Bucket, FutureResult};
+use crate::host::resource::{
+    Bucket, Change, Changes, FutureResult, counter_value, decode_fields, encode_fields,
+};
 
-type BucketCache = Cache<String, Vec<u8>>;
+/// Changes buffered per bucket for watchers that have not yet read them.
+const CHANGE_CAPACITY: usize = 1024;
+
+type BucketCache = Cache<String, Entry>;
+
+/// A bucket's entries and the channel its changes are broadcast on.
+#[derive(Clone)]
+struct BucketState {
+    cache: BucketCache,
+    changes: Sender<Change>,
+}
+
+impl BucketState {
+    fn new(limits: Limits) -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_CAPACITY);
+        let removed = changes.clone();
+        let mut builder = Cache::builder()
+            .eviction_policy(EvictionPolicy::lru())
+            .expire_after(EntryExpiry)
+            .eviction_listener(move |key: Arc<String>, _, cause| {
+                if cause == RemovalCause::Size {
+                    tracing::debug!(monotonic_counter.keyvalue_evictions = 1, "evicted key: {key}");
+                }
+                if matches!(cause, RemovalCause::Expired | RemovalCause::Size) {
+                    // No receivers just means nobody is watching.
+                    let _ = removed.send(Change {
+                        key: (*key).clone(),
+                        value: None,
+                    });
+                }
+            });
+        if let Some(max_bytes) = limits.max_bytes {
+            builder = builder.max_capacity(max_bytes).weigher(|key: &String, entry: &Entry| {
+                u32::try_from(key.len() + entry.value.len()).unwrap_or(u32::MAX)
+            });
+        } else if let Some(max_entries) = limits.max_entries {
+            builder = builder.max_capacity(max_entries);
+        }
+        Self {
+            cache: builder.build(),
+            changes,
+        }
+    }
+}
+
+/// Per-bucket size caps; a capped bucket evicts least recently used entries.
+#[derive(Debug, Clone, Copy, Default)]
+struct Limits {
+    max_entries: Option<u64>,
+    max_bytes: Option<u64>,
+}
+
+/// A stored value and when it expires.
+#[derive(Clone)]
+struct Entry {
+    value: Vec<u8>,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn new(value: Vec<u8>, ttl: Option<Duration>) -> Self {
+        Self {
+            value,
+            expires_at: ttl.map(|ttl| Instant::now() + ttl),
+        }
+    }
+
+    /// The time the entry has left at `now`, if it expires.
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.expires_at.map(|at| at.saturating_duration_since(now))
+    }
+}
+
+/// Expires each entry at the deadline of its latest write. A plain write
+/// clears the deadline, while an increment, swap, or hash update carries the
+/// old one over, so the TTL does not restart. A swap with a TTL sets a new
+/// deadline.
+struct EntryExpiry;
+
+impl Expiry<String, Entry> for EntryExpiry {
+    fn expire_after_create(
+        &self, _key: &String, value: &Entry, created_at: Instant,
+    ) -> Option<Duration> {
+        value.remaining(created_at)
+    }
+
+    fn expire_after_update(
+        &self, _key: &String, value: &Entry, updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        value.remaining(updated_at)
+    }
+}
 
 /// Options used to connect to the key-value store.
-#[derive(Debug, Clone, Default)]
-pub struct ConnectOptions;
+///
+/// This struct is used to load connection options from environment variables.
+#[derive(Debug, Clone, FromEnv)]
+pub struct ConnectOptions {
+    /// File the store is restored from and saved to (`KEYVALUE_SNAPSHOT`,
+    /// default none: state is lost on restart).
+    #[env(from = "KEYVALUE_SNAPSHOT")]
+    pub snapshot: Option<PathBuf>,
+    /// How often the snapshot is saved (`KEYVALUE_SNAPSHOT_INTERVAL_MS`,
+    /// default 5000).
+    #[env(from = "KEYVALUE_SNAPSHOT_INTERVAL_MS", default = "5000", with = parse_interval)]
+    pub snapshot_interval: Duration,
+    /// Most entries a bucket holds (`KEYVALUE_MAX_ENTRIES`, default
+    /// unbounded).
+    #[env(from = "KEYVALUE_MAX_ENTRIES")]
+    pub max_entries: Option<u64>,
+    /// Most key and value bytes a bucket holds (`KEYVALUE_MAX_BYTES`, default
+    /// unbounded). Takes precedence over `max_entries`.
+    #[env(from = "KEYVALUE_MAX_BYTES")]
+    pub max_bytes: Option<u64>,
+}
 
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            snapshot: None,
+            snapshot_interval: Duration::from_secs(5),
+            max_entries: None,
+            max_bytes: None,
+        }
+    }
+}
+
+/// Parse milliseconds, clamping to a 1ms minimum so the save interval can
+/// never be zero; used by the `FromEnv` derive.
+fn parse_interval(value: &str) -> ParseResult<Duration> {
+    Ok(Duration::from_millis(value.trim().parse::<u64>()?.max(1)))
+}
+
+/// Loads connection options from environment variables with error context.
 impl omnia::FromEnv for ConnectOptions {
     fn from_env() -> Result<Self> {
-        Ok(Self)
+        Self::from_env().finalize().context("issue loading connection options")
     }
 }
 
 /// Default implementation for `wasi:keyvalue`.
 #[derive(Clone)]
 pub struct KeyValueDefault {
-    store: Cache<String, BucketCache>,
+    store: Cache<String, BucketState>,
+    limits: Limits,
+    // Shared by every clone; the last one to drop saves the final snapshot.
+    _persistence: Option<Arc<Persistence>>,
 }
 
 impl std::fmt::Debug for KeyValueDefault {
@@ -43,21 +213,156 @@ impl Backend for KeyValueDefault {
     #[instrument]
     async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
         tracing::debug!("initializing in-memory key-value store");
+        let store = Cache::builder().build();
+        let limits = Limits {
+            max_entries: options.max_entries,
+            max_bytes: options.max_bytes,
+        };
+
+        let persistence = match options.snapshot {
+            Some(path) => {
+                restore(&store, limits, &path)?;
+                Some(Arc::new(Persistence::start(store.clone(), path, options.snapshot_interval)))
+            }
+            None => None,
+        };
+
         Ok(Self {
-            store: Cache::builder().build(),
+            store,
+            limits,
+            _persistence: persistence,
         })
     }
 }
 
+/// The on-disk form of the store: entries by key, by bucket.
+#[derive(Default, Serialize, Deserialize)]
+struct Snapshot {
+    buckets: BTreeMap<String, BTreeMap<String, SavedEntry>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntry {
+    value: Vec<u8>,
+    /// When the entry expires, in milliseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+/// Milliseconds since the Unix epoch.
+fn unix_millis(time: SystemTime) -> u64 {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Load the snapshot at `path` into `store`. A missing file is an empty
+/// store, so the first run needs no setup.
+fn restore(store: &Cache<String, BucketState>, limits: Limits, path: &Path) -> Result<()> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("reading snapshot {}", path.display()));
+        }
+    };
+    let snapshot: Snapshot = serde_json::from_slice(&bytes)
+        .with_context(|| format!("decoding snapshot {}", path.display()))?;
+
+    let now_ms = unix_millis(SystemTime::now());
+    for (name, entries) in snapshot.buckets {
+        let state = store.get_with(name, || BucketState::new(limits));
+        for (key, saved) in entries {
+            let ttl = match saved.expires_at_ms {
+                Some(at) if at <= now_ms => continue,
+                Some(at) => Some(Duration::from_millis(at - now_ms)),
+                None => None,
+            };
+            state.cache.insert(key, Entry::new(saved.value, ttl));
+        }
+    }
+    tracing::info!("restored key-value store from {}", path.display());
+    Ok(())
+}
+
+/// Write every live entry in `store` to `path`, via a temporary file so a
+/// crash mid-save leaves the previous snapshot intact.
+fn save(store: &Cache<String, BucketState>, path: &Path) -> Result<()> {
+    let mut snapshot = Snapshot::default();
+    let now = Instant::now();
+    let wall_now = SystemTime::now();
+    for (name, state) in store {
+        let entries = state
+            .cache
+            .iter()
+            .map(|(key, entry)| {
+                let expires_at_ms = entry
+                    .remaining(now)
+                    .map(|ttl| wall_now.checked_add(ttl).map_or(u64::MAX, unix_millis));
+                (
+                    (*key).clone(),
+                    SavedEntry {
+                        value: entry.value,
+                        expires_at_ms,
+                    },
+                )
+            })
+            .collect();
+        snapshot.buckets.insert((*name).clone(), entries);
+    }
+
+    let partial = path.with_extension("partial");
+    fs::write(&partial, serde_json::to_vec(&snapshot)?)
+        .with_context(|| format!("writing snapshot {}", partial.display()))?;
+    fs::rename(&partial, path).with_context(|| format!("replacing snapshot {}", path.display()))
+}
+
+/// Saves the store on an interval, and once more on drop.
+struct Persistence {
+    store: Cache<String, BucketState>,
+    path: PathBuf,
+    saver: JoinHandle<()>,
+}
+
+impl Persistence {
+    fn start(store: Cache<String, BucketState>, path: PathBuf, interval: Duration) -> Self {
+        let saver = tokio::spawn({
+            let store = store.clone();
+            let path = path.clone();
+            async move {
+                let mut ticks = tokio::time::interval(interval);
+                // The first tick completes immediately; the store was just restored.
+                ticks.tick().await;
+                loop {
+                    ticks.tick().await;
+                    if let Err(error) = save(&store, &path) {
+                        tracing::warn!(%error, "saving key-value snapshot");
+                    }
+                }
+            }
+        });
+        Self { store, path, saver }
+    }
+}
+
+impl Drop for Persistence {
+    fn drop(&mut self) {
+        self.saver.abort();
+        if let Err(error) = save(&self.store, &self.path) {
+            tracing::warn!(%error, "saving key-value snapshot");
+        }
+    }
+}
+
 impl WasiKeyValueCtx for KeyValueDefault {
     fn open_bucket(&self, identifier: String) -> FutureResult<Arc<dyn Bucket>> {
         tracing::debug!("opening bucket: {identifier}");
 
-        let cache = self.store.get_with(identifier.clone(), || Cache::builder().build());
+        let state = self.store.get_with(identifier.clone(), || BucketState::new(self.limits));
 
         let bucket = InMemBucket {
             name: identifier,
-            cache,
+            cache: state.cache,
+            changes: state.changes,
         };
 
         async move { Ok(Arc::new(bucket) as Arc<dyn Bucket>) }.boxed()
@@ -68,6 +373,41 @@ impl WasiKeyValueCtx for KeyValueDefault {
 struct InMemBucket {
     name: String,
     cache: BucketCache,
+    changes: Sender<Change>,
+}
+
+impl InMemBucket {
+    fn notify(&self, key: String, value: Option<Vec<u8>>) {
+        // No receivers just means nobody is watching.
+        let _ = self.changes.send(Change { key, value });
+    }
+
+    /// Set `key` to `new` if it holds `expected`, under the cache's per-key
+    /// lock. The key expires after `ttl`, or keeps its expiry without one.
+    fn swap(
+        &self, key: String, expected: Option<&[u8]>, new: Vec<u8>, ttl: Option<Duration>,
+    ) -> bool {
+        let mut swapped = false;
+        self.cache.entry(key.clone()).and_compute_with(|current| {
+            let current = current.map(moka::Entry::into_value);
+            if current.as_ref().map(|entry| entry.value.as_slice()) != expected {
+                return Op::Nop;
+            }
+            swapped = true;
+            let expires_at = ttl.map_or_else(
+                || current.and_then(|entry| entry.expires_at),
+                |ttl| Some(Instant::now() + ttl),
+            );
+            Op::Put(Entry {
+                value: new.clone(),
+                expires_at,
+            })
+        });
+        if swapped {
+            self.notify(key, Some(new));
+        }
+        swapped
+    }
 }
 
 impl std::fmt::Debug for InMemBucket {
@@ -83,19 +423,29 @@ impl Bucket for InMemBucket {
 
     fn get(&self, key: String) -> FutureResult<Option<Vec<u8>>> {
         tracing::debug!("getting key: {key} from bucket: {}", self.name);
-        let result = self.cache.get(&key);
+        let result = self.cache.get(&key).map(|entry| entry.value);
         async move { Ok(result) }.boxed()
     }
 
     fn set(&self, key: String, value: Vec<u8>) -> FutureResult<()> {
         tracing::debug!("setting key: {key} in bucket: {}", self.name);
-        self.cache.insert(key, value);
+        self.cache.insert(key.clone(), Entry::new(value.clone(), None));
+        self.notify(key, Some(value));
+        async move { Ok(()) }.boxed()
+    }
+
+    fn set_with_ttl(&self, key: String, value: Vec<u8>, ttl: Duration) -> FutureResult<()> {
+        tracing::debug!("setting key: {key} in bucket: {} with ttl {ttl:?}", self.name);
+        self.cache.insert(key.clone(), Entry::new(value.clone(), Some(ttl)));
+        self.notify(key, Some(value));
         async move { Ok(()) }.boxed()
     }
 
     fn delete(&self, key: String) -> FutureResult<()> {
         tracing::debug!("deleting key: {key} from bucket: {}", self.name);
-        self.cache.invalidate(&key);
+        if self.cache.remove(&key).is_some() {
+            self.notify(key, None);
+        }
         async move { Ok(()) }.boxed()
     }
 
@@ -110,4 +460,317 @@ impl Bucket for InMemBucket {
         let keys = self.cache.iter().map(|(k, _)| (*k).clone()).collect();
         async move { Ok(keys) }.boxed()
     }
+
+    fn get_and_delete(self: Arc<Self>, key: String) -> FutureResult<Option<Vec<u8>>> {
+        tracing::debug!("taking key: {key} from bucket: {}", self.name);
+        let value = self.cache.remove(&key).map(|entry| entry.value);
+        if value.is_some() {
+            self.notify(key, None);
+        }
+        async move { Ok(value) }.boxed()
+    }
+
+    // Counters are updated under the cache's per-key lock. The key keeps its
+    // expiry.
+    fn increment(self: Arc<Self>, key: String, delta: i64) -> FutureResult<i64> {
+        tracing::debug!("incrementing key: {key} in bucket: {} by {delta}", self.name);
+        let mut outcome = Ok(0);
+        self.cache.entry(key.clone()).and_compute_with(|current| {
+            let current = current.map(moka::Entry::into_value);
+            let base = match current.as_ref().map(|entry| counter_value(&entry.value)) {
+                Some(Ok(base)) => base,
+                Some(Err(e)) => {
+                    outcome = Err(e.context(format!("incrementing `{key}`")));
+                    return Op::Nop;
+                }
+                None => 0,
+            };
+            let Some(count) = base.checked_add(delta) else {
+                outcome = Err(anyhow!("incrementing `{key}` by {delta} overflows"));
+                return Op::Nop;
+            };
+            outcome = Ok(count);
+            Op::Put(Entry {
+                value: count.to_be_bytes().to_vec(),
+                expires_at: current.and_then(|entry| entry.expires_at),
+            })
+        });
+        if let Ok(count) = outcome {
+            self.notify(key, Some(count.to_be_bytes().to_vec()));
+        }
+        async move { outcome }.boxed()
+    }
+
+    // The comparison and the write happen under the cache's per-key lock. The
+    // key keeps its expiry.
+    fn compare_and_swap(
+        self: Arc<Self>, key: String, expected: Option<Vec<u8>>, new: Vec<u8>,
+    ) -> FutureResult<bool> {
+        tracing::debug!("swapping key: {key} in bucket: {}", self.name);
+        let swapped = self.swap(key, expected.as_deref(), new, None);
+        async move { Ok(swapped) }.boxed()
+    }
+
+    fn compare_and_swap_with_ttl(
+        self: Arc<Self>, key: String, expected: Option<Vec<u8>>, new: Vec<u8>, ttl: Duration,
+    ) -> FutureResult<bool> {
+        tracing::debug!("swapping key: {key} in bucket: {} with ttl {ttl:?}", self.name);
+        let swapped = self.swap(key, expected.as_deref(), new, Some(ttl));
+        async move { Ok(swapped) }.boxed()
+    }
+
+    // Hashes are rewritten under the cache's per-key lock, so concurrent
+    // updates to different fields are all kept. The key keeps its expiry.
+    fn set_fields(
+        self: Arc<Self>, key: String, fields: Vec<(String, Vec<u8>)>,
+    ) -> FutureResult<u32> {
+        tracing::debug!("setting fields of key: {key} in bucket: {}", self.name);
+        let mut outcome = Ok(0);
+        let mut written = None;
+        self.cache.entry(key.clone()).and_compute_with(|current| {
+            let (mut hash, expires_at) = match current.map(moka::Entry::into_value) {
+                Some(entry) => match decode_fields(&entry.value) {
+                    Ok(hash) => (hash, entry.expires_at),
+                    Err(e) => {
+                        outcome = Err(e);
+                        return Op::Nop;
+                    }
+                },
+                None => (BTreeMap::new(), None),
+            };
+            let mut added = 0;
+            for (name, value) in fields {
+                if hash.insert(name, value).is_none() {
+                    added += 1;
+                }
+            }
+            outcome = Ok(added);
+            let value = encode_fields(&hash);
+            written = Some(value.clone());
+            Op::Put(Entry { value, expires_at })
+        });
+        if let Some(value) = written {
+            self.notify(key, Some(value));
+        }
+        async move { outcome }.boxed()
+    }
+
+    fn delete_fields(self: Arc<Self>, key: String, fields: Vec<String>) -> FutureResult<u32> {
+        tracing::debug!("deleting fields of key: {key} in bucket: {}", self.name);
+        let mut outcome = Ok(0);
+        let mut written = None;
+        self.cache.entry(key.clone()).and_compute_with(|current| {
+            let Some(entry) = current.map(moka::Entry::into_value) else {
+                return Op::Nop;
+            };
+            let mut hash = match decode_fields(&entry.value) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    outcome = Err(e);
+                    return Op::Nop;
+                }
+            };
+            let mut deleted = 0;
+            for name in &fields {
+                if hash.remove(name).is_some() {
+                    deleted += 1;
+                }
+            }
+            outcome = Ok(deleted);
+            if hash.is_empty() {
+                written = Some(None);
+                Op::Remove
+            } else if deleted == 0 {
+                Op::Nop
+            } else {
+                let value = encode_fields(&hash);
+                written = Some(Some(value.clone()));
+                Op::Put(Entry {
+                    value,
+                    expires_at: entry.expires_at,
+                })
+            }
+        });
+        if let Some(value) = written {
+            self.notify(key, value);
+        }
+        async move { outcome }.boxed()
+    }
+
+    fn watch(&self, prefix: String) -> FutureResult<Changes> {
+        tracing::debug!("watching keys: {prefix}* in bucket: {}", self.name);
+        let name = self.name.clone();
+        let changes = BroadcastStream::new(self.changes.subscribe()).filter_map(move |change| {
+            let change = match change {
+                Ok(change) => change.key.starts_with(&prefix).then_some(change),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    tracing::warn!("watcher of bucket: {name} skipped {missed} changes");
+                    None
+                }
+            };
+            async move { change }
+        });
+        async move { Ok(Box::pin(changes) as Changes) }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn snapshots_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("keyvalue-{}.json", std::process::id()));
+        let options = ConnectOptions {
+            snapshot: Some(path.clone()),
+            ..ConnectOptions::default()
+        };
+
+        let store = KeyValueDefault::connect_with(options.clone()).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+        bucket.set("plain".to_string(), b"a".to_vec()).await.unwrap();
+        bucket
+            .set_with_ttl("session".to_string(), b"b".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        drop(bucket);
+        drop(store);
+
+        let store = KeyValueDefault::connect_with(options).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+        assert_eq!(bucket.get("plain".to_string()).await.unwrap(), Some(b"a".to_vec()));
+        assert_eq!(bucket.get("session".to_string()).await.unwrap(), Some(b"b".to_vec()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn snapshots_keep_the_deadline() {
+        let path = std::env::temp_dir().join(format!("keyvalue-ttl-{}.json", std::process::id()));
+        let now_ms = unix_millis(SystemTime::now());
+        let snapshot = serde_json::json!({
+            "buckets": {
+                "cache": {
+                    "expired": { "value": [1], "expires_at_ms": now_ms - 1_000 },
+                    "session": { "value": [2], "expires_at_ms": now_ms + 150 },
+                },
+            },
+        });
+        fs::write(&path, serde_json::to_vec(&snapshot).unwrap()).unwrap();
+        let options = ConnectOptions {
+            snapshot: Some(path.clone()),
+            ..ConnectOptions::default()
+        };
+
+        let store = KeyValueDefault::connect_with(options).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+        assert_eq!(bucket.get("expired".to_string()).await.unwrap(), None);
+        assert_eq!(bucket.get("session".to_string()).await.unwrap(), Some(vec![2]));
+
+        // A restart does not hand the entry a fresh TTL.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(bucket.get("session".to_string()).await.unwrap(), None);
+        drop(bucket);
+        drop(store);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn capped_buckets_evict_the_least_recently_used() {
+        let options = ConnectOptions {
+            max_entries: Some(2),
+            ..ConnectOptions::default()
+        };
+        let store = KeyValueDefault::connect_with(options).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+
+        // Moka applies recency and capacity in batches; settle each step.
+        let settle = || store.store.get("cache").unwrap().cache.run_pending_tasks();
+        bucket.set("a".to_string(), b"1".to_vec()).await.unwrap();
+        bucket.set("b".to_string(), b"2".to_vec()).await.unwrap();
+        settle();
+        bucket.get("a".to_string()).await.unwrap();
+        settle();
+        bucket.set("c".to_string(), b"3".to_vec()).await.unwrap();
+        settle();
+
+        let mut keys = bucket.keys().await.unwrap();
+        keys.sort();
+        assert_eq!(keys, ["a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn updates_keep_the_expiry() {
+        let store = KeyValueDefault::connect_with(ConnectOptions::default()).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+        let ttl = Duration::from_millis(150);
+
+        bucket.set_with_ttl("hits".to_string(), 1_i64.to_be_bytes().to_vec(), ttl).await.unwrap();
+        bucket.set_with_ttl("version".to_string(), b"v1".to_vec(), ttl).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(Arc::clone(&bucket).increment("hits".to_string(), 1).await.unwrap(), 2);
+        let swap = Arc::clone(&bucket).compare_and_swap(
+            "version".to_string(),
+            Some(b"v1".to_vec()),
+            b"v2".to_vec(),
+        );
+        assert!(swap.await.unwrap());
+
+        // Had the updates restarted the TTL, both keys would live until 250ms.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(bucket.get("hits".to_string()).await.unwrap(), None);
+        assert_eq!(bucket.get("version".to_string()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn swaps_with_a_ttl_restart_the_expiry() {
+        let store = KeyValueDefault::connect_with(ConnectOptions::default()).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+
+        bucket
+            .set_with_ttl("session".to_string(), b"a".to_vec(), Duration::from_millis(100))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let swap = |expected: &[u8]| {
+            Arc::clone(&bucket).compare_and_swap_with_ttl(
+                "session".to_string(),
+                Some(expected.to_vec()),
+                b"a".to_vec(),
+                Duration::from_millis(100),
+            )
+        };
+        assert!(!swap(b"b").await.unwrap());
+        assert!(swap(b"a").await.unwrap());
+
+        // The original TTL ran out at 100ms; the refreshed one lasts until 160ms.
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(bucket.get("session".to_string()).await.unwrap(), Some(b"a".to_vec()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(bucket.get("session".to_string()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn increments_refuse_values_that_are_not_counters() {
+        let store = KeyValueDefault::connect_with(ConnectOptions::default()).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+
+        bucket.set("hits".to_string(), vec![1]).await.unwrap();
+        Arc::clone(&bucket).increment("hits".to_string(), 1).await.unwrap_err();
+        assert_eq!(bucket.get("hits".to_string()).await.unwrap(), Some(vec![1]));
+    }
+
+    #[tokio::test]
+    async fn plain_set_clears_the_ttl() {
+        let store = KeyValueDefault::connect_with(ConnectOptions::default()).await.unwrap();
+        let bucket = store.open_bucket("cache".to_string()).await.unwrap();
+
+        bucket
+            .set_with_ttl("key".to_string(), b"a".to_vec(), Duration::from_millis(50))
+            .await
+            .unwrap();
+        bucket.set("key".to_string(), b"b".to_vec()).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(bucket.get("key".to_string()).await.unwrap(), Some(b"b".to_vec()));
+    }
 }
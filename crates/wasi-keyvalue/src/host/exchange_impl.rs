@@ -0,0 +1,34 @@
+use anyhow::Context;
+use wasmtime::component::{Accessor, Resource};
+
+use crate::WasiKeyValueCtxView;
+use crate::host::generated::omnia::keyvalue::exchange::{Host, HostWithStore};
+use crate::host::resource::BucketProxy;
+use crate::host::store_impl::get_bucket;
+use crate::host::{Result, WasiKeyValue};
+
+impl<T> HostWithStore<T> for WasiKeyValue {
+    /// Store `value` under the key only if it holds `expected` (`none`: the
+    /// key is absent), returning whether it was stored.
+    ///
+    /// Atomicity is the bucket's: see [`crate::Bucket::compare_and_swap`].
+    async fn compare_and_swap(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String,
+        expected: Option<Vec<u8>>, value: Vec<u8>,
+    ) -> Result<bool> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        Ok(bucket.0.compare_and_swap(key, expected, value).await.context("issue swapping value")?)
+    }
+
+    /// Atomically get the value associated with the key and delete it.
+    ///
+    /// Atomicity is the bucket's: see [`crate::Bucket::get_and_delete`].
+    async fn get_and_delete(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String,
+    ) -> Result<Option<Vec<u8>>> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        Ok(bucket.0.get_and_delete(key).await.context("issue taking value")?)
+    }
+}
+
+impl Host for WasiKeyValueCtxView<'_> {}
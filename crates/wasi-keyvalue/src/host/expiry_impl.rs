@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use wasmtime::component::{Accessor, Resource};
+
+use crate::WasiKeyValueCtxView;
+use crate::host::generated::omnia::keyvalue::expiry::{Host, HostWithStore};
+use crate::host::resource::BucketProxy;
+use crate::host::store_impl::get_bucket;
+use crate::host::{Result, WasiKeyValue};
+
+impl<T> HostWithStore<T> for WasiKeyValue {
+    async fn set_with_ttl(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String, value: Vec<u8>,
+        ttl_secs: u64,
+    ) -> Result<()> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        let ttl = Duration::from_secs(ttl_secs);
+        bucket.set_with_ttl(key, value, ttl).await.context("issue setting value with ttl")?;
+        Ok(())
+    }
+
+    /// Store `value` under the key, expiring it after `ttl_secs`, only if it
+    /// holds `expected` (`none`: the key is absent), returning whether it was
+    /// stored.
+    ///
+    /// Atomicity is the bucket's: see [`crate::Bucket::compare_and_swap_with_ttl`].
+    async fn compare_and_swap_with_ttl(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String,
+        expected: Option<Vec<u8>>, value: Vec<u8>, ttl_secs: u64,
+    ) -> Result<bool> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        let ttl = Duration::from_secs(ttl_secs);
+        let swap = bucket.0.compare_and_swap_with_ttl(key, expected, value, ttl);
+        Ok(swap.await.context("issue swapping value with ttl")?)
+    }
+}
+
+impl Host for WasiKeyValueCtxView<'_> {}
@@ -0,0 +1,42 @@
+use anyhow::Context;
+use wasmtime::component::{Accessor, Resource};
+
+use crate::WasiKeyValueCtxView;
+use crate::host::generated::omnia::keyvalue::hash::{Host, HostWithStore};
+use crate::host::resource::BucketProxy;
+use crate::host::store_impl::get_bucket;
+use crate::host::{Result, WasiKeyValue};
+
+impl<T> HostWithStore<T> for WasiKeyValue {
+    async fn set_fields(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String,
+        fields: Vec<(String, Vec<u8>)>,
+    ) -> Result<u32> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        Ok(bucket.0.set_fields(key, fields).await.context("issue setting fields")?)
+    }
+
+    async fn get_field(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String, field: String,
+    ) -> Result<Option<Vec<u8>>> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        Ok(bucket.get_field(key, field).await.context("issue getting field")?)
+    }
+
+    async fn get_all_fields(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        Ok(bucket.get_all_fields(key).await.context("issue getting fields")?)
+    }
+
+    async fn delete_fields(
+        accessor: &Accessor<T, Self>, bucket: Resource<BucketProxy>, key: String,
+        fields: Vec<String>,
+    ) -> Result<u32> {
+        let bucket = get_bucket(accessor, &bucket)?;
+        Ok(bucket.0.delete_fields(key, fields).await.context("issue deleting fields")?)
+    }
+}
+
+impl Host for WasiKeyValueCtxView<'_> {}
@@ -0,0 +1,234 @@
+//! Per-bucket access policy.
+//!
+//! `KEYVALUE_POLICY` restricts what guests may do with named buckets, whatever
+//! backend serves them. It holds comma-separated rules of the form
+//! `bucket=access` or `bucket@guest=access`, where `access` is `write`, `read`,
+//! or `deny`. A guest's own rule wins over the bucket's; a bucket without
+//! rules is writable by every guest. A shared bucket writable only by its
+//! loader is `static=read,static@loader=write`.
+//!
+//! A denied guest cannot open the bucket. A read-only guest opens it behind
+//! [`ReadOnly`], which refuses every write.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use futures::FutureExt;
+use omnia::GuestId;
+
+use crate::host::resource::{Bucket, Changes, FutureResult};
+
+static POLICY: LazyLock<Result<Policy, String>> = LazyLock::new(|| {
+    let spec = std::env::var("KEYVALUE_POLICY").unwrap_or_default();
+    Policy::parse(&spec).map_err(|e| format!("{e:#}"))
+});
+
+/// Check `KEYVALUE_POLICY` parses, so a bad policy fails startup instead of
+/// every `open`.
+///
+/// # Errors
+///
+/// Returns an error if `KEYVALUE_POLICY` is malformed.
+pub fn validate() -> Result<()> {
+    POLICY.as_ref().map(|_| ()).map_err(|e| anyhow!("invalid KEYVALUE_POLICY: {e}"))
+}
+
+/// What the configured policy lets `guest` do with `bucket`. A malformed
+/// policy denies everything.
+pub fn permission(bucket: &str, guest: Option<&GuestId>) -> Permission {
+    POLICY.as_ref().map_or(Permission::Deny, |policy| policy.permission(bucket, guest))
+}
+
+/// What a guest may do with a bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permission {
+    /// Read and write.
+    Write,
+    /// Read only.
+    Read,
+    /// Not open the bucket at all.
+    Deny,
+}
+
+/// Access rules by bucket, and by guest within a bucket.
+#[derive(Debug, Default)]
+pub struct Policy {
+    rules: HashMap<(String, Option<String>), Permission>,
+}
+
+impl Policy {
+    /// Parse comma-separated `bucket[@guest]=access` rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rule is not of that form or names an unknown
+    /// access level.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut rules = HashMap::new();
+        for rule in spec.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let Some((target, access)) = rule.split_once('=') else {
+                bail!("rule `{rule}` is not `bucket[@guest]=access`");
+            };
+            let access = match access.trim() {
+                "write" => Permission::Write,
+                "read" => Permission::Read,
+                "deny" => Permission::Deny,
+                other => bail!("rule `{rule}` has unknown access `{other}`"),
+            };
+            let (bucket, guest) = match target.split_once('@') {
+                Some((bucket, guest)) => (bucket.trim(), Some(guest.trim().to_string())),
+                None => (target.trim(), None),
+            };
+            rules.insert((bucket.to_string(), guest), access);
+        }
+        Ok(Self { rules })
+    }
+
+    /// What `guest` may do with `bucket`.
+    #[must_use]
+    pub fn permission(&self, bucket: &str, guest: Option<&GuestId>) -> Permission {
+        let own = guest.and_then(|guest| {
+            self.rules.get(&(bucket.to_string(), Some(guest.as_str().to_string())))
+        });
+        own.or_else(|| self.rules.get(&(bucket.to_string(), None)))
+            .copied()
+            .unwrap_or(Permission::Write)
+    }
+}
+
+/// A bucket opened read-only: reads pass through, writes are refused.
+#[derive(Debug)]
+pub struct ReadOnly {
+    bucket: Arc<dyn Bucket>,
+    guest: String,
+}
+
+impl ReadOnly {
+    /// Wrap `bucket` for `guest`, who may only read it.
+    #[must_use]
+    pub fn new(bucket: Arc<dyn Bucket>, guest: Option<&GuestId>) -> Self {
+        let guest = guest.map_or_else(|| "unknown".to_string(), |guest| guest.as_str().to_string());
+        Self { bucket, guest }
+    }
+
+    fn denied<T: Send + 'static>(&self, key: &str) -> FutureResult<T> {
+        let error = anyhow!(
+            "access denied: bucket `{}` is read-only for guest `{}`, cannot write `{key}`",
+            self.bucket.name(),
+            self.guest
+        );
+        async move { Err(error) }.boxed()
+    }
+}
+
+impl Bucket for ReadOnly {
+    fn name(&self) -> &str {
+        self.bucket.name()
+    }
+
+    fn get(&self, key: String) -> FutureResult<Option<Vec<u8>>> {
+        self.bucket.get(key)
+    }
+
+    fn set(&self, key: String, _value: Vec<u8>) -> FutureResult<()> {
+        self.denied(&key)
+    }
+
+    fn set_with_ttl(&self, key: String, _value: Vec<u8>, _ttl: Duration) -> FutureResult<()> {
+        self.denied(&key)
+    }
+
+    fn delete(&self, key: String) -> FutureResult<()> {
+        self.denied(&key)
+    }
+
+    fn exists(&self, key: String) -> FutureResult<bool> {
+        self.bucket.exists(key)
+    }
+
+    fn keys(&self) -> FutureResult<Vec<String>> {
+        self.bucket.keys()
+    }
+
+    fn get_and_delete(self: Arc<Self>, key: String) -> FutureResult<Option<Vec<u8>>> {
+        self.denied(&key)
+    }
+
+    fn increment(self: Arc<Self>, key: String, _delta: i64) -> FutureResult<i64> {
+        self.denied(&key)
+    }
+
+    fn compare_and_swap(
+        self: Arc<Self>, key: String, _expected: Option<Vec<u8>>, _new: Vec<u8>,
+    ) -> FutureResult<bool> {
+        self.denied(&key)
+    }
+
+    fn compare_and_swap_with_ttl(
+        self: Arc<Self>, key: String, _expected: Option<Vec<u8>>, _new: Vec<u8>, _ttl: Duration,
+    ) -> FutureResult<bool> {
+        self.denied(&key)
+    }
+
+    fn set_fields(
+        self: Arc<Self>, key: String, _fields: Vec<(String, Vec<u8>)>,
+    ) -> FutureResult<u32> {
+        self.denied(&key)
+    }
+
+    fn get_field(&self, key: String, field: String) -> FutureResult<Option<Vec<u8>>> {
+        self.bucket.get_field(key, field)
+    }
+
+    fn get_all_fields(&self, key: String) -> FutureResult<Vec<(String, Vec<u8>)>> {
+        self.bucket.get_all_fields(key)
+    }
+
+    fn delete_fields(self: Arc<Self>, key: String, _fields: Vec<String>) -> FutureResult<u32> {
+        self.denied(&key)
+    }
+
+    fn watch(&self, prefix: String) -> FutureResult<Changes> {
+        self.bucket.watch(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use omnia::Backend;
+
+    use super::*;
+    use crate::host::WasiKeyValueCtx;
+    use crate::host::default_impl::{ConnectOptions, KeyValueDefault};
+
+    #[test]
+    fn guest_rules_win_over_bucket_rules() {
+        let policy = Policy::parse("static=read, static@loader=write, secrets=deny").unwrap();
+        let loader = GuestId::from("loader");
+        let reader = GuestId::from("reader");
+
+        assert_eq!(policy.permission("static", Some(&loader)), Permission::Write);
+        assert_eq!(policy.permission("static", Some(&reader)), Permission::Read);
+        assert_eq!(policy.permission("static", None), Permission::Read);
+        assert_eq!(policy.permission("secrets", Some(&loader)), Permission::Deny);
+        assert_eq!(policy.permission("cache", Some(&reader)), Permission::Write);
+
+        Policy::parse("static=readonly").unwrap_err();
+        Policy::parse("static").unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn read_only_buckets_refuse_writes() {
+        let store = KeyValueDefault::connect_with(ConnectOptions::default()).await.unwrap();
+        let bucket = store.open_bucket("static".to_string()).await.unwrap();
+        bucket.set("motd".to_string(), b"hello".to_vec()).await.unwrap();
+
+        let reader = GuestId::from("reader");
+        let read_only: Arc<dyn Bucket> = Arc::new(ReadOnly::new(bucket, Some(&reader)));
+        assert_eq!(read_only.get("motd".to_string()).await.unwrap(), Some(b"hello".to_vec()));
+        read_only.set("motd".to_string(), Vec::new()).await.unwrap_err();
+        Arc::clone(&read_only).increment("hits".to_string(), 1).await.unwrap_err();
+    }
+}
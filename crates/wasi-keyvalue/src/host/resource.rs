@@ -1,9 +1,18 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::ops::Deref;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::{Result, bail};
+use futures::lock::Mutex;
+use futures::{FutureExt, Stream};
 pub use omnia::FutureResult;
 
+/// Stream of changes to watched keys.
+pub type Changes = Pin<Box<dyn Stream<Item = Change> + Send>>;
+
 /// Providers implement the [`Bucket`] trait to allow the host to
 /// interact with different backend buckets (stores).
 pub trait Bucket: Debug + Send + Sync + 'static {
@@ -16,6 +25,17 @@ pub trait Bucket: Debug + Send + Sync + 'static {
     /// Set the value associated with the key.
     fn set(&self, key: String, value: Vec<u8>) -> FutureResult<()>;
 
+    /// Set the value associated with the key, expiring it after `ttl`.
+    /// A later [`set`](Self::set) clears the expiry.
+    ///
+    /// The default stores the value with [`set`](Self::set) and no expiry,
+    /// leaving callers such as the guest `Cache` to enforce the deadline they
+    /// record with the value; backends with key-level expiry should override
+    /// it.
+    fn set_with_ttl(&self, key: String, value: Vec<u8>, _ttl: Duration) -> FutureResult<()> {
+        self.set(key, value)
+    }
+
     /// Delete the value associated with the key.
     fn delete(&self, key: String) -> FutureResult<()>;
 
@@ -24,6 +44,148 @@ pub trait Bucket: Debug + Send + Sync + 'static {
 
     /// List all keys in the bucket.
     fn keys(&self) -> FutureResult<Vec<String>>;
+
+    /// Get the value associated with the key and delete it, in one atomic
+    /// operation, so at most one caller observes the value.
+    ///
+    /// The default reports that the store cannot; backends with a native
+    /// get-and-delete or an atomic update should override it.
+    fn get_and_delete(self: Arc<Self>, key: String) -> FutureResult<Option<Vec<u8>>> {
+        let name = self.name().to_string();
+        async move { bail!("bucket `{name}` cannot take `{key}`: the store has no get-and-delete") }
+            .boxed()
+    }
+
+    /// Add `delta` to the counter stored under `key`, in one atomic operation,
+    /// and return the new value. A missing key starts from zero; counters are
+    /// stored as big-endian `i64`s (see [`counter_value`]). The key keeps its
+    /// expiry.
+    ///
+    /// The default reports that the store cannot; backends with a native
+    /// increment or an atomic update should override it.
+    fn increment(self: Arc<Self>, key: String, _delta: i64) -> FutureResult<i64> {
+        let name = self.name().to_string();
+        async move { bail!("bucket `{name}` cannot increment `{key}`: the store has no counters") }
+            .boxed()
+    }
+
+    /// Store `new` under `key` only if the key holds `expected`, where `None`
+    /// means the key is absent, in one atomic operation, and return whether
+    /// the value was swapped. The key keeps its expiry.
+    ///
+    /// The default reports that the store cannot; backends with a native
+    /// compare-and-swap or an atomic update should override it.
+    fn compare_and_swap(
+        self: Arc<Self>, key: String, _expected: Option<Vec<u8>>, _new: Vec<u8>,
+    ) -> FutureResult<bool> {
+        let name = self.name().to_string();
+        async move {
+            bail!("bucket `{name}` cannot swap `{key}`: the store has no compare-and-swap")
+        }
+        .boxed()
+    }
+
+    /// Set the value associated with the key to `new`, expiring it after
+    /// `ttl`, if the key holds `expected` (`None`: the key is absent), in one
+    /// atomic operation, and return whether it was set.
+    ///
+    /// The default swaps with [`compare_and_swap`](Self::compare_and_swap)
+    /// and no expiry, as [`set_with_ttl`](Self::set_with_ttl) does; backends
+    /// with key-level expiry and an atomic update should override it.
+    fn compare_and_swap_with_ttl(
+        self: Arc<Self>, key: String, expected: Option<Vec<u8>>, new: Vec<u8>, _ttl: Duration,
+    ) -> FutureResult<bool> {
+        self.compare_and_swap(key, expected, new)
+    }
+
+    /// Set fields of the hash stored under `key`, creating it if absent, and
+    /// return how many fields were added.
+    ///
+    /// The default reports that the store cannot, since a get followed by a
+    /// set would let two callers lose each other's fields; backends with
+    /// native hashes or an atomic update should override it, storing the hash
+    /// as one value in the [`encode_fields`] layout unless they also override
+    /// the reads.
+    fn set_fields(
+        self: Arc<Self>, key: String, _fields: Vec<(String, Vec<u8>)>,
+    ) -> FutureResult<u32> {
+        let name = self.name().to_string();
+        async move {
+            bail!("bucket `{name}` cannot set fields of `{key}`: the store has no hashes")
+        }
+        .boxed()
+    }
+
+    /// Get one field of the hash stored under `key`.
+    fn get_field(&self, key: String, field: String) -> FutureResult<Option<Vec<u8>>> {
+        let value = self.get(key);
+        async move {
+            let Some(value) = value.await? else {
+                return Ok(None);
+            };
+            Ok(decode_fields(&value)?.remove(&field))
+        }
+        .boxed()
+    }
+
+    /// Get every field of the hash stored under `key`, ordered by name.
+    fn get_all_fields(&self, key: String) -> FutureResult<Vec<(String, Vec<u8>)>> {
+        let value = self.get(key);
+        async move {
+            let Some(value) = value.await? else {
+                return Ok(Vec::new());
+            };
+            Ok(decode_fields(&value)?.into_iter().collect())
+        }
+        .boxed()
+    }
+
+    /// Delete fields of the hash stored under `key`, deleting the hash once
+    /// it is empty, and return how many fields were deleted.
+    ///
+    /// The default reports that the store cannot, for the same reason as
+    /// [`set_fields`](Self::set_fields).
+    fn delete_fields(self: Arc<Self>, key: String, _fields: Vec<String>) -> FutureResult<u32> {
+        let name = self.name().to_string();
+        async move {
+            bail!("bucket `{name}` cannot delete fields of `{key}`: the store has no hashes")
+        }
+        .boxed()
+    }
+
+    /// Watch the keys starting with `prefix` (every key, when empty) and
+    /// stream their changes from now on.
+    ///
+    /// The default reports that the store cannot watch keys; backends with
+    /// change notifications should override it.
+    fn watch(&self, prefix: String) -> FutureResult<Changes> {
+        let name = self.name().to_string();
+        async move { bail!("bucket `{name}` cannot watch `{prefix}`: the store has no change feed") }
+            .boxed()
+    }
+}
+
+/// A change to one watched key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// The key that changed.
+    pub key: String,
+
+    /// The key's new value, or `None` if it was deleted or expired.
+    pub value: Option<Vec<u8>>,
+}
+
+/// Feed of changes handed to the guest by `watch`.
+///
+/// The stream is shared behind a lock so a `next` call can await it without
+/// holding the resource table.
+#[derive(Clone)]
+pub struct Feed(pub Arc<Mutex<Changes>>);
+
+impl Debug for Feed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Feed").finish_non_exhaustive()
+    }
 }
 
 /// Proxy for a Key-Value bucket.
@@ -50,3 +212,139 @@ pub struct Cas {
     /// The current value associated with the key.
     pub current: Option<Vec<u8>>,
 }
+
+/// Encode a hash as one value: for each field in name order, the name's
+/// length as a big-endian `u32`, the name, the value's length as a big-endian
+/// `u32`, and the value.
+#[must_use]
+pub fn encode_fields(hash: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (name, value) in hash {
+        for part in [name.as_bytes(), value.as_slice()] {
+            bytes.extend(u32::try_from(part.len()).unwrap_or(u32::MAX).to_be_bytes());
+            bytes.extend(part);
+        }
+    }
+    bytes
+}
+
+/// Decode a hash written by [`encode_fields`].
+///
+/// # Errors
+///
+/// Returns an error if `bytes` is not in the [`encode_fields`] layout, for
+/// example because the key holds a plain value.
+pub fn decode_fields(bytes: &[u8]) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut hash = BTreeMap::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let name = take_part(&mut rest)?;
+        let value = take_part(&mut rest)?;
+        let Ok(name) = String::from_utf8(name.to_vec()) else {
+            bail!("hash field name is not UTF-8");
+        };
+        hash.insert(name, value.to_vec());
+    }
+    Ok(hash)
+}
+
+fn take_part<'a>(rest: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let Some((len, tail)) = rest.split_first_chunk::<4>() else {
+        bail!("value is not a hash: truncated field length");
+    };
+    let len = u32::from_be_bytes(*len) as usize;
+    let Some((part, tail)) = tail.split_at_checked(len) else {
+        bail!("value is not a hash: truncated field");
+    };
+    *rest = tail;
+    Ok(part)
+}
+
+/// The counter a value holds: eight bytes read as a big-endian `i64`.
+///
+/// # Errors
+///
+/// Returns an error if `value` is not eight bytes long, for example because
+/// the key holds a plain value.
+pub fn counter_value(value: &[u8]) -> Result<i64> {
+    let Ok(bytes) = <[u8; 8]>::try_from(value) else {
+        bail!("value is not a counter: {} bytes rather than 8", value.len());
+    };
+    Ok(i64::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bucket with only the required methods, so every expiring or atomic
+    /// operation falls back to the trait's default.
+    #[derive(Debug, Default)]
+    struct Plain(std::sync::Mutex<BTreeMap<String, Vec<u8>>>);
+
+    impl Bucket for Plain {
+        fn name(&self) -> &'static str {
+            "plain"
+        }
+
+        fn get(&self, key: String) -> FutureResult<Option<Vec<u8>>> {
+            let value = self.0.lock().unwrap().get(&key).cloned();
+            async move { Ok(value) }.boxed()
+        }
+
+        fn set(&self, key: String, value: Vec<u8>) -> FutureResult<()> {
+            self.0.lock().unwrap().insert(key, value);
+            async { Ok(()) }.boxed()
+        }
+
+        fn delete(&self, key: String) -> FutureResult<()> {
+            self.0.lock().unwrap().remove(&key);
+            async { Ok(()) }.boxed()
+        }
+
+        fn exists(&self, key: String) -> FutureResult<bool> {
+            let exists = self.0.lock().unwrap().contains_key(&key);
+            async move { Ok(exists) }.boxed()
+        }
+
+        fn keys(&self) -> FutureResult<Vec<String>> {
+            let keys = self.0.lock().unwrap().keys().cloned().collect();
+            async move { Ok(keys) }.boxed()
+        }
+    }
+
+    // Regression: a `Cache::set` with a TTL failed on stores without
+    // key-level expiry, although its envelope enforces the deadline anyway.
+    #[tokio::test]
+    async fn ttls_fall_back_to_a_plain_set() {
+        let bucket = Plain::default();
+        let ttl = Duration::from_secs(60);
+        bucket.set_with_ttl("session".to_string(), vec![1], ttl).await.unwrap();
+        assert_eq!(bucket.get("session".to_string()).await.unwrap(), Some(vec![1]));
+    }
+
+    #[tokio::test]
+    async fn atomic_operations_are_refused_by_default() {
+        let bucket = Arc::new(Plain::default());
+        Arc::clone(&bucket).increment("hits".to_string(), 1).await.unwrap_err();
+        Arc::clone(&bucket).compare_and_swap("v".to_string(), None, vec![1]).await.unwrap_err();
+        let ttl = Duration::from_secs(60);
+        let swap =
+            Arc::clone(&bucket).compare_and_swap_with_ttl("v".to_string(), None, vec![1], ttl);
+        swap.await.unwrap_err();
+        Arc::clone(&bucket).get_and_delete("token".to_string()).await.unwrap_err();
+        let fields = vec![("lat".to_string(), vec![1])];
+        Arc::clone(&bucket).set_fields("vehicle".to_string(), fields).await.unwrap_err();
+        Arc::clone(&bucket)
+            .delete_fields("vehicle".to_string(), vec!["lat".to_string()])
+            .await
+            .unwrap_err();
+    }
+
+    #[test]
+    fn counters_are_eight_bytes() {
+        assert_eq!(counter_value(&(-3_i64).to_be_bytes()).unwrap(), -3);
+        counter_value(&[1]).unwrap_err();
+        counter_value(&[0; 9]).unwrap_err();
+    }
+}
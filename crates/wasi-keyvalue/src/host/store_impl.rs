@@ -1,18 +1,35 @@
+use std::sync::Arc;
+
 use anyhow::Context;
 use wasmtime::component::{Access, Accessor, Resource};
 
 use crate::host::generated::wasi::keyvalue::store::{
     Error, HostBucketWithStore, HostWithStore, KeyResponse,
 };
-use crate::host::resource::BucketProxy;
+use crate::host::policy::{self, Permission, ReadOnly};
+use crate::host::resource::{Bucket, BucketProxy};
 use crate::host::store::{Host, HostBucket};
 use crate::host::{Result, WasiKeyValue, WasiKeyValueCtxView};
 
 impl<T> HostWithStore<T> for WasiKeyValue {
+    /// Open a bucket, subject to the `KEYVALUE_POLICY` rules for the calling
+    /// guest.
     async fn open(
         accessor: &Accessor<T, Self>, identifier: String,
     ) -> Result<Resource<BucketProxy>> {
+        let guest = accessor.with(|mut store| store.get().guest.cloned());
+        let permission = policy::permission(&identifier, guest.as_ref());
+        if permission == Permission::Deny {
+            tracing::warn!(bucket = %identifier, guest = ?guest, "bucket access denied");
+            return Err(Error::AccessDenied);
+        }
+
         let bucket = accessor.with(|mut store| store.get().ctx.open_bucket(identifier)).await?;
+        let bucket = if permission == Permission::Read {
+            Arc::new(ReadOnly::new(bucket, guest.as_ref())) as Arc<dyn Bucket>
+        } else {
+            bucket
+        };
         let proxy = BucketProxy(bucket);
         Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
     }
@@ -15,5 +15,6 @@ mod generated {
 }
 
 pub use self::generated::exports::wasi::messaging::*;
+pub use self::generated::omnia::messaging::*;
 pub use self::generated::wasi::messaging::*;
 pub use self::generated::*;
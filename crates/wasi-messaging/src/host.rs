@@ -1,8 +1,11 @@
+mod batch_impl;
 mod default_impl;
 mod producer_impl;
 mod request_reply_impl;
 mod resource;
+mod schema;
 mod server;
+mod settlement_impl;
 mod types_impl;
 
 mod generated {
@@ -43,6 +46,7 @@ use wasmtime::component::{HasData, Linker};
 
 pub use self::default_impl::MessagingDefault;
 pub use self::generated::MessagingRequestReply;
+use self::generated::omnia::messaging::{batch, settlement};
 pub use self::generated::wasi::messaging::types::Error;
 use self::generated::wasi::messaging::{producer, request_reply, types};
 pub use self::resource::*;
@@ -63,8 +67,11 @@ where
     T: WasiMessagingView + 'static,
 {
     fn add_to_linker(linker: &mut Linker<T>) -> anyhow::Result<()> {
+        schema::validate()?;
         producer::add_to_linker::<_, Self>(linker, T::messaging)?;
         request_reply::add_to_linker::<_, Self>(linker, T::messaging)?;
+        batch::add_to_linker::<_, Self>(linker, T::messaging)?;
+        settlement::add_to_linker::<_, Self>(linker, T::messaging)?;
         Ok(types::add_to_linker::<_, Self>(linker, T::messaging)?)
     }
 }
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use wasmtime::component::{Accessor, Resource};
+
+use crate::host::generated::omnia::messaging::batch::{Host, HostWithStore, Topic};
+use crate::host::resource::{ClientProxy, Message};
+use crate::host::types_impl::{get_client, get_message};
+use crate::host::{Result, WasiMessaging, WasiMessagingCtxView, schema};
+
+impl<T> HostWithStore<T> for WasiMessaging {
+    async fn send_batch(
+        accessor: &Accessor<T, Self>, c: Resource<ClientProxy>, topic: Topic,
+        messages: Vec<Resource<Message>>,
+    ) -> Result<()> {
+        let client = get_client(accessor, &c)?;
+        let batch = messages
+            .iter()
+            .map(|message| get_message(accessor, message))
+            .collect::<Result<Vec<_>>>()?;
+        // Check the whole batch first, so a bad message publishes none of it.
+        for message in &batch {
+            schema::check(&topic, &message.payload)?;
+        }
+        Arc::clone(&client.0).send_batch(topic, batch).await?;
+
+        Ok(())
+    }
+}
+
+impl Host for WasiMessagingCtxView<'_> {}
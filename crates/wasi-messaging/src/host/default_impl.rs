@@ -4,19 +4,28 @@
 //! fan out over a 32-slot [`tokio::sync::broadcast`] channel: a subscriber
 //! that falls more than 32 messages behind silently loses the overwritten
 //! messages (the lag error is filtered out of the subscription stream).
+//!
+//! The last 1024 messages sent to each topic are retained for replay.
 
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
 use anyhow::{Result, anyhow};
 use futures::FutureExt;
-use futures::stream::StreamExt;
+use futures::stream::{self, StreamExt};
 use omnia::Backend;
+use parking_lot::Mutex;
 use tokio::sync::broadcast::{self, Receiver, Sender};
 use tokio_stream::wrappers::BroadcastStream;
 use tracing::instrument;
 
 use crate::host::WasiMessagingCtx;
-use crate::host::resource::{Client, FutureResult, Message, RequestOptions, Subscriptions};
+use crate::host::resource::{
+    Client, FutureResult, Message, RequestOptions, Retention, Subscriptions,
+};
+
+/// Messages retained per topic for replay.
+const RETAINED: usize = 1024;
 
 /// Options used to connect to the messaging system.
 #[derive(Debug, Clone, Default)]
@@ -33,6 +42,15 @@ impl omnia::FromEnv for ConnectOptions {
 pub struct MessagingDefault {
     sender: Sender<Message>,
     receiver: Receiver<Message>,
+    history: Arc<Mutex<HashMap<String, TopicLog>>>,
+}
+
+/// The retained tail of one topic.
+#[derive(Debug, Default)]
+struct TopicLog {
+    /// Offset the next message sent to the topic will get.
+    next: u64,
+    messages: VecDeque<Message>,
 }
 
 impl Clone for MessagingDefault {
@@ -40,7 +58,21 @@ impl Clone for MessagingDefault {
         Self {
             sender: self.sender.clone(),
             receiver: self.sender.subscribe(),
+            history: Arc::clone(&self.history),
+        }
+    }
+}
+
+impl MessagingDefault {
+    fn retain(&self, message: &Message) {
+        let mut history = self.history.lock();
+        let log = history.entry(message.topic.clone()).or_default();
+        if log.messages.len() == RETAINED {
+            log.messages.pop_front();
         }
+        log.messages.push_back(message.clone());
+        log.next += 1;
+        drop(history);
     }
 }
 
@@ -51,7 +83,11 @@ impl Backend for MessagingDefault {
     async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
         tracing::debug!("initializing in-memory messaging");
         let (sender, receiver) = broadcast::channel::<Message>(32);
-        Ok(Self { sender, receiver })
+        Ok(Self {
+            sender,
+            receiver,
+            history: Arc::default(),
+        })
     }
 }
 
@@ -77,10 +113,11 @@ impl Client for MessagingDefault {
 
     fn send(&self, topic: String, mut message: Message) -> FutureResult<()> {
         tracing::debug!("sending message to topic: {topic}");
+        message.topic = topic;
+        self.retain(&message);
         let sender = self.sender.clone();
 
         async move {
-            message.topic = topic;
             sender.send(message).map_err(|e| anyhow!("send error: {e}"))?;
             Ok(())
         }
@@ -109,6 +146,25 @@ impl Client for MessagingDefault {
         }
         .boxed()
     }
+
+    fn retention(&self, topic: String) -> FutureResult<Option<Retention>> {
+        let retention = self.history.lock().get(&topic).map(|log| Retention {
+            earliest: log.next - log.messages.len() as u64,
+            latest: log.next,
+        });
+        async move { Ok(retention) }.boxed()
+    }
+
+    fn replay(&self, topic: String) -> FutureResult<Subscriptions> {
+        tracing::debug!("replaying topic: {topic}");
+        let messages: Vec<Message> = self
+            .history
+            .lock()
+            .get(&topic)
+            .map(|log| log.messages.iter().cloned().collect())
+            .unwrap_or_default();
+        async move { Ok(Box::pin(stream::iter(messages)) as Subscriptions) }.boxed()
+    }
 }
 
 #[cfg(test)]
@@ -131,4 +187,28 @@ mod tests {
 
         assert_eq!(reply.payload, b"ACK");
     }
+
+    #[tokio::test]
+    async fn replay_retained_messages() {
+        let backend = <MessagingDefault as Backend>::connect().await.expect("connect");
+        let client = WasiMessagingCtx::connect(&backend).await.expect("client");
+
+        for payload in [b"one", b"two"] {
+            client.send("fleet".to_string(), Message::new(payload.to_vec())).await.expect("send");
+        }
+
+        let retention = client.retention("fleet".to_string()).await.expect("retention");
+        assert_eq!(
+            retention,
+            Some(Retention {
+                earliest: 0,
+                latest: 2
+            })
+        );
+        assert_eq!(client.retention("other".to_string()).await.expect("retention"), None);
+
+        let replayed = client.replay("fleet".to_string()).await.expect("replay");
+        let payloads: Vec<_> = replayed.map(|message| message.payload).collect().await;
+        assert_eq!(payloads, [b"one".to_vec(), b"two".to_vec()]);
+    }
 }
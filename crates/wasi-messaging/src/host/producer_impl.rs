@@ -4,7 +4,7 @@ use crate::host::generated::wasi::messaging::producer::{Host, HostWithStore};
 use crate::host::generated::wasi::messaging::types::Topic;
 use crate::host::resource::{ClientProxy, Message};
 use crate::host::types_impl::{get_client, get_message};
-use crate::host::{Result, WasiMessaging, WasiMessagingCtxView};
+use crate::host::{Result, WasiMessaging, WasiMessagingCtxView, schema};
 
 impl<T> HostWithStore<T> for WasiMessaging {
     async fn send(
@@ -13,6 +13,7 @@ impl<T> HostWithStore<T> for WasiMessaging {
     ) -> Result<()> {
         let client = get_client(accessor, &c)?;
         let msg = get_message(accessor, &message)?;
+        schema::check(&topic, &msg.payload)?;
         client.send(topic, msg).await?;
 
         Ok(())
@@ -8,7 +8,7 @@ use crate::host::generated::wasi::messaging::request_reply::{
 use crate::host::generated::wasi::messaging::types::Topic;
 use crate::host::resource::{ClientProxy, Message, RequestOptions};
 use crate::host::types_impl::{get_client, get_message};
-use crate::host::{Result, WasiMessaging, WasiMessagingCtxView};
+use crate::host::{Result, WasiMessaging, WasiMessagingCtxView, schema};
 
 impl<T> HostWithStore<T> for WasiMessaging {
     async fn request(
@@ -17,6 +17,7 @@ impl<T> HostWithStore<T> for WasiMessaging {
     ) -> Result<Vec<Resource<Message>>> {
         let client = get_client(accessor, &c)?;
         let request = get_message(accessor, &message)?;
+        schema::check(&topic, &request.payload)?;
         let options = accessor.with(|mut access| {
             let options = if let Some(opts) = options {
                 let options = access.get().table.get(&opts)?;
@@ -3,10 +3,14 @@ use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use futures::Stream;
+use anyhow::bail;
+use futures::{FutureExt, Stream, stream};
 pub use omnia::FutureResult;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 
 use crate::host::generated::wasi::messaging::types;
 /// Stream of messages.
@@ -20,10 +24,135 @@ pub trait Client: Debug + Send + Sync + 'static {
     /// Send a message to a topic.
     fn send(&self, topic: String, message: Message) -> FutureResult<()>;
 
+    /// Send a batch of messages to a topic, in order.
+    ///
+    /// The default sends them one at a time; backends with a batching
+    /// producer should override it to hand the broker the whole batch.
+    fn send_batch(self: Arc<Self>, topic: String, messages: Vec<Message>) -> FutureResult<()> {
+        async move {
+            for message in messages {
+                self.send(topic.clone(), message).await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
     /// Request a response from a topic.
     fn request(
         &self, topic: String, message: Message, options: Option<RequestOptions>,
     ) -> FutureResult<Message>;
+
+    /// The range of messages the backend retains for `topic`, or `None` if it
+    /// retains none.
+    fn retention(&self, _topic: String) -> FutureResult<Option<Retention>> {
+        async { Ok(None) }.boxed()
+    }
+
+    /// Replay `topic` from its earliest retained message up to the latest
+    /// message published when the replay starts, then end.
+    ///
+    /// The default yields nothing, for backends that do not retain messages.
+    fn replay(&self, _topic: String) -> FutureResult<Subscriptions> {
+        async { Ok(Box::pin(stream::empty()) as Subscriptions) }.boxed()
+    }
+
+    /// Subscribe as a member of `group`, so replicas sharing the group id
+    /// split the messages between them instead of each receiving all of them.
+    ///
+    /// The default ignores the group and calls [`subscribe`](Self::subscribe),
+    /// for backends that deliver every message to every subscriber.
+    fn subscribe_group(&self, group: ConsumerGroup) -> FutureResult<Subscriptions> {
+        tracing::debug!(group = %group.id, "backend has no consumer groups; subscribing to all");
+        self.subscribe()
+    }
+
+    /// Record that `message`, received through
+    /// [`subscribe_group`](Self::subscribe_group), has been consumed, so the
+    /// group does not redeliver it.
+    ///
+    /// The default does nothing, for backends that do not track offsets.
+    fn commit(&self, _message: Message) -> FutureResult<()> {
+        async { Ok(()) }.boxed()
+    }
+
+    /// Hand `message` back for redelivery, because its handler rejected it
+    /// with `nack(requeue)`.
+    ///
+    /// The default does nothing, so a backend that redelivers uncommitted
+    /// messages does so on its own schedule, and one that does not drops it.
+    fn requeue(&self, _message: Message) -> FutureResult<()> {
+        async { Ok(()) }.boxed()
+    }
+
+    /// Keep `message` from being redelivered for at least `timeout`, because
+    /// its handler is still working on it, such as by extending an SQS
+    /// visibility timeout or renewing a lock.
+    ///
+    /// The default does nothing, for backends that never redeliver a message
+    /// still being handled.
+    fn extend(&self, _message: Message, _timeout: Duration) -> FutureResult<()> {
+        async { Ok(()) }.boxed()
+    }
+}
+
+/// Consumer-group membership for the host's subscription.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsumerGroup {
+    /// Group id; replicas with the same id share one subscription.
+    pub id: String,
+    /// Where a group with no committed offset starts consuming.
+    pub start: StartOffset,
+    /// When the host commits a message.
+    pub commit: CommitStrategy,
+}
+
+/// Where a new consumer group starts consuming.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartOffset {
+    /// From the earliest retained message.
+    Earliest,
+    /// From messages published after the group subscribes.
+    #[default]
+    Latest,
+}
+
+/// When the host commits a consumed message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommitStrategy {
+    /// After the handler succeeds, so a failed or interrupted message is
+    /// redelivered (at least once).
+    #[default]
+    AfterHandle,
+    /// As soon as the message is received, so it is never redelivered (at
+    /// most once).
+    OnReceive,
+    /// Never; the backend commits on its own schedule, such as Kafka's
+    /// auto-commit interval.
+    Backend,
+}
+
+/// The messages a backend retains for a topic, as offsets in publish order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// Offset of the earliest retained message.
+    pub earliest: u64,
+    /// Offset one past the latest published message.
+    pub latest: u64,
+}
+
+impl Retention {
+    /// The number of messages a replay would deliver.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.latest.saturating_sub(self.earliest)
+    }
+
+    /// Whether a replay would deliver nothing.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Proxy for a messaging client.
@@ -56,6 +185,9 @@ pub struct Message {
     pub description: Option<String>,
     /// Optional reply topic to which a response can be published.
     pub reply: Option<Reply>,
+    /// How the handler settled the message, for a message delivered to a
+    /// handler.
+    pub settlement: Option<Arc<Settlement>>,
 }
 
 impl Message {
@@ -69,6 +201,93 @@ impl Message {
     }
 }
 
+/// How a handler settled a message delivered to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disposition {
+    /// Processed; commit it.
+    Ack,
+    /// Not processed; redeliver it when `requeue`, otherwise dead-letter it.
+    Nack {
+        /// Whether the backend should redeliver it.
+        requeue: bool,
+    },
+}
+
+/// The settlement of one delivered message, shared between the host's
+/// delivery loop and the handler that received it.
+#[derive(Debug)]
+pub struct Settlement {
+    disposition: Mutex<Option<Disposition>>,
+    deadline: watch::Sender<Instant>,
+}
+
+impl Default for Settlement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Settlement {
+    /// An unsettled delivery whose deadline is now, until the handler starts.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            disposition: Mutex::new(None),
+            deadline: watch::Sender::new(Instant::now()),
+        }
+    }
+
+    /// How the handler settled the message, if it has.
+    #[must_use]
+    pub fn disposition(&self) -> Option<Disposition> {
+        *self.disposition.lock()
+    }
+
+    /// Record the handler's `disposition`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is already settled.
+    pub fn settle(&self, disposition: Disposition) -> anyhow::Result<()> {
+        let mut settled = self.disposition.lock();
+        if let Some(existing) = *settled {
+            bail!("message is already settled ({existing:?})");
+        }
+        *settled = Some(disposition);
+        drop(settled);
+        Ok(())
+    }
+
+    /// When the current handling attempt times out.
+    #[must_use]
+    pub fn deadline(&self) -> Instant {
+        *self.deadline.borrow()
+    }
+
+    /// Start a handling attempt that times out at `deadline`.
+    pub fn start(&self, deadline: Instant) {
+        self.deadline.send_replace(deadline);
+    }
+
+    /// Move the deadline to at least `timeout` from now.
+    pub fn extend(&self, timeout: Duration) {
+        let until = Instant::now() + timeout;
+        self.deadline.send_if_modified(|deadline| {
+            let later = until > *deadline;
+            if later {
+                *deadline = until;
+            }
+            later
+        });
+    }
+
+    /// Watch the deadline as attempts start and the handler extends it.
+    #[must_use]
+    pub fn watch(&self) -> watch::Receiver<Instant> {
+        self.deadline.subscribe()
+    }
+}
+
 /// Metadata associated with a message.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Metadata {
@@ -0,0 +1,193 @@
+//! Per-topic payload schemas.
+//!
+//! `MESSAGING_SCHEMAS` maps topics to JSON Schema documents, as
+//! comma-separated `topic=path` entries, and the host checks every payload a
+//! guest publishes to one of those topics against its schema. Topics without a
+//! schema are not checked.
+//!
+//! `MESSAGING_SCHEMA_MODE` decides what happens to a non-conforming payload:
+//! `reject` (the default) fails the publish with an `other` error
+//! starting `invalid message:`, and
+//! `flag` publishes it anyway, logging the violation and counting it in the
+//! `schema_violations` metric.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::{fmt, fs};
+
+use anyhow::{Context, Result, anyhow, bail};
+use jsonschema::Validator;
+use serde_json::Value;
+
+use crate::host::Error;
+
+static SCHEMAS: LazyLock<Result<Schemas, String>> = LazyLock::new(|| {
+    let spec = std::env::var("MESSAGING_SCHEMAS").unwrap_or_default();
+    let mode = std::env::var("MESSAGING_SCHEMA_MODE").unwrap_or_default();
+    Schemas::load(&spec, &mode).map_err(|e| format!("{e:#}"))
+});
+
+/// Check the configured schemas load and compile, so a bad schema fails
+/// startup instead of every publish.
+///
+/// # Errors
+///
+/// Returns an error if `MESSAGING_SCHEMAS` or `MESSAGING_SCHEMA_MODE` is
+/// malformed, or a schema cannot be read or compiled.
+pub fn validate() -> Result<()> {
+    SCHEMAS.as_ref().map(|_| ()).map_err(|e| anyhow!("invalid messaging schemas: {e}"))
+}
+
+/// Check `payload`, about to be published to `topic`, against the topic's
+/// configured schema.
+///
+/// # Errors
+///
+/// Returns an [`Error::Other`] starting `invalid message:` if the payload
+/// does not conform and non-conforming payloads are rejected.
+pub fn check(topic: &str, payload: &[u8]) -> Result<(), Error> {
+    match SCHEMAS.as_ref() {
+        Ok(schemas) => schemas.check(topic, payload),
+        Err(e) => Err(Error::Other(format!("invalid messaging schemas: {e}"))),
+    }
+}
+
+/// What the host does with a payload that does not match its topic's schema.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Fail the publish.
+    #[default]
+    Reject,
+    /// Publish it, logging and counting the violation.
+    Flag,
+}
+
+/// Compiled schemas by topic.
+#[derive(Default)]
+pub struct Schemas {
+    mode: Mode,
+    topics: HashMap<String, Validator>,
+}
+
+impl fmt::Debug for Schemas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schemas")
+            .field("mode", &self.mode)
+            .field("topics", &self.topics.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Schemas {
+    /// Load comma-separated `topic=path` schema entries from disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not of that form, `mode` is not
+    /// `reject` or `flag`, or a schema cannot be read or compiled.
+    pub fn load(spec: &str, mode: &str) -> Result<Self> {
+        let mode = match mode {
+            "" | "reject" => Mode::Reject,
+            "flag" => Mode::Flag,
+            other => bail!("MESSAGING_SCHEMA_MODE must be `reject` or `flag`, not `{other}`"),
+        };
+        let mut schemas = Self {
+            mode,
+            topics: HashMap::new(),
+        };
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let Some((topic, path)) = entry.split_once('=') else {
+                bail!("schema entry `{entry}` is not `topic=path`");
+            };
+            let path = path.trim();
+            let document = fs::read_to_string(path)
+                .with_context(|| format!("reading schema `{path}` for topic `{topic}`"))?;
+            let schema: Value = serde_json::from_str(&document)
+                .with_context(|| format!("schema `{path}` is not valid JSON"))?;
+            schemas.insert(topic.trim(), &schema)?;
+        }
+        Ok(schemas)
+    }
+
+    /// Check payloads published to `topic` against `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `schema` is not a valid JSON Schema.
+    pub fn insert(&mut self, topic: &str, schema: &Value) -> Result<()> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| anyhow!("schema for topic `{topic}` is not a valid JSON Schema: {e}"))?;
+        self.topics.insert(topic.to_string(), validator);
+        Ok(())
+    }
+
+    /// Check `payload` against `topic`'s schema, if it has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::Other`] starting `invalid message:` if the payload
+    /// does not conform and non-conforming payloads are rejected.
+    pub fn check(&self, topic: &str, payload: &[u8]) -> Result<(), Error> {
+        let Some(validator) = self.topics.get(topic) else {
+            return Ok(());
+        };
+        let violation = match serde_json::from_slice::<Value>(payload) {
+            Ok(value) => validator
+                .iter_errors(&value)
+                .next()
+                .map(|error| format!("payload for `{topic}` does not match its schema: {error}")),
+            Err(e) => Some(format!("payload for `{topic}` is not JSON: {e}")),
+        };
+        let Some(violation) = violation else {
+            return Ok(());
+        };
+
+        match self.mode {
+            Mode::Reject => Err(Error::Other(format!("invalid message: {violation}"))),
+            Mode::Flag => {
+                tracing::warn!(monotonic_counter.schema_violations = 1, %topic, "{violation}");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schemas(mode: &str) -> Schemas {
+        let mut schemas = Schemas::load("", mode).unwrap();
+        let schema = json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {"id": {"type": "integer"}},
+        });
+        schemas.insert("orders.created", &schema).unwrap();
+        schemas
+    }
+
+    #[test]
+    fn non_conforming_payloads_are_rejected() {
+        let schemas = schemas("");
+        schemas.check("orders.created", br#"{"id":1}"#).unwrap();
+        schemas.check("orders.cancelled", b"not json").unwrap();
+
+        let error = schemas.check("orders.created", br#"{"id":"one"}"#).unwrap_err();
+        assert!(matches!(error, Error::Other(m) if m.contains("does not match")));
+        let error = schemas.check("orders.created", b"not json").unwrap_err();
+        assert!(matches!(error, Error::Other(m) if m.contains("not JSON")));
+    }
+
+    #[test]
+    fn flagged_payloads_are_published() {
+        let schemas = schemas("flag");
+        schemas.check("orders.created", br#"{"id":"one"}"#).unwrap();
+
+        Schemas::load("", "warn").unwrap_err();
+        Schemas::load("orders.created", "").unwrap_err();
+        Schemas::load("orders.created=/does/not/exist.json", "").unwrap_err();
+    }
+}
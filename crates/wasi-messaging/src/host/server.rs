@@ -1,14 +1,26 @@
 use std::env;
+use std::pin::pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use futures::StreamExt;
-use omnia::{PatternRoutes, Runtime, StoreCtx, TriggerRouter};
+use omnia::{Crash, PatternRoutes, Quarantined, Runtime, StoreCtx, TriggerRouter};
+use tokio::sync::watch;
 use tracing::{Instrument, debug_span, instrument};
 
 use crate::host::WasiMessagingView;
 use crate::host::generated::MessagingRequestReplyIndices;
-use crate::host::resource::{Message, Subscriptions};
+use crate::host::resource::{
+    Client, CommitStrategy, ConsumerGroup, Disposition, Message, Metadata, Settlement, StartOffset,
+    Subscriptions,
+};
+
+/// Metadata key marking a message delivered by the bootstrap replay.
+const REPLAY_METADATA: &str = "omnia-replay";
+
+/// Suffix of the topic a poisoned message is dead-lettered to.
+const DEAD_LETTER_SUFFIX: &str = ".dlq";
 
 #[instrument("messaging-server", skip(state))]
 pub async fn run<B>(state: &Runtime<B>) -> Result<()>
@@ -33,27 +45,93 @@ where
         return Ok(());
     }
 
+    let group = consumer_group(&component, |name| env::var(name).ok())?;
+    tracing::info!(
+        group = %group.id,
+        start = ?group.start,
+        commit = ?group.commit,
+        "joining consumer group",
+    );
+    let strategy = group.commit;
+    let retry = retry_policy(|name| env::var(name).ok())?;
+
     let handler = Handler {
         state: state.clone(),
         component,
         routing: Arc::new(routing),
     };
-    let mut stream = handler.subscriptions().await?;
+    // Subscribe before replaying so nothing published during the replay is
+    // missed; a message can then arrive through both and be handled twice.
+    let (client, mut stream) = handler.subscriptions(group).await?;
+    handler.bootstrap().await?;
 
-    while let Some(message) = stream.next().await {
+    while let Some(mut message) = stream.next().await {
         let handler = handler.clone();
+        let client = Arc::clone(&client);
         tokio::spawn(async move {
             tracing::info!(monotonic_counter.message_counter = 1, service = %handler.component);
 
             let topic = message.topic.clone();
-            if let Err(e) = handler.handle(message).await {
-                tracing::error!("issue processing message: {e}");
-                tracing::error!(
-                    monotonic_counter.processing_errors = 1,
-                    service = %handler.component,
-                    topic = %topic,
-                    error = %e,
-                );
+            let settlement = Arc::new(Settlement::new());
+            message.settlement = Some(Arc::clone(&settlement));
+            let mut receipt = (strategy != CommitStrategy::Backend).then(|| message.clone());
+            if strategy == CommitStrategy::OnReceive
+                && let Some(receipt) = receipt.take()
+            {
+                commit(client.as_ref(), receipt).await;
+            }
+
+            // Tell the backend whenever the handler's deadline moves, so it
+            // does not redeliver a message that is still being handled.
+            let extender =
+                tokio::spawn(extend(Arc::clone(&client), message.clone(), settlement.watch()));
+            let (attempts, outcome) = handler.handle_with_retries(&message, retry).await;
+            extender.abort();
+
+            // The guest never saw a message refused by quarantine, so it is
+            // left uncommitted for redelivery once the guest is released.
+            if let Err(e) = &outcome
+                && let Some(quarantined) = e.downcast_ref::<Quarantined>()
+            {
+                tracing::warn!(%topic, %quarantined, "leaving message unconsumed");
+                return;
+            }
+
+            let error = match (settlement.disposition(), outcome) {
+                (Some(Disposition::Ack), _) | (None, Ok(())) => {
+                    if let Some(receipt) = receipt {
+                        commit(client.as_ref(), receipt).await;
+                    }
+                    return;
+                }
+                (Some(Disposition::Nack { requeue: true }), _) => {
+                    tracing::info!(%topic, "handler requeued message");
+                    if let Err(e) = client.requeue(message).await {
+                        tracing::warn!(%topic, error = %e, "issue requeuing message");
+                    }
+                    return;
+                }
+                (Some(Disposition::Nack { requeue: false }), outcome) => {
+                    outcome.err().unwrap_or_else(|| anyhow!("message rejected by handler"))
+                }
+                (None, Err(e)) => e,
+            };
+
+            tracing::error!("issue processing message: {error}");
+            tracing::error!(
+                monotonic_counter.processing_errors = 1,
+                service = %handler.component,
+                topic = %topic,
+                error = %error,
+            );
+            // A dead-lettered message is dealt with, so it is committed rather
+            // than redelivered.
+            if retry.dead_letter
+                && !topic.ends_with(DEAD_LETTER_SUFFIX)
+                && dead_letter(client.as_ref(), message, &error, attempts).await
+                && let Some(receipt) = receipt
+            {
+                commit(client.as_ref(), receipt).await;
             }
         });
     }
@@ -61,6 +139,130 @@ where
     Ok(())
 }
 
+/// The host's consumer group, from `MESSAGING_CONSUMER_GROUP` (default the
+/// component name), `MESSAGING_START_OFFSET` (`latest` or `earliest`), and
+/// `MESSAGING_COMMIT` (`after-handle`, `on-receive`, or `backend`).
+fn consumer_group(component: &str, var: impl Fn(&str) -> Option<String>) -> Result<ConsumerGroup> {
+    let id = var("MESSAGING_CONSUMER_GROUP")
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| component.to_string());
+    let start = match var("MESSAGING_START_OFFSET").as_deref() {
+        None | Some("" | "latest") => StartOffset::Latest,
+        Some("earliest") => StartOffset::Earliest,
+        Some(other) => {
+            bail!("MESSAGING_START_OFFSET must be `latest` or `earliest`, not `{other}`")
+        }
+    };
+    let commit = match var("MESSAGING_COMMIT").as_deref() {
+        None | Some("" | "after-handle") => CommitStrategy::AfterHandle,
+        Some("on-receive") => CommitStrategy::OnReceive,
+        Some("backend") => CommitStrategy::Backend,
+        Some(other) => bail!(
+            "MESSAGING_COMMIT must be `after-handle`, `on-receive`, or `backend`, not `{other}`"
+        ),
+    };
+    Ok(ConsumerGroup { id, start, commit })
+}
+
+/// How failed deliveries are retried, from `MESSAGING_RETRIES` (default 0),
+/// `MESSAGING_RETRY_DELAY_MS` (default 100, multiplied by the attempt), and
+/// `MESSAGING_DEAD_LETTER` (`true` or `false`, default `false`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Retry {
+    retries: u32,
+    delay: Duration,
+    dead_letter: bool,
+}
+
+fn retry_policy(var: impl Fn(&str) -> Option<String>) -> Result<Retry> {
+    let retries = match var("MESSAGING_RETRIES").filter(|v| !v.is_empty()) {
+        Some(retries) => retries
+            .parse()
+            .with_context(|| format!("MESSAGING_RETRIES must be a count, not `{retries}`"))?,
+        None => 0,
+    };
+    let delay_ms = match var("MESSAGING_RETRY_DELAY_MS").filter(|v| !v.is_empty()) {
+        Some(delay) => delay.parse().with_context(|| {
+            format!("MESSAGING_RETRY_DELAY_MS must be milliseconds, not `{delay}`")
+        })?,
+        None => 100,
+    };
+    let dead_letter = match var("MESSAGING_DEAD_LETTER").as_deref() {
+        Some("true") => true,
+        None | Some("" | "false") => false,
+        Some(other) => bail!("MESSAGING_DEAD_LETTER must be `true` or `false`, not `{other}`"),
+    };
+    Ok(Retry {
+        retries,
+        delay: Duration::from_millis(delay_ms),
+        dead_letter,
+    })
+}
+
+// Publish a message that exhausted its retries to `<topic>.dlq`, with the
+// failure in its metadata. Returns whether it was published.
+async fn dead_letter(
+    client: &dyn Client, mut message: Message, error: &anyhow::Error, attempts: u32,
+) -> bool {
+    let topic = message.topic.clone();
+    let dead_topic = format!("{topic}{DEAD_LETTER_SUFFIX}");
+    let metadata = message.metadata.get_or_insert_with(Metadata::new);
+    metadata.insert("omnia-dlq-topic".to_string(), topic.clone());
+    metadata.insert("omnia-dlq-error".to_string(), format!("{error:#}"));
+    metadata.insert("omnia-dlq-attempts".to_string(), attempts.to_string());
+    message.topic.clone_from(&dead_topic);
+    message.reply = None;
+    message.settlement = None;
+
+    match client.send(dead_topic.clone(), message).await {
+        Ok(()) => {
+            tracing::warn!(
+                monotonic_counter.dead_letters = 1,
+                %topic,
+                dlq = %dead_topic,
+                "message dead-lettered",
+            );
+            true
+        }
+        Err(e) => {
+            tracing::error!(%topic, dlq = %dead_topic, error = %e, "issue dead-lettering message");
+            false
+        }
+    }
+}
+
+// Forward each move of a delivery's deadline to the backend until aborted.
+async fn extend(client: Arc<dyn Client>, message: Message, mut deadline: watch::Receiver<Instant>) {
+    while deadline.changed().await.is_ok() {
+        let timeout = deadline.borrow_and_update().saturating_duration_since(Instant::now());
+        if let Err(e) = client.extend(message.clone(), timeout).await {
+            tracing::warn!(topic = %message.topic, error = %e, "issue extending message deadline");
+        }
+    }
+}
+
+// A failed commit is logged, not retried: the message may be redelivered.
+async fn commit(client: &dyn Client, message: Message) {
+    let topic = message.topic.clone();
+    if let Err(e) = client.commit(message).await {
+        tracing::warn!(%topic, error = %e, "issue committing message");
+    }
+}
+
+// Run a handler until it finishes or its deadline passes, following any
+// extensions it makes to the deadline meanwhile.
+async fn until_deadline<T>(run: impl Future<Output = T>, settlement: &Settlement) -> Result<T> {
+    let mut run = pin!(run);
+    loop {
+        let deadline = settlement.deadline();
+        match tokio::time::timeout_at(deadline.into(), &mut run).await {
+            Ok(output) => return Ok(output),
+            Err(_) if settlement.deadline() > deadline => {}
+            Err(_) => bail!("messaging handler timed out"),
+        }
+    }
+}
+
 #[derive(Clone)]
 struct Handler<B>
 where
@@ -87,45 +289,247 @@ where
             return Ok(());
         };
         let guest = self.state.registry().get(guest_id).expect("a capable guest is registered");
+        let settlement = message.settlement.clone();
+
+        // A crash-looping guest is quarantined rather than fed every event.
+        self.state
+            .supervise(guest_id, async {
+                let mut store_data = self.state.store();
+                let msg_res = store_data
+                    .messaging()
+                    .table
+                    .push(message)
+                    .map_err(|e| anyhow!("failed to push message: {e}"))?;
+
+                let mut store = self.state.build_store(store_data);
+                self.state.meter(guest_id, &mut store);
+                let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
+                let messaging = indices.load(&mut store, &instance)?;
 
-        let mut store_data = self.state.store();
-        let msg_res = store_data
-            .messaging()
-            .table
-            .push(message)
-            .map_err(|e| anyhow!("failed to push message: {e}"))?;
+                let run = store
+                    .run_concurrent(async |store| {
+                        let guest = messaging.wasi_messaging_incoming_handler();
+                        guest
+                            .call_handle(store, msg_res)
+                            .await
+                            .map(|_| ())
+                            .map_err(Crash::classify)
+                            .context("issue sending message")
+                    })
+                    .instrument(debug_span!("messaging-handle"));
+
+                let deadline = Instant::now() + self.state.options().guest_timeout;
+                match &settlement {
+                    Some(settlement) => {
+                        settlement.start(deadline);
+                        until_deadline(run, settlement).await??
+                    }
+                    None => tokio::time::timeout_at(deadline.into(), run)
+                        .await
+                        .context("messaging handler timed out")??,
+                }
+            })
+            .await
+    }
 
+    // Handle a message, retrying a failed attempt up to `retry.retries` times
+    // with a linearly growing delay, unless the handler settled it. Returns
+    // the number of attempts made and the last outcome. A quarantined guest
+    // is not retried.
+    async fn handle_with_retries(&self, message: &Message, retry: Retry) -> (u32, Result<()>) {
+        let mut attempt = 0;
+        loop {
+            match self.handle(message.clone()).await {
+                Err(e)
+                    if attempt < retry.retries
+                        && e.downcast_ref::<Quarantined>().is_none()
+                        && message
+                            .settlement
+                            .as_ref()
+                            .is_none_or(|s| s.disposition().is_none()) =>
+                {
+                    attempt += 1;
+                    tracing::warn!(topic = %message.topic, attempt, error = %e, "retrying message");
+                    tokio::time::sleep(retry.delay * attempt).await;
+                }
+                outcome => return (attempt + 1, outcome),
+            }
+        }
+    }
+
+    // Replay each `MESSAGING_BOOTSTRAP_TOPICS` topic from its earliest retained
+    // message, one message at a time and in order, so guests rebuild state
+    // before handling live traffic.
+    async fn bootstrap(&self) -> Result<()> {
+        let topics = env::var("MESSAGING_BOOTSTRAP_TOPICS").unwrap_or_default();
+        let topics: Vec<_> =
+            topics.split(',').map(str::trim).filter(|topic| !topic.is_empty()).collect();
+        if topics.is_empty() {
+            return Ok(());
+        }
+
+        for topic in topics {
+            let mut replay = self.replay(topic.to_string()).await?;
+            let mut replayed = 0_u64;
+            while let Some(mut message) = replay.next().await {
+                message
+                    .metadata
+                    .get_or_insert_with(Metadata::new)
+                    .insert(REPLAY_METADATA.to_string(), "true".to_string());
+                if let Err(e) = self.handle(message).await {
+                    tracing::error!(%topic, "issue replaying message: {e}");
+                    tracing::error!(
+                        monotonic_counter.processing_errors = 1,
+                        service = %self.component,
+                        topic = %topic,
+                        error = %e,
+                    );
+                }
+                replayed += 1;
+            }
+            tracing::info!(%topic, replayed, "bootstrap replay caught up");
+        }
+
+        tracing::info!(service = %self.component, "messaging bootstrap complete; consuming live");
+        Ok(())
+    }
+
+    // Replay a topic from the earliest message the backend retains.
+    async fn replay(&self, topic: String) -> Result<Subscriptions> {
+        let store_data = self.state.store();
         let mut store = self.state.build_store(store_data);
-        let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
-        let messaging = indices.load(&mut store, &instance)?;
 
-        let run = store
+        store
             .run_concurrent(async |store| {
-                let guest = messaging.wasi_messaging_incoming_handler();
-                guest
-                    .call_handle(store, msg_res)
-                    .await
-                    .map(|_| ())
-                    .map_err(anyhow::Error::from)
-                    .context("issue sending message")
+                let client = store.with(|mut store| store.get().messaging().ctx.connect()).await?;
+                if let Some(retention) = client.retention(topic.clone()).await? {
+                    tracing::info!(%topic, messages = retention.len(), "replaying retained messages");
+                }
+                client.replay(topic.clone()).await
             })
-            .instrument(debug_span!("messaging-handle"));
-
-        tokio::time::timeout(self.state.options().guest_timeout, run)
-            .await
-            .context("messaging handler timed out")??
+            .await?
     }
 
-    // Get subscriptions for the topics configured in the wasm component.
-    async fn subscriptions(&self) -> Result<Subscriptions> {
+    // Subscribe as a member of `group`, keeping the client to commit with.
+    async fn subscriptions(
+        &self, group: ConsumerGroup,
+    ) -> Result<(Arc<dyn Client>, Subscriptions)> {
         let store_data = self.state.store();
         let mut store = self.state.build_store(store_data);
 
         store
             .run_concurrent(async |store| {
                 let client = store.with(|mut store| store.get().messaging().ctx.connect()).await?;
-                client.subscribe().await
+                let stream = client.subscribe_group(group).await?;
+                Ok((client, stream))
             })
             .await?
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use omnia::Backend;
+
+    use super::*;
+    use crate::host::{MessagingDefault, WasiMessagingCtx};
+
+    #[test]
+    fn consumer_group_defaults_to_the_component() {
+        let group = consumer_group("fleet", |_| None).unwrap();
+        assert_eq!(
+            group,
+            ConsumerGroup {
+                id: "fleet".to_string(),
+                start: StartOffset::Latest,
+                commit: CommitStrategy::AfterHandle,
+            }
+        );
+
+        let group = consumer_group("fleet", |name| {
+            let value = match name {
+                "MESSAGING_CONSUMER_GROUP" => "fleet-eu",
+                "MESSAGING_START_OFFSET" => "earliest",
+                _ => "on-receive",
+            };
+            Some(value.to_string())
+        })
+        .unwrap();
+        assert_eq!(group.id, "fleet-eu");
+        assert_eq!(group.start, StartOffset::Earliest);
+        assert_eq!(group.commit, CommitStrategy::OnReceive);
+
+        consumer_group("fleet", |name| (name == "MESSAGING_COMMIT").then(|| "never".to_string()))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn retry_policy_defaults_to_no_retries() {
+        let retry = retry_policy(|_| None).unwrap();
+        assert_eq!(
+            retry,
+            Retry {
+                retries: 0,
+                delay: Duration::from_millis(100),
+                dead_letter: false,
+            }
+        );
+
+        let retry = retry_policy(|name| {
+            let value = match name {
+                "MESSAGING_RETRIES" => "3",
+                "MESSAGING_RETRY_DELAY_MS" => "250",
+                _ => "true",
+            };
+            Some(value.to_string())
+        })
+        .unwrap();
+        assert_eq!(retry.retries, 3);
+        assert_eq!(retry.delay, Duration::from_millis(250));
+        assert!(retry.dead_letter);
+
+        retry_policy(|name| (name == "MESSAGING_RETRIES").then(|| "many".to_string())).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn handlers_can_extend_their_deadline() {
+        let settlement = Settlement::new();
+        settlement.start(Instant::now() + Duration::from_millis(20));
+        let run = async {
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            7
+        };
+        let extend = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            settlement.extend(Duration::from_millis(500));
+        };
+        let (output, ()) = tokio::join!(until_deadline(run, &settlement), extend);
+        assert_eq!(output.unwrap(), 7);
+
+        settlement.start(Instant::now() + Duration::from_millis(10));
+        let slow = tokio::time::sleep(Duration::from_millis(100));
+        until_deadline(slow, &settlement).await.unwrap_err();
+
+        settlement.settle(Disposition::Nack { requeue: true }).unwrap();
+        settlement.settle(Disposition::Ack).unwrap_err();
+        assert_eq!(settlement.disposition(), Some(Disposition::Nack { requeue: true }));
+    }
+
+    #[tokio::test]
+    async fn poisoned_messages_land_on_the_dead_letter_topic() {
+        let messaging = <MessagingDefault as Backend>::connect().await.unwrap();
+        let client = WasiMessagingCtx::connect(&messaging).await.unwrap();
+        let mut message = Message::new(b"{}".to_vec());
+        message.topic = "orders".to_string();
+
+        let error = anyhow!("handler failed");
+        assert!(dead_letter(client.as_ref(), message, &error, 3).await);
+
+        let dead: Vec<_> = client.replay("orders.dlq".to_string()).await.unwrap().collect().await;
+        assert_eq!(dead.len(), 1);
+        let metadata = dead[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata["omnia-dlq-topic"], "orders");
+        assert_eq!(metadata["omnia-dlq-error"], "handler failed");
+        assert_eq!(metadata["omnia-dlq-attempts"], "3");
+    }
+}
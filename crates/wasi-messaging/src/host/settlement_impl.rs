@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use wasmtime::component::{Access, Resource};
+
+use crate::host::generated::omnia::messaging::settlement::{Host, HostWithStore};
+use crate::host::resource::{Disposition, Message, Settlement};
+use crate::host::{Error, Result, WasiMessaging, WasiMessagingCtxView};
+
+impl<T> HostWithStore<T> for WasiMessaging {
+    /// Acknowledge a delivered message.
+    fn ack(mut host: Access<'_, T, Self>, m: Resource<Message>) -> Result<()> {
+        settlement(host.get().table.get(&m)?)?.settle(Disposition::Ack)?;
+        Ok(())
+    }
+
+    /// Reject a delivered message, for redelivery if `requeue`.
+    fn nack(mut host: Access<'_, T, Self>, m: Resource<Message>, requeue: bool) -> Result<()> {
+        settlement(host.get().table.get(&m)?)?.settle(Disposition::Nack { requeue })?;
+        Ok(())
+    }
+
+    /// Extend the deadline for handling a delivered message.
+    fn extend_deadline(
+        mut host: Access<'_, T, Self>, m: Resource<Message>, timeout_ms: u32,
+    ) -> Result<()> {
+        let timeout = Duration::from_millis(u64::from(timeout_ms));
+        settlement(host.get().table.get(&m)?)?.extend(timeout);
+        Ok(())
+    }
+}
+
+fn settlement(message: &Message) -> Result<&Settlement> {
+    message.settlement.as_deref().ok_or_else(|| {
+        Error::Other("only a message delivered to the handler can be settled".to_string())
+    })
+}
+
+impl Host for WasiMessagingCtxView<'_> {}
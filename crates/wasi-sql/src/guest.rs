@@ -12,13 +12,33 @@ mod generated {
     });
 }
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use base64ct::{Base64, Encoding};
+use futures::stream::{self, Stream, TryStreamExt};
 use serde_json::Value;
 
+pub use self::generated::omnia::sql::*;
 pub use self::generated::wasi::sql::*;
+pub use crate::results::ExecResult;
 pub use crate::types::{DataType, Field, Row};
 
+/// Stream the rows of an open cursor, fetching each batch only once the
+/// previous one has been consumed.
+///
+/// # Errors
+/// The stream yields an error, and then ends, if fetching a batch fails.
+pub fn rows(cursor: cursors::Cursor) -> impl Stream<Item = Result<Row>> + Send {
+    stream::try_unfold(cursor, |cursor| async move {
+        let batch = cursor.next().await.map_err(|e| anyhow!("cursor failed: {}", e.trace()))?;
+        if batch.is_empty() {
+            return Ok(None);
+        }
+        let page: Vec<Result<Row>> = batch.into_iter().map(Ok).collect();
+        Ok::<_, anyhow::Error>(Some((stream::iter(page), cursor)))
+    })
+    .try_flatten()
+}
+
 /// Helper function to create JSON output from rows returned by a query.
 ///
 /// # Errors
@@ -2,15 +2,20 @@
 //!
 //! This module implements the host-side logic for the WASI SQL service.
 
+mod cursors_impl;
 mod default_impl;
+mod errors_impl;
 mod readwrite_impl;
 mod resource;
+mod results_impl;
+mod trace;
+mod transactional_impl;
 mod types_impl;
 
 mod generated {
     #![allow(missing_docs)]
 
-    pub use super::{ConnectionProxy, Error, Statement};
+    pub use super::{ConnectionProxy, CursorProxy, Error, Statement, TransactionProxy};
 
     wasmtime::component::bindgen!({
         world: "imports",
@@ -21,6 +26,8 @@ mod generated {
         with: {
             "wasi:sql/types.connection": ConnectionProxy,
             "wasi:sql/types.statement": Statement,
+            "omnia:sql/transactional.transaction": TransactionProxy,
+            "omnia:sql/cursors.cursor": CursorProxy,
             "wasi:sql/types.error": Error,
         },
         trappable_error_type: {
@@ -36,8 +43,11 @@ pub use omnia::FutureResult;
 use omnia::{Host, Server};
 use wasmtime::component::{HasData, Linker};
 
+use self::generated::omnia::sql::{cursors, errors, results, transactional};
 use self::generated::wasi::sql::{readwrite, types};
 pub use crate::host::default_impl::SqlDefault;
+pub use crate::host::generated::omnia::sql::results::ExecResult;
+pub use crate::host::generated::omnia::sql::transactional::{IsolationLevel, TransactionOptions};
 pub use crate::host::generated::wasi::sql::types::{DataType, Field, Row};
 pub use crate::host::resource::*;
 
@@ -46,18 +56,90 @@ pub use crate::host::resource::*;
 pub enum Error {
     /// Untyped host failure; the message preserves the backend context chain.
     Other(String),
+    /// A failure the database reported with a SQLSTATE code.
+    Database {
+        /// The five-character SQLSTATE code, such as `40001`.
+        sqlstate: String,
+        /// The message, with the backend context chain.
+        trace: String,
+    },
 }
 
 impl Error {
     /// The failure trace exposed to guests via the WIT `error` resource.
     #[must_use]
     pub fn trace(&self) -> &str {
-        let Self::Other(msg) = self;
-        msg
+        match self {
+            Self::Other(trace) | Self::Database { trace, .. } => trace,
+        }
+    }
+
+    /// The SQLSTATE code exposed to guests via `omnia:sql/errors.sqlstate`.
+    #[must_use]
+    pub fn sqlstate(&self) -> Option<&str> {
+        match self {
+            Self::Other(_) => None,
+            Self::Database { sqlstate, .. } => Some(sqlstate),
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        // `:#` keeps the full context chain from backend errors.
+        let trace = format!("{err:#}");
+        match err.chain().find_map(|cause| cause.downcast_ref::<DatabaseError>()) {
+            Some(database) => Self::Database {
+                sqlstate: database.sqlstate.clone(),
+                trace,
+            },
+            None => Self::Other(trace),
+        }
+    }
+}
+
+impl From<wasmtime::Error> for Error {
+    fn from(err: wasmtime::Error) -> Self {
+        Self::Other(format!("{err:#}"))
+    }
+}
+
+impl From<wasmtime::component::ResourceTableError> for Error {
+    fn from(err: wasmtime::component::ResourceTableError) -> Self {
+        Self::Other(err.to_string())
+    }
+}
+
+/// A failure the database reported with a SQLSTATE code.
+///
+/// Backends return it, with any context they add on top, so that guests can
+/// tell failures apart by code rather than by message, for example to retry a
+/// serialization failure (`40001`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseError {
+    /// The five-character SQLSTATE code.
+    pub sqlstate: String,
+    /// The database's message.
+    pub message: String,
+}
+
+impl DatabaseError {
+    /// A failure with `sqlstate` and `message`.
+    pub fn new(sqlstate: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            sqlstate: sqlstate.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (SQLSTATE {})", self.message, self.sqlstate)
     }
 }
 
-omnia::host_error!(Error, Other);
+impl std::error::Error for DatabaseError {}
 
 /// Host-side service for `wasi:sql`.
 #[derive(Debug)]
@@ -73,6 +155,10 @@ where
 {
     fn add_to_linker(linker: &mut Linker<T>) -> anyhow::Result<()> {
         readwrite::add_to_linker::<_, Self>(linker, T::sql)?;
+        results::add_to_linker::<_, Self>(linker, T::sql)?;
+        transactional::add_to_linker::<_, Self>(linker, T::sql)?;
+        cursors::add_to_linker::<_, Self>(linker, T::sql)?;
+        errors::add_to_linker::<_, Self>(linker, T::sql)?;
         Ok(types::add_to_linker::<_, Self>(linker, T::sql)?)
     }
 }
@@ -0,0 +1,55 @@
+use wasmtime::component::{Access, Accessor, Resource};
+
+use crate::host::generated::omnia::sql::cursors::{
+    Connection, Cursor, Error, Host, HostCursor, HostCursorWithStore, Row, Statement,
+};
+use crate::host::readwrite_impl::{get_connection, get_statement};
+use crate::host::resource::CursorProxy;
+use crate::host::trace::traced;
+use crate::host::{WasiSql, WasiSqlCtxView};
+
+impl<T> HostCursorWithStore<T> for WasiSql {
+    async fn open(
+        accessor: &Accessor<T, Self>, c: Resource<Connection>, q: Resource<Statement>,
+        batch_size: u32,
+    ) -> wasmtime::Result<Result<Resource<Cursor>, Resource<Error>>> {
+        let connection = get_connection(accessor, &c).map_err(wasmtime::Error::from_anyhow)?;
+        let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
+
+        let result = match traced(
+            &statement.query,
+            connection.cursor(statement.query.clone(), statement.params, batch_size),
+            |_| None,
+        )
+        .await
+        {
+            Ok(cursor) => {
+                let proxy = CursorProxy(cursor);
+                Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
+            }
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    async fn next(
+        accessor: &Accessor<T, Self>, self_: Resource<Cursor>,
+    ) -> wasmtime::Result<Result<Vec<Row>, Resource<Error>>> {
+        let cursor = accessor.with(|mut store| store.get().table.get(&self_).cloned())?;
+
+        let result = match cursor.next().await {
+            Ok(rows) => Ok(rows),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    fn drop(mut accessor: Access<'_, T, Self>, rep: Resource<CursorProxy>) -> wasmtime::Result<()> {
+        accessor.get().table.delete(rep).map(|_| Ok(()))?
+    }
+}
+
+impl Host for WasiSqlCtxView<'_> {}
+impl HostCursor for WasiSqlCtxView<'_> {}
@@ -6,18 +6,25 @@
 // functions that would otherwise trip `missing_docs`.
 #![allow(missing_docs)]
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use fromenv::FromEnv;
+use fromenv::{FromEnv, ParseResult};
 use futures::FutureExt;
 use omnia::Backend;
 use rusqlite::types::ValueRef;
-use rusqlite::{Connection as SqliteConnection, params_from_iter};
+use rusqlite::{
+    Connection as SqliteConnection, ErrorCode, Statement as SqliteStatement, params_from_iter,
+};
 use tracing::instrument;
 
-use crate::host::resource::{Connection, FutureResult};
-use crate::host::{DataType, Field, Row, WasiSqlCtx};
+use crate::host::resource::{Connection, Cursor, FutureResult, Transaction};
+use crate::host::{
+    DataType, DatabaseError, ExecResult, Field, Row, TransactionOptions, WasiSqlCtx,
+};
 
 /// Options used to connect to the SQL database.
 ///
@@ -26,6 +33,78 @@ use crate::host::{DataType, Field, Row, WasiSqlCtx};
 pub struct ConnectOptions {
     #[env(from = "SQL_DATABASE", default = "file::memory:?cache=shared")]
     pub database: String,
+    /// Named databases opened by `Connection::open(name)`, listed in
+    /// `SQL_CONNECTIONS` and each read from `SQL_<NAME>_DATABASE`.
+    #[env(from = "SQL_CONNECTIONS", default = "", with = parse_connections)]
+    pub connections: BTreeMap<String, String>,
+    /// Artificial latency per statement, `N` or `MIN-MAX` milliseconds (`SQL_MOCK_LATENCY_MS`, default 0).
+    #[env(from = "SQL_MOCK_LATENCY_MS", default = "0", with = parse_latency)]
+    pub latency: Latency,
+    /// Repeat each query's result rows this many times (`SQL_MOCK_ROW_MULTIPLIER`, default 1).
+    #[env(from = "SQL_MOCK_ROW_MULTIPLIER", default = "1")]
+    pub row_multiplier: usize,
+}
+
+/// Artificial statement latency, sampled uniformly between `min` and `max`.
+///
+/// Used to profile guests against realistic round-trip times without a
+/// production database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    min: Duration,
+    max: Duration,
+}
+
+impl Latency {
+    /// A fixed delay.
+    #[must_use]
+    pub const fn fixed(delay: Duration) -> Self {
+        Self {
+            min: delay,
+            max: delay,
+        }
+    }
+
+    /// A delay sampled uniformly from `min..=max`.
+    #[must_use]
+    pub fn uniform(min: Duration, max: Duration) -> Self {
+        Self {
+            min: min.min(max),
+            max: max.max(min),
+        }
+    }
+
+    fn sample(self) -> Duration {
+        let spread =
+            u64::try_from(self.max.saturating_sub(self.min).as_micros()).unwrap_or(u64::MAX);
+        if spread == 0 {
+            return self.min;
+        }
+        self.min + Duration::from_micros(rand::random::<u64>() % spread.saturating_add(1))
+    }
+}
+
+/// Parse `N` or `MIN-MAX` milliseconds; used by the `FromEnv` derive.
+fn parse_latency(value: &str) -> ParseResult<Latency> {
+    let latency = match value.split_once('-') {
+        Some((min, max)) => Latency::uniform(
+            Duration::from_millis(min.trim().parse()?),
+            Duration::from_millis(max.trim().parse()?),
+        ),
+        None => Latency::fixed(Duration::from_millis(value.trim().parse()?)),
+    };
+    Ok(latency)
+}
+
+/// Load the databases named in `SQL_CONNECTIONS` from their `SQL_<NAME>_DATABASE` variables.
+fn parse_connections(names: &str) -> ParseResult<BTreeMap<String, String>> {
+    let mut connections = BTreeMap::new();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let var = format!("SQL_{}_DATABASE", name.to_ascii_uppercase().replace('-', "_"));
+        let database = std::env::var(&var).ok().ok_or_else(|| format!("{var} is not set"))?;
+        connections.insert(name.to_string(), database);
+    }
+    Ok(connections)
 }
 
 /// Loads connection options from environment variables with error context.
@@ -36,36 +115,130 @@ impl omnia::FromEnv for ConnectOptions {
 }
 
 /// Default implementation for `wasi:sql`.
+///
+/// `Connection::open(name)` opens the database configured for `name`, or the
+/// default database when `name` has none.
 #[derive(Debug, Clone)]
 pub struct SqlDefault {
+    default: Database,
+    named: Arc<BTreeMap<String, Database>>,
+    mock: Mock,
+}
+
+#[derive(Debug, Clone)]
+struct Database {
     // Store the database path to create new connections on demand
+    path: Arc<str>,
     // Mutex is necessary since rusqlite::Connection isn't `Sync`
     conn: Arc<parking_lot::Mutex<SqliteConnection>>,
 }
 
+impl Database {
+    fn open(path: &str) -> Result<Self> {
+        tracing::debug!("initializing SQLite connection to: {path}");
+
+        // Create initial connection to validate database path
+        let conn = SqliteConnection::open(path)
+            .with_context(|| format!("failed to open SQLite database {path}"))?;
+        Ok(Self {
+            path: path.into(),
+            conn: Arc::new(parking_lot::Mutex::new(conn)),
+        })
+    }
+}
+
+/// Test-only load shaping applied on top of the real `SQLite` results.
+#[derive(Debug, Clone, Copy)]
+struct Mock {
+    latency: Latency,
+    row_multiplier: usize,
+}
+
 impl Backend for SqlDefault {
     type ConnectOptions = ConnectOptions;
 
     #[instrument]
     async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
-        tracing::debug!("initializing SQLite connection to: {}", options.database);
+        Self::open_with(&options)
+    }
+}
 
-        // Create initial connection to validate database path
-        let conn = Arc::new(parking_lot::Mutex::new(
-            SqliteConnection::open(&options.database).context("failed to open SQLite database")?,
-        ));
+impl SqlDefault {
+    /// Open a fresh in-memory database private to this instance.
+    ///
+    /// Unlike the default `file::memory:?cache=shared`, which every connection
+    /// in the process shares, each call gets its own named database, so tests
+    /// running in parallel never see each other's tables. The database lives
+    /// as long as any clone of the returned instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `SQLite` cannot open the database.
+    pub fn in_memory() -> Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        Self::open_with(&ConnectOptions {
+            database: format!(
+                "file:omnia-sql-{}-{id}?mode=memory&cache=shared",
+                std::process::id()
+            ),
+            connections: BTreeMap::new(),
+            latency: Latency::default(),
+            row_multiplier: 1,
+        })
+    }
+
+    /// Serve `Connection::open(name)` from `database` instead of the default
+    /// database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `SQLite` cannot open the database.
+    pub fn with_connection(mut self, name: impl Into<String>, database: &str) -> Result<Self> {
+        Arc::make_mut(&mut self.named).insert(name.into(), Database::open(database)?);
+        Ok(self)
+    }
 
-        Ok(Self { conn })
+    /// Run one or more `;`-separated statements against the default database,
+    /// typically to create the schema and seed fixtures before a test.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any statement fails; statements before it stay
+    /// applied.
+    pub fn execute_batch(&self, sql: &str) -> Result<()> {
+        self.default.conn.lock().execute_batch(sql).context("failed to execute batch")
+    }
+
+    fn open_with(options: &ConnectOptions) -> Result<Self> {
+        let default = Database::open(&options.database)?;
+        let named = options
+            .connections
+            .iter()
+            .map(|(name, path)| Ok((name.clone(), Database::open(path)?)))
+            .collect::<Result<_>>()?;
+
+        let mock = Mock {
+            latency: options.latency,
+            row_multiplier: options.row_multiplier.max(1),
+        };
+        Ok(Self {
+            default,
+            named: Arc::new(named),
+            mock,
+        })
     }
 }
 
 impl WasiSqlCtx for SqlDefault {
-    fn open(&self, _name: String) -> FutureResult<Arc<dyn Connection>> {
-        tracing::debug!("opening SQL connection");
-        let conn = Arc::clone(&self.conn);
+    fn open(&self, name: String) -> FutureResult<Arc<dyn Connection>> {
+        tracing::debug!(name, "opening SQL connection");
+        let Database { path: database, conn } =
+            self.named.get(&name).unwrap_or(&self.default).clone();
+        let mock = self.mock;
 
         async move {
-            let connection = SqliteConnectionImpl { conn };
+            let connection = SqliteConnectionImpl { database, conn, mock };
             Ok(Arc::new(connection) as Arc<dyn Connection>)
         }
         .boxed()
@@ -74,90 +247,352 @@ impl WasiSqlCtx for SqlDefault {
 
 #[derive(Debug, Clone)]
 struct SqliteConnectionImpl {
+    database: Arc<str>,
     conn: Arc<parking_lot::Mutex<SqliteConnection>>,
+    mock: Mock,
 }
 
 impl Connection for SqliteConnectionImpl {
-    // The mutex guard must outlive the prepared statement that borrows the
-    // connection, so the drop cannot be tightened further.
-    #[expect(clippy::significant_drop_tightening)]
     fn query(&self, query: String, params: Vec<DataType>) -> FutureResult<Vec<Row>> {
         tracing::debug!("executing query: {}", query);
         let conn = Arc::clone(&self.conn);
+        let mock = self.mock;
 
         async move {
+            delay(mock.latency).await;
+
             // Blocking rusqlite work (and the mutex held around it) runs on a
             // blocking thread so it never pins an executor thread.
-            tokio::task::spawn_blocking(move || {
-                let rusqlite_params: Vec<_> =
-                    params.iter().map(datatype_to_rusqlite_value).collect();
-
-                let conn = conn.lock();
-                let mut stmt = conn.prepare(&query).context("failed to prepare statement")?;
-
-                let column_names: Vec<String> =
-                    stmt.column_names().iter().map(ToString::to_string).collect();
-
-                let mut rows = stmt
-                    .query(params_from_iter(rusqlite_params.iter()))
-                    .context("failed to execute query")?;
-
-                let mut result_rows = Vec::new();
-                let mut index = 0;
-                while let Some(row) = rows.next().context("failed to fetch row")? {
-                    let mut fields = Vec::new();
-
-                    for (i, name) in column_names.iter().enumerate() {
-                        let value = row.get_ref(i).context("failed to get column value")?;
-                        let data_type = rusqlite_value_to_datatype(value)?;
-
-                        fields.push(Field {
-                            name: name.clone(),
-                            value: data_type,
-                        });
-                    }
-
-                    result_rows.push(Row {
-                        index: index.to_string(),
-                        fields,
-                    });
-                    index += 1;
-                }
-
-                Ok(result_rows)
-            })
-            .await
-            .context("query task panicked")?
+            tokio::task::spawn_blocking(move || run_query(&conn.lock(), &query, &params))
+                .await
+                .context("query task panicked")?
+                .map(|rows| multiply_rows(rows, mock.row_multiplier))
         }
         .boxed()
     }
 
-    // See `query`: the guard must outlive the prepared statement.
-    #[expect(clippy::significant_drop_tightening)]
     fn exec(&self, query: String, params: Vec<DataType>) -> FutureResult<u32> {
         tracing::debug!("executing statement: {}", query);
         let conn = Arc::clone(&self.conn);
+        let latency = self.mock.latency;
 
         async move {
+            delay(latency).await;
+
             // See `query`: keep the blocking work off the executor.
+            tokio::task::spawn_blocking(move || run_exec(&conn.lock(), &query, &params))
+                .await
+                .context("exec task panicked")?
+        }
+        .boxed()
+    }
+
+    fn exec_with_result(&self, query: String, params: Vec<DataType>) -> FutureResult<ExecResult> {
+        tracing::debug!("executing statement with result: {}", query);
+        let conn = Arc::clone(&self.conn);
+        let latency = self.mock.latency;
+
+        async move {
+            delay(latency).await;
+            tokio::task::spawn_blocking(move || run_exec_result(&conn.lock(), &query, &params))
+                .await
+                .context("exec task panicked")?
+        }
+        .boxed()
+    }
+
+    fn begin(&self, options: TransactionOptions) -> FutureResult<Arc<dyn Transaction>> {
+        tracing::debug!("beginning transaction");
+        let database = Arc::clone(&self.database);
+        let mock = self.mock;
+
+        async move {
+            // A transaction owns its connection for its whole lifetime, so it
+            // opens a dedicated one rather than holding the shared mutex.
+            let conn = tokio::task::spawn_blocking(move || {
+                let conn =
+                    SqliteConnection::open(&*database).context("failed to open SQLite database")?;
+                // SQLite transactions are always serializable; only the
+                // access mode carries over.
+                if options.read_only {
+                    conn.execute_batch("PRAGMA query_only = ON")
+                        .context("failed to set read-only mode")?;
+                }
+                conn.execute_batch("BEGIN").context("failed to begin transaction")?;
+                Ok::<_, anyhow::Error>(conn)
+            })
+            .await
+            .context("begin task panicked")??;
+
+            let transaction = SqliteTransaction {
+                conn: Arc::new(parking_lot::Mutex::new(conn)),
+                mock,
+            };
+            Ok(Arc::new(transaction) as Arc<dyn Transaction>)
+        }
+        .boxed()
+    }
+
+    fn cursor(
+        &self, query: String, params: Vec<DataType>, batch_size: u32,
+    ) -> FutureResult<Arc<dyn Cursor>> {
+        tracing::debug!("opening cursor: {}", query);
+        let cursor = SqliteCursor {
+            conn: Arc::clone(&self.conn),
+            query: query.trim_end().trim_end_matches(';').to_string(),
+            params,
+            batch_size: batch_size.max(1),
+            offset: Arc::new(parking_lot::Mutex::new(0)),
+            latency: self.mock.latency,
+        };
+        async move { Ok(Arc::new(cursor) as Arc<dyn Cursor>) }.boxed()
+    }
+}
+
+/// Pages through a query with `LIMIT`/`OFFSET`, so only one batch is ever
+/// materialized. Each batch is a separate statement, so the cursor does not
+/// read a snapshot: a row written between batches ahead of the offset shifts
+/// the rest, and the next batch repeats or skips rows.
+#[derive(Debug)]
+struct SqliteCursor {
+    conn: Arc<parking_lot::Mutex<SqliteConnection>>,
+    query: String,
+    params: Vec<DataType>,
+    batch_size: u32,
+    offset: Arc<parking_lot::Mutex<u64>>,
+    latency: Latency,
+}
+
+impl Cursor for SqliteCursor {
+    fn next(&self) -> FutureResult<Vec<Row>> {
+        let conn = Arc::clone(&self.conn);
+        let offset = Arc::clone(&self.offset);
+        let query = format!("SELECT * FROM ({}) LIMIT {} OFFSET ?", self.query, self.batch_size);
+        let params = self.params.clone();
+        let latency = self.latency;
+
+        async move {
+            delay(latency).await;
             tokio::task::spawn_blocking(move || {
-                let rusqlite_params: Vec<_> =
-                    params.iter().map(datatype_to_rusqlite_value).collect();
+                // Holding the offset lock serializes batches from one cursor.
+                let mut offset = offset.lock();
+                let mut params = params;
+                params.push(DataType::Int64(Some(i64::try_from(*offset).unwrap_or(i64::MAX))));
+                let mut rows = run_query(&conn.lock(), &query, &params)?;
+                for (index, row) in rows.iter_mut().enumerate() {
+                    row.index = (*offset + index as u64).to_string();
+                }
+                *offset += rows.len() as u64;
+                drop(offset);
+                Ok(rows)
+            })
+            .await
+            .context("cursor task panicked")?
+        }
+        .boxed()
+    }
+}
 
-                let conn = conn.lock();
-                let mut stmt = conn.prepare(&query).context("failed to prepare statement")?;
+/// A transaction on its own `SQLite` connection. Dropping the connection
+/// without a `COMMIT` rolls the transaction back.
+#[derive(Debug)]
+struct SqliteTransaction {
+    conn: Arc<parking_lot::Mutex<SqliteConnection>>,
+    mock: Mock,
+}
 
-                let rows_affected = stmt
-                    .execute(params_from_iter(rusqlite_params.iter()))
-                    .context("failed to execute statement")?;
+impl SqliteTransaction {
+    fn finish(&self, statement: &'static str) -> FutureResult<()> {
+        tracing::debug!("ending transaction: {}", statement);
+        let conn = Arc::clone(&self.conn);
 
-                Ok(u32::try_from(rows_affected).unwrap_or(u32::MAX))
+        async move {
+            tokio::task::spawn_blocking(move || {
+                conn.lock()
+                    .execute_batch(statement)
+                    .map_err(sqlite_error)
+                    .with_context(|| format!("failed to {statement}"))
             })
             .await
-            .context("exec task panicked")?
+            .context("transaction task panicked")?
+        }
+        .boxed()
+    }
+}
+
+impl Transaction for SqliteTransaction {
+    fn query(&self, query: String, params: Vec<DataType>) -> FutureResult<Vec<Row>> {
+        tracing::debug!("executing query in transaction: {}", query);
+        let conn = Arc::clone(&self.conn);
+        let mock = self.mock;
+
+        async move {
+            delay(mock.latency).await;
+            tokio::task::spawn_blocking(move || run_query(&conn.lock(), &query, &params))
+                .await
+                .context("query task panicked")?
+                .map(|rows| multiply_rows(rows, mock.row_multiplier))
+        }
+        .boxed()
+    }
+
+    fn exec(&self, query: String, params: Vec<DataType>) -> FutureResult<u32> {
+        tracing::debug!("executing statement in transaction: {}", query);
+        let conn = Arc::clone(&self.conn);
+        let latency = self.mock.latency;
+
+        async move {
+            delay(latency).await;
+            tokio::task::spawn_blocking(move || run_exec(&conn.lock(), &query, &params))
+                .await
+                .context("exec task panicked")?
+        }
+        .boxed()
+    }
+
+    fn exec_with_result(&self, query: String, params: Vec<DataType>) -> FutureResult<ExecResult> {
+        tracing::debug!("executing statement with result in transaction: {}", query);
+        let conn = Arc::clone(&self.conn);
+        let latency = self.mock.latency;
+
+        async move {
+            delay(latency).await;
+            tokio::task::spawn_blocking(move || run_exec_result(&conn.lock(), &query, &params))
+                .await
+                .context("exec task panicked")?
         }
         .boxed()
     }
+
+    fn commit(&self) -> FutureResult<()> {
+        self.finish("COMMIT")
+    }
+
+    fn rollback(&self) -> FutureResult<()> {
+        self.finish("ROLLBACK")
+    }
+}
+
+fn run_query(conn: &SqliteConnection, query: &str, params: &[DataType]) -> Result<Vec<Row>> {
+    let mut stmt = conn.prepare(query).context("failed to prepare statement")?;
+    collect_rows(&mut stmt, params)
+}
+
+fn collect_rows(stmt: &mut SqliteStatement<'_>, params: &[DataType]) -> Result<Vec<Row>> {
+    let rusqlite_params: Vec<_> = params.iter().map(datatype_to_rusqlite_value).collect();
+
+    let column_names: Vec<String> = stmt.column_names().iter().map(ToString::to_string).collect();
+
+    let mut rows = stmt
+        .query(params_from_iter(rusqlite_params.iter()))
+        .map_err(sqlite_error)
+        .context("failed to execute query")?;
+
+    let mut result_rows = Vec::new();
+    let mut index = 0;
+    while let Some(row) = rows.next().map_err(sqlite_error).context("failed to fetch row")? {
+        let mut fields = Vec::new();
+
+        for (i, name) in column_names.iter().enumerate() {
+            let value = row.get_ref(i).context("failed to get column value")?;
+            let data_type = rusqlite_value_to_datatype(value)?;
+
+            fields.push(Field {
+                name: name.clone(),
+                value: data_type,
+            });
+        }
+
+        result_rows.push(Row {
+            index: index.to_string(),
+            fields,
+        });
+        index += 1;
+    }
+
+    Ok(result_rows)
+}
+
+fn run_exec(conn: &SqliteConnection, query: &str, params: &[DataType]) -> Result<u32> {
+    let rusqlite_params: Vec<_> = params.iter().map(datatype_to_rusqlite_value).collect();
+
+    let mut stmt = conn.prepare(query).context("failed to prepare statement")?;
+
+    let rows_affected = stmt
+        .execute(params_from_iter(rusqlite_params.iter()))
+        .map_err(sqlite_error)
+        .context("failed to execute statement")?;
+
+    Ok(u32::try_from(rows_affected).unwrap_or(u32::MAX))
+}
+
+/// Run a statement, collecting `RETURNING` rows when it has any. `SQLite`
+/// keeps the last insert rowid per connection, so an id is reported only when
+/// this statement changed it.
+fn run_exec_result(
+    conn: &SqliteConnection, query: &str, params: &[DataType],
+) -> Result<ExecResult> {
+    let rowid_before = conn.last_insert_rowid();
+
+    let mut stmt = conn.prepare(query).context("failed to prepare statement")?;
+    let readonly = stmt.readonly();
+    let rows = if stmt.column_count() > 0 {
+        collect_rows(&mut stmt, params)?
+    } else {
+        let rusqlite_params: Vec<_> = params.iter().map(datatype_to_rusqlite_value).collect();
+        stmt.execute(params_from_iter(rusqlite_params.iter()))
+            .map_err(sqlite_error)
+            .context("failed to execute statement")?;
+        Vec::new()
+    };
+    drop(stmt);
+
+    // `changes` reports the last modifying statement, which is stale after a
+    // read-only one.
+    let affected_rows = if readonly { 0 } else { conn.changes() };
+    let rowid = conn.last_insert_rowid();
+    Ok(ExecResult {
+        affected_rows,
+        last_insert_id: (rowid != rowid_before).then_some(rowid),
+        rows,
+    })
+}
+
+async fn delay(latency: Latency) {
+    let duration = latency.sample();
+    if !duration.is_zero() {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// Repeat `rows` `multiplier` times, renumbering the row indices.
+fn multiply_rows(rows: Vec<Row>, multiplier: usize) -> Vec<Row> {
+    if multiplier <= 1 {
+        return rows;
+    }
+    let count = rows.len();
+    rows.iter()
+        .cycle()
+        .take(count * multiplier)
+        .enumerate()
+        .map(|(index, row)| Row {
+            index: index.to_string(),
+            fields: row.fields.clone(),
+        })
+        .collect()
+}
+
+/// Report `SQLite` failures that have a SQLSTATE equivalent as a
+/// [`DatabaseError`], so guests see the same codes as from other backends.
+fn sqlite_error(err: rusqlite::Error) -> anyhow::Error {
+    let sqlstate = match err.sqlite_error_code() {
+        // A conflicting writer held the database; retrying the transaction
+        // succeeds, as it does after a serialization failure.
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => "40001",
+        Some(ErrorCode::ConstraintViolation) => "23000",
+        _ => return err.into(),
+    };
+    DatabaseError::new(sqlstate, err.to_string()).into()
 }
 
 // `u64 as i64` is the standard SQLite convention: store the raw bits and let
@@ -227,6 +662,34 @@ mod tests {
         assert_eq!(datatype_to_rusqlite_value(&DataType::Str(None)), Value::Null);
     }
 
+    #[test]
+    fn latency_parses_fixed_and_range() {
+        assert_eq!(parse_latency("0").unwrap(), Latency::default());
+        assert_eq!(parse_latency("25").unwrap(), Latency::fixed(Duration::from_millis(25)));
+
+        let range = parse_latency("50-10").unwrap();
+        assert_eq!(range, Latency::uniform(Duration::from_millis(10), Duration::from_millis(50)));
+        let sample = range.sample();
+        assert!((Duration::from_millis(10)..=Duration::from_millis(50)).contains(&sample));
+
+        parse_latency("fast").unwrap_err();
+    }
+
+    #[test]
+    fn rows_multiply_with_fresh_indices() {
+        let rows = vec![Row {
+            index: "0".to_string(),
+            fields: vec![Field {
+                name: "id".to_string(),
+                value: DataType::Int64(Some(1)),
+            }],
+        }];
+
+        let rows = multiply_rows(rows, 3);
+        let indices = rows.iter().map(|row| row.index.as_str()).collect::<Vec<_>>();
+        assert_eq!(indices, ["0", "1", "2"]);
+    }
+
     #[test]
     fn sqlite_value_to_datatypes() {
         assert!(matches!(
@@ -251,4 +714,66 @@ mod tests {
         ));
         assert!(rusqlite_value_to_datatype(ValueRef::Text(&[0xff])).is_err(), "invalid UTF-8");
     }
+
+    #[tokio::test]
+    async fn in_memory_databases_are_isolated() {
+        let first = SqlDefault::in_memory().unwrap();
+        let second = SqlDefault::in_memory().unwrap();
+        first
+            .execute_batch("CREATE TABLE stops (id INTEGER); INSERT INTO stops VALUES (1);")
+            .unwrap();
+
+        let conn = first.open("db".to_string()).await.unwrap();
+        let rows = conn.query("SELECT id FROM stops".to_string(), Vec::new()).await.unwrap();
+        assert_eq!(rows.len(), 1);
+
+        let conn = second.open("db".to_string()).await.unwrap();
+        conn.query("SELECT id FROM stops".to_string(), Vec::new()).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn named_connections_open_their_own_database() {
+        let sql = SqlDefault::in_memory()
+            .unwrap()
+            .with_connection("analytics", "file:omnia-sql-analytics?mode=memory&cache=shared")
+            .unwrap();
+        sql.execute_batch("CREATE TABLE stops (id INTEGER)").unwrap();
+
+        let analytics = sql.open("analytics".to_string()).await.unwrap();
+        analytics.exec("CREATE TABLE trips (id INTEGER)".to_string(), Vec::new()).await.unwrap();
+        analytics.query("SELECT id FROM stops".to_string(), Vec::new()).await.unwrap_err();
+
+        // unconfigured names fall back to the default database
+        let db = sql.open("db".to_string()).await.unwrap();
+        db.query("SELECT id FROM stops".to_string(), Vec::new()).await.unwrap();
+        db.query("SELECT id FROM trips".to_string(), Vec::new()).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn cursors_see_writes_between_batches() {
+        let sql = SqlDefault::in_memory().unwrap();
+        sql.execute_batch(
+            "CREATE TABLE stops (id INTEGER); INSERT INTO stops VALUES (2), (3), (4);",
+        )
+        .unwrap();
+        let conn = sql.open("db".to_string()).await.unwrap();
+        let cursor = conn
+            .cursor("SELECT id FROM stops ORDER BY id".to_string(), Vec::new(), 2)
+            .await
+            .unwrap();
+        let ids = |rows: Vec<Row>| {
+            rows.into_iter()
+                .map(|row| match row.fields[0].value {
+                    DataType::Int64(Some(id)) => id,
+                    ref other => panic!("unexpected id {other:?}"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(cursor.next().await.unwrap()), [2, 3]);
+        // a row inserted ahead of the offset pushes 3 into the next batch again
+        conn.exec("INSERT INTO stops VALUES (1)".to_string(), Vec::new()).await.unwrap();
+        assert_eq!(ids(cursor.next().await.unwrap()), [3, 4]);
+        assert!(cursor.next().await.unwrap().is_empty());
+    }
 }
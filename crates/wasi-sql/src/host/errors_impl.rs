@@ -0,0 +1,15 @@
+use wasmtime::component::{Access, Resource};
+
+use crate::host::generated::omnia::sql::errors::{Error, Host, HostWithStore};
+use crate::host::{WasiSql, WasiSqlCtxView};
+
+impl<T> HostWithStore<T> for WasiSql {
+    fn sqlstate(
+        mut host: Access<'_, T, Self>, e: Resource<Error>,
+    ) -> wasmtime::Result<Option<String>> {
+        let err = host.get().table.get(&e)?;
+        Ok(err.sqlstate().map(ToString::to_string))
+    }
+}
+
+impl Host for WasiSqlCtxView<'_> {}
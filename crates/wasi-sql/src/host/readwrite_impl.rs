@@ -5,6 +5,7 @@ use crate::ConnectionProxy;
 use crate::host::generated::wasi::sql::readwrite::{
     Connection, Error, Host, HostWithStore, Row, Statement,
 };
+use crate::host::trace::traced;
 use crate::host::{WasiSql, WasiSqlCtxView};
 
 impl<T> HostWithStore<T> for WasiSql {
@@ -14,9 +15,13 @@ impl<T> HostWithStore<T> for WasiSql {
         let connection = get_connection(accessor, &c).map_err(wasmtime::Error::from_anyhow)?;
         let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
 
-        let (query, params) = (statement.query.clone(), statement.params.clone());
-
-        let result = match connection.query(query, params).await {
+        let result = match traced(
+            &statement.query,
+            connection.query(statement.query.clone(), statement.params),
+            |rows| Some(rows.len()),
+        )
+        .await
+        {
             Ok(rows) => Ok(rows),
             Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
         };
@@ -30,9 +35,13 @@ impl<T> HostWithStore<T> for WasiSql {
         let connection = get_connection(accessor, &c).map_err(wasmtime::Error::from_anyhow)?;
         let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
 
-        let (query, params) = (statement.query.clone(), statement.params.clone());
-
-        let result = match connection.exec(query, params).await {
+        let result = match traced(
+            &statement.query,
+            connection.exec(statement.query.clone(), statement.params),
+            |affected| usize::try_from(*affected).ok(),
+        )
+        .await
+        {
             Ok(rows) => Ok(rows),
             Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
         };
@@ -1,10 +1,14 @@
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::sync::Arc;
 
+use anyhow::anyhow;
+use futures::FutureExt;
 pub use omnia::FutureResult;
+use parking_lot::Mutex;
 
-use crate::host::{DataType, Row};
+use crate::host::{DataType, ExecResult, Row, TransactionOptions};
 
 /// SQL providers implement the [`Connection`] trait to allow the host to
 /// connect to a backend (Azure Table Storage, Postgres, etc) and execute SQL
@@ -15,6 +19,100 @@ pub trait Connection: Debug + Send + Sync + 'static {
 
     /// Execute a query that does not return rows (e.g., an `INSERT`, `UPDATE`, or `DELETE`).
     fn exec(&self, query: String, params: Vec<DataType>) -> FutureResult<u32>;
+
+    /// Execute a statement and report its effects: the affected row count,
+    /// the last insert id, and any `RETURNING` rows.
+    ///
+    /// The default reports only the count from [`exec`](Self::exec).
+    fn exec_with_result(&self, query: String, params: Vec<DataType>) -> FutureResult<ExecResult> {
+        self.exec(query, params).map(|affected| affected.map(affected_only)).boxed()
+    }
+
+    /// Begin a transaction pinned to one database session.
+    ///
+    /// Backends without transaction support keep the default, which fails.
+    fn begin(&self, _options: TransactionOptions) -> FutureResult<Arc<dyn Transaction>> {
+        async { Err(anyhow!("transactions are not supported by this backend")) }.boxed()
+    }
+
+    /// Open a cursor yielding the query's rows in batches of at most
+    /// `batch_size`.
+    ///
+    /// The default runs the query to completion and pages through the buffered
+    /// rows, which bounds guest memory but not host memory.
+    fn cursor(
+        &self, query: String, params: Vec<DataType>, batch_size: u32,
+    ) -> FutureResult<Arc<dyn Cursor>> {
+        let rows = self.query(query, params);
+        async move {
+            let cursor = BufferedCursor::new(rows.await?, batch_size);
+            Ok(Arc::new(cursor) as Arc<dyn Cursor>)
+        }
+        .boxed()
+    }
+}
+
+/// An open cursor over a query's rows.
+pub trait Cursor: Debug + Send + Sync + 'static {
+    /// The next batch of rows; empty once the cursor is exhausted.
+    fn next(&self) -> FutureResult<Vec<Row>>;
+}
+
+/// A [`Cursor`] over rows already held in memory.
+#[derive(Debug)]
+pub struct BufferedCursor {
+    rows: Mutex<VecDeque<Row>>,
+    batch_size: usize,
+}
+
+impl BufferedCursor {
+    /// Page through `rows` in batches of at most `batch_size` (at least one).
+    #[must_use]
+    pub fn new(rows: Vec<Row>, batch_size: u32) -> Self {
+        Self {
+            rows: Mutex::new(rows.into()),
+            batch_size: usize::try_from(batch_size).unwrap_or(usize::MAX).max(1),
+        }
+    }
+}
+
+impl Cursor for BufferedCursor {
+    fn next(&self) -> FutureResult<Vec<Row>> {
+        let mut rows = self.rows.lock();
+        let take = self.batch_size.min(rows.len());
+        let batch: Vec<Row> = rows.drain(..take).collect();
+        drop(rows);
+        async move { Ok(batch) }.boxed()
+    }
+}
+
+/// An open transaction. Dropping it without committing must roll it back.
+pub trait Transaction: Debug + Send + Sync + 'static {
+    /// Execute a query within the transaction and return the resulting rows.
+    fn query(&self, query: String, params: Vec<DataType>) -> FutureResult<Vec<Row>>;
+
+    /// Execute a statement within the transaction and return the affected row count.
+    fn exec(&self, query: String, params: Vec<DataType>) -> FutureResult<u32>;
+
+    /// Execute a statement within the transaction and report its effects;
+    /// see [`Connection::exec_with_result`].
+    fn exec_with_result(&self, query: String, params: Vec<DataType>) -> FutureResult<ExecResult> {
+        self.exec(query, params).map(|affected| affected.map(affected_only)).boxed()
+    }
+
+    /// Commit the transaction.
+    fn commit(&self) -> FutureResult<()>;
+
+    /// Roll the transaction back.
+    fn rollback(&self) -> FutureResult<()>;
+}
+
+fn affected_only(affected: u32) -> ExecResult {
+    ExecResult {
+        affected_rows: u64::from(affected),
+        last_insert_id: None,
+        rows: Vec::new(),
+    }
 }
 
 /// [`ConnectionProxy`] provides a concrete wrapper around a `dyn Connection` object.
@@ -30,6 +128,32 @@ impl Deref for ConnectionProxy {
     }
 }
 
+/// [`TransactionProxy`] provides a concrete wrapper around a `dyn Transaction` object.
+/// It is used to store transaction resources in the resource table.
+#[derive(Clone, Debug)]
+pub struct TransactionProxy(pub Arc<dyn Transaction>);
+
+impl Deref for TransactionProxy {
+    type Target = Arc<dyn Transaction>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// [`CursorProxy`] provides a concrete wrapper around a `dyn Cursor` object.
+/// It is used to store cursor resources in the resource table.
+#[derive(Clone, Debug)]
+pub struct CursorProxy(pub Arc<dyn Cursor>);
+
+impl Deref for CursorProxy {
+    type Target = Arc<dyn Cursor>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 /// Represents a statement resource in the WASI SQL host.
 #[derive(Clone, Debug)]
 pub struct Statement {
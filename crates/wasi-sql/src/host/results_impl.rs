@@ -0,0 +1,32 @@
+use wasmtime::component::{Accessor, Resource};
+
+use crate::host::generated::omnia::sql::results::{
+    Connection, Error, ExecResult, Host, HostWithStore, Statement,
+};
+use crate::host::readwrite_impl::{get_connection, get_statement};
+use crate::host::trace::traced;
+use crate::host::{WasiSql, WasiSqlCtxView};
+
+impl<T> HostWithStore<T> for WasiSql {
+    async fn exec_with_result(
+        accessor: &Accessor<T, Self>, c: Resource<Connection>, q: Resource<Statement>,
+    ) -> wasmtime::Result<Result<ExecResult, Resource<Error>>> {
+        let connection = get_connection(accessor, &c).map_err(wasmtime::Error::from_anyhow)?;
+        let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
+
+        let result = match traced(
+            &statement.query,
+            connection.exec_with_result(statement.query.clone(), statement.params),
+            |outcome| usize::try_from(outcome.affected_rows).ok(),
+        )
+        .await
+        {
+            Ok(outcome) => Ok(outcome),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+}
+
+impl Host for WasiSqlCtxView<'_> {}
@@ -0,0 +1,214 @@
+//! Query spans.
+//!
+//! Every statement a guest runs is wrapped in a `sql.query` span carrying the
+//! sanitized statement, the operation, the row count, the duration, and, on
+//! failure, an error class. The span opens under the current host span — the
+//! one the HTTP or messaging server opened for the guest request, which guest
+//! spans exported through `wasi:otel` are parented to — so database time shows
+//! up in the request's trace. Parameter values are never recorded, and literals
+//! inlined into the statement are replaced with `?`.
+
+use std::future::Future;
+use std::time::Instant;
+
+use anyhow::Result;
+use tracing::field::Empty;
+use tracing::{Instrument, info_span};
+
+use crate::host::DatabaseError;
+
+/// Longest sanitized statement recorded on a span, in bytes.
+const MAX_STATEMENT: usize = 2048;
+
+/// Run `call` for `sql` inside a query span, recording the rows `rows`
+/// reports for a successful result.
+pub async fn traced<R>(
+    sql: &str, call: impl Future<Output = Result<R>>, rows: impl FnOnce(&R) -> Option<usize>,
+) -> Result<R> {
+    let operation = operation(sql);
+    let span = info_span!(
+        "sql.query",
+        otel.name = %format!("sql {operation}"),
+        otel.status_code = Empty,
+        db.operation = %operation,
+        db.statement = %sanitize(sql),
+        db.rows = Empty,
+        db.duration_ms = Empty,
+        error.type = Empty,
+    );
+
+    let started = Instant::now();
+    let result = call.instrument(span.clone()).await;
+    span.record("db.duration_ms", started.elapsed().as_secs_f64() * 1000.0);
+
+    match &result {
+        Ok(value) => {
+            if let Some(count) = rows(value) {
+                span.record("db.rows", count);
+            }
+        }
+        Err(err) => {
+            span.record("otel.status_code", "ERROR");
+            span.record("error.type", error_class(err));
+            tracing::debug!(parent: &span, "sql {operation} failed: {err:#}");
+        }
+    }
+    result
+}
+
+/// The statement's leading keyword, upper-cased: `SELECT`, `INSERT`, ...
+pub fn operation(sql: &str) -> String {
+    let keyword = sql.split_whitespace().next().unwrap_or_default();
+    keyword.trim_start_matches('(').to_ascii_uppercase()
+}
+
+/// The statement with string, dollar-quoted (`$$...$$`, `$tag$...$tag$`), and
+/// numeric literals replaced by `?` and whitespace collapsed. Identifiers,
+/// keywords, and placeholders (`?`, `$1`, `:name`) are kept.
+pub fn sanitize(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len().min(MAX_STATEMENT));
+    let mut chars = sql.chars().peekable();
+    // The previous significant character, to tell `t1` and `$1` from `1`.
+    let mut prev = ' ';
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // `''` inside a literal is an escaped quote.
+                while let Some(c) = chars.next() {
+                    if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+                        break;
+                    }
+                }
+                out.push('?');
+                prev = '?';
+            }
+            // `$` inside an identifier, as Postgres allows, opens no quote.
+            '$' if !(prev.is_alphanumeric() || prev == '_') => {
+                let Some(tag) = dollar_tag(chars.clone()) else {
+                    out.push('$');
+                    prev = '$';
+                    continue;
+                };
+                for _ in 0..=tag.chars().count() {
+                    chars.next();
+                }
+                let close = format!("${tag}$");
+                let mut body = String::new();
+                for c in chars.by_ref() {
+                    body.push(c);
+                    if body.ends_with(&close) {
+                        break;
+                    }
+                }
+                out.push('?');
+                prev = '?';
+            }
+            c if c.is_ascii_digit() && !(prev.is_alphanumeric() || matches!(prev, '_' | '$')) => {
+                while chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.').is_some() {}
+                out.push('?');
+                prev = '?';
+            }
+            c if c.is_whitespace() => {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                out.push(' ');
+                prev = ' ';
+            }
+            c => {
+                out.push(c);
+                prev = c;
+            }
+        }
+        if out.len() >= MAX_STATEMENT {
+            out.push_str("...");
+            break;
+        }
+    }
+    out.trim().to_string()
+}
+
+/// The tag of a dollar quote whose opening `$` was just read, from the
+/// characters after it: empty for `$$`, `tag` for `$tag$`. `None` when they
+/// do not complete a quote, as for the `$1` placeholder.
+fn dollar_tag(rest: impl Iterator<Item = char>) -> Option<String> {
+    let mut tag = String::new();
+    for c in rest {
+        match c {
+            '$' => return Some(tag),
+            c if c.is_alphabetic() || c == '_' || (c.is_ascii_digit() && !tag.is_empty()) => {
+                tag.push(c);
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// A coarse, low-cardinality class for a failed statement, read from the
+/// SQLSTATE of the [`DatabaseError`] the backend reported. A failure without
+/// one is `other`.
+pub fn error_class(err: &anyhow::Error) -> &'static str {
+    let Some(database) = err.chain().find_map(|cause| cause.downcast_ref::<DatabaseError>()) else {
+        return "other";
+    };
+    let sqlstate = database.sqlstate.as_str();
+    match sqlstate {
+        // Statement timeout (`57014`) and ODBC's timeouts.
+        "57014" | "HYT00" | "HYT01" => "timeout",
+        "42601" | "42000" => "syntax",
+        // Undefined table, column, object, and function, and ODBC's missing
+        // table and column.
+        "42P01" | "42703" | "42704" | "42883" | "42S02" | "42S22" => "undefined_object",
+        _ => match sqlstate.get(..2) {
+            Some("08") => "connection",
+            Some("23") => "constraint",
+            Some("40") => "serialization",
+            _ => "other",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn statements_are_summarized() {
+        assert_eq!(
+            sanitize("SELECT *\n  FROM t1 WHERE name = 'O''Brien' AND age > 42 AND id = $1"),
+            "SELECT * FROM t1 WHERE name = ? AND age > ? AND id = $1"
+        );
+        assert_eq!(operation("  with recent AS (SELECT 1) SELECT * FROM recent"), "WITH");
+        assert_eq!(
+            sanitize("INSERT INTO t (a, b) VALUES (?, 3.5)"),
+            "INSERT INTO t (a, b) VALUES (?, ?)"
+        );
+    }
+
+    #[test]
+    fn dollar_quotes_are_literals() {
+        assert_eq!(
+            sanitize("SELECT $$it's 42$$, $body$ $1 'x' $$ $body$ FROM t$1 WHERE id = $2"),
+            "SELECT ?, ? FROM t$1 WHERE id = $2"
+        );
+        assert_eq!(sanitize("SELECT $tag$unterminated"), "SELECT ?");
+    }
+
+    #[test]
+    fn errors_are_classified_by_sqlstate() {
+        let database = |sqlstate: &str, message: &str| {
+            anyhow::Error::new(DatabaseError::new(sqlstate, message)).context("executing query")
+        };
+        assert_eq!(error_class(&database("23505", "duplicate key")), "constraint");
+        assert_eq!(error_class(&database("42601", "syntax error")), "syntax");
+        assert_eq!(error_class(&database("42P01", "relation does not exist")), "undefined_object");
+        assert_eq!(error_class(&database("40P01", "deadlock detected")), "serialization");
+        assert_eq!(error_class(&database("08006", "connection failure")), "connection");
+        assert_eq!(error_class(&database("57014", "canceling statement")), "timeout");
+        assert_eq!(error_class(&database("XX000", "internal error")), "other");
+        // A message is never read for its class.
+        assert_eq!(error_class(&anyhow!("UNIQUE constraint failed: t.id")), "other");
+    }
+}
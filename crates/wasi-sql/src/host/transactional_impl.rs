@@ -0,0 +1,136 @@
+use anyhow::Result;
+use wasmtime::component::{Access, Accessor, Resource};
+
+use crate::TransactionProxy;
+use crate::host::generated::omnia::sql::transactional::{
+    Connection, Error, ExecResult, Host, HostTransaction, HostTransactionWithStore, HostWithStore,
+    Row, Statement, Transaction, TransactionOptions,
+};
+use crate::host::readwrite_impl::{get_connection, get_statement};
+use crate::host::trace::traced;
+use crate::host::{WasiSql, WasiSqlCtxView};
+
+impl<T> HostWithStore<T> for WasiSql {
+    async fn query(
+        accessor: &Accessor<T, Self>, t: Resource<Transaction>, q: Resource<Statement>,
+    ) -> wasmtime::Result<Result<Vec<Row>, Resource<Error>>> {
+        let transaction = get_transaction(accessor, &t).map_err(wasmtime::Error::from_anyhow)?;
+        let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
+
+        let result = match traced(
+            &statement.query,
+            transaction.query(statement.query.clone(), statement.params),
+            |rows| Some(rows.len()),
+        )
+        .await
+        {
+            Ok(rows) => Ok(rows),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    async fn exec(
+        accessor: &Accessor<T, Self>, t: Resource<Transaction>, q: Resource<Statement>,
+    ) -> wasmtime::Result<Result<u32, Resource<Error>>> {
+        let transaction = get_transaction(accessor, &t).map_err(wasmtime::Error::from_anyhow)?;
+        let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
+
+        let result = match traced(
+            &statement.query,
+            transaction.exec(statement.query.clone(), statement.params),
+            |affected| usize::try_from(*affected).ok(),
+        )
+        .await
+        {
+            Ok(rows) => Ok(rows),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    async fn exec_with_result(
+        accessor: &Accessor<T, Self>, t: Resource<Transaction>, q: Resource<Statement>,
+    ) -> wasmtime::Result<Result<ExecResult, Resource<Error>>> {
+        let transaction = get_transaction(accessor, &t).map_err(wasmtime::Error::from_anyhow)?;
+        let statement = get_statement(accessor, &q).map_err(wasmtime::Error::from_anyhow)?;
+
+        let result = match traced(
+            &statement.query,
+            transaction.exec_with_result(statement.query.clone(), statement.params),
+            |outcome| usize::try_from(outcome.affected_rows).ok(),
+        )
+        .await
+        {
+            Ok(outcome) => Ok(outcome),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+}
+
+impl<T> HostTransactionWithStore<T> for WasiSql {
+    async fn begin(
+        accessor: &Accessor<T, Self>, c: Resource<Connection>, options: TransactionOptions,
+    ) -> wasmtime::Result<Result<Resource<Transaction>, Resource<Error>>> {
+        let connection = get_connection(accessor, &c).map_err(wasmtime::Error::from_anyhow)?;
+
+        let result = match connection.begin(options).await {
+            Ok(transaction) => {
+                let proxy = TransactionProxy(transaction);
+                Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
+            }
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    async fn commit(
+        accessor: &Accessor<T, Self>, t: Resource<Transaction>,
+    ) -> wasmtime::Result<Result<(), Resource<Error>>> {
+        let transaction = accessor.with(|mut store| store.get().table.delete(t))?;
+
+        let result = match transaction.commit().await {
+            Ok(()) => Ok(()),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    async fn rollback(
+        accessor: &Accessor<T, Self>, t: Resource<Transaction>,
+    ) -> wasmtime::Result<Result<(), Resource<Error>>> {
+        let transaction = accessor.with(|mut store| store.get().table.delete(t))?;
+
+        let result = match transaction.rollback().await {
+            Ok(()) => Ok(()),
+            Err(err) => Err(accessor.with(|mut store| store.get().table.push(Error::from(err)))?),
+        };
+
+        Ok(result)
+    }
+
+    fn drop(
+        mut accessor: Access<'_, T, Self>, rep: Resource<TransactionProxy>,
+    ) -> wasmtime::Result<()> {
+        // The backend rolls back a transaction dropped without commit.
+        accessor.get().table.delete(rep).map(|_| Ok(()))?
+    }
+}
+
+impl Host for WasiSqlCtxView<'_> {}
+impl HostTransaction for WasiSqlCtxView<'_> {}
+
+fn get_transaction<T>(
+    accessor: &Accessor<T, WasiSql>, self_: &Resource<TransactionProxy>,
+) -> Result<TransactionProxy> {
+    accessor.with(|mut store| {
+        let transaction = store.get().table.get(self_)?;
+        Ok::<_, anyhow::Error>(transaction.clone())
+    })
+}
@@ -18,3 +18,45 @@ mod generated {
 pub use self::generated::exports::omnia::websocket::*;
 pub use self::generated::omnia::websocket::*;
 pub use self::generated::*;
+
+// Bindings for the optional `authorizer` export. They live in their own world
+// so guests that do not authorize subscriptions need not implement it.
+#[doc(hidden)]
+pub mod subscription_authorizer {
+    #![allow(missing_docs)]
+
+    wit_bindgen::generate!({
+        world: "subscription-authorizer",
+        path: "wit",
+        with: {
+            "omnia:websocket/types@0.1.0": super::generated::omnia::websocket::types,
+        },
+        pub_export_macro: true,
+        export_macro_name: "export_authorizer",
+        default_bindings_module: "omnia_wasi_websocket::subscription_authorizer",
+    });
+}
+
+pub use self::subscription_authorizer::exports::omnia::websocket::authorizer;
+pub use self::subscription_authorizer::export_authorizer;
+
+// Bindings for the optional `connect-authorizer` export, in their own world
+// for the same reason.
+#[doc(hidden)]
+pub mod connection_authorizer {
+    #![allow(missing_docs)]
+
+    wit_bindgen::generate!({
+        world: "connection-authorizer",
+        path: "wit",
+        with: {
+            "omnia:websocket/types@0.1.0": super::generated::omnia::websocket::types,
+        },
+        pub_export_macro: true,
+        export_macro_name: "export_connect_authorizer",
+        default_bindings_module: "omnia_wasi_websocket::connection_authorizer",
+    });
+}
+
+pub use self::connection_authorizer::exports::omnia::websocket::connect_authorizer;
+pub use self::connection_authorizer::export_connect_authorizer;
@@ -33,6 +33,42 @@ mod generated {
     });
 }
 
+// Bindings for the optional `authorizer` export; a guest opts in by also
+// targeting the `subscription-authorizer` world.
+mod authorizer {
+    #![allow(missing_docs)]
+
+    wasmtime::component::bindgen!({
+        world: "subscription-authorizer",
+        path: "wit",
+        exports: {
+            default: store | tracing | trappable,
+        },
+        with: {
+            "omnia:websocket/types": super::generated::omnia::websocket::types,
+        },
+        require_store_data_send: true,
+    });
+}
+
+// Bindings for the optional `connect-authorizer` export; a guest opts in by
+// also targeting the `connection-authorizer` world.
+mod connect_authorizer {
+    #![allow(missing_docs)]
+
+    wasmtime::component::bindgen!({
+        world: "connection-authorizer",
+        path: "wit",
+        exports: {
+            default: store | tracing | trappable,
+        },
+        with: {
+            "omnia:websocket/types": super::generated::omnia::websocket::types,
+        },
+        require_store_data_send: true,
+    });
+}
+
 use std::fmt::Debug;
 use std::sync::Arc;
 
@@ -40,7 +76,9 @@ pub use omnia::FutureResult;
 use omnia::{Host, Runtime, Server, StoreCtx};
 use wasmtime::component::{HasData, Linker};
 
-pub use self::default_impl::{ConnectOptions, WebSocketDefault};
+pub use self::default_impl::{
+    ConnectOptions, Listener, OriginCheck, RateLimit, SlowPeer, Visibility, WebSocketDefault,
+};
 pub use self::generated::Duplex;
 pub use self::generated::omnia::websocket::types::Error;
 use self::generated::omnia::websocket::{client, types as generated_types};
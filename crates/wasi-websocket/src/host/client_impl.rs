@@ -2,7 +2,7 @@ use wasmtime::component::{Accessor, Resource};
 
 use crate::host::generated::omnia::websocket::client::{Host, HostWithStore};
 use crate::host::generated::omnia::websocket::types::SocketAddr;
-use crate::host::resource::{ClientProxy, Event};
+use crate::host::resource::{ClientProxy, Close, Event};
 use crate::host::types_impl::{get_client, get_event};
 use crate::host::{Result, WasiWebSocket, WasiWebSocketCtxView};
 
@@ -17,6 +17,41 @@ impl<T> HostWithStore<T> for WasiWebSocket {
 
         Ok(())
     }
+
+    async fn send_to_peer(
+        accessor: &Accessor<T, Self>, s: Resource<ClientProxy>, peer: SocketAddr,
+        event: Resource<Event>,
+    ) -> Result<()> {
+        let client = get_client(accessor, &s)?;
+        let evt = get_event(accessor, &event)?;
+        client.send_to_peer(peer, evt).await?;
+        Ok(())
+    }
+
+    async fn join_group(
+        accessor: &Accessor<T, Self>, s: Resource<ClientProxy>, peer: SocketAddr, group: String,
+    ) -> Result<()> {
+        let client = get_client(accessor, &s)?;
+        client.join_group(peer, group).await?;
+        Ok(())
+    }
+
+    async fn leave_group(
+        accessor: &Accessor<T, Self>, s: Resource<ClientProxy>, peer: SocketAddr, group: String,
+    ) -> Result<()> {
+        let client = get_client(accessor, &s)?;
+        client.leave_group(peer, group).await?;
+        Ok(())
+    }
+
+    async fn close_peer(
+        accessor: &Accessor<T, Self>, s: Resource<ClientProxy>, peer: SocketAddr, code: u16,
+        reason: String,
+    ) -> Result<()> {
+        let client = get_client(accessor, &s)?;
+        client.close_peer(peer, Close { code, reason }).await?;
+        Ok(())
+    }
 }
 
 impl Host for WasiWebSocketCtxView<'_> {}
@@ -1,59 +1,658 @@
 //! Default implementation for wasi-websocket
 //!
 //! This implementation runs a real tungstenite WebSocket server that external
-//! clients can connect to. Incoming messages from WS clients are broadcast as
-//! events to the guest handler. Outbound events from the guest are sent to
-//! connected WS clients, optionally filtered by group.
+//! clients can connect to. Incoming messages from WS clients are sent as
+//! events to the guest handler, and outbound events from the guest are sent
+//! to connected clients, optionally filtered by peer or group. Each
+//! [`Listener`] carries its own authentication, limits, and session policy.
 //!
 //! For production use, use a backend with proper WebSocket connection
 //! management and authentication.
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
 use dashmap::DashMap;
-use futures::FutureExt;
+use futures::{FutureExt, SinkExt};
 use futures_channel::mpsc;
 use futures_util::stream::TryStreamExt;
 use futures_util::{StreamExt, future, pin_mut};
 use omnia::{Backend, FutureResult};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast::{self, Receiver, Sender};
-use tokio_stream::wrappers::BroadcastStream;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+use tokio_stream::wrappers::{BroadcastStream, UnboundedReceiverStream};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::header::{
+    ORIGIN, SEC_WEBSOCKET_EXTENSIONS, SEC_WEBSOCKET_PROTOCOL,
+};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
 use tokio_tungstenite::tungstenite::{Error as WsError, Message};
-use tokio_tungstenite::{WebSocketStream, accept_async};
+use tokio_tungstenite::{WebSocketStream, accept_hdr_async_with_config};
 use tracing::instrument;
 
 use crate::host::WasiWebSocketCtx;
-use crate::host::resource::{Client, Event, Events};
+use crate::host::resource::{
+    Client, Close, ConnectRequest, ConnectRequests, Event, Events, MessageKind, SubscribeRequest,
+    SubscribeRequests,
+};
 
 const MAX_CONNECTIONS: usize = 1024;
-const BROADCAST_CHANNEL_CAPACITY: usize = 256;
-const PER_CLIENT_CHANNEL_CAPACITY: usize = 256;
+const BACKLOG: u32 = 1024;
+const EVENT_CAPACITY: usize = 256;
+const QUEUE_CAPACITY: usize = 256;
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const SEND_TIMEOUT: Duration = Duration::from_secs(1);
+const RESUME_REPLAY: usize = 64;
+// A close frame's payload is at most 125 bytes, two of them the code.
+const MAX_CLOSE_REASON: usize = 123;
 
-type ConnectionMap = Arc<DashMap<String, mpsc::Sender<Message>>>;
+type ConnectionMap = Arc<DashMap<String, Peer>>;
+type SessionMap = Arc<DashMap<String, Session>>;
+// The socket addresses of each group's members, so a send to a group visits
+// only its members.
+type GroupIndex = Arc<DashMap<String, HashSet<String>>>;
+// The connections open on each listener, by name.
+type OpenCounts = Arc<DashMap<String, usize>>;
+
+/// Decides whether a handshake from the given `Origin` is admitted.
+pub type OriginCheck = Arc<dyn Fn(&str) -> bool + Send + Sync>;
 
 /// Options used to connect to the WebSocket service.
 #[derive(Debug, Clone)]
 pub struct ConnectOptions {
-    /// The address to bind the WebSocket server to.
-    pub socket_addr: String,
+    /// The listeners to bind; each accepts connections independently.
+    pub listeners: Vec<Listener>,
+    /// Inbound events buffered for the guest handler before the slowest
+    /// subscriber starts missing them.
+    pub event_capacity: usize,
+    /// How often to publish a heartbeat event to the guest, if at all.
+    pub heartbeat: Option<Duration>,
+}
+
+impl ConnectOptions {
+    /// Options for a single unauthenticated listener on `socket_addr`.
+    #[must_use]
+    pub fn single(socket_addr: impl Into<String>) -> Self {
+        Self {
+            listeners: vec![Listener::new("default", socket_addr)],
+            event_capacity: EVENT_CAPACITY,
+            heartbeat: None,
+        }
+    }
 }
 
 impl omnia::FromEnv for ConnectOptions {
+    /// Reads `WEBSOCKET_EVENT_CAPACITY`, `WEBSOCKET_HEARTBEAT_SECS` (`0`
+    /// disables), `WEBSOCKET_LISTENERS` (comma-separated
+    /// names) and, for each name, `WEBSOCKET_<NAME>_ADDR`, `_TOKEN`,
+    /// `_AUTHORIZE`, `_VISIBILITY`, the socket options `_DUAL_STACK`,
+    /// `_REUSE_ADDRESS`, `_REUSE_PORT`, and `_BACKLOG`, and the connection
+    /// options `_ALLOWED_ORIGINS`, `_MAX_CONNECTIONS`, `_QUEUE_CAPACITY`,
+    /// `_PING_INTERVAL_SECS`, `_IDLE_TIMEOUT_SECS`, `_SEND_TIMEOUT_MS`,
+    /// `_SLOW_PEER`, `_MAX_MESSAGE_BYTES`, `_RATE_LIMIT`, `_RATE_BURST`,
+    /// `_PROTOCOLS`, `_PATH_GROUPS`, `_RESUME_WINDOW_SECS`, `_RESUME_REPLAY`,
+    /// `_CLOSE_EVENTS`, and `_OPEN_SUBSCRIBE`. Without `WEBSOCKET_LISTENERS`,
+    /// a single listener binds `WEBSOCKET_ADDR` with the socket and
+    /// connection options under `WEBSOCKET_`.
     fn from_env() -> Result<Self> {
-        let socket_addr =
-            std::env::var("WEBSOCKET_ADDR").unwrap_or_else(|_| "0.0.0.0:80".to_string());
-        Ok(Self { socket_addr })
+        let event_capacity = unsigned("WEBSOCKET", "EVENT_CAPACITY")?
+            .map(usize::try_from)
+            .transpose()
+            .map_err(|e| anyhow!("invalid WEBSOCKET_EVENT_CAPACITY: {e}"))?
+            .unwrap_or(EVENT_CAPACITY);
+        if event_capacity == 0 {
+            return Err(anyhow!("WEBSOCKET_EVENT_CAPACITY must be positive"));
+        }
+        let heartbeat = unsigned("WEBSOCKET", "HEARTBEAT_SECS")?
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+
+        let Ok(names) = std::env::var("WEBSOCKET_LISTENERS") else {
+            let socket_addr =
+                std::env::var("WEBSOCKET_ADDR").unwrap_or_else(|_| "0.0.0.0:80".to_string());
+            let listener = Listener::new("default", socket_addr)
+                .socket_options_from_env("WEBSOCKET")?
+                .connection_options_from_env("WEBSOCKET")?;
+            return Ok(Self {
+                listeners: vec![listener],
+                event_capacity,
+                heartbeat,
+            });
+        };
+
+        let listeners = names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(Listener::from_env)
+            .collect::<Result<Vec<_>>>()?;
+        if listeners.is_empty() {
+            return Err(anyhow!("WEBSOCKET_LISTENERS names no listeners"));
+        }
+        Ok(Self {
+            listeners,
+            event_capacity,
+            heartbeat,
+        })
+    }
+}
+
+/// A single bind address and the policy applied to its connections.
+// Each boolean is an independent per-listener switch read from its own
+// environment variable.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone)]
+pub struct Listener {
+    /// Name used in logs and environment variables.
+    pub name: String,
+    /// The address to bind to.
+    pub socket_addr: String,
+    /// Bearer token clients must present, if any.
+    pub token: Option<String>,
+    /// Browser origins admitted, such as `https://app.example.com`. With
+    /// neither these nor an origin check, every origin is admitted.
+    pub allowed_origins: Vec<String>,
+    /// Admits origins not in `allowed_origins`, if set.
+    pub check_origin: Option<OriginCheck>,
+    /// Whether the host must authorize each connection, given the bearer
+    /// token the peer presented, before the peer is admitted.
+    pub authorize: bool,
+    /// Whether peers may subscribe to any group without the host authorizing
+    /// the request. Off by default, so groups nobody authorizes are refused.
+    pub open_subscribe: bool,
+    /// Maximum concurrent connections on this listener. Handshakes past it
+    /// are refused with `503 Service Unavailable`.
+    pub max_connections: usize,
+    /// Outbound messages queued per peer before sends wait for room.
+    pub queue_capacity: usize,
+    /// Which outbound events reach this listener's peers.
+    pub visibility: Visibility,
+    /// Whether an IPv6 address also accepts IPv4 peers (clears `IPV6_V6ONLY`).
+    pub dual_stack: bool,
+    /// Set `SO_REUSEADDR`, so a restart can rebind while old connections
+    /// linger in `TIME_WAIT`.
+    pub reuse_address: bool,
+    /// Set `SO_REUSEPORT` (Unix only), so several processes can share the
+    /// port, e.g. an old and a new instance during a rolling restart.
+    pub reuse_port: bool,
+    /// Length of the queue of accepted connections awaiting `accept`.
+    pub backlog: u32,
+    /// How often to ping each peer, if at all.
+    pub ping_interval: Option<Duration>,
+    /// How long a peer may stay silent, pongs included, before it is
+    /// disconnected, if ever.
+    pub idle_timeout: Option<Duration>,
+    /// How long a send waits for room in a peer's outbound queue.
+    pub send_timeout: Duration,
+    /// What happens to a peer whose queue is still full after the send
+    /// timeout.
+    pub slow_peer: SlowPeer,
+    /// Largest inbound message, and frame, a peer may send, in bytes. A peer
+    /// that sends a larger one is disconnected. `None` keeps tungstenite's
+    /// defaults.
+    pub max_message_size: Option<usize>,
+    /// How many messages a peer may send, if limited. A peer that sends
+    /// faster is disconnected.
+    pub rate_limit: Option<RateLimit>,
+    /// Subprotocols the listener speaks, most preferred first. The handshake
+    /// selects the first one the peer offers in `Sec-WebSocket-Protocol`.
+    /// Extensions are never negotiated: tungstenite does not implement
+    /// permessage-deflate, so frames are always sent uncompressed.
+    pub protocols: Vec<String>,
+    /// Groups a peer joins on connecting, by the path it upgraded on.
+    pub path_groups: HashMap<String, Vec<String>>,
+    /// Whether, and for how long, a disconnected peer's session can be
+    /// resumed.
+    pub resume: Option<Resume>,
+    /// Whether each disconnect is sent to the guest as an event carrying the
+    /// close code and reason.
+    pub close_events: bool,
+}
+
+/// A token-bucket limit on the messages one peer sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Messages per second, sustained.
+    pub per_second: u32,
+    /// Messages a peer may send at once after being quiet.
+    pub burst: u32,
+}
+
+/// How long a disconnected peer's session is kept, and how much it buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resume {
+    /// How long after a disconnect the peer can resume its session.
+    pub window: Duration,
+    /// Most recent events kept for the disconnected peer; older ones are
+    /// dropped.
+    pub replay: usize,
+}
+
+impl Listener {
+    /// An unauthenticated listener with default limits that sees every event.
+    #[must_use]
+    pub fn new(name: impl Into<String>, socket_addr: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            socket_addr: socket_addr.into(),
+            token: None,
+            allowed_origins: Vec::new(),
+            check_origin: None,
+            authorize: false,
+            open_subscribe: false,
+            max_connections: MAX_CONNECTIONS,
+            queue_capacity: QUEUE_CAPACITY,
+            visibility: Visibility::All,
+            dual_stack: true,
+            reuse_address: true,
+            reuse_port: false,
+            backlog: BACKLOG,
+            ping_interval: Some(PING_INTERVAL),
+            idle_timeout: Some(IDLE_TIMEOUT),
+            send_timeout: SEND_TIMEOUT,
+            slow_peer: SlowPeer::Drop,
+            max_message_size: None,
+            rate_limit: None,
+            protocols: Vec::new(),
+            path_groups: HashMap::new(),
+            resume: None,
+            close_events: false,
+        }
+    }
+
+    fn from_env(name: &str) -> Result<Self> {
+        let prefix = format!("WEBSOCKET_{}", name.to_ascii_uppercase());
+        let var = |suffix: &str| std::env::var(format!("{prefix}_{suffix}")).ok();
+
+        let socket_addr = var("ADDR").ok_or_else(|| anyhow!("{prefix}_ADDR is not set"))?;
+        let visibility = var("VISIBILITY").as_deref().map(Visibility::parse).transpose()?;
+
+        Self {
+            token: var("TOKEN"),
+            authorize: flag(&prefix, "AUTHORIZE")?.unwrap_or(false),
+            visibility: visibility.unwrap_or(Visibility::All),
+            ..Self::new(name, socket_addr)
+        }
+        .socket_options_from_env(&prefix)?
+        .connection_options_from_env(&prefix)
+    }
+
+    /// Override the socket options from `<prefix>_DUAL_STACK`,
+    /// `_REUSE_ADDRESS`, `_REUSE_PORT`, and `_BACKLOG`.
+    fn socket_options_from_env(mut self, prefix: &str) -> Result<Self> {
+        if let Some(dual_stack) = flag(prefix, "DUAL_STACK")? {
+            self.dual_stack = dual_stack;
+        }
+        if let Some(reuse_address) = flag(prefix, "REUSE_ADDRESS")? {
+            self.reuse_address = reuse_address;
+        }
+        if let Some(reuse_port) = flag(prefix, "REUSE_PORT")? {
+            self.reuse_port = reuse_port;
+        }
+        if let Ok(backlog) = std::env::var(format!("{prefix}_BACKLOG")) {
+            self.backlog = backlog.parse().map_err(|e| anyhow!("invalid {prefix}_BACKLOG: {e}"))?;
+        }
+        Ok(self)
+    }
+
+    /// Override the connection options from `<prefix>_ALLOWED_ORIGINS`
+    /// (comma-separated), `_MAX_CONNECTIONS`, `_QUEUE_CAPACITY`,
+    /// `_PING_INTERVAL_SECS`, `_IDLE_TIMEOUT_SECS`, `_SEND_TIMEOUT_MS`,
+    /// `_SLOW_PEER`, `_MAX_MESSAGE_BYTES`, `_RATE_LIMIT`, `_RATE_BURST`,
+    /// `_PROTOCOLS` (comma-separated), `_PATH_GROUPS` (comma-separated
+    /// `path=group` entries), `_RESUME_WINDOW_SECS`, `_RESUME_REPLAY`,
+    /// `_CLOSE_EVENTS`, and `_OPEN_SUBSCRIBE`, where `0` disables pings, the
+    /// idle timeout, the size limit, the rate limit, or session resumption.
+    fn connection_options_from_env(mut self, prefix: &str) -> Result<Self> {
+        if let Ok(origins) = std::env::var(format!("{prefix}_ALLOWED_ORIGINS")) {
+            self.allowed_origins = origins
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/'))
+                .filter(|origin| !origin.is_empty())
+                .map(ToString::to_string)
+                .collect();
+        }
+        if let Some(max) = unsigned(prefix, "MAX_CONNECTIONS")? {
+            self.max_connections = usize::try_from(max)
+                .map_err(|e| anyhow!("invalid {prefix}_MAX_CONNECTIONS: {e}"))?;
+        }
+        if let Some(capacity) = unsigned(prefix, "QUEUE_CAPACITY")? {
+            let capacity = usize::try_from(capacity)
+                .map_err(|e| anyhow!("invalid {prefix}_QUEUE_CAPACITY: {e}"))?;
+            if capacity == 0 {
+                return Err(anyhow!("{prefix}_QUEUE_CAPACITY must be positive"));
+            }
+            self.queue_capacity = capacity;
+        }
+        if let Some(interval) = unsigned(prefix, "PING_INTERVAL_SECS")? {
+            self.ping_interval = (interval > 0).then_some(Duration::from_secs(interval));
+        }
+        if let Some(timeout) = unsigned(prefix, "IDLE_TIMEOUT_SECS")? {
+            self.idle_timeout = (timeout > 0).then_some(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = unsigned(prefix, "SEND_TIMEOUT_MS")? {
+            self.send_timeout = Duration::from_millis(timeout);
+        }
+        if let Ok(slow_peer) = std::env::var(format!("{prefix}_SLOW_PEER")) {
+            self.slow_peer = SlowPeer::parse(&slow_peer)?;
+        }
+        if let Some(size) = unsigned(prefix, "MAX_MESSAGE_BYTES")? {
+            let size = usize::try_from(size)
+                .map_err(|e| anyhow!("invalid {prefix}_MAX_MESSAGE_BYTES: {e}"))?;
+            self.max_message_size = (size > 0).then_some(size);
+        }
+        if let Some(per_second) = unsigned(prefix, "RATE_LIMIT")? {
+            let per_second = u32::try_from(per_second)
+                .map_err(|e| anyhow!("invalid {prefix}_RATE_LIMIT: {e}"))?;
+            let burst = unsigned(prefix, "RATE_BURST")?
+                .map(|burst| {
+                    u32::try_from(burst).map_err(|e| anyhow!("invalid {prefix}_RATE_BURST: {e}"))
+                })
+                .transpose()?
+                .unwrap_or(per_second);
+            self.rate_limit = (per_second > 0).then_some(RateLimit { per_second, burst });
+        }
+        if let Ok(protocols) = std::env::var(format!("{prefix}_PROTOCOLS")) {
+            self.protocols = protocols
+                .split(',')
+                .map(str::trim)
+                .filter(|protocol| !protocol.is_empty())
+                .map(ToString::to_string)
+                .collect();
+        }
+        if let Ok(entries) = std::env::var(format!("{prefix}_PATH_GROUPS")) {
+            self.path_groups = HashMap::new();
+            for entry in entries.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let Some((path, group)) = entry.split_once('=') else {
+                    return Err(anyhow!(
+                        "invalid {prefix}_PATH_GROUPS entry '{entry}': expected path=group"
+                    ));
+                };
+                self.path_groups
+                    .entry(path.trim().to_string())
+                    .or_default()
+                    .push(group.trim().to_string());
+            }
+        }
+        if let Some(window) = unsigned(prefix, "RESUME_WINDOW_SECS")? {
+            let replay = unsigned(prefix, "RESUME_REPLAY")?
+                .map(|replay| {
+                    usize::try_from(replay)
+                        .map_err(|e| anyhow!("invalid {prefix}_RESUME_REPLAY: {e}"))
+                })
+                .transpose()?
+                .unwrap_or(RESUME_REPLAY);
+            self.resume = (window > 0).then_some(Resume {
+                window: Duration::from_secs(window),
+                replay,
+            });
+        }
+        if let Some(close_events) = flag(prefix, "CLOSE_EVENTS")? {
+            self.close_events = close_events;
+        }
+        if let Some(open_subscribe) = flag(prefix, "OPEN_SUBSCRIBE")? {
+            self.open_subscribe = open_subscribe;
+        }
+        Ok(self)
+    }
+
+    /// Bind the first address `socket_addr` resolves to that accepts this
+    /// listener's socket options.
+    async fn bind(&self) -> Result<TcpListener> {
+        let mut last_error = None;
+        for addr in tokio::net::lookup_host(&self.socket_addr).await? {
+            match self.bind_addr(addr) {
+                Ok(tcp) => return Ok(tcp),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("{} resolves to no addresses", self.socket_addr)))
+    }
+
+    fn bind_addr(&self, addr: SocketAddr) -> Result<TcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        if addr.is_ipv6() {
+            socket.set_only_v6(!self.dual_stack)?;
+        }
+        socket.set_reuse_address(self.reuse_address)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(self.reuse_port)?;
+        #[cfg(not(unix))]
+        if self.reuse_port {
+            tracing::warn!("listener {}: SO_REUSEPORT is not supported here", self.name);
+        }
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(i32::try_from(self.backlog).unwrap_or(i32::MAX))?;
+        Ok(TcpListener::from_std(socket.into())?)
+    }
+
+    /// The most preferred of this listener's subprotocols the handshake
+    /// offers, if any.
+    fn negotiate(&self, request: &Request) -> Option<HeaderValue> {
+        let offered = request
+            .headers()
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        self.protocols
+            .iter()
+            .find(|protocol| offered.contains(&protocol.as_str()))
+            .and_then(|protocol| HeaderValue::from_str(protocol).ok())
+    }
+
+    /// Whether the handshake's `Origin`, if any, is admitted.
+    fn origin_allowed(&self, request: &Request) -> bool {
+        if self.allowed_origins.is_empty() && self.check_origin.is_none() {
+            return true;
+        }
+        let Some(origin) = request.headers().get(ORIGIN) else {
+            return true;
+        };
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        self.allowed_origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+            || self.check_origin.as_ref().is_some_and(|check| check(origin))
+    }
+
+    /// Whether the handshake carries this listener's token.
+    fn authorized(&self, request: &Request) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        bearer(request) == Some(token.as_str())
+    }
+}
+
+/// The bearer token a handshake carries, as an `Authorization: Bearer` header
+/// or a `token` query parameter (browsers cannot set headers on WebSocket
+/// requests).
+fn bearer(request: &Request) -> Option<&str> {
+    let header = request
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    header.or_else(|| query(request, "token"))
+}
+
+/// Whether the handshake offers permessage-deflate, which is declined by
+/// leaving it out of the response.
+fn offers_deflate(request: &Request) -> bool {
+    request
+        .headers()
+        .get_all(SEC_WEBSOCKET_EXTENSIONS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|extension| extension.trim().starts_with("permessage-deflate"))
+}
+
+/// The value of the handshake's query parameter `name`, if present.
+fn query<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| {
+            pair.split_once('=').filter(|(key, _)| *key == name).map(|(_, value)| value)
+        })
+    })
+}
+
+/// Read the boolean environment variable `<prefix>_<suffix>`, if set.
+fn flag(prefix: &str, suffix: &str) -> Result<Option<bool>> {
+    std::env::var(format!("{prefix}_{suffix}"))
+        .ok()
+        .map(|value| match value.to_ascii_lowercase().as_str() {
+            "yes" | "true" | "on" | "1" => Ok(true),
+            "no" | "false" | "off" | "0" => Ok(false),
+            other => Err(anyhow!("invalid {prefix}_{suffix} '{other}': expected true or false")),
+        })
+        .transpose()
+}
+
+/// Read the unsigned integer `<prefix>_<suffix>`, if set.
+fn unsigned(prefix: &str, suffix: &str) -> Result<Option<u64>> {
+    std::env::var(format!("{prefix}_{suffix}"))
+        .ok()
+        .map(|value| value.parse().map_err(|e| anyhow!("invalid {prefix}_{suffix}: {e}")))
+        .transpose()
+}
+
+// Keep the token out of `#[instrument]`ed connect options.
+impl std::fmt::Debug for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Listener")
+            .field("name", &self.name)
+            .field("socket_addr", &self.socket_addr)
+            .field("token", &self.token.as_ref().map(|_| "[redacted]"))
+            .field("allowed_origins", &self.allowed_origins)
+            .field("check_origin", &self.check_origin.as_ref().map(|_| "Fn"))
+            .field("authorize", &self.authorize)
+            .field("open_subscribe", &self.open_subscribe)
+            .field("max_connections", &self.max_connections)
+            .field("queue_capacity", &self.queue_capacity)
+            .field("visibility", &self.visibility)
+            .field("dual_stack", &self.dual_stack)
+            .field("reuse_address", &self.reuse_address)
+            .field("reuse_port", &self.reuse_port)
+            .field("backlog", &self.backlog)
+            .field("ping_interval", &self.ping_interval)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("send_timeout", &self.send_timeout)
+            .field("slow_peer", &self.slow_peer)
+            .field("max_message_size", &self.max_message_size)
+            .field("rate_limit", &self.rate_limit)
+            .field("protocols", &self.protocols)
+            .field("path_groups", &self.path_groups)
+            .field("resume", &self.resume)
+            .field("close_events", &self.close_events)
+            .finish()
+    }
+}
+
+/// Which outbound events a listener's peers receive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// Broadcasts and events addressed to the peer's socket.
+    All,
+    /// Only events explicitly addressed to the peer's socket.
+    Targeted,
+}
+
+impl Visibility {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "all" => Ok(Self::All),
+            "targeted" => Ok(Self::Targeted),
+            other => {
+                Err(anyhow!("invalid listener visibility '{other}': expected all or targeted"))
+            }
+        }
+    }
+}
+
+/// What a send does with a peer whose outbound queue stays full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowPeer {
+    /// Disconnect the peer, which may reconnect and resubscribe.
+    Drop,
+    /// Keep the peer, which misses this event.
+    Block,
+}
+
+impl SlowPeer {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "drop" => Ok(Self::Drop),
+            "block" => Ok(Self::Block),
+            other => Err(anyhow!("invalid slow peer policy '{other}': expected drop or block")),
+        }
     }
 }
 
+/// A connected peer, the listener it arrived on, the groups it joined, and its
+/// session, if the listener keeps them.
+#[derive(Debug)]
+struct Peer {
+    listener: Arc<Listener>,
+    tx: mpsc::Sender<Message>,
+    groups: HashSet<String>,
+    session: Option<String>,
+}
+
+/// The session of a disconnected peer, kept until it resumes or the session
+/// expires: the groups it had joined and the events it has missed since.
+#[derive(Debug)]
+struct Session {
+    listener: Arc<Listener>,
+    groups: HashSet<String>,
+    missed: VecDeque<Message>,
+    expires: Instant,
+}
+
+/// Group membership control frames exchanged with peers.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Control {
+    Subscribe { groups: Vec<String> },
+    Unsubscribe { groups: Vec<String> },
+    Subscribed { groups: Vec<String> },
+    Session { id: String },
+}
+
 /// Default implementation for `wasi:websocket`.
 #[derive(Debug)]
 pub struct WebSocketDefault {
     event_tx: Sender<Event>,
     event_rx: Receiver<Event>,
     connections: ConnectionMap,
+    members: GroupIndex,
+    sessions: SessionMap,
+    open: OpenCounts,
+    request_tx: UnboundedSender<SubscribeRequest>,
+    // Taken by the first `subscribe_requests` caller, the host's authorizer.
+    request_rx: Arc<Mutex<Option<UnboundedReceiver<SubscribeRequest>>>>,
+    connect_tx: UnboundedSender<ConnectRequest>,
+    // Taken by the first `connect_requests` caller, likewise.
+    connect_rx: Arc<Mutex<Option<UnboundedReceiver<ConnectRequest>>>>,
+    // Set once on shutdown; listeners stop accepting when it is.
+    closing: Arc<watch::Sender<bool>>,
 }
 
 impl Clone for WebSocketDefault {
@@ -62,6 +661,14 @@ impl Clone for WebSocketDefault {
             event_tx: self.event_tx.clone(),
             event_rx: self.event_tx.subscribe(),
             connections: Arc::clone(&self.connections),
+            members: Arc::clone(&self.members),
+            sessions: Arc::clone(&self.sessions),
+            open: Arc::clone(&self.open),
+            request_tx: self.request_tx.clone(),
+            request_rx: Arc::clone(&self.request_rx),
+            connect_tx: self.connect_tx.clone(),
+            connect_rx: Arc::clone(&self.connect_rx),
+            closing: Arc::clone(&self.closing),
         }
     }
 }
@@ -73,21 +680,38 @@ impl Backend for WebSocketDefault {
     async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
         tracing::debug!("using default WebSocket backend");
 
-        let (event_tx, event_rx) = broadcast::channel::<Event>(BROADCAST_CHANNEL_CAPACITY);
+        let (event_tx, event_rx) = broadcast::channel::<Event>(options.event_capacity);
         let connections: ConnectionMap = Arc::new(DashMap::new());
+        let (request_tx, request_rx) = unbounded_channel();
+        let (connect_tx, connect_rx) = unbounded_channel();
 
         let websocket = Self {
             event_tx,
             event_rx,
             connections,
+            members: Arc::new(DashMap::new()),
+            sessions: Arc::new(DashMap::new()),
+            open: Arc::new(DashMap::new()),
+            request_tx,
+            request_rx: Arc::new(Mutex::new(Some(request_rx))),
+            connect_tx,
+            connect_rx: Arc::new(Mutex::new(Some(connect_rx))),
+            closing: Arc::new(watch::Sender::new(false)),
         };
         let server = websocket.clone();
 
-        tokio::spawn(async move {
-            if let Err(e) = server.listen(options.socket_addr).await {
-                tracing::error!("issue starting websocket server: {e}");
-            }
-        });
+        if let Some(period) = options.heartbeat {
+            tokio::spawn(server.clone().heartbeat(period));
+        }
+        for listener in options.listeners {
+            let server = server.clone();
+            let listener = Arc::new(listener);
+            tokio::spawn(async move {
+                if let Err(e) = server.listen(Arc::clone(&listener)).await {
+                    tracing::error!("issue starting websocket listener {}: {e}", listener.name);
+                }
+            });
+        }
 
         Ok(websocket)
     }
@@ -119,23 +743,166 @@ impl Client for WebSocketDefault {
         .boxed()
     }
 
-    /// Send event to WebSocket clients, optionally filtered by group.
+    /// Send event to WebSocket clients, optionally filtered by socket or group.
     fn send(&self, event: Event, sockets: Option<Vec<String>>) -> FutureResult<()> {
         tracing::debug!("sending event to WebSocket clients, sockets: {:?}", sockets);
 
-        self.connections.retain(|_, sender| !sender.is_closed());
+        let msg = frame(event);
+        self.buffer(sockets.as_deref(), &msg);
 
-        let msg = Message::Binary(event.data.into());
-        for mut entry in self.connections.iter_mut() {
-            if sockets.as_ref().is_some_and(|s| !s.contains(entry.key())) {
-                continue;
+        // A broadcast visits every peer; a targeted send visits only the peers
+        // addressed and the members of the groups named.
+        let addressed = sockets.map(|targets| {
+            let mut addressed = HashSet::new();
+            for target in targets {
+                // Copy the members out, so no index lock is held while peers
+                // are looked up.
+                let members = self.members.get(&target).map(|members| members.value().clone());
+                if let Some(members) = members {
+                    tracing::debug!(
+                        histogram.websocket_group_fanout = members.len() as u64,
+                        group = %target
+                    );
+                    addressed.extend(members);
+                }
+                if self.connections.contains_key(&target) {
+                    addressed.insert(target);
+                }
+            }
+            addressed
+        });
+        let reach = |socket_addr: &str, peer: &Peer| {
+            (!peer.tx.is_closed())
+                .then(|| (socket_addr.to_string(), Arc::clone(&peer.listener), peer.tx.clone()))
+        };
+        let peers = addressed.map_or_else(
+            || {
+                self.connections
+                    .iter()
+                    .filter(|entry| entry.value().listener.visibility == Visibility::All)
+                    .filter_map(|entry| reach(entry.key(), entry.value()))
+                    .collect::<Vec<_>>()
+            },
+            |addressed| {
+                addressed
+                    .iter()
+                    .filter_map(|socket_addr| {
+                        let peer = self.connections.get(socket_addr)?;
+                        reach(socket_addr, &peer)
+                    })
+                    .collect()
+            },
+        );
+        tracing::debug!(histogram.websocket_fanout = peers.len() as u64);
+
+        // Peers are sent to concurrently, so the slowest bounds the send.
+        let sends = peers.into_iter().map(move |(socket_addr, listener, tx)| {
+            deliver(socket_addr, listener, tx, msg.clone())
+        });
+        async move {
+            let delivered = future::join_all(sends).await;
+            report_delivery(&delivered);
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn subscribe_requests(&self) -> FutureResult<SubscribeRequests> {
+        let receiver = self.request_rx.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+        async move {
+            let receiver =
+                receiver.ok_or_else(|| anyhow!("subscribe requests are already being consumed"))?;
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as SubscribeRequests)
+        }
+        .boxed()
+    }
+
+    fn send_to_peer(&self, peer: String, event: Event) -> FutureResult<()> {
+        let connected = self
+            .connections
+            .get(&peer)
+            .filter(|connected| !connected.tx.is_closed())
+            .map(|connected| (Arc::clone(&connected.listener), connected.tx.clone()));
+        let msg = frame(event);
+
+        async move {
+            let (listener, tx) =
+                connected.ok_or_else(|| anyhow!("peer {peer} is not connected"))?;
+            report_delivery(&[deliver(peer, listener, tx, msg).await]);
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn shutdown(&self) -> FutureResult<()> {
+        tracing::info!("closing websocket listeners and {} connections", self.connections.len());
+        self.closing.send_replace(true);
+
+        let going_away = Message::Close(Some(CloseFrame {
+            code: CloseCode::Away,
+            reason: "server shutting down".into(),
+        }));
+        for mut peer in self.connections.iter_mut() {
+            if let Err(e) = peer.tx.try_send(going_away.clone()) {
+                tracing::debug!("failed to close peer {}: {e}", peer.key());
             }
-            if let Err(e) = entry.value_mut().try_send(msg.clone()) {
-                tracing::warn!("failed to send to peer, channel full or disconnected: {e}");
+        }
+        async { Ok(()) }.boxed()
+    }
+
+    fn join_group(&self, peer: String, group: String) -> FutureResult<()> {
+        let joined = self.connections.get_mut(&peer).map(|mut connected| {
+            self.join(&peer, &mut connected, group);
+        });
+        async move { joined.ok_or_else(|| anyhow!("peer {peer} is not connected")) }.boxed()
+    }
+
+    fn leave_group(&self, peer: String, group: String) -> FutureResult<()> {
+        let left = self.connections.get_mut(&peer).map(|mut connected| {
+            self.leave(&peer, &mut connected, &group);
+        });
+        async move { left.ok_or_else(|| anyhow!("peer {peer} is not connected")) }.boxed()
+    }
+
+    fn close_peer(&self, peer: String, close: Close) -> FutureResult<()> {
+        let tx = self
+            .connections
+            .get(&peer)
+            .filter(|connected| !connected.tx.is_closed())
+            .map(|connected| connected.tx.clone());
+
+        async move {
+            let mut tx = tx.ok_or_else(|| anyhow!("peer {peer} is not connected"))?;
+            if !sendable(close.code) {
+                return Err(anyhow!("close code {} cannot be sent to a peer", close.code));
+            }
+            if close.reason.len() > MAX_CLOSE_REASON {
+                return Err(anyhow!("close reason exceeds {MAX_CLOSE_REASON} bytes"));
             }
+            let frame = CloseFrame {
+                code: CloseCode::from(close.code),
+                reason: close.reason.into(),
+            };
+            if let Err(e) = tx.try_send(Message::Close(Some(frame))) {
+                // The close frame cannot be queued; drop the connection instead.
+                tracing::debug!("failed to send close frame to {peer}: {e}");
+                tx.close_channel();
+            }
+            Ok(())
         }
+        .boxed()
+    }
+
+    fn connect_requests(&self) -> FutureResult<ConnectRequests> {
+        let receiver = self.connect_rx.lock().unwrap_or_else(PoisonError::into_inner).take();
 
-        async move { Ok(()) }.boxed()
+        async move {
+            let receiver =
+                receiver.ok_or_else(|| anyhow!("connect requests are already being consumed"))?;
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as ConnectRequests)
+        }
+        .boxed()
     }
 }
 
@@ -145,12 +912,36 @@ impl Client for WebSocketDefault {
 /// separate task. It broadcasts incoming messages to all connected peers and
 /// forwards outgoing messages to connected clients.
 impl WebSocketDefault {
-    async fn listen(self, socket_addr: String) -> Result<()> {
-        let listener = TcpListener::bind(socket_addr).await?;
-        tracing::info!("websocket server listening on: {}", listener.local_addr()?);
+    /// Publish a heartbeat event to the guest every `period` until shutdown.
+    async fn heartbeat(self, period: Duration) {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut closing = self.closing.subscribe();
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {}
+                _ = closing.wait_for(|closing| *closing) => return,
+            }
+            if let Err(e) = self.event_tx.send(Event::heartbeat()) {
+                tracing::debug!("no subscriber for websocket heartbeat: {e}");
+            }
+        }
+    }
+
+    async fn listen(self, listener: Arc<Listener>) -> Result<()> {
+        let tcp = listener.bind().await?;
+        tracing::info!("websocket listener {} listening on: {}", listener.name, tcp.local_addr()?);
 
+        let mut closing = self.closing.subscribe();
         loop {
-            let (stream, sender_addr) = match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = tcp.accept() => accepted,
+                _ = closing.wait_for(|closing| *closing) => {
+                    tracing::info!("websocket listener {} stopped accepting", listener.name);
+                    return Ok(());
+                }
+            };
+            let (stream, sender_addr) = match accepted {
                 Ok(conn) => conn,
                 Err(e) => {
                     tracing::error!("accept error: {e}");
@@ -160,67 +951,823 @@ impl WebSocketDefault {
             tracing::info!("new connection from: {sender_addr}");
 
             let server = self.clone();
+            let listener = Arc::clone(&listener);
             tokio::spawn(async move {
-                match accept_async(stream).await {
-                    Ok(ws_stream) => server.handle_socket(ws_stream, sender_addr.to_string()).await,
-                    Err(e) => tracing::error!("handshake failed for {sender_addr}: {e}"),
+                // Take the connection's place before upgrading, so a full
+                // listener refuses the handshake rather than the socket.
+                let slot = Slot::reserve(&server.open, &listener);
+                let full = slot.is_none();
+                let (mut token, mut session, mut groups) = (None, None, HashSet::new());
+                // The handshake callback's error type is fixed by tungstenite.
+                #[allow(clippy::result_large_err)]
+                let authorize = |request: &Request, mut response: Response| {
+                    if full {
+                        tracing::warn!(
+                            "refusing {sender_addr}: max connections reached on listener {}",
+                            listener.name
+                        );
+                        let mut rejection = ErrorResponse::new(Some("too many connections".into()));
+                        *rejection.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                        return Err(rejection);
+                    }
+                    token = bearer(request).map(ToString::to_string);
+                    session = query(request, "session").map(ToString::to_string);
+                    if let Some(joined) = listener.path_groups.get(request.uri().path()) {
+                        groups.extend(joined.iter().cloned());
+                    }
+                    if !listener.origin_allowed(request) {
+                        tracing::warn!(
+                            monotonic_counter.refused_origins = 1,
+                            "refusing {sender_addr}: origin not allowed"
+                        );
+                        let mut rejection = ErrorResponse::new(Some("forbidden".to_string()));
+                        *rejection.status_mut() = StatusCode::FORBIDDEN;
+                        return Err(rejection);
+                    }
+                    if offers_deflate(request) {
+                        tracing::debug!("declining permessage-deflate from {sender_addr}");
+                    }
+                    if listener.authorized(request) {
+                        if let Some(protocol) = listener.negotiate(request) {
+                            response.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, protocol);
+                        }
+                        Ok(response)
+                    } else {
+                        let mut rejection = ErrorResponse::new(Some("unauthorized".to_string()));
+                        *rejection.status_mut() = StatusCode::UNAUTHORIZED;
+                        Err(rejection)
+                    }
+                };
+                let mut config = WebSocketConfig::default();
+                if let Some(size) = listener.max_message_size {
+                    config = config.max_message_size(Some(size)).max_frame_size(Some(size));
                 }
+                let handshake = accept_hdr_async_with_config(stream, authorize, Some(config)).await;
+                let (mut ws_stream, slot) = match (handshake, slot) {
+                    (Ok(ws_stream), Some(slot)) => (ws_stream, slot),
+                    (Ok(_), None) => return,
+                    (Err(e), _) => {
+                        tracing::error!("handshake failed for {sender_addr}: {e}");
+                        return;
+                    }
+                };
+
+                let socket_addr = sender_addr.to_string();
+                if listener.authorize && !server.admit(&socket_addr, token).await {
+                    let frame = CloseFrame {
+                        code: CloseCode::Policy,
+                        reason: "unauthorized".into(),
+                    };
+                    if let Err(e) = ws_stream.close(Some(frame)).await {
+                        tracing::debug!("issue closing refused connection {socket_addr}: {e}");
+                    }
+                    return;
+                }
+                server.handle_socket(ws_stream, socket_addr, listener, groups, session, slot).await;
             });
         }
     }
 
-    async fn handle_socket(&self, ws_stream: WebSocketStream<TcpStream>, socket_addr: String) {
-        let (tx, rx) = mpsc::channel(PER_CLIENT_CHANNEL_CAPACITY);
+    async fn handle_socket(
+        &self, ws_stream: WebSocketStream<TcpStream>, socket_addr: String, listener: Arc<Listener>,
+        groups: HashSet<String>, session: Option<String>, _slot: Slot,
+    ) {
+        let (tx, rx) = mpsc::channel(listener.queue_capacity);
+        let (pinger, options) = (tx.clone(), Arc::clone(&listener));
 
-        if let Err(e) = self.add_socket(socket_addr.clone(), tx) {
-            tracing::error!("issue adding peer connection: {e}");
-            return;
+        let peer = Peer {
+            listener,
+            tx,
+            groups,
+            session: None,
+        };
+        self.add_socket(socket_addr.clone(), peer);
+        if options.resume.is_some() {
+            self.open_session(&socket_addr, session);
         }
 
-        let (outgoing, incoming) = ws_stream.split();
+        let (mut outgoing, incoming) = ws_stream.split();
+        let last_seen = Mutex::new(Instant::now());
+        let mut bucket = options.rate_limit.map(TokenBucket::new);
 
-        let incoming_broadcaster = incoming.try_for_each(|msg| {
-            match msg {
-                Message::Text(text) => {
-                    self.send_to_guest(socket_addr.clone(), text.as_bytes().to_vec());
+        // The first close, sent or received, is the one reported to the guest.
+        let closed = Mutex::new(None);
+        let close_with = |code: CloseCode, reason: &str| {
+            closed.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert_with(|| Close {
+                code: code.into(),
+                reason: reason.to_string(),
+            });
+        };
+        // A close the host decides on is sent once the tasks below stop, as
+        // the forwarder holds the sink until then.
+        let host_close = Mutex::new(None);
+        let close_by_host = |code: CloseCode, reason: &'static str| {
+            close_with(code, reason);
+            host_close.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(CloseFrame {
+                code,
+                reason: reason.into(),
+            });
+        };
+
+        {
+            let incoming_broadcaster = incoming.try_for_each(|msg| {
+                *last_seen.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+                let data = matches!(msg, Message::Text(_) | Message::Binary(_));
+                if data {
+                    tracing::debug!(
+                        monotonic_counter.websocket_messages_in = 1,
+                        listener = %options.name
+                    );
                 }
-                Message::Binary(data) => self.send_to_guest(socket_addr.clone(), data.to_vec()),
-                Message::Close(_) => {
-                    tracing::info!("peer {socket_addr} sent close frame");
+                if data && bucket.as_mut().is_some_and(|bucket| !bucket.take()) {
+                    tracing::warn!(
+                        monotonic_counter.rate_limited_disconnects = 1,
+                        "disconnecting {socket_addr}: rate limit exceeded"
+                    );
+                    close_by_host(CloseCode::Policy, "rate limit exceeded");
                     return future::err(WsError::ConnectionClosed);
                 }
-                _ => {}
+                match msg {
+                    Message::Text(text) => match serde_json::from_str::<Control>(&text) {
+                        Ok(control) => self.control(&socket_addr, control),
+                        Err(_) => self.send_to_guest(&socket_addr, Event::text(text.to_string())),
+                    },
+                    Message::Binary(data) => {
+                        self.send_to_guest(&socket_addr, Event::new(data.to_vec()));
+                    }
+                    Message::Close(frame) => {
+                        tracing::info!("peer {socket_addr} sent close frame");
+                        match frame {
+                            Some(frame) => close_with(frame.code, &frame.reason),
+                            None => close_with(CloseCode::Status, ""),
+                        }
+                        return future::err(WsError::ConnectionClosed);
+                    }
+                    _ => {}
+                }
+                future::ok(())
+            });
+
+            let outgoing_forwarder = rx
+                .map(|msg| {
+                    if let Message::Close(Some(frame)) = &msg {
+                        close_with(frame.code, &frame.reason);
+                    }
+                    Ok(msg)
+                })
+                .forward(&mut outgoing);
+            let idle = async {
+                keepalive(&options, pinger, &socket_addr, &last_seen).await;
+                close_by_host(CloseCode::Away, "idle timeout");
+            };
+
+            pin_mut!(incoming_broadcaster, outgoing_forwarder, idle);
+            future::select(incoming_broadcaster, future::select(outgoing_forwarder, idle)).await
+        };
+
+        let frame = host_close.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if let Some(frame) = frame {
+            let sent = outgoing.send(Message::Close(Some(frame)));
+            if !matches!(tokio::time::timeout(options.send_timeout, sent).await, Ok(Ok(()))) {
+                tracing::debug!("failed to send close frame to {socket_addr}");
             }
-            future::ok(())
+        }
+
+        let close = closed.lock().unwrap_or_else(PoisonError::into_inner).take();
+        let close = close.unwrap_or_else(|| Close {
+            code: CloseCode::Abnormal.into(),
+            reason: "connection lost".to_string(),
         });
+        tracing::info!("{socket_addr} disconnected with close code {}", close.code);
+        self.disconnected(&socket_addr, close, options.close_events);
+    }
+
+    /// Forget the disconnected `socket_addr`, suspending its session, and
+    /// report the close to the guest if the listener asks for it.
+    fn disconnected(&self, socket_addr: &str, close: Close, close_events: bool) {
+        if let Some((_, peer)) = self.connections.remove(socket_addr) {
+            for group in &peer.groups {
+                self.unindex(socket_addr, group);
+            }
+            self.suspend(&peer);
+        }
+        if close_events {
+            self.send_to_guest(socket_addr, Event::disconnected(close));
+        }
+    }
+
+    /// Give the newly connected `socket_addr` a session: the suspended session
+    /// `resume` if it belongs to the peer's listener and has not expired, or
+    /// a new one. The peer is told the session id, then sent the events it
+    /// missed.
+    fn open_session(&self, socket_addr: &str, resume: Option<String>) {
+        let Some(mut peer) = self.connections.get_mut(socket_addr) else {
+            return;
+        };
+        let now = Instant::now();
+        let resumed = resume.and_then(|id| {
+            self.sessions.remove_if(&id, |_, session| {
+                Arc::ptr_eq(&session.listener, &peer.listener) && session.expires > now
+            })
+        });
+
+        let (id, missed) = match resumed {
+            Some((id, session)) => {
+                tracing::info!(
+                    monotonic_counter.sessions_resumed = 1,
+                    "{socket_addr} resumed session with {} missed events",
+                    session.missed.len()
+                );
+                for group in session.groups {
+                    self.join(socket_addr, &mut peer, group);
+                }
+                (id, session.missed)
+            }
+            None => (format!("{:032x}", rand::random::<u128>()), VecDeque::new()),
+        };
+        let hello = match serde_json::to_string(&Control::Session { id: id.clone() }) {
+            Ok(text) => Message::Text(text.into()),
+            Err(e) => {
+                tracing::error!("failed to encode session frame: {e}");
+                return;
+            }
+        };
+        peer.session = Some(id);
+
+        for msg in std::iter::once(hello).chain(missed) {
+            if let Err(e) = peer.tx.try_send(msg) {
+                tracing::warn!("failed to replay session to {socket_addr}: {e}");
+                break;
+            }
+        }
+    }
+
+    /// Keep the session of `peer`, which has disconnected, for its listener's
+    /// resume window.
+    fn suspend(&self, peer: &Peer) {
+        let (Some(id), Some(resume)) = (&peer.session, peer.listener.resume) else {
+            return;
+        };
+        let session = Session {
+            listener: Arc::clone(&peer.listener),
+            groups: peer.groups.clone(),
+            missed: VecDeque::new(),
+            expires: Instant::now() + resume.window,
+        };
+        self.sessions.insert(id.clone(), session);
+    }
+
+    /// Buffer `msg` for every suspended session it would have reached, as a
+    /// send to `sockets`, dropping sessions that have expired. Sends to a
+    /// peer's old address are not buffered.
+    fn buffer(&self, sockets: Option<&[String]>, msg: &Message) {
+        let now = Instant::now();
+        self.sessions.retain(|_, session| session.expires > now);
+
+        for mut session in self.sessions.iter_mut() {
+            let reaches = sockets.map_or_else(
+                || session.listener.visibility == Visibility::All,
+                |s| session.groups.iter().any(|group| s.contains(group)),
+            );
+            let Some(resume) = session.listener.resume.filter(|_| reaches) else {
+                continue;
+            };
+            if session.missed.len() >= resume.replay {
+                session.missed.pop_front();
+            }
+            if resume.replay > 0 {
+                session.missed.push_back(msg.clone());
+            }
+        }
+    }
+
+    /// Ask the host whether to admit `socket_addr`, which presented `token`.
+    /// An unanswered request refuses the peer.
+    async fn admit(&self, socket_addr: &str, token: Option<String>) -> bool {
+        let (request, answer) = ConnectRequest::new(socket_addr.to_string(), token);
+        if self.connect_tx.send(request).is_err() {
+            tracing::warn!("no connection authorizer; refusing {socket_addr}");
+            return false;
+        }
+        let allowed = matches!(tokio::time::timeout(CONNECT_TIMEOUT, answer).await, Ok(Ok(true)));
+        if !allowed {
+            tracing::warn!("connection from {socket_addr} was not authorized");
+        }
+        allowed
+    }
 
-        let outgoing_forwarder = rx.map(Ok).forward(outgoing);
+    /// Add a new socket, which holds a place in its listener's limit, to the
+    /// connection map.
+    fn add_socket(&self, socket_addr: String, peer: Peer) {
+        for group in &peer.groups {
+            self.members.entry(group.clone()).or_default().insert(socket_addr.clone());
+        }
+        self.connections.insert(socket_addr, peer);
+    }
 
-        pin_mut!(incoming_broadcaster, outgoing_forwarder);
-        future::select(incoming_broadcaster, outgoing_forwarder).await;
-        tracing::info!("{socket_addr} disconnected");
+    /// Add `peer`, connected from `socket_addr`, to `group`, and index it
+    /// among the group's members.
+    fn join(&self, socket_addr: &str, peer: &mut Peer, group: String) {
+        self.members.entry(group.clone()).or_default().insert(socket_addr.to_string());
+        peer.groups.insert(group);
+    }
 
-        self.connections.remove(&socket_addr);
+    /// Remove `peer`, connected from `socket_addr`, from `group`.
+    fn leave(&self, socket_addr: &str, peer: &mut Peer, group: &str) {
+        peer.groups.remove(group);
+        self.unindex(socket_addr, group);
     }
 
-    /// Add a new socket to the connection map.
-    fn add_socket(&self, socket_addr: String, tx: mpsc::Sender<Message>) -> Result<()> {
-        if self.connections.len() >= MAX_CONNECTIONS {
-            return Err(anyhow!("max connections reached"));
+    /// Drop `socket_addr` from `group`'s indexed members, and the group once
+    /// it has none.
+    fn unindex(&self, socket_addr: &str, group: &str) {
+        if let Some(mut members) = self.members.get_mut(group) {
+            members.remove(socket_addr);
         }
-        self.connections.insert(socket_addr, tx);
-        Ok(())
+        self.members.remove_if(group, |_, members| members.is_empty());
     }
 
-    /// Send event to the wasm guest's websocket event handler.
-    fn send_to_guest(&self, socket_addr: String, data: Vec<u8>) {
-        let event = Event {
-            socket_addr: Some(socket_addr),
-            data,
-            route: None,
+    /// Apply a group membership control frame from `socket_addr`.
+    fn control(&self, socket_addr: &str, control: Control) {
+        match control {
+            Control::Subscribe { groups } => {
+                let server = self.clone();
+                let socket_addr = socket_addr.to_string();
+                tokio::spawn(async move { server.subscribe(socket_addr, groups).await });
+            }
+            Control::Unsubscribe { groups } => {
+                if let Some(mut peer) = self.connections.get_mut(socket_addr) {
+                    for group in &groups {
+                        self.leave(socket_addr, &mut peer, group);
+                    }
+                }
+            }
+            Control::Subscribed { .. } | Control::Session { .. } => {
+                tracing::debug!("ignoring server control frame from peer {socket_addr}");
+            }
+        }
+    }
+
+    /// Join `socket_addr` to the groups the host authorizes, or to all of them
+    /// on a listener with open subscribe, then tell the peer which groups it
+    /// joined. An unanswered request joins none.
+    async fn subscribe(&self, socket_addr: String, groups: Vec<String>) {
+        let open =
+            self.connections.get(&socket_addr).is_some_and(|peer| peer.listener.open_subscribe);
+        let allowed =
+            if open { groups } else { self.authorize_subscribe(&socket_addr, groups).await };
+
+        let Some(mut peer) = self.connections.get_mut(&socket_addr) else {
+            return;
         };
+        for group in &allowed {
+            self.join(&socket_addr, &mut peer, group.clone());
+        }
+        let reply = Control::Subscribed { groups: allowed };
+        match serde_json::to_string(&reply) {
+            Ok(text) => {
+                if let Err(e) = peer.tx.try_send(Message::Text(text.into())) {
+                    tracing::warn!("failed to confirm subscription to {socket_addr}: {e}");
+                }
+            }
+            Err(e) => tracing::error!("failed to encode subscription reply: {e}"),
+        }
+    }
+
+    /// Ask the host which of `groups` `socket_addr` may join.
+    async fn authorize_subscribe(&self, socket_addr: &str, groups: Vec<String>) -> Vec<String> {
+        let (request, answer) = SubscribeRequest::new(socket_addr.to_string(), groups);
+        if self.request_tx.send(request).is_err() {
+            tracing::warn!("no subscription authorizer; refusing subscribe from {socket_addr}");
+            return Vec::new();
+        }
+        if let Ok(Ok(allowed)) = tokio::time::timeout(SUBSCRIBE_TIMEOUT, answer).await {
+            allowed
+        } else {
+            tracing::warn!("subscribe from {socket_addr} was not authorized in time");
+            Vec::new()
+        }
+    }
+
+    /// Send event, received from `socket_addr`, to the wasm guest's websocket
+    /// event handler.
+    fn send_to_guest(&self, socket_addr: &str, mut event: Event) {
+        event.socket_addr = Some(socket_addr.to_string());
         if let Err(e) = self.event_tx.send(event) {
             tracing::warn!("issue sending WebSocket event: {e}");
         }
     }
 }
+
+/// The frame that carries `event` to peers: text for a text event whose data
+/// is valid UTF-8, binary otherwise.
+fn frame(event: Event) -> Message {
+    if event.kind == MessageKind::Binary {
+        return Message::Binary(event.data.into());
+    }
+    match String::from_utf8(event.data) {
+        Ok(text) => Message::Text(text.into()),
+        Err(e) => {
+            tracing::warn!("text event is not valid UTF-8; sending it as binary");
+            Message::Binary(e.into_bytes().into())
+        }
+    }
+}
+
+/// Whether an endpoint may send `code` in a close frame: the defined codes
+/// other than those reserved for reporting, and the registered and
+/// application ranges.
+const fn sendable(code: u16) -> bool {
+    matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999)
+}
+
+/// A connection's place within its listener's connection limit, given back
+/// when dropped.
+struct Slot {
+    open: OpenCounts,
+    listener: String,
+}
+
+impl Slot {
+    /// Take a place on `listener`, if it is below its limit. The count is
+    /// checked and raised under the map's entry lock, so concurrent
+    /// handshakes cannot overshoot the limit.
+    fn reserve(open: &OpenCounts, listener: &Listener) -> Option<Self> {
+        let mut count = open.entry(listener.name.clone()).or_default();
+        if *count >= listener.max_connections {
+            return None;
+        }
+        *count += 1;
+        report_connections(&listener.name, *count);
+        drop(count);
+        Some(Self {
+            open: Arc::clone(open),
+            listener: listener.name.clone(),
+        })
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some(mut count) = self.open.get_mut(&self.listener) {
+            *count = count.saturating_sub(1);
+            report_connections(&self.listener, *count);
+        }
+    }
+}
+
+/// Report how many connections are open on `listener`.
+fn report_connections(listener: &str, open: usize) {
+    tracing::debug!(gauge.websocket_connections = open as u64, listener = %listener);
+}
+
+/// Tokens for one peer's messages, refilled continuously at the limit's rate.
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            rate: f64::from(limit.per_second),
+            burst: f64::from(limit.burst.max(1)),
+            tokens: f64::from(limit.burst.max(1)),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Take a token for one message, if one is left.
+    fn take(&mut self) -> bool {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.burst);
+        self.refilled = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// Send `msg` to one peer, waiting up to its listener's send timeout for room
+/// in the peer's queue, then applying the listener's slow peer policy.
+/// Resolves to whether the message was queued for the peer.
+async fn deliver(
+    socket_addr: String, listener: Arc<Listener>, mut tx: mpsc::Sender<Message>, msg: Message,
+) -> bool {
+    match tokio::time::timeout(listener.send_timeout, tx.send(msg)).await {
+        Ok(Ok(())) => return true,
+        Ok(Err(e)) => tracing::debug!("peer {socket_addr} disconnected before send: {e}"),
+        Err(_) if listener.slow_peer == SlowPeer::Drop => {
+            tracing::warn!(
+                monotonic_counter.slow_peer_disconnects = 1,
+                "disconnecting {socket_addr}: outbound queue full"
+            );
+            // Ends the peer's forwarder, which closes the connection.
+            tx.close_channel();
+        }
+        Err(_) => tracing::warn!(
+            monotonic_counter.slow_peer_skips = 1,
+            "skipping event for {socket_addr}: outbound queue full"
+        ),
+    }
+    false
+}
+
+/// Count the outcomes of one send's deliveries, as returned by [`deliver`].
+fn report_delivery(delivered: &[bool]) {
+    let sent = delivered.iter().filter(|delivered| **delivered).count();
+    tracing::debug!(
+        monotonic_counter.websocket_messages_out = sent as u64,
+        monotonic_counter.websocket_send_failures = (delivered.len() - sent) as u64,
+    );
+}
+
+/// Ping the peer every ping interval, and return once nothing has been heard
+/// from it, as recorded in `last_seen`, for the idle timeout. Never returns
+/// when both are disabled.
+async fn keepalive(
+    listener: &Listener, mut tx: mpsc::Sender<Message>, socket_addr: &str,
+    last_seen: &Mutex<Instant>,
+) {
+    let Some(period) = listener.ping_interval.or(listener.idle_timeout) else {
+        return future::pending().await;
+    };
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+
+    loop {
+        ticks.tick().await;
+        let silent = last_seen.lock().unwrap_or_else(PoisonError::into_inner).elapsed();
+        if listener.idle_timeout.is_some_and(|idle_timeout| silent >= idle_timeout) {
+            tracing::info!(
+                monotonic_counter.idle_disconnects = 1,
+                "closing {socket_addr} after {}s without a frame",
+                silent.as_secs()
+            );
+            return;
+        }
+        if listener.ping_interval.is_some()
+            && let Err(e) = tx.try_send(Message::Ping(Vec::new().into()))
+        {
+            tracing::debug!("failed to ping peer {socket_addr}: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_tungstenite::tungstenite::http::Request as HttpRequest;
+
+    use super::*;
+
+    fn handshake(uri: &str, headers: &[(&str, &str)]) -> Request {
+        let mut request = HttpRequest::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(()).unwrap()
+    }
+
+    // A server without listeners, for driving its state directly.
+    async fn server() -> WebSocketDefault {
+        let options = ConnectOptions {
+            listeners: Vec::new(),
+            event_capacity: EVENT_CAPACITY,
+            heartbeat: None,
+        };
+        WebSocketDefault::connect_with(options).await.unwrap()
+    }
+
+    fn peer(server: &WebSocketDefault, listener: Listener) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel(8);
+        let peer = Peer {
+            listener: Arc::new(listener),
+            tx,
+            groups: HashSet::new(),
+            session: None,
+        };
+        server.add_socket("127.0.0.1:1".to_string(), peer);
+        rx
+    }
+
+    #[test]
+    fn origins_are_checked_against_the_allow_list_and_callback() {
+        let open = Listener::new("open", "127.0.0.1:0");
+        assert!(open.origin_allowed(&handshake("/", &[("origin", "https://any.example")])));
+
+        let listener = Listener {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            check_origin: Some(Arc::new(|origin: &str| origin.ends_with(".trusted.example"))),
+            ..Listener::new("browser", "127.0.0.1:0")
+        };
+        let from = |origin| listener.origin_allowed(&handshake("/", &[("origin", origin)]));
+        assert!(from("https://APP.example.com"));
+        assert!(from("https://ops.trusted.example"));
+        assert!(!from("https://evil.example"));
+        assert!(listener.origin_allowed(&handshake("/", &[])), "non-browser clients pass");
+    }
+
+    #[test]
+    fn bearer_tokens_come_from_the_header_or_query() {
+        let header = handshake("/?token=query", &[("authorization", "Bearer header")]);
+        assert_eq!(bearer(&header), Some("header"));
+        assert_eq!(bearer(&handshake("/?session=s&token=query", &[])), Some("query"));
+        assert_eq!(bearer(&handshake("/", &[("authorization", "Basic abc")])), None);
+    }
+
+    #[test]
+    fn deflate_offers_are_recognized() {
+        let offer = "permessage-deflate; client_max_window_bits, x-webkit-deflate-frame";
+        assert!(offers_deflate(&handshake("/", &[("sec-websocket-extensions", offer)])));
+        assert!(!offers_deflate(&handshake("/", &[])));
+    }
+
+    #[test]
+    fn query_finds_the_named_parameter() {
+        let request = handshake("/ws?session=abc&token=t&flag", &[]);
+        assert_eq!(query(&request, "session"), Some("abc"));
+        assert_eq!(query(&request, "token"), Some("t"));
+        assert_eq!(query(&request, "flag"), None);
+        assert_eq!(query(&handshake("/ws", &[]), "session"), None);
+    }
+
+    #[test]
+    fn token_bucket_allows_a_burst_then_refills() {
+        let mut bucket = TokenBucket::new(RateLimit {
+            per_second: 1,
+            burst: 2,
+        });
+        assert!(bucket.take());
+        assert!(bucket.take());
+        assert!(!bucket.take(), "the burst is spent");
+
+        bucket.refilled -= Duration::from_secs(1);
+        assert!(bucket.take(), "a second refills one token");
+        assert!(!bucket.take());
+    }
+
+    #[test]
+    fn slots_hold_places_within_the_limit() {
+        let open = OpenCounts::default();
+        let listener = Listener {
+            max_connections: 2,
+            ..Listener::new("limited", "127.0.0.1:0")
+        };
+        let first = Slot::reserve(&open, &listener).unwrap();
+        let _second = Slot::reserve(&open, &listener).unwrap();
+        assert!(Slot::reserve(&open, &listener).is_none(), "the listener is full");
+
+        drop(first);
+        assert!(Slot::reserve(&open, &listener).is_some(), "a closed connection frees its place");
+        assert_eq!(open.get("limited").as_deref(), Some(&1));
+    }
+
+    #[test]
+    fn only_endpoint_close_codes_are_sendable() {
+        for code in [1000, 1003, 1008, 1014, 3000, 4000, 4999] {
+            assert!(sendable(code), "{code} may be sent");
+        }
+        for code in [999, 1004, 1005, 1006, 1015, 2000, 5000] {
+            assert!(!sendable(code), "{code} may not be sent");
+        }
+    }
+
+    #[test]
+    fn frames_follow_the_event_kind() {
+        assert_eq!(frame(Event::text("hi".to_string())), Message::text("hi"));
+        assert_eq!(frame(Event::new(b"hi".to_vec())), Message::binary(b"hi".to_vec()));
+
+        let invalid = Event {
+            data: vec![0xff, 0xfe],
+            ..Event::text(String::new())
+        };
+        assert_eq!(frame(invalid), Message::binary(vec![0xff, 0xfe]), "invalid text is binary");
+    }
+
+    #[tokio::test]
+    async fn sessions_buffer_the_latest_events_that_would_reach_them() {
+        let server = server().await;
+        let listener = Arc::new(Listener {
+            resume: Some(Resume {
+                window: Duration::from_secs(60),
+                replay: 2,
+            }),
+            ..Listener::new("resumable", "127.0.0.1:0")
+        });
+        let session = |expires| Session {
+            listener: Arc::clone(&listener),
+            groups: HashSet::from(["vehicles".to_string()]),
+            missed: VecDeque::new(),
+            expires,
+        };
+        server
+            .sessions
+            .insert("live".to_string(), session(Instant::now() + Duration::from_secs(60)));
+        server.sessions.insert("expired".to_string(), session(Instant::now()));
+
+        let vehicles = ["vehicles".to_string()];
+        for n in 0..3 {
+            server.buffer(Some(&vehicles), &Message::text(n.to_string()));
+        }
+        server.buffer(Some(&["alerts".to_string()]), &Message::text("alert"));
+
+        assert!(!server.sessions.contains_key("expired"), "expired sessions are dropped");
+        let missed =
+            server.sessions.get("live").unwrap().missed.iter().cloned().collect::<Vec<_>>();
+        assert_eq!(
+            missed,
+            vec![Message::text("1"), Message::text("2")],
+            "only the latest are kept"
+        );
+    }
+
+    #[tokio::test]
+    async fn subscribes_without_an_authorizer_are_refused() {
+        let server = server().await;
+        drop(server.request_rx.lock().unwrap().take());
+        let mut rx = peer(&server, Listener::new("closed", "127.0.0.1:0"));
+
+        server.subscribe("127.0.0.1:1".to_string(), vec!["vehicles".to_string()]).await;
+        assert!(server.connections.get("127.0.0.1:1").unwrap().groups.is_empty());
+        assert_eq!(rx.try_recv().unwrap(), Message::text(r#"{"type":"subscribed","groups":[]}"#));
+    }
+
+    #[tokio::test]
+    async fn open_listeners_admit_every_group() {
+        let server = server().await;
+        let listener = Listener {
+            open_subscribe: true,
+            ..Listener::new("open", "127.0.0.1:0")
+        };
+        let _rx = peer(&server, listener);
+
+        server.subscribe("127.0.0.1:1".to_string(), vec!["vehicles".to_string()]).await;
+        let groups = server.connections.get("127.0.0.1:1").unwrap().groups.clone();
+        assert_eq!(groups, HashSet::from(["vehicles".to_string()]));
+        assert!(server.members.get("vehicles").unwrap().contains("127.0.0.1:1"));
+    }
+
+    #[allow(unsafe_code)]
+    #[test]
+    fn connection_options_are_read_from_the_environment() {
+        let vars = [
+            ("ALLOWED_ORIGINS", "https://a.example/, https://b.example"),
+            ("MAX_CONNECTIONS", "10"),
+            ("PING_INTERVAL_SECS", "0"),
+            ("RATE_LIMIT", "5"),
+            ("PATH_GROUPS", "/ws/vehicles=vehicles,/ws/all=vehicles,/ws/all=alerts"),
+            ("RESUME_WINDOW_SECS", "30"),
+            ("CLOSE_EVENTS", "true"),
+        ];
+        for (suffix, value) in vars {
+            // SAFETY: no other test in this crate reads or writes the
+            // environment, and the variables use a prefix of their own.
+            unsafe { std::env::set_var(format!("WSTEST_OPTIONS_{suffix}"), value) };
+        }
+
+        let listener =
+            Listener::new("test", "127.0.0.1:0").connection_options_from_env("WSTEST_OPTIONS");
+        for (suffix, _) in vars {
+            // SAFETY: as above.
+            unsafe { std::env::remove_var(format!("WSTEST_OPTIONS_{suffix}")) };
+        }
+        let listener = listener.unwrap();
+
+        assert_eq!(listener.allowed_origins, ["https://a.example", "https://b.example"]);
+        assert_eq!(listener.max_connections, 10);
+        assert_eq!(listener.ping_interval, None, "0 disables pings");
+        assert_eq!(
+            listener.rate_limit,
+            Some(RateLimit {
+                per_second: 5,
+                burst: 5
+            }),
+            "the burst defaults to the rate"
+        );
+        assert_eq!(listener.path_groups["/ws/all"], ["vehicles", "alerts"]);
+        assert_eq!(
+            listener.resume,
+            Some(Resume {
+                window: Duration::from_secs(30),
+                replay: RESUME_REPLAY
+            })
+        );
+        assert!(listener.close_events);
+    }
+
+    #[allow(unsafe_code)]
+    #[test]
+    fn invalid_connection_options_are_rejected() {
+        // SAFETY: as above; the variable is unique to this test.
+        unsafe { std::env::set_var("WSTEST_INVALID_QUEUE_CAPACITY", "0") };
+        let listener =
+            Listener::new("test", "127.0.0.1:0").connection_options_from_env("WSTEST_INVALID");
+        // SAFETY: as above.
+        unsafe { std::env::remove_var("WSTEST_INVALID_QUEUE_CAPACITY") };
+        assert!(listener.is_err(), "a zero queue capacity is refused");
+    }
+}
@@ -3,12 +3,20 @@ use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::Arc;
 
-use futures::Stream;
+use anyhow::anyhow;
+use futures::{FutureExt, Stream, stream};
 use omnia::FutureResult;
+use tokio::sync::oneshot;
 
 /// Stream of events.
 pub type Events = Pin<Box<dyn Stream<Item = Event> + Send>>;
 
+/// Stream of peers' requests to subscribe to groups.
+pub type SubscribeRequests = Pin<Box<dyn Stream<Item = SubscribeRequest> + Send>>;
+
+/// Stream of peers' requests to connect.
+pub type ConnectRequests = Pin<Box<dyn Stream<Item = ConnectRequest> + Send>>;
+
 /// Providers implement the [`Client`] trait to allow the host to interact with
 /// backend WebSocket resources.
 pub trait Client: Debug + Send + Sync + 'static {
@@ -17,6 +25,133 @@ pub trait Client: Debug + Send + Sync + 'static {
 
     /// Send an event to connected WebSocket clients, optionally filtered by sockets.
     fn send(&self, event: Event, sockets: Option<Vec<String>>) -> FutureResult<()>;
+
+    /// Subscribe to peers' requests to join groups, for the host to authorize.
+    ///
+    /// Backends without groups keep the default, an empty stream.
+    fn subscribe_requests(&self) -> FutureResult<SubscribeRequests> {
+        async { Ok(Box::pin(stream::empty()) as SubscribeRequests) }.boxed()
+    }
+
+    /// Subscribe to peers' requests to connect, for the host to authorize
+    /// before the peer is admitted.
+    ///
+    /// Backends that authenticate peers themselves, or not at all, keep the
+    /// default, an empty stream.
+    fn connect_requests(&self) -> FutureResult<ConnectRequests> {
+        async { Ok(Box::pin(stream::empty()) as ConnectRequests) }.boxed()
+    }
+
+    /// Send an event to the connected peer `peer` alone.
+    ///
+    /// The default sends the event addressed to the peer's socket.
+    fn send_to_peer(&self, peer: String, event: Event) -> FutureResult<()> {
+        self.send(event, Some(vec![peer]))
+    }
+
+    /// Stop accepting connections and close the open ones as going away, for
+    /// a graceful shutdown.
+    ///
+    /// Backends without connections of their own keep the default, which
+    /// does nothing.
+    fn shutdown(&self) -> FutureResult<()> {
+        async { Ok(()) }.boxed()
+    }
+
+    /// Add the connected peer `peer` to `group`.
+    ///
+    /// Backends without groups keep the default, which fails.
+    fn join_group(&self, _peer: String, group: String) -> FutureResult<()> {
+        async move { Err(anyhow!("cannot join group {group}: backend has no groups")) }.boxed()
+    }
+
+    /// Remove the connected peer `peer` from `group`.
+    ///
+    /// Backends without groups keep the default, which fails.
+    fn leave_group(&self, _peer: String, group: String) -> FutureResult<()> {
+        async move { Err(anyhow!("cannot leave group {group}: backend has no groups")) }.boxed()
+    }
+
+    /// Close the connection to `peer` with `close`'s code and reason.
+    ///
+    /// Backends that cannot close peers keep the default, which fails.
+    fn close_peer(&self, peer: String, _close: Close) -> FutureResult<()> {
+        async move { Err(anyhow!("cannot close peer {peer}: backend cannot close peers")) }.boxed()
+    }
+}
+
+/// A peer's request to join groups, awaiting authorization.
+///
+/// Dropping the request without answering it refuses every group.
+#[derive(Debug)]
+pub struct SubscribeRequest {
+    /// The socket address of the requesting peer.
+    pub peer: String,
+    /// The groups the peer asked to join.
+    pub groups: Vec<String>,
+    reply: oneshot::Sender<Vec<String>>,
+}
+
+impl SubscribeRequest {
+    /// Create a request, returning the receiver its answer arrives on.
+    #[must_use]
+    pub fn new(peer: String, groups: Vec<String>) -> (Self, oneshot::Receiver<Vec<String>>) {
+        let (reply, answer) = oneshot::channel();
+        (Self { peer, groups, reply }, answer)
+    }
+
+    /// Answer the request with the groups the peer may join.
+    pub fn allow(self, groups: Vec<String>) {
+        // The backend stops waiting once the peer disconnects.
+        let _ = self.reply.send(groups);
+    }
+
+    /// Answer the request by allowing every requested group.
+    pub fn allow_all(self) {
+        let groups = self.groups.clone();
+        self.allow(groups);
+    }
+}
+
+/// A peer's request to connect, awaiting authorization.
+///
+/// Dropping the request without answering it refuses the connection.
+pub struct ConnectRequest {
+    /// The socket address of the connecting peer.
+    pub peer: String,
+    /// The bearer token the peer presented at the handshake, if any.
+    pub token: Option<String>,
+    reply: oneshot::Sender<bool>,
+}
+
+impl ConnectRequest {
+    /// Create a request, returning the receiver its answer arrives on.
+    #[must_use]
+    pub fn new(peer: String, token: Option<String>) -> (Self, oneshot::Receiver<bool>) {
+        let (reply, answer) = oneshot::channel();
+        (Self { peer, token, reply }, answer)
+    }
+
+    /// Answer the request by admitting the peer.
+    pub fn allow(self) {
+        // The backend stops waiting once the peer disconnects.
+        let _ = self.reply.send(true);
+    }
+
+    /// Answer the request by refusing the peer.
+    pub fn refuse(self) {
+        let _ = self.reply.send(false);
+    }
+}
+
+// Keep the token out of logs.
+impl Debug for ConnectRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectRequest")
+            .field("peer", &self.peer)
+            .field("token", &self.token.as_ref().map(|_| "[redacted]"))
+            .finish_non_exhaustive()
+    }
 }
 
 /// Proxy for a WebSocket server client.
@@ -49,10 +184,17 @@ pub struct Event {
     /// `None` fans the event into the trigger's catch-all guest (the sole
     /// websocket exporter), preserving single-guest behaviour.
     pub route: Option<String>,
+    /// Whether the data is text or binary.
+    pub kind: MessageKind,
+    /// How the connection closed, when the event reports that the peer
+    /// disconnected.
+    pub close: Option<Close>,
+    /// Whether the host generated the event on its heartbeat interval.
+    pub heartbeat: bool,
 }
 
 impl Event {
-    /// Create an event with the given payload.
+    /// Create a binary event with the given payload.
     #[must_use]
     pub fn new(data: Vec<u8>) -> Self {
         Self {
@@ -60,4 +202,51 @@ impl Event {
             ..Self::default()
         }
     }
+
+    /// Create a text event with the given payload.
+    #[must_use]
+    pub fn text(data: String) -> Self {
+        Self {
+            data: data.into_bytes(),
+            kind: MessageKind::Text,
+            ..Self::default()
+        }
+    }
+
+    /// Create a heartbeat event, with no data or peer.
+    #[must_use]
+    pub fn heartbeat() -> Self {
+        Self {
+            heartbeat: true,
+            ..Self::default()
+        }
+    }
+
+    /// Create an event, with no data, reporting that the peer disconnected.
+    #[must_use]
+    pub fn disconnected(close: Close) -> Self {
+        Self {
+            close: Some(close),
+            ..Self::default()
+        }
+    }
+}
+
+/// A close code and the reason given with it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Close {
+    /// The close code, as defined by RFC 6455 or the application.
+    pub code: u16,
+    /// The reason given with the code, possibly empty.
+    pub reason: String,
+}
+
+/// Whether an event's data is text or binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageKind {
+    /// UTF-8 text, sent to peers as a text frame.
+    Text,
+    /// Opaque bytes, sent to peers as a binary frame.
+    #[default]
+    Binary,
 }
@@ -1,14 +1,20 @@
+use std::collections::HashMap;
 use std::env;
 use std::sync::Arc;
 
 use anyhow::{Context, Result, anyhow};
-use futures::StreamExt;
-use omnia::{PatternRoutes, Runtime, StoreCtx, TriggerRouter};
+use futures::{StreamExt, future};
+use omnia::{Crash, GuestId, PatternRoutes, Runtime, StoreCtx, TriggerRouter};
+use tokio::task::JoinSet;
 use tracing::{Instrument, debug_span, instrument};
 
 use crate::host::WasiWebSocketView;
+use crate::host::authorizer::SubscriptionAuthorizerIndices;
+use crate::host::connect_authorizer::ConnectionAuthorizerIndices;
 use crate::host::generated::DuplexIndices;
-use crate::host::resource::{Event, Events};
+use crate::host::resource::{
+    ConnectRequest, ConnectRequests, Event, Events, SubscribeRequest, SubscribeRequests,
+};
 
 #[instrument("websocket-server", skip(state))]
 pub async fn run<B>(state: &Runtime<B>) -> Result<()>
@@ -28,35 +34,82 @@ where
         state.registry().routes().websocket().clone(),
         DuplexIndices::new,
     )?;
-    if routing.is_inert() {
-        tracing::info!("no guest exports the websocket handler; websocket trigger inert");
-        return Ok(());
-    }
+
+    // Each requested group is authorized by the guest its route resolves to,
+    // when that guest exports the optional authorizer; otherwise it is refused.
+    let authorizers = state
+        .registry()
+        .guests()
+        .filter_map(|guest| {
+            let indices = SubscriptionAuthorizerIndices::new(guest.instance_pre()).ok()?;
+            Some((guest.id().clone(), indices))
+        })
+        .collect::<HashMap<_, _>>();
+    // Connections on listeners that require authorization are authorized by
+    // the same guest; without the export, they are refused.
+    let connect_authorizer = routing.catch_all().and_then(|(id, _)| {
+        let guest = state.registry().get(id)?;
+        let indices = ConnectionAuthorizerIndices::new(guest.instance_pre()).ok()?;
+        Some(Arc::new((id.clone(), indices)))
+    });
+    let inert = routing.is_inert();
 
     let handler = Handler {
         state: state.clone(),
         component,
         routing: Arc::new(routing),
+        authorizers: Arc::new(authorizers),
+        connect_authorizer,
     };
 
-    // handle events from the websocket clients
-    while let Some(event) = handler.events().await?.next().await {
-        let handler = handler.clone();
+    let authorizing = handler.clone();
+    tokio::spawn(async move {
+        if let Err(e) = authorizing.authorize_subscriptions().await {
+            tracing::error!("issue authorizing websocket subscriptions: {e}");
+        }
+    });
 
-        tokio::spawn(async move {
-            tracing::info!(monotonic_counter.event_counter = 1, service = %handler.component);
+    let authorizing = handler.clone();
+    tokio::spawn(async move {
+        if let Err(e) = authorizing.authorize_connections().await {
+            tracing::error!("issue authorizing websocket connections: {e}");
+        }
+    });
 
-            if let Err(e) = handler.handle(event).await {
-                tracing::error!(
-                    monotonic_counter.processing_errors = 1,
-                    service = %handler.component,
-                    error = %e,
-                );
+    if inert {
+        tracing::info!("no guest exports the websocket handler; websocket trigger inert");
+        return Ok(());
+    }
+
+    // Handle events from the websocket clients until the runtime shuts down.
+    let mut events = handler.events().await?;
+    let mut in_flight = JoinSet::new();
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                let handler = handler.clone();
+                in_flight.spawn(async move {
+                    tracing::info!(monotonic_counter.event_counter = 1, service = %handler.component);
+
+                    if let Err(e) = handler.handle(event).await {
+                        tracing::error!(
+                            monotonic_counter.processing_errors = 1,
+                            service = %handler.component,
+                            error = %e,
+                        );
+                    }
+                });
             }
-        });
+            // Reap finished handlers so the set only holds in-flight ones.
+            Some(_) = in_flight.join_next(), if !in_flight.is_empty() => {}
+            () = state.shutting_down() => break,
+        }
     }
 
-    Ok(())
+    handler.drain(in_flight).await
 }
 
 #[derive(Clone)]
@@ -68,6 +121,8 @@ where
     state: Runtime<B>,
     component: String,
     routing: Arc<TriggerRouter<DuplexIndices, PatternRoutes>>,
+    authorizers: Arc<HashMap<GuestId, SubscriptionAuthorizerIndices>>,
+    connect_authorizer: Option<Arc<(GuestId, ConnectionAuthorizerIndices)>>,
 }
 
 impl<B> Handler<B>
@@ -89,32 +144,224 @@ where
         };
         let guest = self.state.registry().get(guest_id).expect("a capable guest is registered");
 
-        let mut store_data = self.state.store();
-        let event_res = store_data
-            .websocket()
-            .table
-            .push(event)
-            .map_err(|e| anyhow!("failed to push event: {e}"))?;
+        // A crash-looping guest is quarantined rather than fed every event.
+        self.state
+            .supervise(guest_id, async {
+                let mut store_data = self.state.store();
+                let event_res = store_data
+                    .websocket()
+                    .table
+                    .push(event)
+                    .map_err(|e| anyhow!("failed to push event: {e}"))?;
 
-        let mut store = self.state.build_store(store_data);
-        let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
-        let websocket = indices.load(&mut store, &instance)?;
+                let mut store = self.state.build_store(store_data);
+                self.state.meter(guest_id, &mut store);
+                let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
+                let websocket = indices.load(&mut store, &instance)?;
+
+                let run = store
+                    .run_concurrent(async |store| {
+                        let guest = websocket.omnia_websocket_handler();
+                        guest
+                            .call_handle(store, event_res)
+                            .await
+                            .map(|_| ())
+                            .map_err(Crash::classify)
+                            .context("issue handling event")
+                    })
+                    .instrument(debug_span!("websocket-handle"));
+
+                tokio::time::timeout(self.state.options().guest_timeout, run)
+                    .await
+                    .context("websocket handler timed out")??
+            })
+            .await
+    }
 
-        let run = store
+    /// Answer peers' subscribe requests until the backend stops sending them.
+    async fn authorize_subscriptions(&self) -> Result<()> {
+        let mut requests = self.subscribe_requests().await?;
+        while let Some(request) = requests.next().await {
+            let handler = self.clone();
+            tokio::spawn(async move { handler.authorize(request).await });
+        }
+        Ok(())
+    }
+
+    /// Ask the guest that owns each requested group, by the group's route,
+    /// which of its groups the peer may join. A group with no route, or whose
+    /// guest does not export the authorizer, is refused.
+    async fn authorize(&self, request: SubscribeRequest) {
+        let mut owned = HashMap::<&GuestId, Vec<String>>::new();
+        for group in &request.groups {
+            if let Some((guest_id, _)) = self.routing.resolve(group) {
+                owned.entry(guest_id).or_default().push(group.clone());
+            } else {
+                tracing::warn!(peer = %request.peer, %group, "no route owns group; refused");
+            }
+        }
+        let answers = owned
+            .into_iter()
+            .map(|(guest_id, groups)| self.authorize_groups(guest_id, &request.peer, groups));
+        let allowed = future::join_all(answers).await.into_iter().flatten().collect();
+        request.allow(allowed);
+    }
+
+    /// Ask `guest_id` which of `groups` the peer may join. Any failure refuses
+    /// them all.
+    async fn authorize_groups(
+        &self, guest_id: &GuestId, peer: &str, groups: Vec<String>,
+    ) -> Vec<String> {
+        let Some(indices) = self.authorizers.get(guest_id) else {
+            tracing::warn!(%peer, guest = %guest_id, "guest does not authorize subscribe; refused");
+            return Vec::new();
+        };
+        let Some(guest) = self.state.registry().get(guest_id) else {
+            return Vec::new();
+        };
+        let (peer, requested) = (peer.to_string(), groups.clone());
+
+        let outcome = async {
+            let mut store = self.state.build_store(self.state.store());
+            self.state.meter(guest_id, &mut store);
+            let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
+            let exports = indices.load(&mut store, &instance)?;
+
+            let run = store.run_concurrent(async |store| {
+                exports
+                    .omnia_websocket_authorizer()
+                    .call_authorize_subscribe(store, peer, requested)
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .context("issue authorizing subscribe")?
+                    .map_err(|e| anyhow!("subscribe refused: {e:?}"))
+            });
+
+            tokio::time::timeout(self.state.options().guest_timeout, run)
+                .await
+                .context("websocket authorizer timed out")??
+        }
+        .instrument(debug_span!("websocket-authorize", guest = %guest_id))
+        .await;
+
+        match outcome {
+            // The guest may only narrow the request, never widen it.
+            Ok(allowed) => allowed.into_iter().filter(|group| groups.contains(group)).collect(),
+            Err(e) => {
+                tracing::warn!(guest = %guest_id, error = %e, "subscribe request refused");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Stop accepting connections, close the open ones as going away, then
+    /// wait up to the runtime's shutdown grace for in-flight handlers.
+    async fn drain(&self, mut in_flight: JoinSet<()>) -> Result<()> {
+        let store_data = self.state.store();
+        let mut store = self.state.build_store(store_data);
+        store
             .run_concurrent(async |store| {
-                let guest = websocket.omnia_websocket_handler();
-                guest
-                    .call_handle(store, event_res)
+                let client = store.with(|mut store| store.get().websocket().ctx.connect()).await?;
+                client.shutdown().await
+            })
+            .await??;
+
+        let grace = self.state.options().shutdown_grace;
+        let finished = async { while in_flight.join_next().await.is_some() {} };
+        if tokio::time::timeout(grace, finished).await.is_err() {
+            tracing::warn!("abandoning {} websocket handlers after {grace:?}", in_flight.len());
+        }
+        Ok(())
+    }
+
+    /// Answer peers' connect requests until the backend stops sending them.
+    async fn authorize_connections(&self) -> Result<()> {
+        let mut requests = self.connect_requests().await?;
+        while let Some(request) = requests.next().await {
+            let Some(authorizer) = &self.connect_authorizer else {
+                tracing::warn!(peer = %request.peer, "no guest authorizes connections; refused");
+                request.refuse();
+                continue;
+            };
+            let handler = self.clone();
+            let authorizer = Arc::clone(authorizer);
+            tokio::spawn(async move { handler.authorize_connect(&authorizer, request).await });
+        }
+        Ok(())
+    }
+
+    /// Ask the guest whether the peer may connect. Any failure refuses it.
+    async fn authorize_connect(
+        &self, authorizer: &(GuestId, ConnectionAuthorizerIndices), request: ConnectRequest,
+    ) {
+        let (guest_id, indices) = authorizer;
+        let Some(guest) = self.state.registry().get(guest_id) else {
+            request.refuse();
+            return;
+        };
+        let (peer, token) = (request.peer.clone(), request.token.clone());
+
+        let outcome = async {
+            let mut store = self.state.build_store(self.state.store());
+            self.state.meter(guest_id, &mut store);
+            let instance = self.state.instantiate(guest.instance_pre(), &mut store).await?;
+            let exports = indices.load(&mut store, &instance)?;
+
+            let run = store.run_concurrent(async |store| {
+                exports
+                    .omnia_websocket_connect_authorizer()
+                    .call_authorize_connect(store, peer, token)
                     .await
-                    .map(|_| ())
                     .map_err(anyhow::Error::from)
-                    .context("issue handling event")
+                    .context("issue authorizing connection")?
+                    .map_err(|e| anyhow!("connection refused: {e:?}"))
+            });
+
+            tokio::time::timeout(self.state.options().guest_timeout, run)
+                .await
+                .context("websocket connect authorizer timed out")??
+        }
+        .instrument(debug_span!("websocket-authorize-connect", guest = %guest_id))
+        .await;
+
+        match outcome {
+            Ok(()) => request.allow(),
+            Err(e) => {
+                tracing::warn!(
+                    monotonic_counter.refused_connections = 1,
+                    peer = %request.peer,
+                    error = %e,
+                    "connection refused"
+                );
+                request.refuse();
+            }
+        }
+    }
+
+    /// Get peers' connect requests from the WebSocket backend.
+    async fn connect_requests(&self) -> Result<ConnectRequests> {
+        let store_data = self.state.store();
+        let mut store = self.state.build_store(store_data);
+
+        store
+            .run_concurrent(async |store| {
+                let client = store.with(|mut store| store.get().websocket().ctx.connect()).await?;
+                client.connect_requests().await
             })
-            .instrument(debug_span!("websocket-handle"));
+            .await?
+    }
 
-        tokio::time::timeout(self.state.options().guest_timeout, run)
-            .await
-            .context("websocket handler timed out")??
+    /// Get peers' subscribe requests from the WebSocket backend.
+    async fn subscribe_requests(&self) -> Result<SubscribeRequests> {
+        let store_data = self.state.store();
+        let mut store = self.state.build_store(store_data);
+
+        store
+            .run_concurrent(async |store| {
+                let client = store.with(|mut store| store.get().websocket().ctx.connect()).await?;
+                client.subscribe_requests().await
+            })
+            .await?
     }
 
     /// Get events for incoming WebSocket events.
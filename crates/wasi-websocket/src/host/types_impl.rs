@@ -1,9 +1,10 @@
 use wasmtime::component::{Access, Accessor, Resource};
 
 pub use crate::host::generated::omnia::websocket::types::{
-    Error, Host, HostClient, HostClientWithStore, HostEvent, HostEventWithStore, SocketAddr,
+    CloseFrame, Error, Host, HostClient, HostClientWithStore, HostEvent, HostEventWithStore,
+    MessageKind, SocketAddr,
 };
-use crate::host::resource::{ClientProxy, Event};
+use crate::host::resource::{self, ClientProxy, Event};
 use crate::host::{Result, WasiWebSocket, WasiWebSocketCtxView};
 
 impl<T> HostClientWithStore<T> for WasiWebSocket {
@@ -28,6 +29,11 @@ impl<T> HostEventWithStore<T> for WasiWebSocket {
         Ok(host.get().table.push(Event::new(data))?)
     }
 
+    /// Create a new text event with the given payload.
+    fn text(mut host: Access<'_, T, Self>, data: String) -> wasmtime::Result<Resource<Event>> {
+        Ok(host.get().table.push(Event::text(data))?)
+    }
+
     /// The socket address this event was received from.
     fn socket_addr(
         mut host: Access<'_, T, Self>, self_: Resource<Event>,
@@ -42,6 +48,34 @@ impl<T> HostEventWithStore<T> for WasiWebSocket {
         Ok(event.data.clone())
     }
 
+    /// Whether the event is text or binary.
+    fn kind(
+        mut host: Access<'_, T, Self>, self_: Resource<Event>,
+    ) -> wasmtime::Result<MessageKind> {
+        let event = host.get().table.get(&self_)?;
+        Ok(match event.kind {
+            resource::MessageKind::Text => MessageKind::Text,
+            resource::MessageKind::Binary => MessageKind::Binary,
+        })
+    }
+
+    /// How the connection closed, for an event reporting a disconnect.
+    fn close(
+        mut host: Access<'_, T, Self>, self_: Resource<Event>,
+    ) -> wasmtime::Result<Option<CloseFrame>> {
+        let event = host.get().table.get(&self_)?;
+        Ok(event.close.as_ref().map(|close| CloseFrame {
+            code: close.code,
+            reason: close.reason.clone(),
+        }))
+    }
+
+    /// Whether the host generated the event on its heartbeat interval.
+    fn heartbeat(mut host: Access<'_, T, Self>, self_: Resource<Event>) -> wasmtime::Result<bool> {
+        let event = host.get().table.get(&self_)?;
+        Ok(event.heartbeat)
+    }
+
     fn drop(mut accessor: Access<'_, T, Self>, rep: Resource<Event>) -> wasmtime::Result<()> {
         Ok(accessor.get().table.delete(rep).map(|_| ())?)
     }
@@ -23,11 +23,13 @@ use omnia_wasi_blobstore::types::{IncomingValue, OutgoingValue};
 use omnia_wasi_config::store as config_store;
 use omnia_wasi_identity::credentials::get_identity;
 use omnia_wasi_keyvalue::atomics::{self, Cas, CasError};
-use omnia_wasi_keyvalue::store as kv_store;
-use omnia_wasi_messaging::producer;
-use omnia_wasi_messaging::types::{Client as MessagingClient, Message};
-use omnia_wasi_sql::readwrite;
+use omnia_wasi_keyvalue::{changes, exchange, expiry, hash, store as kv_store};
+use omnia_wasi_messaging::types::{Client as MessagingClient, Error as MessagingError, Message};
+use omnia_wasi_messaging::{batch, producer, settlement};
+use omnia_wasi_sql::cursors::Cursor;
+use omnia_wasi_sql::transactional::{self, Transaction, TransactionOptions};
 use omnia_wasi_sql::types::{Connection, DataType, Statement};
+use omnia_wasi_sql::{errors as sql_errors, readwrite, results};
 use omnia_wasi_vault::vault;
 use omnia_wasi_websocket::client as ws_client;
 use omnia_wasi_websocket::types::{Client as WsClient, Error as WsHandlerError, Event};
@@ -46,13 +48,30 @@ impl Guest for Http {
         let router = Router::new()
             .route("/echo", post(echo))
             .route("/keyvalue", post(keyvalue_round_trip))
+            .route("/keyvalue/expiry", post(keyvalue_set_with_ttl))
+            .route("/keyvalue/hash", post(keyvalue_hash_fields))
+            .route("/keyvalue/counter", post(keyvalue_increment))
+            .route("/keyvalue/exchange", post(keyvalue_exchange))
+            .route("/keyvalue/changes", post(keyvalue_watch))
             .route("/blobstore", post(blobstore_round_trip))
             .route("/config", get(config_get_all))
             .route("/identity", get(identity_token))
             .route("/sql/agencies", post(sql_insert_agency))
+            .route("/sql/errors", post(sql_constraint_violation))
+            .route("/sql/transactions", post(sql_transactions))
+            .route("/sql/cursors", post(sql_cursor_batches))
+            .route("/sql/results", post(sql_write_effects))
             .route("/vault", post(vault_round_trip))
             .route("/messaging/pub-sub", post(messaging_publish))
+            .route("/messaging/batch", post(messaging_send_batch))
             .route("/websocket", post(websocket_send))
+            .route("/websocket/groups/{group}", post(websocket_send_to_group))
+            .route("/websocket/peers/{peer}", post(websocket_send_to_peer))
+            .route(
+                "/websocket/peers/{peer}/groups/{group}",
+                post(websocket_join_group).delete(websocket_leave_group),
+            )
+            .route("/websocket/peers/{peer}/close", post(websocket_close_peer))
             .route("/otel", post(otel_emit))
             .route("/docstore/stops", get(docstore_list_stops).post(docstore_create_stop))
             .route("/docstore/stops/{id}", get(docstore_get_stop).delete(docstore_delete_stop));
@@ -111,6 +130,137 @@ async fn keyvalue_round_trip(
     Ok(Json(json!({ "message": "keyvalue ok" })))
 }
 
+// --- omnia:keyvalue/expiry ---
+
+#[derive(Debug, Deserialize)]
+struct ExpiryParams {
+    key: String,
+    ttl: u64,
+}
+
+#[omnia_wasi_otel::instrument]
+async fn keyvalue_set_with_ttl(
+    Query(p): Query<ExpiryParams>, body: Bytes,
+) -> HttpResult<Json<Value>> {
+    let bucket = kv_store::open("omnia_bucket".to_string()).await.context("opening bucket")?;
+    expiry::set_with_ttl(&bucket, p.key.clone(), body.to_vec(), p.ttl)
+        .await
+        .context("storing data with a ttl")?;
+    let stored = bucket.get(p.key).await.context("reading data")?;
+    if stored.as_deref() != Some(body.as_ref()) {
+        Err(anyhow!("set-with-ttl/get round-trip mismatch"))?;
+    }
+
+    Ok(Json(json!({ "message": "keyvalue expiry ok" })))
+}
+
+// --- omnia:keyvalue/hash ---
+
+#[derive(Debug, Deserialize)]
+struct HashParams {
+    key: String,
+}
+
+// Builds a vehicle position hash field by field, leaving only `lat` behind.
+#[omnia_wasi_otel::instrument]
+async fn keyvalue_hash_fields(Query(p): Query<HashParams>) -> HttpResult<Json<Value>> {
+    let bucket = kv_store::open("omnia_bucket".to_string()).await.context("opening bucket")?;
+    let field = |name: &str, value: &str| (name.to_string(), value.as_bytes().to_vec());
+
+    let fields = vec![field("lat", "1"), field("lon", "2")];
+    let created = hash::set_fields(&bucket, p.key.clone(), fields).await.context("creating")?;
+    let fields = vec![field("lat", "3")];
+    let updated = hash::set_fields(&bucket, p.key.clone(), fields).await.context("updating")?;
+    let lat =
+        hash::get_field(&bucket, p.key.clone(), "lat".to_string()).await.context("reading lat")?;
+    let fields = vec!["lon".to_string(), "speed".to_string()];
+    let deleted = hash::delete_fields(&bucket, p.key, fields).await.context("deleting")?;
+
+    Ok(Json(json!({
+        "added": [created, updated],
+        "lat": lat.map(|lat| String::from_utf8_lossy(&lat).into_owned()),
+        "deleted": deleted,
+    })))
+}
+
+// --- wasi:keyvalue/atomics increment and omnia:keyvalue/exchange ---
+
+#[derive(Debug, Deserialize)]
+struct CounterParams {
+    key: String,
+    by: i64,
+}
+
+#[omnia_wasi_otel::instrument]
+async fn keyvalue_increment(Query(p): Query<CounterParams>) -> HttpResult<Json<Value>> {
+    let bucket = kv_store::open("omnia_bucket".to_string()).await.context("opening bucket")?;
+    let count = atomics::increment(&bucket, p.key, p.by).await.context("incrementing")?;
+    Ok(Json(json!({ "count": count })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeParams {
+    key: String,
+}
+
+// Claims a version key with compare-and-swap, then takes it with
+// get-and-delete, reporting the outcome of each step.
+#[omnia_wasi_otel::instrument]
+async fn keyvalue_exchange(Query(p): Query<ExchangeParams>) -> HttpResult<Json<Value>> {
+    let bucket = kv_store::open("omnia_bucket".to_string()).await.context("opening bucket")?;
+    let swap = |expected: Option<&str>, new: &str| {
+        exchange::compare_and_swap(
+            &bucket,
+            p.key.clone(),
+            expected.map(|expected| expected.as_bytes().to_vec()),
+            new.as_bytes().to_vec(),
+        )
+    };
+    let swapped = [
+        swap(None, "v1").await.context("creating")?,
+        swap(None, "v2").await.context("recreating")?,
+        swap(Some("v1"), "v2").await.context("updating")?,
+    ];
+
+    let taken = exchange::get_and_delete(&bucket, p.key.clone()).await.context("taking")?;
+    let again = exchange::get_and_delete(&bucket, p.key).await.context("taking again")?;
+
+    Ok(Json(json!({
+        "swapped": swapped,
+        "taken": taken.map(|taken| String::from_utf8_lossy(&taken).into_owned()),
+        "again": again.is_some(),
+    })))
+}
+
+// --- omnia:keyvalue/changes ---
+
+#[derive(Debug, Deserialize)]
+struct ChangesParams {
+    prefix: String,
+}
+
+// Watches `prefix`, then sets and deletes a key under it and sets one outside
+// it, returning the changes the feed reports.
+#[omnia_wasi_otel::instrument]
+async fn keyvalue_watch(Query(p): Query<ChangesParams>) -> HttpResult<Json<Value>> {
+    let bucket = kv_store::open("omnia_bucket".to_string()).await.context("opening bucket")?;
+    let feed = changes::watch(&bucket, p.prefix.clone()).await.context("watching")?;
+
+    let toggle = format!("{}dark", p.prefix);
+    bucket.set(toggle.clone(), b"on".to_vec()).await.context("setting toggle")?;
+    bucket.set(format!("unwatched-{}", p.prefix), b"a".to_vec()).await.context("setting")?;
+    bucket.delete(toggle).await.context("deleting toggle")?;
+
+    let mut seen = Vec::new();
+    for _ in 0..2 {
+        let change = feed.next().await.context("reading feed")?.context("feed closed")?;
+        let value = change.value.map(|value| String::from_utf8_lossy(&value).into_owned());
+        seen.push(json!({ "key": change.key, "value": value }));
+    }
+
+    Ok(Json(json!({ "changes": seen })))
+}
+
 // --- wasi:blobstore (streaming write, then read back) ---
 
 #[derive(Debug, Deserialize)]
@@ -215,6 +365,199 @@ async fn sql_insert_agency(Json(req): Json<CreateAgencyRequest>) -> HttpResult<J
     Ok(Json(json!({ "agency": { "name": req.name } })))
 }
 
+// --- omnia:sql/errors (the SQLSTATE of a failed statement) ---
+
+#[derive(Debug, Deserialize)]
+struct RegistrationRequest {
+    code: String,
+}
+
+// Registers `code` twice, so the second insert violates the primary key, and
+// inserts into a table that does not exist, which has no SQLSTATE.
+#[omnia_wasi_otel::instrument]
+async fn sql_constraint_violation(Json(req): Json<RegistrationRequest>) -> HttpResult<Json<Value>> {
+    let pool = sql_open().await?;
+    let create =
+        sql_prepare("CREATE TABLE IF NOT EXISTS registration (code TEXT PRIMARY KEY)", vec![])
+            .await?;
+    readwrite::exec(&pool, &create).await.map_err(|e| anyhow!("creating table: {}", e.trace()))?;
+
+    let code = vec![DataType::Str(Some(req.code))];
+    let insert = sql_prepare("INSERT INTO registration (code) VALUES ($1)", code.clone()).await?;
+    readwrite::exec(&pool, &insert).await.map_err(|e| anyhow!("registering: {}", e.trace()))?;
+    let duplicate = readwrite::exec(&pool, &insert)
+        .await
+        .err()
+        .ok_or_else(|| anyhow!("duplicate registration succeeded"))?;
+
+    let insert = sql_prepare("INSERT INTO unregistered (code) VALUES ($1)", code).await?;
+    let missing = readwrite::exec(&pool, &insert)
+        .await
+        .err()
+        .ok_or_else(|| anyhow!("insert into a missing table succeeded"))?;
+
+    Ok(Json(json!({
+        "duplicate": sql_errors::sqlstate(&duplicate),
+        "missing": sql_errors::sqlstate(&missing),
+    })))
+}
+
+// --- omnia:sql/transactional (commit and rollback) ---
+
+#[derive(Debug, Deserialize)]
+struct LedgerRequest {
+    kept: String,
+    discarded: String,
+}
+
+// Writes `discarded` in a transaction it rolls back and `kept` in one it
+// commits; each transaction must see its own write before it settles.
+#[omnia_wasi_otel::instrument]
+async fn sql_transactions(Json(req): Json<LedgerRequest>) -> HttpResult<Json<Value>> {
+    let pool = sql_open().await?;
+    let create =
+        sql_prepare("CREATE TABLE IF NOT EXISTS ledger (entry TEXT NOT NULL)", vec![]).await?;
+    readwrite::exec(&pool, &create).await.map_err(|e| anyhow!("creating table: {}", e.trace()))?;
+
+    for (entry, commit) in [(req.discarded, false), (req.kept, true)] {
+        let options = TransactionOptions {
+            isolation: None,
+            read_only: false,
+            deferrable: false,
+        };
+        let tx = Transaction::begin(&pool, options)
+            .await
+            .map_err(|e| anyhow!("beginning transaction: {}", e.trace()))?;
+
+        let entry = vec![DataType::Str(Some(entry))];
+        let insert = sql_prepare("INSERT INTO ledger (entry) VALUES ($1)", entry.clone()).await?;
+        transactional::exec(&tx, &insert)
+            .await
+            .map_err(|e| anyhow!("inserting entry: {}", e.trace()))?;
+        let select = sql_prepare("SELECT entry FROM ledger WHERE entry = $1", entry).await?;
+        let rows = transactional::query(&tx, &select)
+            .await
+            .map_err(|e| anyhow!("querying entry: {}", e.trace()))?;
+        if rows.len() != 1 {
+            Err(anyhow!("transaction does not see its own insert"))?;
+        }
+
+        let settled =
+            if commit { Transaction::commit(tx).await } else { Transaction::rollback(tx).await };
+        settled.map_err(|e| anyhow!("settling transaction: {}", e.trace()))?;
+    }
+
+    Ok(Json(json!({ "message": "transactions settled" })))
+}
+
+// --- omnia:sql/cursors (a result set fetched in batches) ---
+
+#[derive(Debug, Deserialize)]
+struct CursorParams {
+    tag: String,
+    rows: u32,
+    batch: u32,
+}
+
+// Inserts `rows` sequence numbers under `tag`, then reads them back through a
+// cursor of `batch` rows, returning each batch as fetched.
+#[omnia_wasi_otel::instrument]
+async fn sql_cursor_batches(Query(p): Query<CursorParams>) -> HttpResult<Json<Value>> {
+    let pool = sql_open().await?;
+    let create = "CREATE TABLE IF NOT EXISTS stop_time (tag TEXT NOT NULL, seq INTEGER NOT NULL)";
+    let create = sql_prepare(create, vec![]).await?;
+    readwrite::exec(&pool, &create).await.map_err(|e| anyhow!("creating table: {}", e.trace()))?;
+
+    let tag = DataType::Str(Some(p.tag));
+    for seq in 0..p.rows {
+        let params = vec![tag.clone(), DataType::Int64(Some(i64::from(seq)))];
+        let insert =
+            sql_prepare("INSERT INTO stop_time (tag, seq) VALUES ($1, $2)", params).await?;
+        readwrite::exec(&pool, &insert).await.map_err(|e| anyhow!("inserting: {}", e.trace()))?;
+    }
+
+    let select =
+        sql_prepare("SELECT seq FROM stop_time WHERE tag = $1 ORDER BY seq", vec![tag]).await?;
+    let cursor = Cursor::open(&pool, &select, p.batch)
+        .await
+        .map_err(|e| anyhow!("opening cursor: {}", e.trace()))?;
+    let mut batches = Vec::new();
+    loop {
+        let batch = cursor.next().await.map_err(|e| anyhow!("fetching batch: {}", e.trace()))?;
+        if batch.is_empty() {
+            break;
+        }
+        let seqs = batch
+            .iter()
+            .map(|row| match row.fields.first().map(|field| &field.value) {
+                Some(DataType::Int64(Some(seq))) => Ok(*seq),
+                other => Err(anyhow!("unexpected seq {other:?}")),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        batches.push(seqs);
+    }
+
+    Ok(Json(json!({ "batches": batches })))
+}
+
+// --- omnia:sql/results (the effects of a write) ---
+
+#[derive(Debug, Deserialize)]
+struct ResultsRequest {
+    route: String,
+}
+
+// Inserts two trips on `route`, then renames the second with a `RETURNING`
+// clause, reporting what each write says it did.
+#[omnia_wasi_otel::instrument]
+async fn sql_write_effects(Json(req): Json<ResultsRequest>) -> HttpResult<Json<Value>> {
+    let pool = sql_open().await?;
+    let create =
+        "CREATE TABLE IF NOT EXISTS trip (trip_id INTEGER PRIMARY KEY, route TEXT NOT NULL)";
+    let create = sql_prepare(create, vec![]).await?;
+    readwrite::exec(&pool, &create).await.map_err(|e| anyhow!("creating table: {}", e.trace()))?;
+
+    let route = DataType::Str(Some(req.route.clone()));
+    let insert = sql_prepare("INSERT INTO trip (route) VALUES ($1), ($1)", vec![route]).await?;
+    let inserted = results::exec_with_result(&pool, &insert)
+        .await
+        .map_err(|e| anyhow!("inserting trips: {}", e.trace()))?;
+    let trip_id = inserted.last_insert_id.ok_or_else(|| anyhow!("no last insert id"))?;
+
+    let renamed = DataType::Str(Some(format!("{}-renamed", req.route)));
+    let update = "UPDATE trip SET route = $1 WHERE trip_id = $2 RETURNING route";
+    let update = sql_prepare(update, vec![renamed, DataType::Int64(Some(trip_id))]).await?;
+    let updated = results::exec_with_result(&pool, &update)
+        .await
+        .map_err(|e| anyhow!("renaming trip: {}", e.trace()))?;
+    let returned = updated
+        .rows
+        .iter()
+        .map(|row| match row.fields.first().map(|field| &field.value) {
+            Some(DataType::Str(Some(route))) => Ok(route.clone()),
+            other => Err(anyhow!("unexpected route {other:?}")),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Json(json!({
+        "inserted": { "affected": inserted.affected_rows, "returned": inserted.rows.len() },
+        "updated": { "affected": updated.affected_rows, "returned": returned },
+        "trip_id": trip_id,
+    })))
+}
+
+async fn sql_open() -> anyhow::Result<Connection> {
+    Connection::open("db".to_string())
+        .await
+        .map_err(|e| anyhow!("failed to open connection: {}", e.trace()))
+}
+
+async fn sql_prepare(sql: &str, params: Vec<DataType>) -> anyhow::Result<Statement> {
+    Statement::prepare(sql.to_string(), params)
+        .await
+        .map_err(|e| anyhow!("preparing `{sql}`: {}", e.trace()))
+}
+
 // --- wasi:vault ---
 
 #[derive(Debug, Deserialize)]
@@ -252,6 +595,62 @@ async fn messaging_publish(Json(body): Json<Value>) -> HttpResult<Json<Value>> {
     Ok(Json(json!({ "message": "message published" })))
 }
 
+// --- omnia:messaging/batch (send many messages in one call) ---
+
+#[derive(Debug, Deserialize)]
+struct BatchParams {
+    topic: String,
+}
+
+#[omnia_wasi_otel::instrument]
+async fn messaging_send_batch(
+    Query(p): Query<BatchParams>, Json(payloads): Json<Vec<String>>,
+) -> HttpResult<Json<Value>> {
+    let client = MessagingClient::connect("default".to_string())
+        .await
+        .map_err(|e| anyhow!("connect: {e}"))?;
+    let messages = payloads.iter().map(|payload| Message::new(payload.as_bytes())).collect();
+
+    batch::send_batch(&client, p.topic.clone(), messages)
+        .await
+        .map_err(|e| anyhow!("publishing batch to topic '{}': {e}", p.topic))?;
+
+    Ok(Json(json!({ "message": "batch published" })))
+}
+
+struct Messaging;
+omnia_wasi_messaging::export!(Messaging with_types_in omnia_wasi_messaging);
+
+impl omnia_wasi_messaging::incoming_handler::Guest for Messaging {
+    // Deliveries on the conformance topics record what the handler observed
+    // in the keyvalue store, under the message payload, so a seam test can
+    // assert on it from the host side. Other topics are ignored.
+    async fn handle(message: Message) -> Result<(), MessagingError> {
+        let observed = match message.topic().as_deref() {
+            Some("conformance.headers") => {
+                json!({ "headers": message.metadata().unwrap_or_default() })
+            }
+            Some("conformance.settlement") => json!({
+                "extended": settlement::extend_deadline(&message, 60_000).is_ok(),
+                "acked": settlement::ack(&message).is_ok(),
+                "acked_again": settlement::ack(&message).is_ok(),
+                "foreign": settlement::ack(&Message::new(b"not delivered")).is_ok(),
+            }),
+            _ => return Ok(()),
+        };
+
+        let bucket = kv_store::open("omnia_bucket".to_string())
+            .await
+            .map_err(|e| MessagingError::Other(format!("opening bucket: {e}")))?;
+        let key = String::from_utf8_lossy(&message.data()).into_owned();
+        bucket
+            .set(key, observed.to_string().into_bytes())
+            .await
+            .map_err(|e| MessagingError::Other(format!("recording delivery: {e}")))?;
+        Ok(())
+    }
+}
+
 // --- wasi:websocket (connect + send an event) ---
 
 #[omnia_wasi_otel::instrument]
@@ -264,8 +663,71 @@ async fn websocket_send(message: String) -> HttpResult<Json<Value>> {
     Ok(Json(json!({ "message": "event sent" })))
 }
 
+#[omnia_wasi_otel::instrument]
+async fn websocket_send_to_group(
+    Path(group): Path<String>, message: String,
+) -> HttpResult<Json<Value>> {
+    let client = ws_connect().await?;
+    let event = Event::text(&message);
+    ws_client::send(&client, event, Some(vec![group]))
+        .await
+        .map_err(|e| anyhow!("sending event to group: {e}"))?;
+
+    Ok(Json(json!({ "message": "event sent" })))
+}
+
+#[omnia_wasi_otel::instrument]
+async fn websocket_send_to_peer(
+    Path(peer): Path<String>, message: String,
+) -> HttpResult<Json<Value>> {
+    let client = ws_connect().await?;
+    ws_client::send_to_peer(&client, peer, Event::text(&message))
+        .await
+        .map_err(|e| anyhow!("sending event to peer: {e}"))?;
+
+    Ok(Json(json!({ "message": "event sent" })))
+}
+
+#[omnia_wasi_otel::instrument]
+async fn websocket_join_group(
+    Path((peer, group)): Path<(String, String)>,
+) -> HttpResult<Json<Value>> {
+    let client = ws_connect().await?;
+    ws_client::join_group(&client, peer, group).await.map_err(|e| anyhow!("joining: {e}"))?;
+
+    Ok(Json(json!({ "message": "joined" })))
+}
+
+#[omnia_wasi_otel::instrument]
+async fn websocket_leave_group(
+    Path((peer, group)): Path<(String, String)>,
+) -> HttpResult<Json<Value>> {
+    let client = ws_connect().await?;
+    ws_client::leave_group(&client, peer, group).await.map_err(|e| anyhow!("leaving: {e}"))?;
+
+    Ok(Json(json!({ "message": "left" })))
+}
+
+// Closes with an application close code, so the test can tell it from the
+// host's own closes.
+#[omnia_wasi_otel::instrument]
+async fn websocket_close_peer(Path(peer): Path<String>, reason: String) -> HttpResult<Json<Value>> {
+    let client = ws_connect().await?;
+    ws_client::close_peer(&client, peer, 4000, reason)
+        .await
+        .map_err(|e| anyhow!("closing peer: {e}"))?;
+
+    Ok(Json(json!({ "message": "closed" })))
+}
+
+async fn ws_connect() -> anyhow::Result<WsClient> {
+    WsClient::connect("default".to_string()).await.map_err(|e| anyhow!("connecting: {e}"))
+}
+
 struct WebSocket;
 omnia_wasi_websocket::export!(WebSocket);
+omnia_wasi_websocket::export_authorizer!(WebSocket);
+omnia_wasi_websocket::export_connect_authorizer!(WebSocket);
 
 impl omnia_wasi_websocket::handler::Guest for WebSocket {
     // Inbound peer messages land here; mirror them into the keyvalue store so
@@ -282,6 +744,27 @@ impl omnia_wasi_websocket::handler::Guest for WebSocket {
     }
 }
 
+impl omnia_wasi_websocket::authorizer::Guest for WebSocket {
+    // Groups prefixed `private.` are never granted to a subscribing peer; the
+    // guest can still join peers to them directly.
+    async fn authorize_subscribe(
+        _peer: String, groups: Vec<String>,
+    ) -> Result<Vec<String>, WsHandlerError> {
+        Ok(groups.into_iter().filter(|group| !group.starts_with("private.")).collect())
+    }
+}
+
+impl omnia_wasi_websocket::connect_authorizer::Guest for WebSocket {
+    // Admits peers on listeners that require authorization when they present
+    // the conformance token.
+    async fn authorize_connect(_peer: String, token: Option<String>) -> Result<(), WsHandlerError> {
+        match token.as_deref() {
+            Some("conformance-token") => Ok(()),
+            _ => Err(WsHandlerError::PermissionDenied("invalid token".to_string())),
+        }
+    }
+}
+
 // --- wasi:otel (metrics via both the tracing and native OTel APIs) ---
 
 #[omnia_wasi_otel::instrument]
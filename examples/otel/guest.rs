@@ -6,14 +6,14 @@
 
 #![cfg(target_arch = "wasm32")]
 
-use axum::routing::{options, post};
+use axum::routing::post;
 use axum::{Json, Router};
 use http::Method;
 use omnia_guest::HttpResult;
+use omnia_guest::api::Cors;
 use opentelemetry::trace::{TraceContextExt, Tracer};
 use opentelemetry::{KeyValue, global};
 use serde_json::{Value, json};
-use tower_http::cors::{Any, CorsLayer};
 use tracing::Level;
 use wasip3::exports::http::handler::Guest;
 use wasip3::http::types::{ErrorCode, Request, Response};
@@ -53,15 +53,11 @@ impl Guest for Http {
             .in_scope(|| {
                 tracing::info!("received request");
 
-                let router = Router::new()
-                    .layer(
-                        CorsLayer::new()
-                            .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-                            .allow_headers(Any)
-                            .allow_origin(Any),
-                    )
-                    .route("/", post(handler))
-                    .route("/", options(handle_options));
+                let router = Cors::new()
+                    .allow_any_origin()
+                    .allow_methods([Method::GET, Method::POST])
+                    .allow_any_header()
+                    .apply(Router::new().route("/", post(handler)));
 
                 omnia_wasi_http::serve(router, request)
             })
@@ -78,8 +74,3 @@ async fn handler(Json(body): Json<Value>) -> HttpResult<Json<Value>> {
         "request": body
     })))
 }
-
-/// Handles CORS preflight OPTIONS requests.
-async fn handle_options() -> HttpResult<()> {
-    Ok(())
-}
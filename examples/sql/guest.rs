@@ -68,11 +68,7 @@ async fn list_agencies() -> HttpResult<Json<Value>> {
         .await
         .context("failed to execute query")?;
 
-    let agencies = rows
-        .iter()
-        .map(Agency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let agencies = Agency::from_rows(&rows).context("failed row mapping")?;
 
     Ok(Json(json!(agencies)))
 }
@@ -94,11 +90,7 @@ async fn create_agency(Json(req): Json<CreateAgencyRequest>) -> HttpResult<Json<
         .await
         .context("failed to execute query")?;
 
-    let agencies = rows
-        .iter()
-        .map(Agency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let agencies = Agency::from_rows(&rows).context("failed row mapping")?;
 
     // Not worried about concurrency issue here as one request will fail. Moreover, this
     // is an example. Ideally, this will be handled in a more idiomatic way.
@@ -140,11 +132,7 @@ async fn get_agency(Path(id): Path<i64>) -> HttpResult<Json<Value>> {
         .await
         .context("failed to execute query")?;
 
-    let agencies = rows
-        .iter()
-        .map(Agency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let agencies = Agency::from_rows(&rows).context("failed row mapping")?;
 
     let agency = agencies.first().ok_or_else(|| anyhow!("agency not found"))?;
 
@@ -171,11 +159,7 @@ async fn update_agency(
         .await
         .context("failed to execute query")?;
 
-    let agencies = rows
-        .iter()
-        .map(Agency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let agencies = Agency::from_rows(&rows).context("failed row mapping")?;
 
     let _ = agencies.first().ok_or_else(|| anyhow!("agency not found"))?;
 
@@ -213,11 +197,7 @@ async fn update_agency(
         .await
         .context("failed to execute query")?;
 
-    let agencies = rows
-        .iter()
-        .map(Agency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let agencies = Agency::from_rows(&rows).context("failed row mapping")?;
 
     let agency = agencies.first().ok_or_else(|| anyhow!("agency not found after update"))?;
 
@@ -241,11 +221,7 @@ async fn list_agency_feeds(Path(agency_id): Path<i64>) -> HttpResult<Json<Value>
         .await
         .context("failed to execute query")?;
 
-    let feeds = rows
-        .iter()
-        .map(Feed::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let feeds = Feed::from_rows(&rows).context("failed row mapping")?;
 
     Ok(Json(json!({ "feeds": feeds })))
 }
@@ -269,11 +245,7 @@ async fn create_feed(
         .await
         .context("failed to execute query")?;
 
-    let agencies = rows
-        .iter()
-        .map(Agency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let agencies = Agency::from_rows(&rows).context("failed row mapping")?;
 
     if agencies.is_empty() {
         return Err(anyhow!("agency not found").into());
@@ -290,11 +262,7 @@ async fn create_feed(
         .await
         .context("failed to execute query")?;
 
-    let feeds = rows
-        .iter()
-        .map(Feed::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let feeds = Feed::from_rows(&rows).context("failed row mapping")?;
 
     // Not worried about concurrency issue here as one request will fail. Moreover, this
     // is an example. Ideally, this will be handled in a more idiomatic way.
@@ -337,11 +305,7 @@ async fn list_all_feeds() -> HttpResult<Json<Value>> {
         .await
         .context("failed to execute query")?;
 
-    let feeds_with_agency = rows
-        .iter()
-        .map(FeedWithAgency::from_row)
-        .collect::<Result<Vec<_>>>()
-        .context("failed row mapping")?;
+    let feeds_with_agency = FeedWithAgency::from_rows(&rows).context("failed row mapping")?;
 
     Ok(Json(json!({ "feeds": feeds_with_agency })))
 }